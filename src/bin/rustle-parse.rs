@@ -1,8 +1,15 @@
-use clap::{Parser, ValueEnum};
-use rustle_parse::{OutputFormat, ParseError, Parser as RustleParser};
+use clap::{Args, Parser, ValueEnum};
+use rustle_parse::parser::config::HashBehaviour;
+use rustle_parse::parser::tags::list_tags;
+use rustle_parse::parser::AnsibleConfig;
+use rustle_parse::{
+    OutputFormat, ParseError, Parser as RustleParser, ParserConfig, StaticConditionMode,
+    TagSelection,
+};
 use std::collections::HashMap;
-use std::path::PathBuf;
-use tracing::{error, info, Level};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{error, info, warn, Level};
 use tracing_subscriber::FmtSubscriber;
 
 #[derive(Parser)]
@@ -14,9 +21,9 @@ struct Cli {
     #[arg(value_name = "PLAYBOOK_FILE")]
     playbook_file: Option<String>,
 
-    /// Inventory file path
-    #[arg(short, long, value_name = "FILE")]
-    inventory: Option<PathBuf>,
+    /// Inventory file or directory; repeat to combine several sources
+    #[arg(short, long, value_name = "PATH")]
+    inventory: Vec<PathBuf>,
 
     /// Extra variables (key=value,...)
     #[arg(short, long, value_name = "VARS")]
@@ -54,9 +61,187 @@ struct Cli {
     #[arg(long)]
     dry_run: bool,
 
-    /// Limit execution to specific hosts or groups (comma-separated patterns)
+    /// Limit execution to hosts matching an Ansible host pattern: unions
+    /// (`a:b` or `a,b`), intersections (`&a`), exclusions (`!a`), regexes
+    /// (`~web\d+`), subscripts (`web[0:2]`) and `@file` lists of patterns
     #[arg(short = 'l', long, value_name = "PATTERN")]
     limit: Option<String>,
+
+    /// Only include tasks tagged with these tags (comma-separated, repeatable)
+    #[arg(short = 't', long, value_name = "TAGS")]
+    tags: Vec<String>,
+
+    /// Exclude tasks tagged with these tags (comma-separated, repeatable)
+    #[arg(long, value_name = "TAGS")]
+    skip_tags: Vec<String>,
+
+    /// List all available tags
+    #[arg(long)]
+    list_tags: bool,
+
+    /// Evaluate conditions known at parse time and flag or prune tasks that never run
+    #[arg(long, value_enum, value_name = "MODE")]
+    static_conditions: Option<StaticConditionsCli>,
+
+    /// Check a requirements.yml against the playbook and print a lockfile
+    #[arg(long, value_name = "FILE")]
+    requirements: Option<PathBuf>,
+
+    #[command(flatten)]
+    settings: ParserSettings,
+}
+
+/// Options overriding the parser settings from ansible.cfg and the environment
+#[derive(Args, Default)]
+#[command(next_help_heading = "Parser settings")]
+struct ParserSettings {
+    /// Deepest allowed nesting of includes and imports
+    #[arg(long, value_name = "N")]
+    max_include_depth: Option<usize>,
+
+    /// Seconds a parsed include file stays cached
+    #[arg(long, value_name = "SECONDS")]
+    include_cache_ttl: Option<u64>,
+
+    /// Read include files again every time they are used
+    #[arg(long)]
+    no_include_cache: bool,
+
+    /// Allow includes to use absolute paths
+    #[arg(long)]
+    allow_absolute_paths: bool,
+
+    /// Do not check the permissions of included files
+    #[arg(long)]
+    no_strict_file_permissions: bool,
+
+    /// Directories searched for roles (colon-separated)
+    #[arg(long, value_name = "PATHS", value_delimiter = ':')]
+    roles_path: Option<Vec<PathBuf>>,
+
+    /// Directories holding ansible_collections trees (colon-separated)
+    #[arg(long, value_name = "PATHS", value_delimiter = ':')]
+    collections_path: Option<Vec<PathBuf>>,
+
+    /// Fail on inventory warnings such as duplicate hosts
+    #[arg(long)]
+    strict_inventory: bool,
+
+    /// Fail on fully qualified modules missing from their installed collection
+    #[arg(long)]
+    strict_modules: bool,
+
+    /// Most hosts a single inventory host pattern may expand to
+    #[arg(long, value_name = "N")]
+    max_pattern_expansion: Option<usize>,
+
+    /// Seconds an executable inventory script may run
+    #[arg(long, value_name = "SECONDS")]
+    inventory_script_timeout: Option<u64>,
+
+    /// How dictionary variables defined more than once combine
+    #[arg(long, value_enum, value_name = "MODE")]
+    hash_behaviour: Option<HashBehaviourCli>,
+
+    /// Render templates that are a single expression to native values
+    #[arg(long)]
+    jinja2_native: bool,
+
+    /// Largest accepted YAML document, in bytes
+    #[arg(long, value_name = "BYTES")]
+    max_yaml_size: Option<usize>,
+
+    /// Deepest accepted YAML nesting
+    #[arg(long, value_name = "N")]
+    max_yaml_depth: Option<usize>,
+
+    /// Most nodes YAML aliases and merge keys may copy into a document
+    #[arg(long, value_name = "N")]
+    max_yaml_alias_nodes: Option<usize>,
+}
+
+impl ParserSettings {
+    /// Override the settings of `config` given on the command line
+    fn apply_to(&self, config: &mut ParserConfig) {
+        if let Some(depth) = self.max_include_depth {
+            config.include.max_include_depth = depth;
+        }
+        if let Some(ttl) = self.include_cache_ttl {
+            config.include.cache_ttl = Duration::from_secs(ttl);
+        }
+        if self.no_include_cache {
+            config.include.enable_include_cache = false;
+        }
+        if self.allow_absolute_paths {
+            config.include.allow_absolute_paths = true;
+        }
+        if self.no_strict_file_permissions {
+            config.include.strict_file_permissions = false;
+        }
+        if let Some(paths) = &self.roles_path {
+            config.include.role_search_paths = paths.clone();
+        }
+        if let Some(paths) = &self.collections_path {
+            config.collections_paths = paths.clone();
+        }
+        if self.strict_inventory {
+            config.inventory.strict_mode = true;
+        }
+        if self.strict_modules {
+            config.strict_modules = true;
+        }
+        if let Some(max) = self.max_pattern_expansion {
+            config.inventory.max_pattern_expansion = max;
+        }
+        if let Some(timeout) = self.inventory_script_timeout {
+            config.inventory.script_timeout = Duration::from_secs(timeout);
+        }
+        if let Some(mode) = self.hash_behaviour {
+            config.inventory.hash_behaviour = mode.into();
+        }
+        if self.jinja2_native {
+            config.jinja2_native = true;
+        }
+        if let Some(size) = self.max_yaml_size {
+            config.yaml_limits.max_document_size = size;
+        }
+        if let Some(depth) = self.max_yaml_depth {
+            config.yaml_limits.max_depth = depth;
+        }
+        if let Some(nodes) = self.max_yaml_alias_nodes {
+            config.yaml_limits.max_alias_nodes = nodes;
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum HashBehaviourCli {
+    Replace,
+    Merge,
+}
+
+impl From<HashBehaviourCli> for HashBehaviour {
+    fn from(cli_mode: HashBehaviourCli) -> Self {
+        match cli_mode {
+            HashBehaviourCli::Replace => HashBehaviour::Replace,
+            HashBehaviourCli::Merge => HashBehaviour::Merge,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum StaticConditionsCli {
+    Flag,
+    Prune,
+}
+
+impl From<StaticConditionsCli> for StaticConditionMode {
+    fn from(cli_mode: StaticConditionsCli) -> Self {
+        match cli_mode {
+            StaticConditionsCli::Flag => StaticConditionMode::Flag,
+            StaticConditionsCli::Prune => StaticConditionMode::Prune,
+        }
+    }
 }
 
 #[derive(Clone, ValueEnum)]
@@ -78,7 +263,7 @@ impl From<OutputFormatCli> for OutputFormat {
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let cli = Cli::parse();
+    let mut cli = Cli::parse();
 
     // Set up logging
     let log_level = if cli.verbose {
@@ -92,17 +277,39 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .finish();
     tracing::subscriber::set_global_default(subscriber)?;
 
+    // Settings from ansible.cfg and ANSIBLE_* fill in options not given on the command line
+    let ansible_config = AnsibleConfig::load()?;
+    if let Some(config_file) = &ansible_config.config_file {
+        info!("Using configuration file {}", config_file.display());
+    }
+    apply_config_defaults(&mut cli, &ansible_config);
+
     // Parse extra variables
     let extra_vars = parse_extra_vars(&cli.extra_vars)?;
 
+    // Command line options override the configuration
+    let mut config = ansible_config.parser_config();
+    cli.settings.apply_to(&mut config);
+
     // Create parser
-    let mut parser = RustleParser::new().with_extra_vars(extra_vars);
+    let mut parser = RustleParser::new()
+        .with_config(config)
+        .with_extra_vars(extra_vars);
 
     // Add cache if specified
     if let Some(ref cache_dir) = cli.cache_dir {
         parser = parser.with_cache(cache_dir.clone());
     }
 
+    // Select tasks by tag if requested
+    if !cli.tags.is_empty() || !cli.skip_tags.is_empty() {
+        parser = parser.with_tag_selection(TagSelection::new(&cli.tags, &cli.skip_tags));
+    }
+
+    if let Some(mode) = cli.static_conditions {
+        parser = parser.with_static_conditions(mode.into());
+    }
+
     // Add vault password if specified
     if let Some(ref vault_file) = cli.vault_password_file {
         let vault_password = tokio::fs::read_to_string(vault_file).await?;
@@ -122,9 +329,15 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let playbook_path = get_playbook_path(&cli)?;
     let mut playbook = parser.parse_playbook(&playbook_path).await?;
 
+    if let Some(requirements_path) = &cli.requirements {
+        return handle_requirements(&parser, &playbook, &playbook_path, requirements_path).await;
+    }
+
     // Parse inventory and add it to the playbook
-    let mut inventory = if let Some(inventory_path) = &cli.inventory {
-        parser.parse_inventory(inventory_path).await?
+    let mut inventory = if !cli.inventory.is_empty() {
+        parser
+            .parse_inventory_for_playbook(&cli.inventory, &playbook_path)
+            .await?
     } else {
         // Use implicit localhost inventory when no inventory file is provided
         parser.create_implicit_inventory()
@@ -143,12 +356,19 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Add inventory to playbook structure
     playbook.inventory = inventory;
+    parser.resolve_play_hosts(&mut playbook)?;
+    parser.apply_static_conditions(&mut playbook);
 
     if cli.list_tasks {
         handle_list_tasks(&playbook);
         return Ok(());
     }
 
+    if cli.list_tags {
+        handle_list_tags(&playbook);
+        return Ok(());
+    }
+
     if !cli.dry_run {
         output_result(&playbook, cli.output.into())?;
     }
@@ -157,6 +377,22 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+/// Use the configured inventory and vault password file when the options are absent.
+/// Configured inventories that do not exist are skipped.
+fn apply_config_defaults(cli: &mut Cli, config: &AnsibleConfig) {
+    if cli.inventory.is_empty() {
+        cli.inventory = config
+            .inventory
+            .iter()
+            .filter(|path| path.exists())
+            .cloned()
+            .collect();
+    }
+    if cli.vault_password_file.is_none() {
+        cli.vault_password_file = config.default_vault_password_file();
+    }
+}
+
 fn parse_extra_vars(
     extra_vars_str: &Option<String>,
 ) -> Result<HashMap<String, serde_json::Value>, ParseError> {
@@ -289,11 +525,15 @@ async fn handle_list_hosts(
     parser: &RustleParser,
     cli: &Cli,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let mut inventory = if let Some(inventory_path) = &cli.inventory {
-        parser.parse_inventory(inventory_path).await?
-    } else {
+    let mut inventory = match &cli.playbook_file {
         // Use implicit localhost inventory when no inventory file is provided
-        parser.create_implicit_inventory()
+        _ if cli.inventory.is_empty() => parser.create_implicit_inventory(),
+        Some(playbook) if playbook != "-" => {
+            parser
+                .parse_inventory_for_playbook(&cli.inventory, Path::new(playbook))
+                .await?
+        }
+        _ => parser.parse_inventories(&cli.inventory).await?,
     };
 
     // Apply limit filter if specified
@@ -318,6 +558,9 @@ async fn handle_list_hosts(
         if let Some(user) = &host.user {
             println!("  user: {user}");
         }
+        if let Some(source) = &host.source {
+            println!("  source: {source}");
+        }
         for (key, value) in &host.vars {
             println!("  {key}: {value}");
         }
@@ -326,6 +569,54 @@ async fn handle_list_hosts(
     Ok(())
 }
 
+async fn handle_requirements(
+    parser: &RustleParser,
+    playbook: &rustle_parse::ParsedPlaybook,
+    playbook_path: &Path,
+    requirements_path: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let requirements = parser.parse_requirements(requirements_path).await?;
+    let project_dir = playbook_path.parent().unwrap_or_else(|| Path::new("."));
+    let report = requirements.check(playbook, project_dir);
+    let lock = requirements.lock(
+        playbook_path,
+        &parser.config().include.role_search_paths,
+        &parser.collection_index(playbook_path)?,
+    );
+
+    for role in &report.missing_roles {
+        warn!(
+            "Role '{}' is used but not in {}",
+            role,
+            requirements_path.display()
+        );
+    }
+    for collection in &report.missing_collections {
+        warn!(
+            "Collection '{}' is used but not in {}",
+            collection,
+            requirements_path.display()
+        );
+    }
+    for role in &report.unused_roles {
+        warn!("Required role '{}' is not used", role);
+    }
+    for collection in &report.unused_collections {
+        warn!("Required collection '{}' is not used", collection);
+    }
+
+    let output = serde_json::json!({ "report": report, "lock": lock });
+    println!("{}", serde_json::to_string_pretty(&output)?);
+    if !report.is_clean() {
+        return Err(format!(
+            "{} does not match the roles and collections the playbook uses",
+            requirements_path.display()
+        )
+        .into());
+    }
+    Ok(())
+}
+
 fn handle_list_tasks(playbook: &rustle_parse::ParsedPlaybook) {
     for (play_idx, play) in playbook.plays.iter().enumerate() {
         println!("Play {}: {}", play_idx + 1, play.name);
@@ -337,8 +628,14 @@ fn handle_list_tasks(playbook: &rustle_parse::ParsedPlaybook) {
                 println!("    Tags: {}", task.tags.join(", "));
             }
 
-            if let Some(when_condition) = &task.when {
-                println!("    When: {when_condition}");
+            if !task.when.is_empty() {
+                let conditions: Vec<&str> =
+                    task.when.iter().map(|c| c.expression.as_str()).collect();
+                println!("    When: {}", conditions.join(" and "));
+            }
+
+            if task.statically_skipped {
+                println!("    Statically skipped");
             }
         }
 
@@ -356,6 +653,37 @@ fn handle_list_tasks(playbook: &rustle_parse::ParsedPlaybook) {
     }
 }
 
+fn handle_list_tags(playbook: &rustle_parse::ParsedPlaybook) {
+    println!("playbook: {}", playbook.metadata.file_path);
+
+    let mut all_tags = std::collections::BTreeSet::new();
+    for (play_idx, play_tags) in list_tags(playbook).into_iter().enumerate() {
+        println!();
+        println!(
+            "  play #{} ({}): {}\tTAGS: [{}]",
+            play_idx + 1,
+            format_hosts(&playbook.plays[play_idx].hosts),
+            play_tags.play,
+            play_tags.play_tags.join(", ")
+        );
+        let task_tags: Vec<&str> = play_tags.task_tags.iter().map(|t| t.as_str()).collect();
+        println!("      TASK TAGS: [{}]", task_tags.join(", "));
+        all_tags.extend(play_tags.task_tags);
+    }
+
+    let all_tags: Vec<String> = all_tags.into_iter().collect();
+    println!();
+    println!("All tags: [{}]", all_tags.join(", "));
+}
+
+fn format_hosts(hosts: &rustle_parse::HostPattern) -> String {
+    match hosts {
+        rustle_parse::HostPattern::All => "all".to_string(),
+        rustle_parse::HostPattern::Single(host) => host.clone(),
+        rustle_parse::HostPattern::Multiple(hosts) => hosts.join(","),
+    }
+}
+
 fn output_result(
     playbook: &rustle_parse::ParsedPlaybook,
     format: OutputFormat,
@@ -483,7 +811,7 @@ mod tests {
     fn test_get_playbook_path_from_arg() {
         let cli = Cli {
             playbook_file: Some("test.yml".to_string()),
-            inventory: None,
+            inventory: Vec::new(),
             extra_vars: None,
             output: OutputFormatCli::Json,
             cache_dir: None,
@@ -494,6 +822,12 @@ mod tests {
             verbose: false,
             dry_run: false,
             limit: None,
+            tags: vec![],
+            skip_tags: vec![],
+            list_tags: false,
+            static_conditions: None,
+            requirements: None,
+            settings: ParserSettings::default(),
         };
 
         let result = get_playbook_path(&cli).unwrap();
@@ -504,7 +838,7 @@ mod tests {
     fn test_get_playbook_path_stdin() {
         let cli = Cli {
             playbook_file: Some("-".to_string()),
-            inventory: None,
+            inventory: Vec::new(),
             extra_vars: None,
             output: OutputFormatCli::Json,
             cache_dir: None,
@@ -515,6 +849,12 @@ mod tests {
             verbose: false,
             dry_run: false,
             limit: None,
+            tags: vec![],
+            skip_tags: vec![],
+            list_tags: false,
+            static_conditions: None,
+            requirements: None,
+            settings: ParserSettings::default(),
         };
 
         let result = get_playbook_path(&cli);
@@ -531,7 +871,7 @@ mod tests {
     fn test_get_playbook_path_none() {
         let cli = Cli {
             playbook_file: None,
-            inventory: None,
+            inventory: Vec::new(),
             extra_vars: None,
             output: OutputFormatCli::Json,
             cache_dir: None,
@@ -542,6 +882,12 @@ mod tests {
             verbose: false,
             dry_run: false,
             limit: None,
+            tags: vec![],
+            skip_tags: vec![],
+            list_tags: false,
+            static_conditions: None,
+            requirements: None,
+            settings: ParserSettings::default(),
         };
 
         let result = get_playbook_path(&cli);
@@ -569,4 +915,52 @@ mod tests {
             OutputFormat::Yaml
         ));
     }
+
+    #[test]
+    fn test_parser_settings_override_config() {
+        let cli = Cli::try_parse_from([
+            "rustle-parse",
+            "site.yml",
+            "--max-include-depth",
+            "5",
+            "--no-include-cache",
+            "--allow-absolute-paths",
+            "--roles-path",
+            "/a/roles:/b/roles",
+            "--hash-behaviour",
+            "merge",
+            "--jinja2-native",
+            "--strict-modules",
+            "--max-yaml-depth",
+            "16",
+        ])
+        .unwrap();
+
+        let mut config = ParserConfig::default();
+        cli.settings.apply_to(&mut config);
+
+        assert_eq!(config.include.max_include_depth, 5);
+        assert!(!config.include.enable_include_cache);
+        assert!(config.include.allow_absolute_paths);
+        assert!(config.include.strict_file_permissions);
+        assert_eq!(
+            config.include.role_search_paths,
+            vec![PathBuf::from("/a/roles"), PathBuf::from("/b/roles")]
+        );
+        assert_eq!(config.inventory.hash_behaviour, HashBehaviour::Merge);
+        assert!(!config.inventory.strict_mode);
+        assert!(config.jinja2_native);
+        assert!(config.strict_modules);
+        assert_eq!(config.yaml_limits.max_depth, 16);
+        assert_eq!(config.include_config().yaml_limits.max_depth, 16);
+        assert_eq!(config.inventory_config().yaml_limits.max_depth, 16);
+
+        // Settings not given on the command line are left alone
+        let cli = Cli::try_parse_from(["rustle-parse", "site.yml"]).unwrap();
+        let mut config = ParserConfig::default();
+        config.include.max_include_depth = 7;
+        cli.settings.apply_to(&mut config);
+        assert_eq!(config.include.max_include_depth, 7);
+        assert!(config.include.enable_include_cache);
+    }
 }
@@ -4,6 +4,6 @@ pub mod types;
 #[cfg(test)]
 pub mod testing;
 
-pub use parser::{ParseError, Parser};
+pub use parser::{ParseError, Parser, ParserConfig, StaticConditionMode, TagSelection, YamlLimits};
 pub use types::output::OutputFormat;
 pub use types::parsed::*;
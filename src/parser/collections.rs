@@ -0,0 +1,487 @@
+//! Index of locally installed Ansible collections.
+//!
+//! Collections live in `ansible_collections/<namespace>/<name>` below each
+//! collections path. The index records every collection's `galaxy.yml` metadata,
+//! modules, `meta/runtime.yml` module routing and roles so that `namespace.collection.role` roles can be found and
+//! fully qualified module names checked without network access.
+
+use crate::parser::error::ParseError;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Namespaces whose modules ship with Ansible itself rather than a collection
+const BUILTIN_NAMESPACES: &[&str] = &["ansible.builtin", "ansible.legacy"];
+
+/// Extensions of module files: Python, and PowerShell for Windows hosts
+const MODULE_EXTENSIONS: &[&str] = &["py", "ps1"];
+
+/// Whether `collection` ships with Ansible itself
+pub fn is_builtin_collection(collection: &str) -> bool {
+    BUILTIN_NAMESPACES.contains(&collection)
+}
+
+/// An installed collection
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Collection {
+    pub namespace: String,
+    pub name: String,
+    pub version: Option<String>,
+    /// Collections this one requires, from `galaxy.yml`, with version ranges
+    pub dependencies: BTreeMap<String, String>,
+    pub path: PathBuf,
+    pub modules: BTreeSet<String>,
+    /// Modules redirected or removed by `plugin_routing` in `meta/runtime.yml`
+    pub module_routing: BTreeMap<String, ModuleRoute>,
+    pub roles: BTreeSet<String>,
+}
+
+/// Where `plugin_routing` sends a module name
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModuleRoute {
+    /// Fully qualified name of the module that replaces this one
+    #[serde(default)]
+    pub redirect: Option<String>,
+    /// Set when the module was removed from the collection
+    #[serde(default)]
+    pub tombstone: Option<Tombstone>,
+}
+
+/// A removed module, and what Ansible says about it
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Tombstone {
+    #[serde(default)]
+    pub removal_version: Option<String>,
+    #[serde(default)]
+    pub removal_date: Option<String>,
+    #[serde(default)]
+    pub warning_text: Option<String>,
+}
+
+impl Collection {
+    /// `namespace.name`
+    pub fn fqcn(&self) -> String {
+        format!("{}.{}", self.namespace, self.name)
+    }
+}
+
+/// Whether a fully qualified module name can be used
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModuleLookup {
+    /// The module exists in an installed collection, or ships with Ansible
+    Found,
+    /// The collection is not installed, so the module cannot be checked
+    UnknownCollection,
+    /// The collection is installed but has no such module
+    Missing,
+    /// The module was removed from its collection
+    Removed(Tombstone),
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct GalaxyFile {
+    version: Option<String>,
+    #[serde(default)]
+    dependencies: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct RuntimeFile {
+    #[serde(default)]
+    plugin_routing: PluginRouting,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PluginRouting {
+    #[serde(default)]
+    modules: BTreeMap<String, ModuleRoute>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Manifest {
+    #[serde(default)]
+    collection_info: GalaxyFile,
+}
+
+/// Collections found under a list of collections paths
+#[derive(Debug, Clone, Default)]
+pub struct CollectionIndex {
+    /// Keyed by `namespace.name`; the first path a collection is found in wins
+    collections: BTreeMap<String, Collection>,
+    /// Whether modules missing from an installed collection are errors
+    strict_modules: bool,
+}
+
+impl CollectionIndex {
+    /// Index the collections below `paths`, in order of precedence. Each path
+    /// may be a collections path or an `ansible_collections` directory itself.
+    pub fn load(paths: &[PathBuf]) -> Result<Self, ParseError> {
+        let mut index = Self::default();
+        for path in paths {
+            let root = if path.ends_with("ansible_collections") {
+                path.clone()
+            } else {
+                path.join("ansible_collections")
+            };
+            for namespace_dir in sorted_dirs(&root)? {
+                for collection_dir in sorted_dirs(&namespace_dir)? {
+                    let collection = Self::load_collection(&collection_dir)?;
+                    index
+                        .collections
+                        .entry(collection.fqcn())
+                        .or_insert(collection);
+                }
+            }
+        }
+        Ok(index)
+    }
+
+    /// Make modules missing from an installed collection errors rather than warnings
+    pub fn with_strict_modules(mut self, strict: bool) -> Self {
+        self.strict_modules = strict;
+        self
+    }
+
+    pub fn strict_modules(&self) -> bool {
+        self.strict_modules
+    }
+
+    fn load_collection(path: &Path) -> Result<Collection, ParseError> {
+        let dir_name = |path: &Path| {
+            path.file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default()
+        };
+
+        // Source checkouts have galaxy.yml, collections installed by
+        // ansible-galaxy have the same metadata in MANIFEST.json
+        let galaxy_path = path.join("galaxy.yml");
+        let manifest_path = path.join("MANIFEST.json");
+        let galaxy: GalaxyFile = if galaxy_path.is_file() {
+            crate::parser::yaml::from_str::<Option<GalaxyFile>>(&fs::read_to_string(&galaxy_path)?)?
+                .unwrap_or_default()
+        } else if manifest_path.is_file() {
+            serde_json::from_str::<Manifest>(&fs::read_to_string(&manifest_path)?)?.collection_info
+        } else {
+            GalaxyFile::default()
+        };
+
+        let mut module_files = Vec::new();
+        collect_files(&path.join("plugins").join("modules"), &mut module_files)?;
+        let modules = module_files
+            .into_iter()
+            .filter(|module| {
+                module
+                    .extension()
+                    .is_some_and(|ext| MODULE_EXTENSIONS.iter().any(|known| ext == *known))
+            })
+            .filter_map(|module| module.file_stem().map(|s| s.to_string_lossy().to_string()))
+            .filter(|module| module != "__init__")
+            .collect();
+        let runtime_path = path.join("meta").join("runtime.yml");
+        let runtime: RuntimeFile = if runtime_path.is_file() {
+            crate::parser::yaml::from_str::<Option<RuntimeFile>>(&fs::read_to_string(
+                &runtime_path,
+            )?)?
+            .unwrap_or_default()
+        } else {
+            RuntimeFile::default()
+        };
+        let roles = sorted_dirs(&path.join("roles"))?
+            .iter()
+            .map(|role| dir_name(role))
+            .collect();
+
+        // Ansible finds collections by their directory, whatever galaxy.yml says
+        Ok(Collection {
+            namespace: dir_name(path.parent().unwrap_or(path)),
+            name: dir_name(path),
+            version: galaxy.version,
+            dependencies: galaxy.dependencies,
+            path: path.to_path_buf(),
+            modules,
+            module_routing: runtime.plugin_routing.modules,
+            roles,
+        })
+    }
+
+    pub fn collections(&self) -> impl Iterator<Item = &Collection> {
+        self.collections.values()
+    }
+
+    pub fn get(&self, fqcn: &str) -> Option<&Collection> {
+        self.collections.get(fqcn)
+    }
+
+    /// Find a role named `namespace.collection.role`, or a short role name in
+    /// one of the play's `collections`. Returns the collection and role directory.
+    pub fn resolve_role(&self, role: &str, search: &[String]) -> Option<(&Collection, PathBuf)> {
+        if let Some((collection, name)) = split_fqcn(role) {
+            return self.collection_role(collection, name);
+        }
+        search
+            .iter()
+            .find_map(|collection| self.collection_role(collection, role))
+    }
+
+    fn collection_role(&self, collection: &str, role: &str) -> Option<(&Collection, PathBuf)> {
+        let collection = self.collections.get(collection)?;
+        collection
+            .roles
+            .contains(role)
+            .then(|| (collection, collection.path.join("roles").join(role)))
+    }
+
+    /// Check a fully qualified module name such as `community.general.ufw`,
+    /// following the collections' `plugin_routing` redirects
+    pub fn lookup_module(&self, module: &str) -> ModuleLookup {
+        let mut module = module.to_string();
+        let mut seen = BTreeSet::new();
+        while seen.insert(module.clone()) {
+            let Some((collection, name)) = split_fqcn(&module) else {
+                return ModuleLookup::UnknownCollection;
+            };
+            if is_builtin_collection(collection) {
+                return ModuleLookup::Found;
+            }
+            let Some(found) = self.collections.get(collection) else {
+                return ModuleLookup::UnknownCollection;
+            };
+            match found.module_routing.get(name) {
+                Some(ModuleRoute {
+                    tombstone: Some(tombstone),
+                    ..
+                }) => return ModuleLookup::Removed(tombstone.clone()),
+                Some(ModuleRoute {
+                    redirect: Some(redirect),
+                    ..
+                }) => module = redirect.clone(),
+                _ if found.modules.contains(name) => return ModuleLookup::Found,
+                _ => return ModuleLookup::Missing,
+            }
+        }
+        // Redirects that lead back to a module already seen
+        ModuleLookup::Missing
+    }
+}
+
+/// Split `namespace.collection.item` into `namespace.collection` and `item`
+pub fn split_fqcn(name: &str) -> Option<(&str, &str)> {
+    let (collection, item) = name.rsplit_once('.')?;
+    let valid =
+        |part: &str| !part.is_empty() && part.chars().all(|c| c.is_alphanumeric() || c == '_');
+    let (namespace, collection_name) = collection.split_once('.')?;
+    (valid(namespace) && valid(collection_name) && valid(item)).then_some((collection, item))
+}
+
+/// Collections paths searched for a playbook: the `collections` directory next
+/// to it, then `configured`
+pub fn playbook_collections_paths(playbook: &Path, configured: &[PathBuf]) -> Vec<PathBuf> {
+    let playbook_dir = playbook.parent().unwrap_or_else(|| Path::new("."));
+    std::iter::once(playbook_dir.join("collections"))
+        .chain(configured.iter().cloned())
+        .collect()
+}
+
+/// Default collections paths: `~/.ansible/collections` and `/usr/share/ansible/collections`
+pub fn default_collections_paths() -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    if let Some(home) = std::env::var_os("HOME") {
+        paths.push(PathBuf::from(home).join(".ansible").join("collections"));
+    }
+    paths.push(PathBuf::from("/usr/share/ansible/collections"));
+    paths
+}
+
+fn sorted_entries(dir: &Path) -> Result<Vec<PathBuf>, ParseError> {
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut entries = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort();
+    Ok(entries)
+}
+
+/// Every file below `dir`, recursively; modules may be grouped in subdirectories
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), ParseError> {
+    for entry in sorted_entries(dir)? {
+        if entry.is_dir() {
+            collect_files(&entry, files)?;
+        } else {
+            files.push(entry);
+        }
+    }
+    Ok(())
+}
+
+fn sorted_dirs(dir: &Path) -> Result<Vec<PathBuf>, ParseError> {
+    Ok(sorted_entries(dir)?
+        .into_iter()
+        .filter(|path| path.is_dir())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn install(root: &Path, namespace: &str, name: &str) -> PathBuf {
+        let path = root.join("ansible_collections").join(namespace).join(name);
+        fs::create_dir_all(path.join("plugins").join("modules")).unwrap();
+        fs::create_dir_all(path.join("roles").join("firewall").join("tasks")).unwrap();
+        fs::write(
+            path.join("galaxy.yml"),
+            format!("namespace: {namespace}\nname: {name}\nversion: 1.2.0\n"),
+        )
+        .unwrap();
+        fs::write(path.join("plugins").join("modules").join("ufw.py"), "").unwrap();
+        fs::write(path.join("plugins").join("modules").join("__init__.py"), "").unwrap();
+        path
+    }
+
+    #[test]
+    fn test_nested_and_powershell_modules() {
+        let root = TempDir::new().unwrap();
+        let path = install(root.path(), "community", "windows");
+        let modules = path.join("plugins").join("modules");
+        fs::create_dir_all(modules.join("net")).unwrap();
+        fs::write(modules.join("net").join("firewall.py"), "").unwrap();
+        fs::write(modules.join("win_feature.ps1"), "").unwrap();
+        fs::write(modules.join("README.md"), "").unwrap();
+
+        let index = CollectionIndex::load(&[root.path().to_path_buf()]).unwrap();
+        let windows = index.get("community.windows").unwrap();
+        assert_eq!(
+            windows.modules,
+            BTreeSet::from([
+                "firewall".to_string(),
+                "ufw".to_string(),
+                "win_feature".to_string(),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_index_and_lookup() {
+        let first = TempDir::new().unwrap();
+        let second = TempDir::new().unwrap();
+        let path = install(first.path(), "community", "general");
+        install(second.path(), "community", "general");
+        install(second.path(), "acme", "web");
+        let installed = second.path().join("ansible_collections/acme/web");
+        fs::remove_file(installed.join("galaxy.yml")).unwrap();
+        fs::write(
+            installed.join("MANIFEST.json"),
+            r#"{"collection_info": {"namespace": "acme", "name": "web", "version": "3.1.0", "dependencies": {"community.general": ">=1.0.0"}}}"#,
+        )
+        .unwrap();
+
+        let index =
+            CollectionIndex::load(&[first.path().to_path_buf(), second.path().to_path_buf()])
+                .unwrap();
+        let general = index.get("community.general").unwrap();
+        assert_eq!(general.path, path);
+        assert_eq!(general.version.as_deref(), Some("1.2.0"));
+        assert_eq!(general.modules, BTreeSet::from(["ufw".to_string()]));
+        assert_eq!(index.collections().count(), 2);
+        let web = index.get("acme.web").unwrap();
+        assert_eq!(web.version.as_deref(), Some("3.1.0"));
+        assert_eq!(web.dependencies["community.general"], ">=1.0.0");
+
+        let (collection, role_path) = index
+            .resolve_role("community.general.firewall", &[])
+            .unwrap();
+        assert_eq!(collection.fqcn(), "community.general");
+        assert_eq!(role_path, path.join("roles").join("firewall"));
+        assert!(index
+            .resolve_role("firewall", &["acme.web".to_string()])
+            .is_some());
+        assert!(index.resolve_role("firewall", &[]).is_none());
+        assert!(index
+            .resolve_role("community.general.missing", &[])
+            .is_none());
+
+        assert_eq!(
+            index.lookup_module("community.general.ufw"),
+            ModuleLookup::Found
+        );
+        assert_eq!(
+            index.lookup_module("ansible.builtin.copy"),
+            ModuleLookup::Found
+        );
+        assert_eq!(
+            index.lookup_module("community.general.nope"),
+            ModuleLookup::Missing
+        );
+        assert_eq!(
+            index.lookup_module("other.coll.x"),
+            ModuleLookup::UnknownCollection
+        );
+    }
+
+    #[test]
+    fn test_lookup_follows_plugin_routing() {
+        let root = TempDir::new().unwrap();
+        let path = install(root.path(), "community", "general");
+        install(root.path(), "community", "network");
+        fs::create_dir_all(path.join("meta")).unwrap();
+        fs::write(
+            path.join("meta").join("runtime.yml"),
+            r#"requires_ansible: ">=2.14"
+plugin_routing:
+  modules:
+    old_ufw:
+      redirect: community.general.ufw
+      deprecation:
+        warning_text: use ufw
+    net_ufw:
+      redirect: community.network.ufw
+    elsewhere:
+      redirect: other.coll.thing
+    gone:
+      tombstone:
+        removal_version: 2.0.0
+        warning_text: use something else
+    loop_a:
+      redirect: community.general.loop_b
+    loop_b:
+      redirect: community.general.loop_a
+"#,
+        )
+        .unwrap();
+
+        let index = CollectionIndex::load(&[root.path().to_path_buf()]).unwrap();
+        for (module, expected) in [
+            ("community.general.old_ufw", ModuleLookup::Found),
+            ("community.general.net_ufw", ModuleLookup::Found),
+            (
+                "community.general.elsewhere",
+                ModuleLookup::UnknownCollection,
+            ),
+            ("community.general.loop_a", ModuleLookup::Missing),
+        ] {
+            assert_eq!(index.lookup_module(module), expected, "{module}");
+        }
+        match index.lookup_module("community.general.gone") {
+            ModuleLookup::Removed(tombstone) => {
+                assert_eq!(
+                    tombstone.warning_text.as_deref(),
+                    Some("use something else")
+                )
+            }
+            other => panic!("unexpected lookup: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_split_fqcn() {
+        assert_eq!(split_fqcn("ns.coll.role"), Some(("ns.coll", "role")));
+        assert_eq!(split_fqcn("geerlingguy.nginx"), None);
+        assert_eq!(split_fqcn("nginx"), None);
+        assert_eq!(split_fqcn("a..b"), None);
+    }
+}
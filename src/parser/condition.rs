@@ -0,0 +1,734 @@
+use crate::parser::error::ParseError;
+use crate::types::parsed::{BinaryOp, CallArg, Condition, ConditionExpr, SourceLocation, UnaryOp};
+use minijinja::Environment;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Deserializer};
+
+/// Environment used only to check condition syntax
+static SYNTAX_CHECK: Lazy<Environment<'static>> = Lazy::new(Environment::new);
+
+const KEYWORDS: &[&str] = &["and", "or", "not", "in", "is", "if", "else"];
+
+/// Parse a `when` expression into a [`Condition`].
+///
+/// Syntax is checked with minijinja, so anything it rejects is reported here
+/// with `location`. Conditions written as a single `{{ ... }}` template are
+/// parsed from their inner expression; other templated conditions are kept
+/// as-is because their expression is only known after rendering.
+pub fn parse_condition(expression: &str, location: &str) -> Result<Condition, ParseError> {
+    let trimmed = expression.trim();
+    let source = strip_template_delimiters(trimmed).unwrap_or(trimmed);
+
+    if source.contains("{{") || source.contains("{%") {
+        return Ok(Condition {
+            expression: expression.to_string(),
+            ast: None,
+        });
+    }
+
+    SYNTAX_CHECK
+        .compile_expression(source)
+        .map_err(|e| ParseError::InvalidCondition {
+            condition: expression.to_string(),
+            location: location.to_string(),
+            message: e.to_string(),
+        })?;
+
+    Ok(Condition {
+        expression: expression.to_string(),
+        ast: ExprParser::new(source).and_then(|p| p.parse()).ok(),
+    })
+}
+
+/// Parse every raw condition of a task, keeping their order
+pub fn parse_conditions(
+    expressions: Option<Vec<String>>,
+    location: &str,
+) -> Result<Vec<Condition>, ParseError> {
+    expressions
+        .unwrap_or_default()
+        .iter()
+        .map(|expression| parse_condition(expression, location))
+        .collect()
+}
+
+/// Where a task's conditions came from, for error messages
+pub fn condition_location(source: Option<&SourceLocation>, task_name: &str) -> String {
+    match source {
+        Some(location) => location.to_string(),
+        None => format!("task '{task_name}'"),
+    }
+}
+
+/// Prepend conditions of an enclosing include so they are evaluated first
+pub fn inherit_conditions(conditions: &mut Vec<Condition>, inherited: &[Condition]) {
+    conditions.splice(0..0, inherited.iter().cloned());
+}
+
+/// Deserialize `when` from a string, a bare boolean or number, or a list of those
+pub fn deserialize_conditions<'de, D>(deserializer: D) -> Result<Option<Vec<String>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    use serde::de::Error;
+
+    fn scalar_to_string<E: Error>(value: serde_json::Value) -> Result<String, E> {
+        match value {
+            serde_json::Value::String(s) => Ok(s),
+            serde_json::Value::Bool(b) => Ok(b.to_string()),
+            serde_json::Value::Number(n) => Ok(n.to_string()),
+            other => Err(E::custom(format!(
+                "Expected condition string or boolean, found {other}"
+            ))),
+        }
+    }
+
+    match Option::<serde_json::Value>::deserialize(deserializer)? {
+        None | Some(serde_json::Value::Null) => Ok(None),
+        Some(serde_json::Value::Array(items)) => items
+            .into_iter()
+            .map(scalar_to_string::<D::Error>)
+            .collect::<Result<Vec<_>, _>>()
+            .map(Some),
+        Some(value) => Ok(Some(vec![scalar_to_string::<D::Error>(value)?])),
+    }
+}
+
+/// Inner expression of a condition written entirely as `{{ expr }}`
+fn strip_template_delimiters(expression: &str) -> Option<&str> {
+    let inner = expression.strip_prefix("{{")?.strip_suffix("}}")?;
+    if inner.contains("{{") || inner.contains("}}") {
+        return None;
+    }
+    Some(inner.trim())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Name(String),
+    Str(String),
+    Int(i64),
+    Float(f64),
+    Op(&'static str),
+    End,
+}
+
+const OPERATORS: &[&str] = &[
+    "//", "**", "==", "!=", "<=", ">=", "+", "-", "*", "/", "%", "~", "<", ">", "(", ")", "[", "]",
+    "{", "}", ",", ".", ":", "|", "=",
+];
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+
+    while pos < chars.len() {
+        let c = chars[pos];
+        if c.is_whitespace() {
+            pos += 1;
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let start = pos;
+            while pos < chars.len() && (chars[pos].is_ascii_alphanumeric() || chars[pos] == '_') {
+                pos += 1;
+            }
+            tokens.push(Token::Name(chars[start..pos].iter().collect()));
+        } else if c.is_ascii_digit() {
+            let start = pos;
+            while pos < chars.len() && (chars[pos].is_ascii_digit() || chars[pos] == '_') {
+                pos += 1;
+            }
+            let is_float =
+                pos + 1 < chars.len() && chars[pos] == '.' && chars[pos + 1].is_ascii_digit();
+            if is_float {
+                pos += 1;
+                while pos < chars.len() && (chars[pos].is_ascii_digit() || chars[pos] == '_') {
+                    pos += 1;
+                }
+            }
+            let text: String = chars[start..pos].iter().filter(|c| **c != '_').collect();
+            tokens.push(if is_float {
+                Token::Float(text.parse().map_err(|_| format!("invalid number {text}"))?)
+            } else {
+                Token::Int(text.parse().map_err(|_| format!("invalid number {text}"))?)
+            });
+        } else if c == '\'' || c == '"' {
+            let mut value = String::new();
+            pos += 1;
+            loop {
+                match chars.get(pos) {
+                    None => return Err("unterminated string".to_string()),
+                    Some(&ch) if ch == c => break,
+                    Some('\\') => {
+                        pos += 1;
+                        match chars.get(pos) {
+                            Some('n') => value.push('\n'),
+                            Some('t') => value.push('\t'),
+                            Some('r') => value.push('\r'),
+                            Some(&other) => value.push(other),
+                            None => return Err("unterminated string".to_string()),
+                        }
+                    }
+                    Some(&ch) => value.push(ch),
+                }
+                pos += 1;
+            }
+            pos += 1;
+            tokens.push(Token::Str(value));
+        } else {
+            let rest: String = chars[pos..chars.len().min(pos + 2)].iter().collect();
+            let op = OPERATORS
+                .iter()
+                .find(|op| rest.starts_with(**op))
+                .ok_or_else(|| format!("unexpected character '{c}'"))?;
+            pos += op.len();
+            tokens.push(Token::Op(op));
+        }
+    }
+
+    tokens.push(Token::End);
+    Ok(tokens)
+}
+
+/// Recursive descent parser following Jinja's expression grammar
+struct ExprParser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+type ParseResult = Result<ConditionExpr, String>;
+
+impl ExprParser {
+    fn new(source: &str) -> Result<Self, String> {
+        Ok(Self {
+            tokens: tokenize(source)?,
+            pos: 0,
+        })
+    }
+
+    fn parse(mut self) -> ParseResult {
+        let expr = self.parse_expr()?;
+        match self.peek() {
+            Token::End => Ok(expr),
+            token => Err(format!("unexpected {token:?}")),
+        }
+    }
+
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos]
+    }
+
+    fn peek_at(&self, offset: usize) -> &Token {
+        self.tokens.get(self.pos + offset).unwrap_or(&Token::End)
+    }
+
+    fn next(&mut self) -> Token {
+        let token = self.tokens[self.pos].clone();
+        if token != Token::End {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn eat_op(&mut self, op: &str) -> bool {
+        if matches!(self.peek(), Token::Op(o) if *o == op) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn eat_name(&mut self, name: &str) -> bool {
+        if matches!(self.peek(), Token::Name(n) if n == name) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect_op(&mut self, op: &str) -> Result<(), String> {
+        if self.eat_op(op) {
+            Ok(())
+        } else {
+            Err(format!("expected '{op}', found {:?}", self.peek()))
+        }
+    }
+
+    fn expect_name(&mut self) -> Result<String, String> {
+        match self.next() {
+            Token::Name(name) => Ok(name),
+            token => Err(format!("expected name, found {token:?}")),
+        }
+    }
+
+    fn parse_expr(&mut self) -> ParseResult {
+        let then = self.parse_or()?;
+        if !self.eat_name("if") {
+            return Ok(then);
+        }
+        let condition = self.parse_or()?;
+        let otherwise = if self.eat_name("else") {
+            Some(Box::new(self.parse_expr()?))
+        } else {
+            None
+        };
+        Ok(ConditionExpr::IfElse {
+            condition: Box::new(condition),
+            then: Box::new(then),
+            otherwise,
+        })
+    }
+
+    fn parse_or(&mut self) -> ParseResult {
+        let mut left = self.parse_and()?;
+        while self.eat_name("or") {
+            left = binary(BinaryOp::Or, left, self.parse_and()?);
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> ParseResult {
+        let mut left = self.parse_not()?;
+        while self.eat_name("and") {
+            left = binary(BinaryOp::And, left, self.parse_not()?);
+        }
+        Ok(left)
+    }
+
+    fn parse_not(&mut self) -> ParseResult {
+        if self.eat_name("not") {
+            return Ok(ConditionExpr::Unary {
+                op: UnaryOp::Not,
+                operand: Box::new(self.parse_not()?),
+            });
+        }
+        self.parse_compare()
+    }
+
+    fn parse_compare(&mut self) -> ParseResult {
+        let mut left = self.parse_concat()?;
+        loop {
+            let op = match self.peek() {
+                Token::Op("==") => BinaryOp::Eq,
+                Token::Op("!=") => BinaryOp::Ne,
+                Token::Op("<") => BinaryOp::Lt,
+                Token::Op("<=") => BinaryOp::Le,
+                Token::Op(">") => BinaryOp::Gt,
+                Token::Op(">=") => BinaryOp::Ge,
+                Token::Name(n) if n == "in" => BinaryOp::In,
+                Token::Name(n)
+                    if n == "not" && matches!(self.peek_at(1), Token::Name(m) if m == "in") =>
+                {
+                    self.pos += 1;
+                    BinaryOp::NotIn
+                }
+                _ => return Ok(left),
+            };
+            self.pos += 1;
+            left = binary(op, left, self.parse_concat()?);
+        }
+    }
+
+    fn parse_concat(&mut self) -> ParseResult {
+        let mut left = self.parse_add()?;
+        while self.eat_op("~") {
+            left = binary(BinaryOp::Concat, left, self.parse_add()?);
+        }
+        Ok(left)
+    }
+
+    fn parse_add(&mut self) -> ParseResult {
+        let mut left = self.parse_mul()?;
+        loop {
+            let op = if self.eat_op("+") {
+                BinaryOp::Add
+            } else if self.eat_op("-") {
+                BinaryOp::Sub
+            } else {
+                return Ok(left);
+            };
+            left = binary(op, left, self.parse_mul()?);
+        }
+    }
+
+    fn parse_mul(&mut self) -> ParseResult {
+        let mut left = self.parse_pow()?;
+        loop {
+            let op = if self.eat_op("*") {
+                BinaryOp::Mul
+            } else if self.eat_op("//") {
+                BinaryOp::FloorDiv
+            } else if self.eat_op("/") {
+                BinaryOp::Div
+            } else if self.eat_op("%") {
+                BinaryOp::Rem
+            } else {
+                return Ok(left);
+            };
+            left = binary(op, left, self.parse_pow()?);
+        }
+    }
+
+    fn parse_pow(&mut self) -> ParseResult {
+        let mut left = self.parse_unary(true)?;
+        while self.eat_op("**") {
+            left = binary(BinaryOp::Pow, left, self.parse_unary(true)?);
+        }
+        Ok(left)
+    }
+
+    fn parse_unary(&mut self, with_filters: bool) -> ParseResult {
+        let expr = if self.eat_op("-") {
+            ConditionExpr::Unary {
+                op: UnaryOp::Neg,
+                operand: Box::new(self.parse_unary(false)?),
+            }
+        } else if self.eat_op("+") {
+            ConditionExpr::Unary {
+                op: UnaryOp::Pos,
+                operand: Box::new(self.parse_unary(false)?),
+            }
+        } else {
+            let primary = self.parse_primary()?;
+            self.parse_postfix(primary)?
+        };
+
+        if with_filters {
+            self.parse_filters_and_tests(expr)
+        } else {
+            Ok(expr)
+        }
+    }
+
+    fn parse_filters_and_tests(&mut self, mut expr: ConditionExpr) -> ParseResult {
+        loop {
+            if self.eat_op("|") {
+                let name = self.parse_dotted_name()?;
+                let args = if matches!(self.peek(), Token::Op("(")) {
+                    self.parse_call_args()?
+                } else {
+                    Vec::new()
+                };
+                expr = ConditionExpr::Filter {
+                    operand: Box::new(expr),
+                    name,
+                    args,
+                };
+            } else if self.eat_name("is") {
+                let negated = self.eat_name("not");
+                let name = self.parse_dotted_name()?;
+                let args = if matches!(self.peek(), Token::Op("(")) {
+                    self.parse_call_args()?
+                } else if self.starts_test_argument() {
+                    // Jinja allows a single argument without parentheses: `x is divisibleby 3`
+                    let primary = self.parse_primary()?;
+                    vec![CallArg {
+                        name: None,
+                        value: self.parse_postfix(primary)?,
+                    }]
+                } else {
+                    Vec::new()
+                };
+                expr = ConditionExpr::Test {
+                    operand: Box::new(expr),
+                    name,
+                    args,
+                    negated,
+                };
+            } else {
+                return Ok(expr);
+            }
+        }
+    }
+
+    fn starts_test_argument(&self) -> bool {
+        match self.peek() {
+            Token::Str(_) | Token::Int(_) | Token::Float(_) => true,
+            Token::Name(name) => !KEYWORDS.contains(&name.as_str()),
+            Token::Op(op) => *op == "[",
+            Token::End => false,
+        }
+    }
+
+    fn parse_dotted_name(&mut self) -> Result<String, String> {
+        let mut name = self.expect_name()?;
+        while matches!(self.peek(), Token::Op(".")) && matches!(self.peek_at(1), Token::Name(_)) {
+            self.pos += 1;
+            name.push('.');
+            name.push_str(&self.expect_name()?);
+        }
+        Ok(name)
+    }
+
+    fn parse_primary(&mut self) -> ParseResult {
+        match self.next() {
+            Token::Name(name) => Ok(match name.as_str() {
+                "true" | "True" => literal(true.into()),
+                "false" | "False" => literal(false.into()),
+                "none" | "None" => literal(serde_json::Value::Null),
+                _ if KEYWORDS.contains(&name.as_str()) => {
+                    return Err(format!("unexpected keyword '{name}'"))
+                }
+                _ => ConditionExpr::Name { name },
+            }),
+            Token::Str(mut value) => {
+                // Adjacent string literals are concatenated
+                while let Token::Str(next) = self.peek().clone() {
+                    value.push_str(&next);
+                    self.pos += 1;
+                }
+                Ok(literal(value.into()))
+            }
+            Token::Int(value) => Ok(literal(value.into())),
+            Token::Float(value) => serde_json::Number::from_f64(value)
+                .map(|n| literal(serde_json::Value::Number(n)))
+                .ok_or_else(|| format!("invalid number {value}")),
+            Token::Op("(") => {
+                if self.eat_op(")") {
+                    return Ok(ConditionExpr::List { items: Vec::new() });
+                }
+                let first = self.parse_expr()?;
+                if !self.eat_op(",") {
+                    self.expect_op(")")?;
+                    return Ok(first);
+                }
+                // Tuples are represented as lists
+                let mut items = vec![first];
+                while !self.eat_op(")") {
+                    items.push(self.parse_expr()?);
+                    if !self.eat_op(",") {
+                        self.expect_op(")")?;
+                        break;
+                    }
+                }
+                Ok(ConditionExpr::List { items })
+            }
+            Token::Op("[") => {
+                let mut items = Vec::new();
+                while !self.eat_op("]") {
+                    items.push(self.parse_expr()?);
+                    if !self.eat_op(",") {
+                        self.expect_op("]")?;
+                        break;
+                    }
+                }
+                Ok(ConditionExpr::List { items })
+            }
+            Token::Op("{") => {
+                let mut entries = Vec::new();
+                while !self.eat_op("}") {
+                    let key = self.parse_expr()?;
+                    self.expect_op(":")?;
+                    entries.push((key, self.parse_expr()?));
+                    if !self.eat_op(",") {
+                        self.expect_op("}")?;
+                        break;
+                    }
+                }
+                Ok(ConditionExpr::Dict { entries })
+            }
+            token => Err(format!("unexpected {token:?}")),
+        }
+    }
+
+    fn parse_postfix(&mut self, mut expr: ConditionExpr) -> ParseResult {
+        loop {
+            if self.eat_op(".") {
+                expr = match self.next() {
+                    Token::Name(attr) => ConditionExpr::GetAttr {
+                        object: Box::new(expr),
+                        attr,
+                    },
+                    Token::Int(index) => ConditionExpr::GetItem {
+                        object: Box::new(expr),
+                        index: Box::new(literal(index.into())),
+                    },
+                    token => return Err(format!("unexpected {token:?} after '.'")),
+                };
+            } else if self.eat_op("[") {
+                let index = self.parse_expr()?;
+                self.expect_op("]")?;
+                expr = ConditionExpr::GetItem {
+                    object: Box::new(expr),
+                    index: Box::new(index),
+                };
+            } else if matches!(self.peek(), Token::Op("(")) {
+                expr = ConditionExpr::Call {
+                    function: Box::new(expr),
+                    args: self.parse_call_args()?,
+                };
+            } else {
+                return Ok(expr);
+            }
+        }
+    }
+
+    fn parse_call_args(&mut self) -> Result<Vec<CallArg>, String> {
+        self.expect_op("(")?;
+        let mut args = Vec::new();
+        while !self.eat_op(")") {
+            let name = match (self.peek(), self.peek_at(1)) {
+                (Token::Name(name), Token::Op("=")) => {
+                    let name = name.clone();
+                    self.pos += 2;
+                    Some(name)
+                }
+                _ => None,
+            };
+            args.push(CallArg {
+                name,
+                value: self.parse_expr()?,
+            });
+            if !self.eat_op(",") {
+                self.expect_op(")")?;
+                break;
+            }
+        }
+        Ok(args)
+    }
+}
+
+fn literal(value: serde_json::Value) -> ConditionExpr {
+    ConditionExpr::Literal { value }
+}
+
+fn binary(op: BinaryOp, left: ConditionExpr, right: ConditionExpr) -> ConditionExpr {
+    ConditionExpr::Binary {
+        op,
+        left: Box::new(left),
+        right: Box::new(right),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ast(expression: &str) -> ConditionExpr {
+        parse_condition(expression, "test")
+            .unwrap()
+            .ast
+            .unwrap_or_else(|| panic!("no AST for {expression}"))
+    }
+
+    fn name(name: &str) -> ConditionExpr {
+        ConditionExpr::Name {
+            name: name.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parse_comparison_and_logic() {
+        assert_eq!(
+            ast("ansible_os_family == 'Debian' and not skip"),
+            binary(
+                BinaryOp::And,
+                binary(
+                    BinaryOp::Eq,
+                    name("ansible_os_family"),
+                    literal("Debian".into())
+                ),
+                ConditionExpr::Unary {
+                    op: UnaryOp::Not,
+                    operand: Box::new(name("skip")),
+                },
+            )
+        );
+
+        assert_eq!(
+            ast("'web' not in group_names"),
+            binary(BinaryOp::NotIn, literal("web".into()), name("group_names"))
+        );
+    }
+
+    #[test]
+    fn test_parse_tests_and_filters() {
+        assert_eq!(
+            ast("result.rc is not defined"),
+            ConditionExpr::Test {
+                operand: Box::new(ConditionExpr::GetAttr {
+                    object: Box::new(name("result")),
+                    attr: "rc".to_string(),
+                }),
+                name: "defined".to_string(),
+                args: Vec::new(),
+                negated: true,
+            }
+        );
+
+        assert_eq!(
+            ast("enable_feature | bool"),
+            ConditionExpr::Filter {
+                operand: Box::new(name("enable_feature")),
+                name: "bool".to_string(),
+                args: Vec::new(),
+            }
+        );
+
+        match ast("ansible_distribution_version is version('22.04', '>=', strict=true)") {
+            ConditionExpr::Test { name, args, .. } => {
+                assert_eq!(name, "version");
+                assert_eq!(args.len(), 3);
+                assert_eq!(args[2].name.as_deref(), Some("strict"));
+            }
+            other => panic!("unexpected {other:?}"),
+        }
+
+        // `not` binds looser than `is`, and filters apply before comparisons
+        assert!(matches!(
+            ast("not x is defined"),
+            ConditionExpr::Unary {
+                op: UnaryOp::Not,
+                ..
+            }
+        ));
+        assert!(matches!(
+            ast("items | length > 0"),
+            ConditionExpr::Binary {
+                op: BinaryOp::Gt,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_parse_literals_and_templates() {
+        assert_eq!(ast("false"), literal(false.into()));
+        assert_eq!(ast("{{ enabled }}"), name("enabled"));
+        assert_eq!(
+            ast("hostvars[inventory_hostname]['port'] == 80"),
+            binary(
+                BinaryOp::Eq,
+                ConditionExpr::GetItem {
+                    object: Box::new(ConditionExpr::GetItem {
+                        object: Box::new(name("hostvars")),
+                        index: Box::new(name("inventory_hostname")),
+                    }),
+                    index: Box::new(literal("port".into())),
+                },
+                literal(80.into()),
+            )
+        );
+
+        let templated = parse_condition("x == '{{ y }}' or {{ z }}", "test").unwrap();
+        assert!(templated.ast.is_none());
+    }
+
+    #[test]
+    fn test_syntax_error_reports_location() {
+        let err = parse_condition("x ==", "site.yml:4:7").unwrap_err();
+        match err {
+            ParseError::InvalidCondition {
+                condition,
+                location,
+                ..
+            } => {
+                assert_eq!(condition, "x ==");
+                assert_eq!(location, "site.yml:4:7");
+            }
+            other => panic!("unexpected error {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,633 @@
+use crate::parser::collections;
+use crate::parser::error::ParseError;
+use crate::parser::include::IncludeConfig;
+use crate::parser::inventory::ini::InventoryParserConfig;
+use crate::parser::template::TemplateEngine;
+use crate::parser::yaml::YamlLimits;
+use configparser::ini::Ini;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// How variables holding dictionaries combine when defined more than once
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HashBehaviour {
+    /// The higher precedence dictionary replaces the other
+    #[default]
+    Replace,
+    /// Dictionaries are merged recursively, higher precedence keys winning
+    Merge,
+}
+
+impl FromStr for HashBehaviour {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_lowercase().as_str() {
+            "replace" => Ok(Self::Replace),
+            "merge" => Ok(Self::Merge),
+            other => Err(format!(
+                "invalid hash_behaviour '{other}', expected 'replace' or 'merge'"
+            )),
+        }
+    }
+}
+
+/// What to do with inventory group names that are not valid variable names
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TransformInvalidGroupChars {
+    /// Keep the name and warn
+    #[default]
+    Never,
+    /// Replace invalid characters with `_` and warn
+    Always,
+    /// Replace invalid characters with `_` without warning
+    Silently,
+    /// Keep the name without warning
+    Ignore,
+}
+
+impl TransformInvalidGroupChars {
+    /// Whether invalid characters are replaced
+    pub fn transforms(self) -> bool {
+        matches!(self, Self::Always | Self::Silently)
+    }
+
+    /// Whether invalid names are reported
+    pub fn warns(self) -> bool {
+        matches!(self, Self::Never | Self::Always)
+    }
+}
+
+impl FromStr for TransformInvalidGroupChars {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_lowercase().as_str() {
+            "never" => Ok(Self::Never),
+            "always" => Ok(Self::Always),
+            "silently" => Ok(Self::Silently),
+            "ignore" => Ok(Self::Ignore),
+            // Older Ansible releases took a boolean
+            other => match parse_bool(other) {
+                Some(true) => Ok(Self::Always),
+                Some(false) => Ok(Self::Never),
+                None => Err(format!(
+                    "invalid force_valid_group_names '{other}', expected never, always, silently or ignore"
+                )),
+            },
+        }
+    }
+}
+
+/// An entry of `vault_identity_list`: `label@source`, or just `source`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VaultIdentity {
+    pub label: Option<String>,
+    /// A password file, or `prompt`
+    pub source: String,
+}
+
+impl VaultIdentity {
+    /// Parse an entry, passing password file sources through `resolve`
+    fn parse(entry: &str, resolve: impl Fn(&str) -> PathBuf) -> Self {
+        let (label, source) = match entry.split_once('@') {
+            Some((label, source)) => (Some(label.to_string()), source),
+            None => (None, entry),
+        };
+        let source = if source == "prompt" {
+            source.to_string()
+        } else {
+            resolve(source).to_string_lossy().into_owned()
+        };
+        Self { label, source }
+    }
+
+    /// The password file, unless the password is prompted for
+    pub fn password_file(&self) -> Option<PathBuf> {
+        (self.source != "prompt").then(|| PathBuf::from(&self.source))
+    }
+}
+
+/// Settings read from `ansible.cfg` and `ANSIBLE_*` environment variables.
+///
+/// The environment overrides the file, and command line options override both.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnsibleConfig {
+    /// The configuration file that was read, if any
+    pub config_file: Option<PathBuf>,
+    /// Directories searched for roles after the ones next to the playbook
+    pub roles_path: Vec<PathBuf>,
+    /// Directories holding `ansible_collections` trees
+    pub collections_paths: Vec<PathBuf>,
+    /// Default inventory sources
+    pub inventory: Vec<PathBuf>,
+    pub vault_password_file: Option<PathBuf>,
+    pub vault_identity_list: Vec<VaultIdentity>,
+    pub hash_behaviour: HashBehaviour,
+    /// Render templates that are a single expression to native values
+    pub jinja2_native: bool,
+    pub transform_invalid_group_chars: TransformInvalidGroupChars,
+}
+
+impl Default for AnsibleConfig {
+    fn default() -> Self {
+        Self {
+            config_file: None,
+            roles_path: default_roles_path(),
+            collections_paths: collections::default_collections_paths(),
+            inventory: Vec::new(),
+            vault_password_file: None,
+            vault_identity_list: Vec::new(),
+            hash_behaviour: HashBehaviour::default(),
+            jinja2_native: false,
+            transform_invalid_group_chars: TransformInvalidGroupChars::default(),
+        }
+    }
+}
+
+impl AnsibleConfig {
+    /// Read the configuration the way `ansible` does, from the first
+    /// configuration file found and the process environment
+    pub fn load() -> Result<Self, ParseError> {
+        let env: HashMap<String, String> = std::env::vars().collect();
+        let cwd = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+        let config_file = find_config_file(&env, &cwd);
+        Self::from_sources(config_file.as_deref(), &env)
+    }
+
+    /// Read `config_file`, if given, then apply the overrides in `env`
+    pub fn from_sources(
+        config_file: Option<&Path>,
+        env: &HashMap<String, String>,
+    ) -> Result<Self, ParseError> {
+        let mut config = Self::default();
+        if let Some(path) = config_file {
+            config.apply_file(path, env)?;
+            config.config_file = Some(path.to_path_buf());
+        }
+        config.apply_env(env)?;
+        Ok(config)
+    }
+
+    /// The vault password file to use when none is given on the command line:
+    /// `vault_password_file`, or else the first file in `vault_identity_list`
+    pub fn default_vault_password_file(&self) -> Option<PathBuf> {
+        self.vault_password_file.clone().or_else(|| {
+            self.vault_identity_list
+                .iter()
+                .find_map(VaultIdentity::password_file)
+        })
+    }
+
+    /// Parser settings with this configuration applied to the defaults
+    pub fn parser_config(&self) -> ParserConfig {
+        let mut config = ParserConfig::default();
+        self.apply_to(&mut config);
+        config
+    }
+
+    /// Override the settings of `config` that `ansible.cfg` covers
+    pub fn apply_to(&self, config: &mut ParserConfig) {
+        config.include.role_search_paths = self.roles_path.clone();
+        config.collections_paths = self.collections_paths.clone();
+        config.inventory.hash_behaviour = self.hash_behaviour;
+        config.inventory.transform_invalid_group_chars = self.transform_invalid_group_chars;
+        config.jinja2_native = self.jinja2_native;
+    }
+
+    fn apply_file(&mut self, path: &Path, env: &HashMap<String, String>) -> Result<(), ParseError> {
+        let invalid = |message: String| ParseError::InvalidConfig {
+            file: path.to_string_lossy().to_string(),
+            message,
+        };
+        let content = std::fs::read_to_string(path)?;
+        let mut ini = Ini::new();
+        ini.set_inline_comment_symbols(Some(&[';']));
+        ini.set_multiline(true);
+        ini.read(content).map_err(invalid)?;
+
+        // Relative paths are relative to the configuration file
+        let base_dir = path.parent().unwrap_or_else(|| Path::new("."));
+        let home = env.get("HOME").map(String::as_str);
+        let get = |key: &str| ini.get("defaults", key).filter(|value| !value.is_empty());
+        let paths = |value: &str, separator: char| -> Vec<PathBuf> {
+            split_list(value, separator)
+                .map(|entry| resolve_path(entry, base_dir, home))
+                .collect()
+        };
+
+        if let Some(value) = get("roles_path") {
+            self.roles_path = paths(&value, ':');
+        }
+        if let Some(value) = get("collections_path").or_else(|| get("collections_paths")) {
+            self.collections_paths = paths(&value, ':');
+        }
+        if let Some(value) = get("inventory").or_else(|| get("hostfile")) {
+            self.inventory = paths(&value, ',');
+        }
+        if let Some(value) = get("vault_password_file") {
+            self.vault_password_file = Some(resolve_path(&value, base_dir, home));
+        }
+        if let Some(value) = get("vault_identity_list") {
+            self.vault_identity_list = split_list(&value, ',')
+                .map(|entry| {
+                    VaultIdentity::parse(entry, |source| resolve_path(source, base_dir, home))
+                })
+                .collect();
+        }
+        if let Some(value) = get("hash_behaviour") {
+            self.hash_behaviour = value.parse().map_err(invalid)?;
+        }
+        if let Some(value) = get("jinja2_native") {
+            self.jinja2_native = parse_bool(&value)
+                .ok_or_else(|| invalid(format!("invalid jinja2_native '{value}'")))?;
+        }
+        if let Some(value) = get("force_valid_group_names") {
+            self.transform_invalid_group_chars = value.parse().map_err(invalid)?;
+        }
+        Ok(())
+    }
+
+    fn apply_env(&mut self, env: &HashMap<String, String>) -> Result<(), ParseError> {
+        let invalid = |message: String| ParseError::InvalidConfig {
+            file: "environment".to_string(),
+            message,
+        };
+        let home = env.get("HOME").map(String::as_str);
+        let get = |key: &str| env.get(key).filter(|value| !value.is_empty());
+        let paths = |value: &str, separator: char| -> Vec<PathBuf> {
+            split_list(value, separator)
+                .map(|entry| expand_home(entry, home))
+                .collect()
+        };
+
+        if let Some(value) = get("ANSIBLE_ROLES_PATH") {
+            self.roles_path = paths(value, ':');
+        }
+        if let Some(value) =
+            get("ANSIBLE_COLLECTIONS_PATH").or_else(|| get("ANSIBLE_COLLECTIONS_PATHS"))
+        {
+            self.collections_paths = paths(value, ':');
+        }
+        if let Some(value) = get("ANSIBLE_INVENTORY") {
+            self.inventory = paths(value, ',');
+        }
+        if let Some(value) = get("ANSIBLE_VAULT_PASSWORD_FILE") {
+            self.vault_password_file = Some(expand_home(value, home));
+        }
+        if let Some(value) = get("ANSIBLE_VAULT_IDENTITY_LIST") {
+            self.vault_identity_list = split_list(value, ',')
+                .map(|entry| VaultIdentity::parse(entry, |source| expand_home(source, home)))
+                .collect();
+        }
+        if let Some(value) = get("ANSIBLE_HASH_BEHAVIOUR") {
+            self.hash_behaviour = value.parse().map_err(invalid)?;
+        }
+        if let Some(value) = get("ANSIBLE_JINJA2_NATIVE") {
+            self.jinja2_native = parse_bool(value)
+                .ok_or_else(|| invalid(format!("invalid ANSIBLE_JINJA2_NATIVE '{value}'")))?;
+        }
+        if let Some(value) = get("ANSIBLE_TRANSFORM_INVALID_GROUP_CHARS") {
+            self.transform_invalid_group_chars = value.parse().map_err(invalid)?;
+        }
+        Ok(())
+    }
+}
+
+/// Settings threaded through every sub-parser of a [`Parser`](crate::parser::Parser)
+#[derive(Debug, Clone)]
+pub struct ParserConfig {
+    /// Include depth, caching, file access and role search paths
+    pub include: IncludeConfig,
+    /// Inventory strictness, pattern expansion and variable merging
+    pub inventory: InventoryParserConfig,
+    /// Limits for every YAML file loaded: playbooks, includes, roles and inventories
+    pub yaml_limits: YamlLimits,
+    /// Directories holding `ansible_collections` trees, searched after the
+    /// `collections` directory next to the playbook
+    pub collections_paths: Vec<PathBuf>,
+    /// Render templates that are a single expression to native values
+    pub jinja2_native: bool,
+    /// Fail on fully qualified modules missing from their installed
+    /// collection instead of warning
+    pub strict_modules: bool,
+}
+
+impl Default for ParserConfig {
+    fn default() -> Self {
+        Self {
+            include: IncludeConfig::default(),
+            inventory: InventoryParserConfig::default(),
+            yaml_limits: YamlLimits::default(),
+            collections_paths: collections::default_collections_paths(),
+            jinja2_native: false,
+            strict_modules: false,
+        }
+    }
+}
+
+impl ParserConfig {
+    /// Settings for the include handler, with the YAML limits applied
+    pub fn include_config(&self) -> IncludeConfig {
+        IncludeConfig {
+            yaml_limits: self.yaml_limits,
+            ..self.include.clone()
+        }
+    }
+
+    /// Settings for the inventory parser, with the YAML limits applied
+    pub fn inventory_config(&self) -> InventoryParserConfig {
+        InventoryParserConfig {
+            yaml_limits: self.yaml_limits,
+            ..self.inventory.clone()
+        }
+    }
+
+    /// A template engine with the configured rendering options
+    pub fn template_engine(&self) -> TemplateEngine {
+        TemplateEngine::new().with_native_types(self.jinja2_native)
+    }
+}
+
+/// The configuration file Ansible would read: `ANSIBLE_CONFIG` (a file, or a
+/// directory holding `ansible.cfg`), `./ansible.cfg`, `~/.ansible.cfg`, then
+/// `/etc/ansible/ansible.cfg`
+pub fn find_config_file(env: &HashMap<String, String>, cwd: &Path) -> Option<PathBuf> {
+    let home = env.get("HOME").map(String::as_str);
+    let mut candidates = Vec::new();
+    if let Some(value) = env.get("ANSIBLE_CONFIG").filter(|value| !value.is_empty()) {
+        let path = resolve_path(value, cwd, home);
+        if path.is_dir() {
+            candidates.push(path.join("ansible.cfg"));
+        } else {
+            candidates.push(path);
+        }
+    }
+    candidates.push(cwd.join("ansible.cfg"));
+    if let Some(home) = home {
+        candidates.push(Path::new(home).join(".ansible.cfg"));
+    }
+    candidates.push(PathBuf::from("/etc/ansible/ansible.cfg"));
+
+    candidates.into_iter().find(|path| path.is_file())
+}
+
+/// Ansible's default roles path: `~/.ansible/roles`, `/usr/share/ansible/roles`
+/// and `/etc/ansible/roles`
+pub fn default_roles_path() -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    if let Some(home) = std::env::var_os("HOME") {
+        paths.push(PathBuf::from(home).join(".ansible").join("roles"));
+    }
+    paths.push(PathBuf::from("/usr/share/ansible/roles"));
+    paths.push(PathBuf::from("/etc/ansible/roles"));
+    paths
+}
+
+fn split_list(value: &str, separator: char) -> impl Iterator<Item = &str> {
+    value
+        .split(separator)
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+}
+
+fn expand_home(value: &str, home: Option<&str>) -> PathBuf {
+    match (value.strip_prefix('~'), home) {
+        (Some(rest), Some(home)) if rest.is_empty() || rest.starts_with('/') => {
+            PathBuf::from(format!("{home}{rest}"))
+        }
+        _ => PathBuf::from(value),
+    }
+}
+
+fn resolve_path(value: &str, base_dir: &Path, home: Option<&str>) -> PathBuf {
+    let path = expand_home(value, home);
+    if path.is_absolute() {
+        path
+    } else {
+        base_dir.join(path)
+    }
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.trim().to_lowercase().as_str() {
+        "true" | "yes" | "on" | "y" | "1" => Some(true),
+        "false" | "no" | "off" | "n" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn env(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_find_config_file_order() {
+        let temp_dir = TempDir::new().unwrap();
+        let home = temp_dir.path().join("home");
+        let cwd = temp_dir.path().join("project");
+        let custom = temp_dir.path().join("custom");
+        for dir in [&home, &cwd, &custom] {
+            std::fs::create_dir_all(dir).unwrap();
+        }
+        let home_str = home.to_string_lossy().to_string();
+
+        let mut vars = env(&[("HOME", &home_str)]);
+        std::fs::write(home.join(".ansible.cfg"), "").unwrap();
+        assert_eq!(
+            find_config_file(&vars, &cwd),
+            Some(home.join(".ansible.cfg"))
+        );
+
+        std::fs::write(cwd.join("ansible.cfg"), "").unwrap();
+        assert_eq!(find_config_file(&vars, &cwd), Some(cwd.join("ansible.cfg")));
+
+        std::fs::write(custom.join("ansible.cfg"), "").unwrap();
+        vars.insert(
+            "ANSIBLE_CONFIG".to_string(),
+            custom.to_string_lossy().to_string(),
+        );
+        assert_eq!(
+            find_config_file(&vars, &cwd),
+            Some(custom.join("ansible.cfg"))
+        );
+
+        // A missing ANSIBLE_CONFIG falls through to the other locations
+        vars.insert("ANSIBLE_CONFIG".to_string(), "missing.cfg".to_string());
+        assert_eq!(find_config_file(&vars, &cwd), Some(cwd.join("ansible.cfg")));
+    }
+
+    #[test]
+    fn test_read_config_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("ansible.cfg");
+        std::fs::write(
+            &path,
+            r#"
+[defaults]
+roles_path = roles:~/shared/roles:/opt/roles
+collections_path = ./collections
+inventory = hosts.ini, /etc/ansible/hosts ; default inventories
+vault_password_file = ~/.vault_pass
+vault_identity_list = dev@~/.dev_pass, prod@secrets/prod, prompt
+hash_behaviour = merge
+jinja2_native = True
+force_valid_group_names = silently
+
+[ssh_connection]
+pipelining = True
+"#,
+        )
+        .unwrap();
+
+        let config =
+            AnsibleConfig::from_sources(Some(&path), &env(&[("HOME", "/home/me")])).unwrap();
+        let dir = temp_dir.path();
+
+        assert_eq!(config.config_file, Some(path.clone()));
+        assert_eq!(
+            config.roles_path,
+            vec![
+                dir.join("roles"),
+                PathBuf::from("/home/me/shared/roles"),
+                PathBuf::from("/opt/roles"),
+            ]
+        );
+        assert_eq!(config.collections_paths, vec![dir.join("./collections")]);
+        assert_eq!(
+            config.inventory,
+            vec![dir.join("hosts.ini"), PathBuf::from("/etc/ansible/hosts")]
+        );
+        assert_eq!(
+            config.vault_password_file,
+            Some(PathBuf::from("/home/me/.vault_pass"))
+        );
+        assert_eq!(
+            config.vault_identity_list,
+            vec![
+                VaultIdentity {
+                    label: Some("dev".to_string()),
+                    source: "/home/me/.dev_pass".to_string(),
+                },
+                VaultIdentity {
+                    label: Some("prod".to_string()),
+                    source: dir.join("secrets/prod").to_string_lossy().into_owned(),
+                },
+                VaultIdentity {
+                    label: None,
+                    source: "prompt".to_string(),
+                },
+            ]
+        );
+        assert_eq!(config.hash_behaviour, HashBehaviour::Merge);
+        assert!(config.jinja2_native);
+        assert_eq!(
+            config.transform_invalid_group_chars,
+            TransformInvalidGroupChars::Silently
+        );
+    }
+
+    #[test]
+    fn test_environment_overrides_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("ansible.cfg");
+        std::fs::write(
+            &path,
+            "[defaults]\nroles_path = roles\nhash_behaviour = merge\ninventory = hosts\n",
+        )
+        .unwrap();
+
+        let config = AnsibleConfig::from_sources(
+            Some(&path),
+            &env(&[
+                ("ANSIBLE_ROLES_PATH", "/a/roles:/b/roles"),
+                ("ANSIBLE_HASH_BEHAVIOUR", "replace"),
+                ("ANSIBLE_COLLECTIONS_PATHS", "/collections"),
+                ("ANSIBLE_VAULT_IDENTITY_LIST", "prod@/secrets/prod"),
+                ("ANSIBLE_TRANSFORM_INVALID_GROUP_CHARS", "always"),
+                ("ANSIBLE_JINJA2_NATIVE", "yes"),
+            ]),
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.roles_path,
+            vec![PathBuf::from("/a/roles"), PathBuf::from("/b/roles")]
+        );
+        assert_eq!(config.hash_behaviour, HashBehaviour::Replace);
+        assert_eq!(
+            config.collections_paths,
+            vec![PathBuf::from("/collections")]
+        );
+        assert_eq!(config.inventory, vec![temp_dir.path().join("hosts")]);
+        assert_eq!(
+            config.default_vault_password_file(),
+            Some(PathBuf::from("/secrets/prod"))
+        );
+        assert_eq!(
+            config.transform_invalid_group_chars,
+            TransformInvalidGroupChars::Always
+        );
+        assert!(config.jinja2_native);
+    }
+
+    #[test]
+    fn test_parser_config() {
+        let config = AnsibleConfig::from_sources(
+            None,
+            &env(&[
+                ("ANSIBLE_ROLES_PATH", "/roles"),
+                ("ANSIBLE_HASH_BEHAVIOUR", "merge"),
+                ("ANSIBLE_JINJA2_NATIVE", "true"),
+            ]),
+        )
+        .unwrap();
+
+        let mut parser_config = ParserConfig {
+            yaml_limits: YamlLimits {
+                max_depth: 10,
+                ..YamlLimits::default()
+            },
+            ..ParserConfig::default()
+        };
+        config.apply_to(&mut parser_config);
+
+        assert_eq!(
+            parser_config.include.role_search_paths,
+            vec![PathBuf::from("/roles")]
+        );
+        assert_eq!(parser_config.inventory.hash_behaviour, HashBehaviour::Merge);
+        assert!(parser_config.jinja2_native);
+        assert_eq!(parser_config.include_config().yaml_limits.max_depth, 10);
+        assert_eq!(parser_config.inventory_config().yaml_limits.max_depth, 10);
+    }
+
+    #[test]
+    fn test_invalid_values() {
+        let result = AnsibleConfig::from_sources(None, &env(&[("ANSIBLE_HASH_BEHAVIOUR", "deep")]));
+        assert!(matches!(result, Err(ParseError::InvalidConfig { .. })));
+
+        let result =
+            AnsibleConfig::from_sources(None, &env(&[("ANSIBLE_JINJA2_NATIVE", "sometimes")]));
+        assert!(matches!(result, Err(ParseError::InvalidConfig { .. })));
+    }
+
+    #[test]
+    fn test_defaults_without_configuration() {
+        let config = AnsibleConfig::from_sources(None, &HashMap::new()).unwrap();
+        assert_eq!(config.roles_path, default_roles_path());
+        assert!(config.inventory.is_empty());
+        assert_eq!(config.default_vault_password_file(), None);
+        assert_eq!(config.hash_behaviour, HashBehaviour::Replace);
+        assert!(!config.jinja2_native);
+    }
+}
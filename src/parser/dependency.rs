@@ -1,7 +1,7 @@
 use crate::parser::error::ParseError;
-use crate::types::parsed::ParsedPlay;
+use crate::types::parsed::{NotificationMap, ParsedPlay, ParsedTask, UnresolvedNotification};
 use petgraph::{graph::DiGraph, Graph};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 
 pub fn resolve_task_dependencies(plays: &[ParsedPlay]) -> Vec<String> {
     let mut all_tasks = Vec::new();
@@ -48,12 +48,21 @@ pub fn resolve_task_dependencies_with_graph(
                         graph.add_edge(*dep_node, *task_node, ());
                     }
                 }
+            }
+        }
 
-                // Add edges for handler notifications
-                for notify in &task.notify {
-                    if let Some(handler_node) = task_indices.get(notify) {
-                        graph.add_edge(*task_node, *handler_node, ());
-                    }
+        // Add edges for handler notifications, resolved by name and listen topic
+        let notifications = resolve_notifications(&play.tasks, &play.handlers);
+        for task in &play.tasks {
+            let (Some(task_node), Some(triggered)) = (
+                task_indices.get(&task.id),
+                notifications.triggers.get(&task.id),
+            ) else {
+                continue;
+            };
+            for handler_id in triggered {
+                if let Some(handler_node) = task_indices.get(handler_id) {
+                    graph.add_edge(*task_node, *handler_node, ());
                 }
             }
         }
@@ -67,10 +76,101 @@ pub fn resolve_task_dependencies_with_graph(
         .map(|sorted| sorted.into_iter().map(|node| graph[node].clone()).collect())
 }
 
+/// Resolve every `notify` entry of a play's tasks and handlers to the handlers it triggers.
+///
+/// Targets match a handler's name (the last handler defined with that name wins,
+/// as in Ansible), every handler listening on that topic, or a handler's id.
+/// Notifications chain through handlers that notify other handlers, so each
+/// entry in the returned map holds the full set of handlers that would run.
+pub fn resolve_notifications(tasks: &[ParsedTask], handlers: &[ParsedTask]) -> NotificationMap {
+    let mut by_name: HashMap<&str, usize> = HashMap::new();
+    let mut by_topic: HashMap<&str, Vec<usize>> = HashMap::new();
+    let mut by_id: HashMap<&str, usize> = HashMap::new();
+
+    for (index, handler) in handlers.iter().enumerate() {
+        by_name.insert(handler.name.as_str(), index);
+        by_id.insert(handler.id.as_str(), index);
+        for topic in &handler.listen {
+            by_topic.entry(topic.as_str()).or_default().push(index);
+        }
+    }
+
+    let direct_targets = |target: &str| -> Vec<usize> {
+        let mut matched = Vec::new();
+        if let Some(index) = by_name.get(target) {
+            matched.push(*index);
+        }
+        if let Some(indices) = by_topic.get(target) {
+            matched.extend(indices.iter().copied());
+        }
+        if matched.is_empty() {
+            if let Some(index) = by_id.get(target) {
+                matched.push(*index);
+            }
+        }
+        matched
+    };
+
+    let mut map = NotificationMap::default();
+
+    let notifiers = tasks
+        .iter()
+        .map(|task| (task, None))
+        .chain(handlers.iter().enumerate().map(|(i, h)| (h, Some(i))));
+
+    for (notifier, own_index) in notifiers {
+        if notifier.notify.is_empty() {
+            continue;
+        }
+
+        let mut reached = HashSet::new();
+        let mut queue = VecDeque::new();
+
+        for target in &notifier.notify {
+            let matched = direct_targets(target);
+            if matched.is_empty() {
+                map.unresolved.push(UnresolvedNotification {
+                    task_id: notifier.id.clone(),
+                    target: target.clone(),
+                });
+            }
+            queue.extend(matched);
+        }
+
+        // Follow handlers that notify further handlers
+        while let Some(index) = queue.pop_front() {
+            if !reached.insert(index) {
+                continue;
+            }
+            for target in &handlers[index].notify {
+                queue.extend(direct_targets(target));
+            }
+        }
+
+        // A handler notifying itself does not re-trigger itself
+        if let Some(own_index) = own_index {
+            reached.remove(&own_index);
+        }
+
+        let mut triggered: Vec<usize> = reached.into_iter().collect();
+        triggered.sort_unstable();
+        let triggered: Vec<String> = triggered
+            .into_iter()
+            .map(|index| handlers[index].id.clone())
+            .collect();
+
+        if !triggered.is_empty() {
+            map.triggers.insert(notifier.id.clone(), triggered);
+        }
+    }
+
+    map
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::types::parsed::ParsedTask;
+    use crate::types::parsed::{ParsedTask, TaskKeywords};
     use std::collections::HashMap;
 
     fn create_test_task(id: &str, dependencies: Vec<String>, notify: Vec<String>) -> ParsedTask {
@@ -80,15 +180,22 @@ mod tests {
             module: "debug".to_string(),
             args: HashMap::new(),
             vars: HashMap::new(),
-            when: None,
+            when: Vec::new(),
             loop_items: None,
             tags: vec![],
             notify,
+            listen: vec![],
             changed_when: None,
             failed_when: None,
-            ignore_errors: false,
+            ignore_errors: false.into(),
             delegate_to: None,
             dependencies,
+            block: None,
+            source: None,
+            register: None,
+            statically_skipped: false,
+            deferred_include: None,
+            keywords: TaskKeywords::default(),
         }
     }
 
@@ -99,30 +206,44 @@ mod tests {
             module: "service".to_string(),
             args: HashMap::new(),
             vars: HashMap::new(),
-            when: None,
+            when: Vec::new(),
             loop_items: None,
             tags: vec![],
             notify: vec![],
+            listen: vec![],
             changed_when: None,
             failed_when: None,
-            ignore_errors: false,
+            ignore_errors: false.into(),
             delegate_to: None,
             dependencies: vec![],
+            block: None,
+            source: None,
+            register: None,
+            statically_skipped: false,
+            deferred_include: None,
+            keywords: TaskKeywords::default(),
         }
     }
 
     fn create_test_play(tasks: Vec<ParsedTask>, handlers: Vec<ParsedTask>) -> ParsedPlay {
-        use crate::types::parsed::{ExecutionStrategy, HostPattern};
+        use crate::types::parsed::{ExecutionStrategy, HostPattern, NotificationMap};
         ParsedPlay {
             name: "Test Play".to_string(),
             hosts: HostPattern::Single("all".to_string()),
+            tags: vec![],
             tasks,
             handlers,
             vars: HashMap::new(),
+            vars_files: Vec::new(),
             roles: vec![],
             strategy: ExecutionStrategy::default(),
             serial: None,
             max_fail_percentage: None,
+            notifications: NotificationMap::default(),
+            source: None,
+            var_sources: HashMap::new(),
+            collections: Vec::new(),
+            matched_hosts: None,
         }
     }
 
@@ -254,4 +375,127 @@ mod tests {
         assert!(task2_pos < handler1_pos);
         assert!(task3_pos < handler2_pos);
     }
+
+    fn create_named_handler(
+        id: &str,
+        name: &str,
+        listen: Vec<String>,
+        notify: Vec<String>,
+    ) -> ParsedTask {
+        let mut handler = create_test_handler(id);
+        handler.name = name.to_string();
+        handler.listen = listen;
+        handler.notify = notify;
+        handler
+    }
+
+    #[test]
+    fn test_resolve_notifications_by_name_and_listen() {
+        let task = create_test_task(
+            "task1",
+            vec![],
+            vec!["restart nginx".to_string(), "web restarts".to_string()],
+        );
+        let handlers = vec![
+            create_named_handler("h1", "restart nginx", vec![], vec![]),
+            create_named_handler(
+                "h2",
+                "restart php",
+                vec!["web restarts".to_string()],
+                vec![],
+            ),
+            create_named_handler("h3", "restart memcached", vec![], vec![]),
+        ];
+
+        let map = resolve_notifications(&[task], &handlers);
+
+        assert_eq!(map.triggers["task1"], vec!["h1", "h2"]);
+        assert!(map.unresolved.is_empty());
+    }
+
+    #[test]
+    fn test_resolve_notifications_listen_topic_shared_by_handlers() {
+        let task = create_test_task("task1", vec![], vec!["restart web stack".to_string()]);
+        let handlers = vec![
+            create_named_handler("h1", "a", vec!["restart web stack".to_string()], vec![]),
+            create_named_handler("h2", "b", vec!["restart web stack".to_string()], vec![]),
+        ];
+
+        let map = resolve_notifications(&[task], &handlers);
+        assert_eq!(map.triggers["task1"], vec!["h1", "h2"]);
+    }
+
+    #[test]
+    fn test_resolve_notifications_last_duplicate_name_wins() {
+        let task = create_test_task("task1", vec![], vec!["restart".to_string()]);
+        let handlers = vec![
+            create_named_handler("h1", "restart", vec![], vec![]),
+            create_named_handler("h2", "restart", vec![], vec![]),
+        ];
+
+        let map = resolve_notifications(&[task], &handlers);
+        assert_eq!(map.triggers["task1"], vec!["h2"]);
+    }
+
+    #[test]
+    fn test_resolve_notifications_chained_handlers() {
+        let task = create_test_task("task1", vec![], vec!["reload config".to_string()]);
+        let handlers = vec![
+            create_named_handler("h1", "restart app", vec![], vec![]),
+            create_named_handler(
+                "h2",
+                "reload config",
+                vec![],
+                vec!["restart app".to_string()],
+            ),
+            create_named_handler(
+                "h3",
+                "loop back",
+                vec![],
+                vec!["reload config".to_string(), "loop back".to_string()],
+            ),
+        ];
+
+        let map = resolve_notifications(&[task], &handlers);
+
+        assert_eq!(map.triggers["task1"], vec!["h1", "h2"]);
+        assert_eq!(map.triggers["h2"], vec!["h1"]);
+        // A handler that notifies itself does not list itself
+        assert_eq!(map.triggers["h3"], vec!["h1", "h2"]);
+    }
+
+    #[test]
+    fn test_resolve_notifications_reports_unknown_targets() {
+        let task = create_test_task(
+            "task1",
+            vec![],
+            vec!["restart nginx".to_string(), "missing".to_string()],
+        );
+        let handlers = vec![create_named_handler("h1", "restart nginx", vec![], vec![])];
+
+        let map = resolve_notifications(&[task], &handlers);
+
+        assert_eq!(map.triggers["task1"], vec!["h1"]);
+        assert_eq!(
+            map.unresolved,
+            vec![UnresolvedNotification {
+                task_id: "task1".to_string(),
+                target: "missing".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_resolve_task_dependencies_with_graph_listen_topic() {
+        let task = create_test_task("task1", vec![], vec!["restart web".to_string()]);
+        let handler =
+            create_named_handler("handler1", "nginx", vec!["restart web".to_string()], vec![]);
+        let play = create_test_play(vec![task], vec![handler]);
+
+        let resolved = resolve_task_dependencies_with_graph(&[play]).unwrap();
+
+        let task_pos = resolved.iter().position(|x| x == "task1").unwrap();
+        let handler_pos = resolved.iter().position(|x| x == "handler1").unwrap();
+        assert!(task_pos < handler_pos);
+    }
 }
@@ -37,9 +37,21 @@ pub enum ParseError {
     #[error("YAML parsing error: {0}")]
     Yaml(#[from] serde_yaml::Error),
 
+    #[error("YAML resource limit exceeded: {message}")]
+    YamlLimitExceeded { message: String },
+
     #[error("JSON parsing error: {0}")]
     Json(#[from] serde_json::Error),
 
+    #[error("Invalid configuration in {file}: {message}")]
+    InvalidConfig { file: String, message: String },
+
+    #[error("Inventory script {script} failed: {message}")]
+    InventoryScript { script: String, message: String },
+
+    #[error("Inventory plugin error in {file}: {message}")]
+    InventoryPlugin { file: String, message: String },
+
     #[error("INI parsing error: {message}")]
     IniParsing { message: String },
 
@@ -49,6 +61,13 @@ pub enum ParseError {
     #[error("Invalid playbook structure: {message}")]
     InvalidStructure { message: String },
 
+    #[error("Invalid condition '{condition}' at {location}: {message}")]
+    InvalidCondition {
+        condition: String,
+        location: String,
+        message: String,
+    },
+
     #[error("Unsupported feature: {feature}")]
     UnsupportedFeature { feature: String },
 
@@ -84,6 +103,13 @@ pub enum ParseError {
         searched_paths: Vec<String>,
     },
 
+    #[error("Invalid arguments for role '{role}' (entry point '{entry_point}'): {message}")]
+    RoleArgumentSpec {
+        role: String,
+        entry_point: String,
+        message: String,
+    },
+
     #[error("Security violation: {message}")]
     SecurityViolation { message: String },
 
@@ -0,0 +1,301 @@
+//! Role argument specs from `meta/argument_specs.yml`.
+//!
+//! Ansible checks the variables a role receives against these specs before the
+//! role's first task runs. Checking them while parsing reports bad role inputs
+//! up front. Values that are still templated are left alone, since they are only
+//! known at runtime.
+
+use crate::parser::error::ParseError;
+use crate::parser::keywords::{deserialize_yaml_bool, parse_bool};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// The `argument_specs` of a role, keyed by entry point (`main`, or a `tasks_from` name)
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RoleArgumentSpecs {
+    #[serde(default)]
+    pub argument_specs: HashMap<String, EntryPointSpec>,
+}
+
+/// Arguments accepted by one entry point of a role
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct EntryPointSpec {
+    #[serde(default)]
+    pub short_description: Option<String>,
+    #[serde(default)]
+    pub options: HashMap<String, ArgumentSpec>,
+}
+
+/// A single argument, as in Ansible's module argument spec format
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ArgumentSpec {
+    #[serde(rename = "type", default)]
+    pub kind: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_yaml_bool")]
+    pub required: Option<bool>,
+    #[serde(default)]
+    pub default: Option<Value>,
+    #[serde(default)]
+    pub choices: Option<Vec<Value>>,
+    /// Type of the items of a `list` argument
+    #[serde(default)]
+    pub elements: Option<String>,
+    /// Suboptions of a `dict` argument, or of each item of a list of dicts
+    #[serde(default)]
+    pub options: Option<HashMap<String, ArgumentSpec>>,
+}
+
+impl RoleArgumentSpecs {
+    /// Check the variables a role receives against the spec of `entry_point`.
+    ///
+    /// Roles without a spec for the entry point accept anything.
+    pub fn validate(
+        &self,
+        role: &str,
+        entry_point: &str,
+        vars: &HashMap<String, Value>,
+    ) -> Result<(), ParseError> {
+        let Some(spec) = self.argument_specs.get(entry_point) else {
+            return Ok(());
+        };
+
+        let vars: serde_json::Map<String, Value> =
+            vars.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+        let mut errors = Vec::new();
+        validate_options(&spec.options, &vars, "", &mut errors);
+        if errors.is_empty() {
+            return Ok(());
+        }
+
+        errors.sort();
+        Err(ParseError::RoleArgumentSpec {
+            role: role.to_string(),
+            entry_point: entry_point.to_string(),
+            message: errors.join("; "),
+        })
+    }
+}
+
+fn validate_options(
+    options: &HashMap<String, ArgumentSpec>,
+    values: &serde_json::Map<String, Value>,
+    prefix: &str,
+    errors: &mut Vec<String>,
+) {
+    for (name, spec) in options {
+        let path = format!("{prefix}{name}");
+        match values.get(name) {
+            None | Some(Value::Null) => {
+                if spec.required == Some(true) && spec.default.is_none() {
+                    errors.push(format!("missing required argument '{path}'"));
+                }
+            }
+            Some(value) => validate_value(spec, value, &path, errors),
+        }
+    }
+}
+
+fn validate_value(spec: &ArgumentSpec, value: &Value, path: &str, errors: &mut Vec<String>) {
+    if is_templated(value) {
+        return;
+    }
+
+    let kind = spec.kind.as_deref().unwrap_or("str");
+    if !matches_type(kind, value) {
+        errors.push(format!(
+            "argument '{path}' is of type {} but must be {kind}",
+            type_name(value)
+        ));
+        return;
+    }
+
+    if let Some(choices) = &spec.choices {
+        let items = match (kind, value) {
+            ("list", Value::Array(items)) => items.iter().collect(),
+            _ => vec![value],
+        };
+        for item in items {
+            if !choices.iter().any(|choice| same_scalar(choice, item)) {
+                let choices: Vec<String> = choices.iter().map(scalar_string).collect();
+                errors.push(format!(
+                    "argument '{path}' is '{}' but must be one of: {}",
+                    scalar_string(item),
+                    choices.join(", ")
+                ));
+            }
+        }
+    }
+
+    match value {
+        Value::Array(items) if kind == "list" => {
+            let elements = spec.elements.as_deref();
+            for (index, item) in items.iter().enumerate() {
+                let item_path = format!("{path}[{index}]");
+                if let Some(elements) = elements {
+                    if !is_templated(item) && !matches_type(elements, item) {
+                        errors.push(format!(
+                            "argument '{item_path}' is of type {} but must be {elements}",
+                            type_name(item)
+                        ));
+                        continue;
+                    }
+                }
+                if let (Some(options), Value::Object(map)) = (&spec.options, item) {
+                    validate_options(options, map, &format!("{item_path}."), errors);
+                }
+            }
+        }
+        Value::Object(map) => {
+            if let Some(options) = &spec.options {
+                validate_options(options, map, &format!("{path}."), errors);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Whether Ansible would accept, possibly after conversion, `value` for type `kind`
+fn matches_type(kind: &str, value: &Value) -> bool {
+    match kind {
+        "str" | "path" | "bytes" | "bits" => !matches!(value, Value::Array(_) | Value::Object(_)),
+        "int" => match value {
+            Value::Number(n) => n.is_i64() || n.is_u64(),
+            Value::String(s) => s.trim().parse::<i64>().is_ok(),
+            _ => false,
+        },
+        "float" => match value {
+            Value::Number(_) => true,
+            Value::String(s) => s.trim().parse::<f64>().is_ok(),
+            _ => false,
+        },
+        "bool" => match value {
+            Value::Bool(_) => true,
+            Value::String(s) => parse_bool(s).is_some(),
+            Value::Number(n) => n.as_f64().is_some_and(|n| n == 0.0 || n == 1.0),
+            _ => false,
+        },
+        // Strings are split on commas
+        "list" => matches!(value, Value::Array(_) | Value::String(_) | Value::Number(_)),
+        // Strings may hold JSON or `k=v` pairs
+        "dict" => matches!(value, Value::Object(_) | Value::String(_)),
+        "json" | "jsonarg" => !matches!(value, Value::Null),
+        _ => true,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "bool",
+        Value::Number(n) if n.is_f64() => "float",
+        Value::Number(_) => "int",
+        Value::String(_) => "str",
+        Value::Array(_) => "list",
+        Value::Object(_) => "dict",
+    }
+}
+
+fn scalar_string(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Choices match after conversion to strings, so `80` matches `"80"`
+fn same_scalar(choice: &Value, value: &Value) -> bool {
+    choice == value || scalar_string(choice) == scalar_string(value)
+}
+
+fn is_templated(value: &Value) -> bool {
+    match value {
+        Value::String(s) => s.contains("{{") || s.contains("{%"),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn specs() -> RoleArgumentSpecs {
+        serde_yaml::from_str(
+            r#"
+argument_specs:
+  main:
+    short_description: Web server
+    options:
+      http_port:
+        type: int
+        required: true
+      state:
+        choices: [present, absent]
+        default: present
+      enabled:
+        type: bool
+      packages:
+        type: list
+        elements: str
+      vhosts:
+        type: list
+        elements: dict
+        options:
+          name:
+            required: true
+          port:
+            type: int
+      tls:
+        type: dict
+        options:
+          cert:
+            type: path
+            required: true
+"#,
+        )
+        .unwrap()
+    }
+
+    fn validate(vars: Value) -> Result<(), String> {
+        let vars: HashMap<String, Value> = serde_json::from_value(vars).unwrap();
+        specs()
+            .validate("web", "main", &vars)
+            .map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn test_valid_arguments() {
+        assert!(validate(json!({
+            "http_port": "8080",
+            "state": "absent",
+            "enabled": "yes",
+            "packages": ["nginx"],
+            "vhosts": [{"name": "a", "port": 80}],
+            "tls": {"cert": "/etc/cert.pem"},
+        }))
+        .is_ok());
+        assert!(validate(json!({"http_port": "{{ port }}"})).is_ok());
+        assert!(specs().validate("web", "other", &HashMap::new()).is_ok());
+    }
+
+    #[test]
+    fn test_invalid_arguments() {
+        let error = validate(json!({
+            "state": "running",
+            "enabled": "maybe",
+            "packages": [["nested"]],
+            "vhosts": [{"port": "eighty"}],
+            "tls": {},
+        }))
+        .unwrap_err();
+        assert!(error.contains("role 'web'"), "{error}");
+        assert!(error.contains("missing required argument 'http_port'"));
+        assert!(error.contains("argument 'state' is 'running' but must be one of: present, absent"));
+        assert!(error.contains("argument 'enabled' is of type str but must be bool"));
+        assert!(error.contains("argument 'packages[0]' is of type list but must be str"));
+        assert!(error.contains("missing required argument 'vhosts[0].name'"));
+        assert!(error.contains("argument 'vhosts[0].port' is of type str but must be int"));
+        assert!(error.contains("missing required argument 'tls.cert'"));
+    }
+}
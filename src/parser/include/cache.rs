@@ -1,19 +1,21 @@
+use crate::parser::include::loader::LoadedFile;
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::{Duration, SystemTime};
 
-/// Cached include file content with metadata
+/// Cached include file with metadata
 #[derive(Debug, Clone)]
 pub struct CachedInclude {
-    pub content: String,
+    pub file: Arc<LoadedFile>,
     pub parsed_at: SystemTime,
     pub file_modified: SystemTime,
 }
 
 impl CachedInclude {
-    pub fn new(content: String, file_modified: SystemTime) -> Self {
+    pub fn new(file: Arc<LoadedFile>, file_modified: SystemTime) -> Self {
         Self {
-            content,
+            file,
             parsed_at: SystemTime::now(),
             file_modified,
         }
@@ -35,7 +37,7 @@ impl CachedInclude {
     }
 }
 
-/// Cache for include file contents
+/// Cache for loaded include files
 #[derive(Debug)]
 pub struct IncludeCache {
     cache: HashMap<PathBuf, CachedInclude>,
@@ -53,10 +55,10 @@ impl IncludeCache {
     }
 
     /// Get cached content if valid
-    pub fn get(&self, path: &PathBuf, file_modified: SystemTime) -> Option<&str> {
+    pub fn get(&self, path: &Path, file_modified: SystemTime) -> Option<Arc<LoadedFile>> {
         self.cache.get(path).and_then(|cached| {
             if cached.is_valid(self.cache_ttl, file_modified) {
-                Some(cached.content.as_str())
+                Some(cached.file.clone())
             } else {
                 None
             }
@@ -64,18 +66,18 @@ impl IncludeCache {
     }
 
     /// Insert or update cache entry
-    pub fn insert(&mut self, path: PathBuf, content: String, file_modified: SystemTime) {
+    pub fn insert(&mut self, path: PathBuf, file: Arc<LoadedFile>, file_modified: SystemTime) {
         // Enforce cache size limit
         if self.cache.len() >= self.max_size {
             self.evict_oldest();
         }
 
-        let cached = CachedInclude::new(content, file_modified);
+        let cached = CachedInclude::new(file, file_modified);
         self.cache.insert(path, cached);
     }
 
     /// Remove entry from cache
-    pub fn remove(&mut self, path: &PathBuf) -> Option<CachedInclude> {
+    pub fn remove(&mut self, path: &Path) -> Option<CachedInclude> {
         self.cache.remove(path)
     }
 
@@ -133,12 +135,22 @@ pub struct CacheStats {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::parser::yaml::YamlLimits;
     use std::time::{Duration, SystemTime};
 
+    fn loaded(content: &str) -> Arc<LoadedFile> {
+        let file = LoadedFile::parse(
+            Path::new("test.yml"),
+            content.to_string(),
+            &YamlLimits::default(),
+        );
+        Arc::new(file.unwrap())
+    }
+
     #[test]
     fn test_cached_include_validity() {
         let file_time = SystemTime::now();
-        let cached = CachedInclude::new("content".to_string(), file_time);
+        let cached = CachedInclude::new(loaded("content"), file_time);
 
         // Should be valid immediately
         assert!(cached.is_valid(Duration::from_secs(300), file_time));
@@ -156,17 +168,17 @@ mod tests {
         let file_time = SystemTime::now();
 
         // Insert first entry
-        cache.insert(path1.clone(), "content1".to_string(), file_time);
-        assert_eq!(cache.get(&path1, file_time), Some("content1"));
+        cache.insert(path1.clone(), loaded("content1"), file_time);
+        assert_eq!(cache.get(&path1, file_time).unwrap().content, "content1");
 
         // Insert second entry
-        cache.insert(path2.clone(), "content2".to_string(), file_time);
-        assert_eq!(cache.get(&path2, file_time), Some("content2"));
+        cache.insert(path2.clone(), loaded("content2"), file_time);
+        assert_eq!(cache.get(&path2, file_time).unwrap().content, "content2");
         assert_eq!(cache.stats().entries, 2);
 
         // Remove entry
         cache.remove(&path1);
-        assert_eq!(cache.get(&path1, file_time), None);
+        assert!(cache.get(&path1, file_time).is_none());
         assert_eq!(cache.stats().entries, 1);
     }
 
@@ -176,24 +188,12 @@ mod tests {
         let file_time = SystemTime::now();
 
         // Fill cache to capacity
-        cache.insert(
-            PathBuf::from("test1.yml"),
-            "content1".to_string(),
-            file_time,
-        );
-        cache.insert(
-            PathBuf::from("test2.yml"),
-            "content2".to_string(),
-            file_time,
-        );
+        cache.insert(PathBuf::from("test1.yml"), loaded("content1"), file_time);
+        cache.insert(PathBuf::from("test2.yml"), loaded("content2"), file_time);
         assert_eq!(cache.stats().entries, 2);
 
         // Adding third entry should evict oldest
-        cache.insert(
-            PathBuf::from("test3.yml"),
-            "content3".to_string(),
-            file_time,
-        );
+        cache.insert(PathBuf::from("test3.yml"), loaded("content3"), file_time);
         assert_eq!(cache.stats().entries, 2);
     }
 
@@ -203,7 +203,7 @@ mod tests {
         let file_time = SystemTime::now();
 
         // Insert entry that will expire quickly
-        cache.insert(PathBuf::from("test.yml"), "content".to_string(), file_time);
+        cache.insert(PathBuf::from("test.yml"), loaded("content"), file_time);
         assert_eq!(cache.stats().entries, 1);
 
         // Wait for expiration
@@ -1,22 +1,39 @@
+use crate::parser::collections::CollectionIndex;
+use crate::parser::condition::parse_condition;
 use crate::parser::error::ParseError;
 use crate::parser::include::{
-    cache::IncludeCache, dependency::IncludeStack, resolver::PathResolver, ImportSpec,
-    IncludeConfig, IncludeContext, IncludeSpec,
+    dependency::IncludeStack,
+    loader::FileLoader,
+    resolver::PathResolver,
+    roles::{RoleIncludeProcessor, RoleIncludeResult},
+    ImportSpec, IncludeConfig, IncludeContext, IncludeSpec, RoleIncludeSpec,
 };
+use crate::parser::playbook::PlaybookParser;
+use crate::parser::source_map::SourceMap;
+use crate::parser::task::RawTask;
 use crate::parser::template::TemplateEngine;
 use crate::types::parsed::*;
+use once_cell::sync::Lazy;
+use regex::Regex;
 use serde_yaml;
 use std::collections::HashMap;
-use std::path::{Path, PathBuf};
-use tokio::fs;
+use std::path::PathBuf;
+
+/// Most files a templated include file name is expanded to
+pub(crate) const MAX_INCLUDE_CANDIDATES: usize = 100;
+
+/// A `{{ ... }}` or `{% ... %}` block in a file name
+static TEMPLATE_EXPRESSION: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\{\{.*?\}\}|\{%.*?%\}").expect("valid regex"));
 
 /// Main handler for include/import operations
 pub struct IncludeHandler {
     path_resolver: PathResolver,
     template_engine: TemplateEngine,
-    cache: IncludeCache,
+    loader: FileLoader,
     include_stack: IncludeStack,
     config: IncludeConfig,
+    collections: Option<CollectionIndex>,
 }
 
 impl IncludeHandler {
@@ -24,16 +41,18 @@ impl IncludeHandler {
         let config = IncludeConfig::default();
         let path_resolver = PathResolver::new(base_path)
             .with_absolute_paths(config.allow_absolute_paths)
-            .with_strict_permissions(config.strict_file_permissions);
-        let cache = IncludeCache::new(1000, config.cache_ttl);
+            .with_strict_permissions(config.strict_file_permissions)
+            .with_roles_path(config.role_search_paths.clone());
+        let loader = FileLoader::new(&config);
         let include_stack = IncludeStack::new(config.max_include_depth);
 
         Self {
             path_resolver,
             template_engine,
-            cache,
+            loader,
             include_stack,
             config,
+            collections: None,
         }
     }
 
@@ -41,221 +60,235 @@ impl IncludeHandler {
         self.path_resolver = self
             .path_resolver
             .with_absolute_paths(config.allow_absolute_paths)
-            .with_strict_permissions(config.strict_file_permissions);
-        self.cache = IncludeCache::new(1000, config.cache_ttl);
+            .with_strict_permissions(config.strict_file_permissions)
+            .with_roles_path(config.role_search_paths.clone());
+        self.loader = FileLoader::new(&config);
         self.include_stack = IncludeStack::new(config.max_include_depth);
         self.config = config;
         self
     }
 
-    /// Process include_tasks directive
-    pub fn include_tasks<'a>(
-        &'a mut self,
-        include_spec: &'a IncludeSpec,
-        context: &'a IncludeContext,
-    ) -> std::pin::Pin<
-        Box<dyn std::future::Future<Output = Result<Vec<ParsedTask>, ParseError>> + 'a>,
-    > {
-        Box::pin(async move {
-            // Check if include should be processed based on when condition
-            if !self
-                .should_process_include(include_spec.when_condition.as_ref(), context)
-                .await?
-            {
-                return Ok(Vec::new());
-            }
-
-            // Resolve the file path
-            let resolved_path = self
-                .path_resolver
-                .resolve_path(&include_spec.file, &context.current_file)?;
-
-            // Manage include stack
-            self.include_stack.push(resolved_path.clone())?;
-
-            // Load and parse the included file
-            let content = self.load_file_cached(&resolved_path).await?;
-
-            // Create context for included tasks
-            let mut include_context = context.clone();
-            include_context.current_file = resolved_path.clone();
-            include_context.include_depth += 1;
-
-            // Merge include variables
-            if let Some(include_vars) = &include_spec.vars {
-                for (key, value) in include_vars {
-                    let rendered_value = self
-                        .template_engine
-                        .render_value(value, &include_context.variables)?;
-                    include_context
-                        .variables
-                        .insert(key.clone(), rendered_value);
-                }
-            }
-
-            // Parse tasks from included file
-            let raw_tasks: Vec<serde_yaml::Value> =
-                serde_yaml::from_str(&content).map_err(ParseError::Yaml)?;
+    /// Installed collections, for collection roles and checking fully
+    /// qualified module names in included files
+    pub fn with_collections(mut self, collections: CollectionIndex) -> Self {
+        self.path_resolver = self.path_resolver.with_collections(collections.clone());
+        self.collections = Some(collections);
+        self
+    }
 
-            let mut parsed_tasks = Vec::new();
-            for (index, raw_task_value) in raw_tasks.into_iter().enumerate() {
-                // TODO: Handle nested includes (simplified for now)
-                // Check if this is a nested include directive
-                // if let Some(nested_directive) = self.detect_include_directive(&raw_task_value)? {
-                //     let nested_tasks = self
-                //         .process_include_directive(&nested_directive, &include_context)
-                //         .await?;
-                //     parsed_tasks.extend(nested_tasks);
-                // } else {
-                // Parse as regular task
-                let raw_task: RawTask =
-                    serde_yaml::from_value(raw_task_value).map_err(ParseError::Yaml)?;
-                let task = self
-                    .parse_task_with_context(raw_task, &include_context, index)
-                    .await?;
+    /// Share `loader`, and so its cache, with the rest of the parser
+    pub fn with_loader(mut self, loader: FileLoader) -> Self {
+        self.loader = loader;
+        self
+    }
 
-                // Apply include-level properties
-                let enhanced_task = self.apply_include_properties(task, include_spec)?;
-                parsed_tasks.push(enhanced_task);
-                // }
-            }
+    /// Read the files a list of sibling includes or imports names concurrently,
+    /// ahead of expanding them one by one. Files whose names need runtime
+    /// variables or that cannot be resolved are skipped.
+    pub async fn prefetch(&self, files: &[&str], context: &IncludeContext) {
+        let paths = files
+            .iter()
+            .filter(|file| {
+                self.template_engine
+                    .undefined_variables(file, &context.variables)
+                    .is_ok_and(|variables| variables.is_empty())
+            })
+            .filter_map(|file| {
+                let file = self
+                    .template_engine
+                    .render_string(file, &context.variables)
+                    .ok()?;
+                self.path_resolver
+                    .resolve_path(&file, &context.current_file)
+                    .ok()
+            })
+            .collect();
+        self.loader.prefetch(paths).await;
+    }
 
-            // Remove from include stack
-            self.include_stack.pop();
+    /// Resolve the file an include or import names, relative to the including file
+    pub fn resolve_path(
+        &self,
+        file: &str,
+        context: &IncludeContext,
+    ) -> Result<PathBuf, ParseError> {
+        self.path_resolver.resolve_path(file, &context.current_file)
+    }
 
-            Ok(parsed_tasks)
+    /// Open the task file at `path` for an include, import or role, with `vars`
+    /// rendered over the variables in scope. Every opened file is closed with
+    /// [`close`](Self::close) once its tasks are parsed.
+    pub(crate) async fn open_tasks(
+        &mut self,
+        path: PathBuf,
+        vars: Option<&HashMap<String, serde_json::Value>>,
+        context: &IncludeContext,
+    ) -> Result<IncludedTasks, ParseError> {
+        let (items, source_map, context) = self.open(path, vars, context).await?;
+        Ok(IncludedTasks {
+            raw_tasks: items,
+            source_map,
+            context,
         })
     }
 
-    /// Process import_tasks directive
-    pub async fn import_tasks(
+    /// Open the playbook at `path` for an `include_playbook` or
+    /// `import_playbook`, like [`open_tasks`](Self::open_tasks) does for task files
+    pub(crate) async fn open_playbook(
         &mut self,
-        import_spec: &ImportSpec,
+        path: PathBuf,
+        vars: Option<&HashMap<String, serde_json::Value>>,
         context: &IncludeContext,
-    ) -> Result<Vec<ParsedTask>, ParseError> {
-        // Check if import should be processed
-        if !self
-            .should_process_include(import_spec.when_condition.as_ref(), context)
-            .await?
-        {
-            return Ok(Vec::new());
-        }
-
-        // Import is processed at parse time, similar to include_tasks
-        // but with different variable scoping rules
-        let resolved_path = self
-            .path_resolver
-            .resolve_path(&import_spec.file, &context.current_file)?;
-
-        self.include_stack.push(resolved_path.clone())?;
-
-        let content = self.load_file_cached(&resolved_path).await?;
-
-        // For imports, variables are applied at parse time
-        let mut import_context = context.clone();
-        import_context.current_file = resolved_path.clone();
-        import_context.include_depth += 1;
-
-        // Apply import variables to context
-        if let Some(import_vars) = &import_spec.vars {
-            for (key, value) in import_vars {
-                let rendered_value = self
-                    .template_engine
-                    .render_value(value, &import_context.variables)?;
-                import_context.variables.insert(key.clone(), rendered_value);
-            }
-        }
-
-        // Parse and process tasks immediately
-        let raw_tasks: Vec<serde_yaml::Value> =
-            serde_yaml::from_str(&content).map_err(ParseError::Yaml)?;
-
-        let mut parsed_tasks = Vec::new();
-        for (index, raw_task_value) in raw_tasks.into_iter().enumerate() {
-            // TODO: Handle nested includes
-            // if let Some(nested_directive) = self.detect_include_directive(&raw_task_value)? {
-            //     let nested_tasks = self
-            //         .process_include_directive(&nested_directive, &import_context)
-            //         .await?;
-            //     parsed_tasks.extend(nested_tasks);
-            // } else {
-            let raw_task: RawTask =
-                serde_yaml::from_value(raw_task_value).map_err(ParseError::Yaml)?;
-            let task = self
-                .parse_task_with_context(raw_task, &import_context, index)
-                .await?;
-            parsed_tasks.push(task);
-            // }
-        }
-
-        self.include_stack.pop();
-        Ok(parsed_tasks)
+    ) -> Result<IncludedPlaybook, ParseError> {
+        let (items, source_map, context) = self.open(path, vars, context).await?;
+        Ok(IncludedPlaybook {
+            items,
+            source_map,
+            context,
+        })
     }
 
-    /// Process include_playbook directive
-    pub async fn include_playbook(
+    async fn open<T: serde::de::DeserializeOwned>(
         &mut self,
-        include_spec: &IncludeSpec,
+        path: PathBuf,
+        vars: Option<&HashMap<String, serde_json::Value>>,
         context: &IncludeContext,
-    ) -> Result<Vec<ParsedPlay>, ParseError> {
-        // Check if include should be processed based on when condition
-        if !self
-            .should_process_include(include_spec.when_condition.as_ref(), context)
-            .await?
-        {
-            return Ok(Vec::new());
+    ) -> Result<(Vec<T>, SourceMap, IncludeContext), ParseError> {
+        self.include_stack.push(path.clone())?;
+        let opened = self.load(path, vars, context).await;
+        if opened.is_err() {
+            self.include_stack.pop();
         }
+        opened
+    }
 
-        // Resolve the playbook path
-        let resolved_path = self
-            .path_resolver
-            .resolve_path(&include_spec.file, &context.current_file)?;
-
-        // Manage include stack
-        self.include_stack.push(resolved_path.clone())?;
-
-        // Load and parse the included playbook
-        let content = self.load_file_cached(&resolved_path).await?;
+    async fn load<T: serde::de::DeserializeOwned>(
+        &self,
+        path: PathBuf,
+        vars: Option<&HashMap<String, serde_json::Value>>,
+        context: &IncludeContext,
+    ) -> Result<(Vec<T>, SourceMap, IncludeContext), ParseError> {
+        let loaded = self.loader.load(&path).await?;
+        let items: Option<Vec<T>> = loaded.deserialize()?;
 
-        // Create context for included playbook
+        // Create context for the included file
         let mut include_context = context.clone();
-        include_context.current_file = resolved_path.clone();
+        include_context.current_file = path;
         include_context.include_depth += 1;
 
         // Merge include variables
-        if let Some(include_vars) = &include_spec.vars {
-            for (key, value) in include_vars {
-                let rendered_value = self
-                    .template_engine
-                    .render_value(value, &include_context.variables)?;
-                include_context
-                    .variables
-                    .insert(key.clone(), rendered_value);
-            }
+        for (key, value) in vars.into_iter().flatten() {
+            let rendered_value = self
+                .template_engine
+                .render_value(value, &include_context.variables)?;
+            include_context
+                .variables
+                .insert(key.clone(), rendered_value);
         }
 
-        // Parse playbook content as array of plays
-        let raw_plays: Vec<serde_yaml::Value> =
-            serde_yaml::from_str(&content).map_err(ParseError::Yaml)?;
+        Ok((
+            items.unwrap_or_default(),
+            loaded.source_map.clone(),
+            include_context,
+        ))
+    }
+
+    /// Close the task or playbook file opened last
+    pub(crate) fn close(&mut self) {
+        self.include_stack.pop();
+    }
 
-        let mut parsed_plays = Vec::new();
-        for raw_play_value in raw_plays.into_iter() {
-            // Parse the play with the include context
-            let raw_play: RawPlay =
-                serde_yaml::from_value(raw_play_value).map_err(ParseError::Yaml)?;
-            let play = self
-                .parse_play_with_context(raw_play, &include_context)
-                .await?;
+    /// The YAML files on disk a templated include file name can match, with
+    /// the first [`MAX_INCLUDE_CANDIDATES`] read ahead
+    pub(crate) async fn include_candidates(
+        &self,
+        file: &str,
+        context: &IncludeContext,
+    ) -> Vec<String> {
+        let pattern = TEMPLATE_EXPRESSION.replace_all(file, "*");
+        let files: Vec<String> = self
+            .path_resolver
+            .glob_paths(&pattern, &context.current_file)
+            .into_iter()
+            .filter(|file| file.ends_with(".yml") || file.ends_with(".yaml"))
+            .collect();
+        let names: Vec<&str> = files
+            .iter()
+            .take(MAX_INCLUDE_CANDIDATES)
+            .map(String::as_str)
+            .collect();
+        self.prefetch(&names, context).await;
+        files
+    }
 
-            // Apply include-level properties to the play
-            let enhanced_play = self.apply_include_properties_to_play(play, include_spec)?;
-            parsed_plays.push(enhanced_play);
-        }
+    /// The marker task kept for an include whose file name needs runtime
+    /// `variables`, with every file the name can match parsed as a candidate
+    pub(crate) fn deferred_include(
+        include_spec: &IncludeSpec,
+        context: &IncludeContext,
+        variables: Vec<String>,
+        candidates: Vec<IncludeCandidate>,
+    ) -> Result<ParsedTask, ParseError> {
+        let name = include_spec
+            .name
+            .clone()
+            .unwrap_or_else(|| format!("include_tasks {}", include_spec.file));
+        let mut args = HashMap::new();
+        args.insert(
+            "_raw_params".to_string(),
+            serde_json::Value::String(include_spec.file.clone()),
+        );
+        // `apply` conditions belong to the included tasks, not the include
+        let location = format!("include of '{}'", include_spec.file);
+        let when = include_spec
+            .when_condition
+            .iter()
+            .flatten()
+            .map(|expression| parse_condition(expression, &location))
+            .collect::<Result<Vec<_>, _>>()?;
 
-        // Remove from include stack
-        self.include_stack.pop();
+        Ok(ParsedTask {
+            id: context.task_path.task_id(),
+            name,
+            module: "include_tasks".to_string(),
+            args,
+            vars: include_spec.vars.clone().unwrap_or_default(),
+            when,
+            loop_items: None,
+            register: None,
+            tags: include_spec.tags.clone().unwrap_or_default(),
+            notify: Vec::new(),
+            listen: Vec::new(),
+            changed_when: None,
+            failed_when: None,
+            ignore_errors: false.into(),
+            delegate_to: include_spec.delegate_to.clone(),
+            dependencies: Vec::new(),
+            block: None,
+            source: None,
+            statically_skipped: false,
+            deferred_include: Some(DeferredInclude {
+                expression: include_spec.file.clone(),
+                variables,
+                candidates,
+            }),
+            keywords: TaskKeywords::default(),
+        })
+    }
 
-        Ok(parsed_plays)
+    /// Process include_playbook directive. The included plays are parsed like
+    /// those of any playbook, with their roles and task includes expanded.
+    pub async fn include_playbook(
+        &mut self,
+        include_spec: &IncludeSpec,
+        context: &IncludeContext,
+    ) -> Result<Vec<ParsedPlay>, ParseError> {
+        let template_engine = self.template_engine.clone();
+        let collections = self.collections.clone();
+        let extra_vars = HashMap::new();
+        self.playbook_parser(&template_engine, &extra_vars, collections.as_ref())
+            .include_playbook(include_spec, self, context)
+            .await
     }
 
     /// Process import_playbook directive
@@ -264,92 +297,71 @@ impl IncludeHandler {
         import_spec: &ImportSpec,
         context: &IncludeContext,
     ) -> Result<Vec<ParsedPlay>, ParseError> {
-        // Check if import should be processed
-        if !self
-            .should_process_include(import_spec.when_condition.as_ref(), context)
-            .await?
-        {
-            return Ok(Vec::new());
-        }
-
-        // Import is processed at parse time, similar to include_playbook
-        // but with different variable scoping rules
-        let resolved_path = self
-            .path_resolver
-            .resolve_path(&import_spec.file, &context.current_file)?;
-
-        self.include_stack.push(resolved_path.clone())?;
-
-        let content = self.load_file_cached(&resolved_path).await?;
-
-        // For imports, variables are applied at parse time
-        let mut import_context = context.clone();
-        import_context.current_file = resolved_path.clone();
-        import_context.include_depth += 1;
+        let template_engine = self.template_engine.clone();
+        let collections = self.collections.clone();
+        let extra_vars = HashMap::new();
+        self.playbook_parser(&template_engine, &extra_vars, collections.as_ref())
+            .import_playbook(import_spec, self, context)
+            .await
+    }
 
-        // Apply import variables to context
-        if let Some(import_vars) = &import_spec.vars {
-            for (key, value) in import_vars {
-                let rendered_value = self
-                    .template_engine
-                    .render_value(value, &import_context.variables)?;
-                import_context.variables.insert(key.clone(), rendered_value);
-            }
+    /// A playbook parser sharing this handler's settings and file cache
+    fn playbook_parser<'a>(
+        &self,
+        template_engine: &'a TemplateEngine,
+        extra_vars: &'a HashMap<String, serde_json::Value>,
+        collections: Option<&'a CollectionIndex>,
+    ) -> PlaybookParser<'a> {
+        let mut parser = PlaybookParser::new(template_engine, extra_vars)
+            .with_include_config(self.config.clone())
+            .with_loader(self.loader.clone());
+        if let Some(collections) = collections {
+            parser = parser.with_collections(collections);
         }
+        parser
+    }
 
-        // Parse and process plays immediately
-        let raw_plays: Vec<serde_yaml::Value> =
-            serde_yaml::from_str(&content).map_err(ParseError::Yaml)?;
-
-        let mut parsed_plays = Vec::new();
-        for raw_play_value in raw_plays.into_iter() {
-            let raw_play: RawPlay =
-                serde_yaml::from_value(raw_play_value).map_err(ParseError::Yaml)?;
-            let play = self
-                .parse_play_with_context(raw_play, &import_context)
-                .await?;
-            parsed_plays.push(play);
-        }
+    /// Process include_role directive, finding the role next to the including
+    /// file, in the roles path or in an installed collection
+    pub async fn include_role(
+        &mut self,
+        role_spec: &RoleIncludeSpec,
+        context: &IncludeContext,
+    ) -> Result<RoleIncludeResult, ParseError> {
+        let (role_spec, role_path) = self.resolve_role(role_spec, context)?;
+        RoleIncludeProcessor::include_role(&role_spec, &role_path, context, &self.loader).await
+    }
 
-        self.include_stack.pop();
-        Ok(parsed_plays)
+    /// Process import_role directive
+    pub async fn import_role(
+        &mut self,
+        role_spec: &RoleIncludeSpec,
+        context: &IncludeContext,
+    ) -> Result<RoleIncludeResult, ParseError> {
+        let (role_spec, role_path) = self.resolve_role(role_spec, context)?;
+        RoleIncludeProcessor::import_role(&role_spec, &role_path, context, &self.loader).await
     }
 
-    /// Check if include should be processed based on when condition
-    async fn should_process_include(
+    /// Render a role name whose variables are known and find the role. Names
+    /// that need runtime variables are left as written, so the role is not found.
+    fn resolve_role(
         &self,
-        when_condition: Option<&String>,
+        role_spec: &RoleIncludeSpec,
         context: &IncludeContext,
-    ) -> Result<bool, ParseError> {
-        if let Some(when_condition) = when_condition {
-            // Evaluate the when condition using template engine
-            // If we get an undefined value error, treat it as false (following Ansible behavior)
-            let result = match self
+    ) -> Result<(RoleIncludeSpec, PathBuf), ParseError> {
+        let mut role_spec = role_spec.clone();
+        let variables = self
+            .template_engine
+            .undefined_variables(&role_spec.name, &context.variables)?;
+        if variables.is_empty() {
+            role_spec.name = self
                 .template_engine
-                .render_string(&format!("{{{{ {when_condition} }}}}"), &context.variables)
-            {
-                Ok(rendered) => rendered,
-                Err(ParseError::Template { message, .. }) if message.contains("undefined") => {
-                    // For undefined variables in when conditions, evaluate to false
-                    // This matches Ansible's behavior
-                    return Ok(false);
-                }
-                Err(e) => return Err(e),
-            };
-
-            // Parse result as boolean
-            match result.trim().to_lowercase().as_str() {
-                "true" | "yes" | "1" => Ok(true),
-                "false" | "no" | "0" => Ok(false),
-                "" => Ok(false),
-                _ => {
-                    // Try to parse as boolean-ish value
-                    Ok(!result.trim().is_empty())
-                }
-            }
-        } else {
-            Ok(true)
+                .render_string(&role_spec.name, &context.variables)?;
         }
+        let role_path = self
+            .path_resolver
+            .resolve_role_path(&role_spec.name, &context.current_file)?;
+        Ok((role_spec, role_path))
     }
 
     // TODO: Re-implement these methods without recursion issues
@@ -401,233 +413,9 @@ impl IncludeHandler {
     //     Ok(None)
     // }
 
-    /// Parse a task with the given context
-    async fn parse_task_with_context(
-        &self,
-        raw_task: RawTask,
-        _context: &IncludeContext,
-        index: usize,
-    ) -> Result<ParsedTask, ParseError> {
-        // This is a simplified task parser - in a full implementation,
-        // this would delegate to the main playbook parser
-        let id = raw_task
-            .id
-            .clone()
-            .unwrap_or_else(|| format!("task_{index}"));
-
-        // Don't render task name - preserve templates for runtime
-        let name = raw_task
-            .name
-            .clone()
-            .unwrap_or_else(|| "Unnamed task".to_string());
-
-        // Extract module and args (simplified)
-        let (module, args) = self.extract_module_and_args(&raw_task)?;
-
-        // Don't render templates in args - preserve them for runtime evaluation
-        let normalized_args = args;
-
-        Ok(ParsedTask {
-            id,
-            name,
-            module,
-            args: normalized_args,
-            vars: raw_task.vars.unwrap_or_default(),
-            when: raw_task.when,
-            loop_items: raw_task.loop_items,
-            tags: raw_task.tags.unwrap_or_default(),
-            notify: raw_task.notify.unwrap_or_default(),
-            changed_when: raw_task.changed_when,
-            failed_when: raw_task.failed_when,
-            ignore_errors: raw_task.ignore_errors.unwrap_or(false),
-            delegate_to: raw_task.delegate_to,
-            dependencies: Vec::new(),
-        })
-    }
-
-    /// Apply include-level properties to a task
-    fn apply_include_properties(
-        &self,
-        mut task: ParsedTask,
-        include_spec: &IncludeSpec,
-    ) -> Result<ParsedTask, ParseError> {
-        // Apply include-level tags
-        if let Some(include_tags) = &include_spec.tags {
-            task.tags.extend(include_tags.clone());
-        }
-
-        // Apply include-level when condition
-        if let Some(include_when) = &include_spec.when_condition {
-            if let Some(existing_when) = &task.when {
-                // Combine conditions with AND
-                task.when = Some(format!("({existing_when}) and ({include_when})"));
-            } else {
-                task.when = Some(include_when.clone());
-            }
-        }
-
-        // Apply delegate_to if specified
-        if let Some(delegate_to) = &include_spec.delegate_to {
-            task.delegate_to = Some(delegate_to.clone());
-        }
-
-        // Apply apply block properties
-        if let Some(apply_spec) = &include_spec.apply {
-            if let Some(apply_tags) = &apply_spec.tags {
-                task.tags.extend(apply_tags.clone());
-            }
-
-            if let Some(apply_when) = &apply_spec.when_condition {
-                if let Some(existing_when) = &task.when {
-                    task.when = Some(format!("({existing_when}) and ({apply_when})"));
-                } else {
-                    task.when = Some(apply_when.clone());
-                }
-            }
-        }
-
-        Ok(task)
-    }
-
-    /// Load file with caching
-    async fn load_file_cached(&mut self, path: &Path) -> Result<String, ParseError> {
-        let file_metadata =
-            fs::metadata(path)
-                .await
-                .map_err(|_| ParseError::IncludeFileNotFound {
-                    file: path.to_string_lossy().to_string(),
-                })?;
-
-        let file_modified = file_metadata
-            .modified()
-            .unwrap_or_else(|_| std::time::SystemTime::now());
-
-        // Check cache first if enabled
-        if self.config.enable_include_cache {
-            if let Some(cached_content) = self.cache.get(&path.to_path_buf(), file_modified) {
-                return Ok(cached_content.to_string());
-            }
-        }
-
-        // Load file and update cache
-        let content =
-            fs::read_to_string(path)
-                .await
-                .map_err(|_| ParseError::IncludeFileNotFound {
-                    file: path.to_string_lossy().to_string(),
-                })?;
-
-        if self.config.enable_include_cache {
-            self.cache
-                .insert(path.to_path_buf(), content.clone(), file_modified);
-        }
-
-        Ok(content)
-    }
-
-    /// Extract module and args from raw task (simplified version)
-    fn extract_module_and_args(
-        &self,
-        raw_task: &RawTask,
-    ) -> Result<(String, HashMap<String, serde_json::Value>), ParseError> {
-        // Look for known module keys
-        let module_keys = [
-            "shell",
-            "command",
-            "copy",
-            "file",
-            "template",
-            "service",
-            "package",
-            "yum",
-            "apt",
-            "git",
-            "debug",
-            "set_fact",
-            "include",
-            "include_tasks",
-            "import_tasks",
-            "include_role",
-            "import_role",
-            "include_vars",
-            "block",
-            "rescue",
-            "always",
-            "meta",
-            "setup",
-            "gather_facts",
-            "ping",
-            "uri",
-            "get_url",
-            "unarchive",
-            "lineinfile",
-            "replace",
-            "stat",
-            "find",
-            "user",
-            "group",
-            "cron",
-            "systemd",
-            "assert",
-            "postgresql_db",
-            "wait_for",
-        ];
-
-        for &key in &module_keys {
-            if let Some(value) = raw_task.module_args.get(key) {
-                let args = match value {
-                    serde_json::Value::String(s) => {
-                        let mut args = HashMap::new();
-                        args.insert(
-                            "_raw_params".to_string(),
-                            serde_json::Value::String(s.clone()),
-                        );
-                        args
-                    }
-                    serde_json::Value::Object(obj) => {
-                        obj.iter().map(|(k, v)| (k.clone(), v.clone())).collect()
-                    }
-                    _ => {
-                        let mut args = HashMap::new();
-                        args.insert("_raw_params".to_string(), value.clone());
-                        args
-                    }
-                };
-                return Ok((key.to_string(), args));
-            }
-        }
-
-        // Provide more context about the failed task
-        let available_modules: Vec<String> = raw_task.module_args.keys().cloned().collect();
-        let task_name = raw_task.name.as_deref().unwrap_or("unnamed");
-        Err(ParseError::InvalidStructure {
-            message: format!("No valid module found in task '{}'. Available keys: {:?}. Known modules: include_tasks={}, import_tasks={}", 
-                task_name, available_modules,
-                module_keys.contains(&"include_tasks"), 
-                module_keys.contains(&"import_tasks")),
-        })
-    }
-
-    /// Render task arguments through template engine
-    #[allow(dead_code)]
-    async fn render_task_args(
-        &self,
-        args: HashMap<String, serde_json::Value>,
-        vars: &HashMap<String, serde_json::Value>,
-    ) -> Result<HashMap<String, serde_json::Value>, ParseError> {
-        let mut rendered_args = HashMap::new();
-
-        for (key, value) in args {
-            let rendered_value = self.template_engine.render_value(&value, vars)?;
-            rendered_args.insert(key, rendered_value);
-        }
-
-        Ok(rendered_args)
-    }
-
     /// Clear include cache
     pub fn clear_cache(&mut self) {
-        self.cache.clear();
+        self.loader.clear();
     }
 
     /// Get include statistics
@@ -635,208 +423,26 @@ impl IncludeHandler {
         IncludeStats {
             current_depth: self.include_stack.depth(),
             max_depth: self.config.max_include_depth,
-            cache_stats: self.cache.stats(),
-        }
-    }
-
-    /// Parse a play with the given context (simplified version for include handlers)
-    async fn parse_play_with_context(
-        &self,
-        raw_play: RawPlay,
-        context: &IncludeContext,
-    ) -> Result<ParsedPlay, ParseError> {
-        // Create a simplified play parser - in a full implementation,
-        // this would delegate to the main playbook parser
-        let mut play_vars = context.variables.clone();
-
-        // Merge play vars and render any templates in them
-        if let Some(vars) = raw_play.vars {
-            // First pass: add all raw variables
-            for (key, value) in &vars {
-                play_vars.insert(key.clone(), value.clone());
-            }
-
-            // Second pass: render templates that may reference other variables
-            for (key, value) in vars {
-                let rendered_value = self.template_engine.render_value(&value, &play_vars)?;
-                play_vars.insert(key, rendered_value);
-            }
-        }
-
-        // Parse hosts pattern and render templates
-        let hosts = match raw_play.hosts {
-            Some(RawHostPattern::Single(host)) => {
-                let rendered_host = if host.contains("{{") && host.contains("}}") {
-                    self.template_engine.render_string(&host, &play_vars)?
-                } else {
-                    host
-                };
-
-                if rendered_host == "all" {
-                    HostPattern::All
-                } else {
-                    HostPattern::Single(rendered_host)
-                }
-            }
-            Some(RawHostPattern::Multiple(hosts)) => {
-                let mut rendered_hosts = Vec::new();
-                for host in hosts {
-                    let rendered_host = if host.contains("{{") && host.contains("}}") {
-                        self.template_engine.render_string(&host, &play_vars)?
-                    } else {
-                        host
-                    };
-                    rendered_hosts.push(rendered_host);
-                }
-                HostPattern::Multiple(rendered_hosts)
-            }
-            Some(RawHostPattern::All) => HostPattern::All,
-            None => HostPattern::Single("localhost".to_string()),
-        };
-
-        // Parse tasks
-        let mut tasks = Vec::new();
-        if let Some(raw_tasks) = raw_play.tasks {
-            for (index, raw_task_value) in raw_tasks.into_iter().enumerate() {
-                let raw_task: RawTask =
-                    serde_yaml::from_value(raw_task_value).map_err(ParseError::Yaml)?;
-                let task = self
-                    .parse_task_with_context(raw_task, context, index)
-                    .await?;
-                tasks.push(task);
-            }
+            cache_stats: self.loader.stats(),
         }
-
-        // Parse handlers
-        let mut handlers = Vec::new();
-        if let Some(raw_handlers) = raw_play.handlers {
-            for (index, raw_handler_value) in raw_handlers.into_iter().enumerate() {
-                let raw_handler: RawTask =
-                    serde_yaml::from_value(raw_handler_value).map_err(ParseError::Yaml)?;
-                let handler = self
-                    .parse_task_with_context(raw_handler, context, index)
-                    .await?;
-                handlers.push(handler);
-            }
-        }
-
-        // Parse roles (simplified)
-        let mut roles = Vec::new();
-        if let Some(raw_roles) = raw_play.roles {
-            for raw_role in raw_roles {
-                let role = self.parse_role_simple(raw_role)?;
-                roles.push(role);
-            }
-        }
-
-        // Don't render play name - preserve templates for runtime
-        let play_name = raw_play.name.unwrap_or_else(|| "Unnamed play".to_string());
-
-        Ok(ParsedPlay {
-            name: play_name,
-            hosts,
-            vars: play_vars,
-            tasks,
-            handlers,
-            roles,
-            strategy: raw_play.strategy.unwrap_or_default(),
-            serial: raw_play.serial,
-            max_fail_percentage: raw_play.max_fail_percentage,
-        })
     }
+}
 
-    /// Apply include-level properties to a play
-    fn apply_include_properties_to_play(
-        &self,
-        mut play: ParsedPlay,
-        include_spec: &IncludeSpec,
-    ) -> Result<ParsedPlay, ParseError> {
-        // Apply include-level variables to play vars
-        if let Some(include_vars) = &include_spec.vars {
-            for (key, value) in include_vars {
-                play.vars.insert(key.clone(), value.clone());
-            }
-        }
-
-        // Apply tags to all tasks in the play
-        if let Some(include_tags) = &include_spec.tags {
-            for task in &mut play.tasks {
-                task.tags.extend(include_tags.clone());
-            }
-            for handler in &mut play.handlers {
-                handler.tags.extend(include_tags.clone());
-            }
-        }
-
-        // Apply when condition to all tasks
-        if let Some(include_when) = &include_spec.when_condition {
-            for task in &mut play.tasks {
-                if let Some(existing_when) = &task.when {
-                    task.when = Some(format!("({existing_when}) and ({include_when})"));
-                } else {
-                    task.when = Some(include_when.clone());
-                }
-            }
-            for handler in &mut play.handlers {
-                if let Some(existing_when) = &handler.when {
-                    handler.when = Some(format!("({existing_when}) and ({include_when})"));
-                } else {
-                    handler.when = Some(include_when.clone());
-                }
-            }
-        }
-
-        // Apply apply block properties
-        if let Some(apply_spec) = &include_spec.apply {
-            if let Some(apply_tags) = &apply_spec.tags {
-                for task in &mut play.tasks {
-                    task.tags.extend(apply_tags.clone());
-                }
-                for handler in &mut play.handlers {
-                    handler.tags.extend(apply_tags.clone());
-                }
-            }
-
-            if let Some(apply_when) = &apply_spec.when_condition {
-                for task in &mut play.tasks {
-                    if let Some(existing_when) = &task.when {
-                        task.when = Some(format!("({existing_when}) and ({apply_when})"));
-                    } else {
-                        task.when = Some(apply_when.clone());
-                    }
-                }
-                for handler in &mut play.handlers {
-                    if let Some(existing_when) = &handler.when {
-                        handler.when = Some(format!("({existing_when}) and ({apply_when})"));
-                    } else {
-                        handler.when = Some(apply_when.clone());
-                    }
-                }
-            }
-        }
-
-        Ok(play)
-    }
+/// A task file opened for an include, import or role
+pub(crate) struct IncludedTasks {
+    pub raw_tasks: Vec<RawTask>,
+    pub source_map: SourceMap,
+    /// Variables and file in scope inside the included file
+    pub context: IncludeContext,
+}
 
-    /// Simple role parser for included playbooks
-    fn parse_role_simple(&self, raw_role: RawRole) -> Result<ParsedRole, ParseError> {
-        match raw_role {
-            RawRole::String(name) => Ok(ParsedRole {
-                name,
-                src: None,
-                version: None,
-                vars: HashMap::new(),
-                tags: Vec::new(),
-            }),
-            RawRole::Object(role_obj) => Ok(ParsedRole {
-                name: role_obj.name,
-                src: role_obj.src,
-                version: role_obj.version,
-                vars: role_obj.vars.unwrap_or_default(),
-                tags: role_obj.tags.unwrap_or_default(),
-            }),
-        }
-    }
+/// A playbook opened for an `include_playbook` or `import_playbook`
+pub(crate) struct IncludedPlaybook {
+    /// Plays and nested playbook includes, in order
+    pub items: Vec<serde_yaml::Value>,
+    pub source_map: SourceMap,
+    /// Variables and file in scope inside the included playbook
+    pub context: IncludeContext,
 }
 
 /// Statistics about include processing
@@ -847,104 +453,10 @@ pub struct IncludeStats {
     pub cache_stats: crate::parser::include::cache::CacheStats,
 }
 
-// Placeholder raw task structure - should match the one in playbook.rs
-#[derive(Debug, serde::Deserialize)]
-struct RawTask {
-    id: Option<String>,
-    name: Option<String>,
-    vars: Option<HashMap<String, serde_json::Value>>,
-    when: Option<String>,
-    #[serde(rename = "loop")]
-    loop_items: Option<serde_json::Value>,
-    tags: Option<Vec<String>>,
-    notify: Option<Vec<String>>,
-    #[serde(
-        deserialize_with = "crate::parser::playbook::deserialize_boolean_or_string",
-        default
-    )]
-    changed_when: Option<crate::types::parsed::BooleanOrString>,
-    #[serde(
-        deserialize_with = "crate::parser::playbook::deserialize_boolean_or_string",
-        default
-    )]
-    failed_when: Option<crate::types::parsed::BooleanOrString>,
-    #[serde(deserialize_with = "deserialize_yaml_bool", default)]
-    ignore_errors: Option<bool>,
-    delegate_to: Option<String>,
-    #[serde(rename = "become", deserialize_with = "deserialize_yaml_bool", default)]
-    #[allow(dead_code)]
-    r#become: Option<bool>,
-    #[allow(dead_code)]
-    become_user: Option<String>,
-    #[allow(dead_code)]
-    become_method: Option<String>,
-    #[allow(dead_code)]
-    register: Option<String>,
-    #[serde(flatten)]
-    module_args: HashMap<String, serde_json::Value>,
-}
-
-// Custom deserializer for boolean fields that handles YAML boolean strings
-fn deserialize_yaml_bool<'de, D>(deserializer: D) -> Result<Option<bool>, D::Error>
-where
-    D: serde::Deserializer<'de>,
-{
-    use serde::de::Error;
-
-    let value: Option<serde_yaml::Value> = serde::Deserialize::deserialize(deserializer)?;
-    match value {
-        None => Ok(None),
-        Some(serde_yaml::Value::Bool(b)) => Ok(Some(b)),
-        Some(serde_yaml::Value::String(s)) => match s.to_lowercase().as_str() {
-            "yes" | "true" | "on" => Ok(Some(true)),
-            "no" | "false" | "off" => Ok(Some(false)),
-            _ => Err(Error::custom(format!("Invalid boolean string: {s}"))),
-        },
-        Some(_) => Err(Error::custom("Expected boolean or boolean string")),
-    }
-}
-
-// Raw data structures for playbook parsing
-#[derive(Debug, serde::Deserialize)]
-struct RawPlay {
-    name: Option<String>,
-    hosts: Option<RawHostPattern>,
-    vars: Option<HashMap<String, serde_json::Value>>,
-    tasks: Option<Vec<serde_yaml::Value>>,
-    handlers: Option<Vec<serde_yaml::Value>>,
-    roles: Option<Vec<RawRole>>,
-    strategy: Option<ExecutionStrategy>,
-    serial: Option<u32>,
-    max_fail_percentage: Option<f32>,
-}
-
-#[derive(Debug, serde::Deserialize)]
-#[serde(untagged)]
-enum RawHostPattern {
-    Single(String),
-    Multiple(Vec<String>),
-    All,
-}
-
-#[derive(Debug, serde::Deserialize)]
-#[serde(untagged)]
-enum RawRole {
-    String(String),
-    Object(RawRoleObject),
-}
-
-#[derive(Debug, serde::Deserialize)]
-struct RawRoleObject {
-    name: String,
-    src: Option<String>,
-    version: Option<String>,
-    vars: Option<HashMap<String, serde_json::Value>>,
-    tags: Option<Vec<String>>,
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::parser::task_id::TaskPath;
     use std::fs;
     use tempfile::TempDir;
 
@@ -983,6 +495,7 @@ mod tests {
             delegate_to: None,
             delegate_facts: None,
             run_once: None,
+            name: None,
         };
 
         let context = IncludeContext {
@@ -991,16 +504,26 @@ mod tests {
             include_depth: 0,
             tags: Vec::new(),
             when_condition: None,
+            task_path: TaskPath::default(),
         };
 
-        let tasks = include_handler
-            .include_tasks(&include_spec, &context)
+        let path = include_handler
+            .resolve_path(&include_spec.file, &context)
+            .unwrap();
+        let included = include_handler
+            .open_tasks(path, include_spec.vars.as_ref(), &context)
             .await
             .unwrap();
-
-        assert_eq!(tasks.len(), 2);
-        assert_eq!(tasks[0].name, "Install package");
-        assert_eq!(tasks[1].name, "Create user");
+        include_handler.close();
+
+        let names: Vec<_> = included
+            .raw_tasks
+            .iter()
+            .map(|task| task.name.as_deref())
+            .collect();
+        assert_eq!(names, vec![Some("Install package"), Some("Create user")]);
+        assert_eq!(included.context.include_depth, 1);
+        assert_eq!(include_handler.get_stats().current_depth, 0);
     }
 
     #[tokio::test]
@@ -1039,6 +562,7 @@ mod tests {
             delegate_to: None,
             delegate_facts: None,
             run_once: None,
+            name: None,
         };
 
         let context = IncludeContext {
@@ -1047,15 +571,27 @@ mod tests {
             include_depth: 0,
             tags: Vec::new(),
             when_condition: None,
+            task_path: TaskPath::default(),
         };
 
-        let tasks = include_handler
-            .include_tasks(&include_spec, &context)
+        let path = include_handler
+            .resolve_path(&include_spec.file, &context)
+            .unwrap();
+        let included = include_handler
+            .open_tasks(path, include_spec.vars.as_ref(), &context)
             .await
             .unwrap();
+        include_handler.close();
 
-        assert_eq!(tasks.len(), 1);
-        // Since we don't render templates during parsing, check for the template
-        assert_eq!(tasks[0].name, "Install {{ package_name }}");
+        assert_eq!(included.raw_tasks.len(), 1);
+        // Tasks are parsed later, with the include's variables in scope
+        assert_eq!(
+            included.raw_tasks[0].name.as_deref(),
+            Some("Install {{ package_name }}")
+        );
+        assert_eq!(
+            included.context.variables["package_name"],
+            serde_json::json!("nginx")
+        );
     }
 }
@@ -0,0 +1,222 @@
+use crate::parser::error::ParseError;
+use crate::parser::include::cache::{CacheStats, IncludeCache};
+use crate::parser::include::IncludeConfig;
+use crate::parser::source_map::SourceMap;
+use crate::parser::yaml::{self, YamlLimits};
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use tokio::fs;
+use tokio::task::JoinSet;
+
+/// A YAML file read from disk, with its parsed document and source positions
+#[derive(Debug)]
+pub struct LoadedFile {
+    pub content: String,
+    pub document: serde_yaml::Value,
+    pub source_map: SourceMap,
+}
+
+impl LoadedFile {
+    /// Parse `content`, read from `path`
+    pub fn parse(path: &Path, content: String, limits: &YamlLimits) -> Result<Self, ParseError> {
+        let document = yaml::load_with_limits(&content, limits)?;
+        let source_map = SourceMap::parse(path.to_string_lossy(), &content);
+        Ok(Self {
+            content,
+            document,
+            source_map,
+        })
+    }
+
+    /// Deserialize the document
+    pub fn deserialize<T: serde::de::DeserializeOwned>(&self) -> Result<T, ParseError> {
+        Ok(serde_yaml::from_value(self.document.clone())?)
+    }
+}
+
+/// Reads and parses include, role and vars files.
+///
+/// Clones share one cache, so a file prefetched by one part of the parser is
+/// not read again by another. Files are parsed on tokio's blocking pool.
+#[derive(Debug, Clone)]
+pub struct FileLoader {
+    cache: Arc<Mutex<IncludeCache>>,
+    enable_cache: bool,
+    yaml_limits: YamlLimits,
+}
+
+impl FileLoader {
+    pub fn new(config: &IncludeConfig) -> Self {
+        Self {
+            cache: Arc::new(Mutex::new(IncludeCache::new(1000, config.cache_ttl))),
+            enable_cache: config.enable_include_cache,
+            yaml_limits: config.yaml_limits,
+        }
+    }
+
+    /// Parse `content` read from `path` with the loader's YAML limits, without caching it
+    pub fn parse(&self, path: &Path, content: String) -> Result<LoadedFile, ParseError> {
+        LoadedFile::parse(path, content, &self.yaml_limits)
+    }
+
+    /// Read and parse `path`, or return the cached copy if the file has not
+    /// changed since it was loaded
+    pub async fn load(&self, path: &Path) -> Result<Arc<LoadedFile>, ParseError> {
+        let not_found = || ParseError::IncludeFileNotFound {
+            file: path.to_string_lossy().to_string(),
+        };
+        let file_modified = fs::metadata(path)
+            .await
+            .map_err(|_| not_found())?
+            .modified()
+            .unwrap_or_else(|_| std::time::SystemTime::now());
+
+        if self.enable_cache {
+            if let Some(file) = self.lock().get(path, file_modified) {
+                return Ok(file);
+            }
+        }
+
+        let content = fs::read_to_string(path).await.map_err(|_| not_found())?;
+        let owned_path = path.to_path_buf();
+        let limits = self.yaml_limits;
+        let file =
+            tokio::task::spawn_blocking(move || LoadedFile::parse(&owned_path, content, &limits))
+                .await
+                .unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()))?;
+        let file = Arc::new(file);
+
+        if self.enable_cache {
+            self.lock()
+                .insert(path.to_path_buf(), file.clone(), file_modified);
+        }
+        Ok(file)
+    }
+
+    /// Load every path concurrently, returning the results in the order given
+    pub async fn load_all(&self, paths: Vec<PathBuf>) -> Vec<Result<Arc<LoadedFile>, ParseError>> {
+        load_concurrently(paths, |path| {
+            let loader = self.clone();
+            async move { loader.load(&path).await }
+        })
+        .await
+    }
+
+    /// Load `paths` into the cache ahead of their use. Failures are left for
+    /// the later load to report, where the file is actually needed.
+    pub async fn prefetch(&self, mut paths: Vec<PathBuf>) {
+        paths.sort();
+        paths.dedup();
+        if self.enable_cache && paths.len() > 1 {
+            self.load_all(paths).await;
+        }
+    }
+
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.lock().stats()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, IncludeCache> {
+        // The cache holds no invariants a panicking holder could break
+        self.cache.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Default for FileLoader {
+    fn default() -> Self {
+        Self::new(&IncludeConfig::default())
+    }
+}
+
+/// Run `load` on every path as its own tokio task, returning the results in
+/// the order of `paths` however the tasks finish
+pub async fn load_concurrently<T, F, Fut>(
+    paths: Vec<PathBuf>,
+    load: F,
+) -> Vec<Result<T, ParseError>>
+where
+    T: Send + 'static,
+    F: Fn(PathBuf) -> Fut,
+    Fut: Future<Output = Result<T, ParseError>> + Send + 'static,
+{
+    let mut tasks = JoinSet::new();
+    for (index, path) in paths.into_iter().enumerate() {
+        let load = load(path);
+        tasks.spawn(async move { (index, load.await) });
+    }
+
+    let mut results: Vec<_> = std::iter::repeat_with(|| None).take(tasks.len()).collect();
+    while let Some(joined) = tasks.join_next().await {
+        let (index, result) = joined.unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()));
+        results[index] = Some(result);
+    }
+    results.into_iter().flatten().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_load_all_keeps_order() {
+        let temp_dir = TempDir::new().unwrap();
+        let paths: Vec<PathBuf> = (0..20)
+            .map(|i| {
+                let path = temp_dir.path().join(format!("file{i}.yml"));
+                std::fs::write(&path, format!("index: {i}\n")).unwrap();
+                path
+            })
+            .collect();
+
+        let loader = FileLoader::default();
+        let results = loader.load_all(paths.clone()).await;
+
+        for (i, result) in results.into_iter().enumerate() {
+            let file = result.unwrap();
+            assert_eq!(file.document["index"], serde_yaml::Value::from(i));
+            assert_eq!(file.source_map.file(), paths[i].to_string_lossy());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_prefetch_shares_cache_between_clones() {
+        let temp_dir = TempDir::new().unwrap();
+        let first = temp_dir.path().join("first.yml");
+        let second = temp_dir.path().join("second.yml");
+        std::fs::write(&first, "- a\n").unwrap();
+        std::fs::write(&second, "- b\n").unwrap();
+
+        let loader = FileLoader::default();
+        loader.prefetch(vec![first.clone(), second.clone()]).await;
+        assert_eq!(loader.stats().entries, 2);
+
+        let cached = loader.clone().load(&first).await.unwrap();
+        let again = loader.load(&first).await.unwrap();
+        assert!(Arc::ptr_eq(&cached, &again));
+    }
+
+    #[tokio::test]
+    async fn test_load_reports_missing_and_invalid_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let invalid = temp_dir.path().join("invalid.yml");
+        std::fs::write(&invalid, "key: [unclosed\n").unwrap();
+
+        let loader = FileLoader::default();
+        let results = loader
+            .load_all(vec![temp_dir.path().join("missing.yml"), invalid])
+            .await;
+
+        assert!(matches!(
+            results[0],
+            Err(ParseError::IncludeFileNotFound { .. })
+        ));
+        assert!(results[1].is_err());
+        assert_eq!(loader.stats().entries, 0);
+    }
+}
@@ -1,16 +1,28 @@
+pub mod argument_specs;
 pub mod cache;
 pub mod dependency;
 pub mod handler;
+pub mod loader;
 pub mod resolver;
 pub mod roles;
 pub mod tasks;
 pub mod variables;
 
+pub use argument_specs::RoleArgumentSpecs;
 pub use cache::CachedInclude;
 pub use dependency::IncludeDependencyGraph;
 pub use handler::IncludeHandler;
+pub use loader::FileLoader;
 pub use resolver::PathResolver;
 
+use crate::parser::condition::{deserialize_conditions, inherit_conditions, parse_condition};
+use crate::parser::config::default_roles_path;
+use crate::parser::error::ParseError;
+use crate::parser::keywords::{deserialize_bool_or_template, deserialize_string, deserialize_tags};
+use crate::parser::tags::inherit_tags;
+use crate::parser::task_id::TaskPath;
+use crate::parser::yaml::YamlLimits;
+use crate::types::parsed::{BooleanOrString, Condition, ParsedTask, TaskKeywords};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::PathBuf;
@@ -25,6 +37,7 @@ pub struct IncludeConfig {
     pub strict_file_permissions: bool,
     pub allow_absolute_paths: bool,
     pub role_search_paths: Vec<PathBuf>,
+    pub yaml_limits: YamlLimits,
 }
 
 impl Default for IncludeConfig {
@@ -35,11 +48,8 @@ impl Default for IncludeConfig {
             cache_ttl: Duration::from_secs(300), // 5 minutes
             strict_file_permissions: true,
             allow_absolute_paths: false,
-            role_search_paths: vec![
-                PathBuf::from("roles"),
-                PathBuf::from("../roles"),
-                PathBuf::from("/etc/ansible/roles"),
-            ],
+            role_search_paths: default_roles_path(),
+            yaml_limits: YamlLimits::default(),
         }
     }
 }
@@ -48,11 +58,15 @@ impl Default for IncludeConfig {
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct IncludeSpec {
     pub file: String,
+    /// Name of the include task
     #[serde(default)]
-    pub vars: Option<HashMap<String, serde_json::Value>>,
-    #[serde(rename = "when")]
-    pub when_condition: Option<String>,
+    pub name: Option<String>,
     #[serde(default)]
+    pub vars: Option<HashMap<String, serde_json::Value>>,
+    /// Clauses that must all hold, evaluated once when the include runs
+    #[serde(rename = "when", default, deserialize_with = "deserialize_conditions")]
+    pub when_condition: Option<Vec<String>>,
+    #[serde(default, deserialize_with = "deserialize_tags")]
     pub tags: Option<Vec<String>>,
     #[serde(default)]
     pub apply: Option<ApplySpec>,
@@ -61,16 +75,57 @@ pub struct IncludeSpec {
     pub run_once: Option<bool>,
 }
 
+impl IncludeSpec {
+    /// What the include passes on to every task it loads.
+    ///
+    /// Keywords on a dynamic include apply to the include itself; only those
+    /// under `apply` reach the included tasks. The include's `when` is kept in
+    /// front of them, since the tasks only exist once it has held, and its
+    /// `vars` are visible to every task it loads.
+    pub fn inherited(&self) -> Result<InheritedKeywords, ParseError> {
+        let location = format!("include of '{}'", self.file);
+        let mut inherited = match &self.apply {
+            Some(apply) => apply.inherited(&location)?,
+            None => InheritedKeywords::default(),
+        };
+        inherited.vars.extend(self.vars.clone().unwrap_or_default());
+        let gate = parse_clauses(self.when_condition.as_deref(), &location)?;
+        inherited.when.splice(0..0, gate);
+        Ok(inherited)
+    }
+}
+
 /// Specification for import directives (subset of include features)
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ImportSpec {
     pub file: String,
     #[serde(default)]
     pub vars: Option<HashMap<String, serde_json::Value>>,
-    #[serde(rename = "when")]
-    pub when_condition: Option<String>,
-    #[serde(default)]
+    #[serde(rename = "when", default, deserialize_with = "deserialize_conditions")]
+    pub when_condition: Option<Vec<String>>,
+    #[serde(default, deserialize_with = "deserialize_tags")]
     pub tags: Option<Vec<String>>,
+    #[serde(default, deserialize_with = "deserialize_string")]
+    pub delegate_to: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_bool_or_template")]
+    pub ignore_errors: Option<BooleanOrString>,
+    #[serde(flatten)]
+    pub keywords: TaskKeywords,
+}
+
+impl ImportSpec {
+    /// Every keyword of a static import is inherited by each imported task
+    pub fn inherited(&self) -> Result<InheritedKeywords, ParseError> {
+        let location = format!("import of '{}'", self.file);
+        Ok(InheritedKeywords {
+            tags: self.tags.clone().unwrap_or_default(),
+            when: parse_clauses(self.when_condition.as_deref(), &location)?,
+            vars: self.vars.clone().unwrap_or_default(),
+            delegate_to: self.delegate_to.clone(),
+            ignore_errors: self.ignore_errors.clone(),
+            keywords: self.keywords.clone(),
+        })
+    }
 }
 
 /// Specification for role includes
@@ -83,23 +138,121 @@ pub struct RoleIncludeSpec {
     pub handlers_from: Option<String>,
     #[serde(default)]
     pub vars: Option<HashMap<String, serde_json::Value>>,
-    #[serde(rename = "when")]
-    pub when_condition: Option<String>,
-    #[serde(default)]
+    #[serde(rename = "when", default, deserialize_with = "deserialize_conditions")]
+    pub when_condition: Option<Vec<String>>,
+    #[serde(default, deserialize_with = "deserialize_tags")]
     pub tags: Option<Vec<String>>,
     #[serde(default)]
     pub apply: Option<ApplySpec>,
+    #[serde(default, deserialize_with = "deserialize_string")]
+    pub delegate_to: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_bool_or_template")]
+    pub ignore_errors: Option<BooleanOrString>,
+    #[serde(flatten)]
+    pub keywords: TaskKeywords,
+}
+
+impl RoleIncludeSpec {
+    /// What `include_role` passes on to the role's tasks and handlers: the
+    /// `apply` keywords, behind the include's own `when`
+    pub fn included(&self) -> Result<InheritedKeywords, ParseError> {
+        let location = format!("include of role '{}'", self.name);
+        let mut inherited = match &self.apply {
+            Some(apply) => apply.inherited(&location)?,
+            None => InheritedKeywords::default(),
+        };
+        let gate = parse_clauses(self.when_condition.as_deref(), &location)?;
+        inherited.when.splice(0..0, gate);
+        Ok(inherited)
+    }
+
+    /// What `import_role` passes on: every keyword of the import
+    pub fn imported(&self) -> Result<InheritedKeywords, ParseError> {
+        let location = format!("import of role '{}'", self.name);
+        Ok(InheritedKeywords {
+            tags: self.tags.clone().unwrap_or_default(),
+            when: parse_clauses(self.when_condition.as_deref(), &location)?,
+            vars: HashMap::new(),
+            delegate_to: self.delegate_to.clone(),
+            ignore_errors: self.ignore_errors.clone(),
+            keywords: self.keywords.clone(),
+        })
+    }
 }
 
 /// Apply block specification for includes
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct ApplySpec {
-    #[serde(default)]
+    #[serde(default, deserialize_with = "deserialize_tags")]
     pub tags: Option<Vec<String>>,
-    #[serde(rename = "when")]
-    pub when_condition: Option<String>,
-    pub r#become: Option<bool>,
-    pub become_user: Option<String>,
+    #[serde(rename = "when", default, deserialize_with = "deserialize_conditions")]
+    pub when_condition: Option<Vec<String>>,
+    #[serde(default)]
+    pub vars: Option<HashMap<String, serde_json::Value>>,
+    #[serde(default, deserialize_with = "deserialize_string")]
+    pub delegate_to: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_bool_or_template")]
+    pub ignore_errors: Option<BooleanOrString>,
+    #[serde(flatten)]
+    pub keywords: TaskKeywords,
+}
+
+impl ApplySpec {
+    fn inherited(&self, location: &str) -> Result<InheritedKeywords, ParseError> {
+        Ok(InheritedKeywords {
+            tags: self.tags.clone().unwrap_or_default(),
+            when: parse_clauses(self.when_condition.as_deref(), location)?,
+            vars: self.vars.clone().unwrap_or_default(),
+            delegate_to: self.delegate_to.clone(),
+            ignore_errors: self.ignore_errors.clone(),
+            keywords: self.keywords.clone(),
+        })
+    }
+}
+
+/// Keywords an import, or an include's `apply`, passes on to each task it loads.
+///
+/// Tags and conditions add to the task's own, with inherited conditions
+/// evaluated first. Vars and the other keywords only fill in what the task
+/// leaves unset.
+#[derive(Debug, Clone, Default)]
+pub struct InheritedKeywords {
+    pub tags: Vec<String>,
+    pub when: Vec<Condition>,
+    pub vars: HashMap<String, serde_json::Value>,
+    pub delegate_to: Option<String>,
+    pub ignore_errors: Option<BooleanOrString>,
+    pub keywords: TaskKeywords,
+}
+
+impl InheritedKeywords {
+    pub fn apply_to(&self, task: &mut ParsedTask) {
+        inherit_tags(task, &self.tags);
+        inherit_conditions(&mut task.when, &self.when);
+        for (key, value) in &self.vars {
+            task.vars
+                .entry(key.clone())
+                .or_insert_with(|| value.clone());
+        }
+        if task.delegate_to.is_none() {
+            task.delegate_to.clone_from(&self.delegate_to);
+        }
+        // A task's own `ignore_errors: false` is not told apart from unset
+        if let Some(ignore_errors) = &self.ignore_errors {
+            if task.ignore_errors == BooleanOrString::Boolean(false) {
+                task.ignore_errors = ignore_errors.clone();
+            }
+        }
+        task.keywords.inherit(&self.keywords);
+    }
+}
+
+fn parse_clauses(clauses: Option<&[String]>, location: &str) -> Result<Vec<Condition>, ParseError> {
+    clauses
+        .unwrap_or_default()
+        .iter()
+        .map(|expression| parse_condition(expression, location))
+        .collect()
 }
 
 /// Context for include processing
@@ -110,6 +263,8 @@ pub struct IncludeContext {
     pub include_depth: usize,
     pub tags: Vec<String>,
     pub when_condition: Option<String>,
+    /// Position of the include directive, used to derive ids for the tasks it loads
+    pub task_path: TaskPath,
 }
 
 /// Specification for include_vars directive
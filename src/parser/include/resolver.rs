@@ -1,3 +1,5 @@
+use crate::parser::collections::CollectionIndex;
+use crate::parser::config::default_roles_path;
 use crate::parser::error::ParseError;
 use std::path::{Path, PathBuf};
 
@@ -7,6 +9,8 @@ pub struct PathResolver {
     base_path: PathBuf,
     allow_absolute_paths: bool,
     strict_permissions: bool,
+    collections: CollectionIndex,
+    roles_path: Vec<PathBuf>,
 }
 
 impl PathResolver {
@@ -17,6 +21,8 @@ impl PathResolver {
             base_path: canonical_base,
             allow_absolute_paths: false,
             strict_permissions: true,
+            collections: CollectionIndex::default(),
+            roles_path: default_roles_path(),
         }
     }
 
@@ -30,6 +36,18 @@ impl PathResolver {
         self
     }
 
+    /// Installed collections, for `namespace.collection.role` role names
+    pub fn with_collections(mut self, collections: CollectionIndex) -> Self {
+        self.collections = collections;
+        self
+    }
+
+    /// Directories searched for roles after the ones next to the current file
+    pub fn with_roles_path(mut self, roles_path: Vec<PathBuf>) -> Self {
+        self.roles_path = roles_path;
+        self
+    }
+
     /// Resolve file path relative to current context with security validation
     pub fn resolve_path(
         &self,
@@ -63,12 +81,76 @@ impl PathResolver {
         Ok(canonical)
     }
 
+    /// Files matching `pattern`, where `*` matches any part of a single path
+    /// component. Paths are returned relative to the current file, like the
+    /// `file_path` given to [`resolve_path`](Self::resolve_path), and only
+    /// paths that pass its checks are kept.
+    pub fn glob_paths(&self, pattern: &str, current_file: &Path) -> Vec<String> {
+        let current_dir = current_file.parent().unwrap_or_else(|| Path::new("."));
+        let mut matches = if pattern.starts_with('/') {
+            vec![(PathBuf::from("/"), "/".to_string())]
+        } else {
+            vec![(current_dir.to_path_buf(), String::new())]
+        };
+
+        for component in pattern.split('/').filter(|c| !c.is_empty()) {
+            let mut next = Vec::new();
+            for (dir, relative) in matches {
+                let join = |name: &str| {
+                    if relative.is_empty() || relative.ends_with('/') {
+                        format!("{relative}{name}")
+                    } else {
+                        format!("{relative}/{name}")
+                    }
+                };
+                if !component.contains('*') {
+                    next.push((dir.join(component), join(component)));
+                    continue;
+                }
+
+                let Ok(entries) = std::fs::read_dir(&dir) else {
+                    continue;
+                };
+                let mut names: Vec<String> = entries
+                    .filter_map(|entry| entry.ok())
+                    .map(|entry| entry.file_name().to_string_lossy().to_string())
+                    .filter(|name| !name.starts_with('.') && wildcard_matches(component, name))
+                    .collect();
+                names.sort();
+                next.extend(names.iter().map(|name| (dir.join(name), join(name))));
+            }
+            matches = next;
+        }
+
+        matches
+            .into_iter()
+            .filter(|(path, _)| path.is_file())
+            .filter(|(_, relative)| self.resolve_path(relative, current_file).is_ok())
+            .map(|(_, relative)| relative)
+            .collect()
+    }
+
     /// Resolve role path from role name
     pub fn resolve_role_path(
         &self,
         role_name: &str,
         current_file: &Path,
     ) -> Result<PathBuf, ParseError> {
+        self.resolve_collection_role_path(role_name, current_file, &[])
+    }
+
+    /// Resolve role path from role name, searching `collections` (a play's
+    /// `collections` keyword) for short names before the roles directories
+    pub fn resolve_collection_role_path(
+        &self,
+        role_name: &str,
+        current_file: &Path,
+        collections: &[String],
+    ) -> Result<PathBuf, ParseError> {
+        if let Some((_, role_path)) = self.collections.resolve_role(role_name, collections) {
+            return Ok(role_path);
+        }
+
         // Try multiple locations for roles following Ansible conventions
         let search_paths = self.get_role_search_paths(current_file);
 
@@ -95,18 +177,17 @@ impl PathResolver {
     fn get_role_search_paths(&self, current_file: &Path) -> Vec<PathBuf> {
         let current_dir = current_file.parent().unwrap_or_else(|| Path::new("."));
 
-        vec![
+        let mut search_paths = vec![
             // Local roles directory relative to current file
             current_dir.join("roles"),
             // Parent directory roles (common in Ansible project structures)
             current_dir.join("..").join("roles"),
             // Base path roles
             self.base_path.join("roles"),
-            // System-wide Ansible roles
-            PathBuf::from("/etc/ansible/roles"),
-            // User Ansible roles (expanded later if needed)
-            PathBuf::from("~/.ansible/roles"),
-        ]
+        ];
+        // Configured roles path (`roles_path` / `ANSIBLE_ROLES_PATH`)
+        search_paths.extend(self.roles_path.iter().cloned());
+        search_paths
     }
 
     /// Validate absolute path is within allowed directories
@@ -194,6 +275,26 @@ impl PathResolver {
     }
 }
 
+/// Match `name` against `pattern`, where each `*` matches any run of characters
+fn wildcard_matches(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -284,6 +385,69 @@ mod tests {
         assert!(role_path.join("tasks").exists());
     }
 
+    #[test]
+    fn test_collection_role_path_resolution() {
+        let temp_dir = TempDir::new().unwrap();
+        let collections_dir = temp_dir.path().join("collections");
+        let role_dir = collections_dir.join("ansible_collections/acme/web/roles/nginx");
+        fs::create_dir_all(role_dir.join("tasks")).unwrap();
+        fs::create_dir_all(temp_dir.path().join("roles/nginx/tasks")).unwrap();
+
+        let collections = CollectionIndex::load(&[collections_dir]).unwrap();
+        let resolver =
+            PathResolver::new(temp_dir.path().to_path_buf()).with_collections(collections);
+        let current_file = temp_dir.path().join("playbook.yml");
+
+        let role_path = resolver
+            .resolve_role_path("acme.web.nginx", &current_file)
+            .unwrap();
+        assert_eq!(role_path, role_dir);
+
+        // Play collections are searched before the roles directories
+        let role_path = resolver
+            .resolve_collection_role_path("nginx", &current_file, &["acme.web".to_string()])
+            .unwrap();
+        assert_eq!(role_path, role_dir);
+        let role_path = resolver.resolve_role_path("nginx", &current_file).unwrap();
+        assert!(role_path.ends_with("roles/nginx"));
+        assert!(!role_path.starts_with(&role_dir));
+
+        assert!(resolver
+            .resolve_role_path("acme.web.missing", &current_file)
+            .is_err());
+    }
+
+    #[test]
+    fn test_glob_paths() {
+        let temp_dir = TempDir::new().unwrap();
+        let resolver = PathResolver::new(temp_dir.path().to_path_buf());
+        fs::create_dir_all(temp_dir.path().join("tasks/os")).unwrap();
+        for file in ["debian.yml", "redhat.yml", "notes.txt", ".hidden.yml"] {
+            fs::write(temp_dir.path().join("tasks/os").join(file), "").unwrap();
+        }
+        fs::create_dir_all(temp_dir.path().join("tasks/os/skip.yml")).unwrap();
+
+        let current_file = temp_dir.path().join("tasks/main.yml");
+        assert_eq!(
+            resolver.glob_paths("os/*.yml", &current_file),
+            vec!["os/debian.yml", "os/redhat.yml"]
+        );
+        assert_eq!(
+            resolver.glob_paths("*/red*.yml", &current_file),
+            vec!["os/redhat.yml"]
+        );
+        assert!(resolver
+            .glob_paths("missing/*.yml", &current_file)
+            .is_empty());
+        // Files outside the base directory are never candidates
+        assert!(resolver.glob_paths("../../*", &current_file).is_empty());
+
+        assert!(wildcard_matches("*.yml", "a.yml"));
+        assert!(wildcard_matches("a*b*c", "abc"));
+        assert!(!wildcard_matches("a*a", "a"));
+        assert!(!wildcard_matches("*.yml", "a.yaml"));
+    }
+
     #[test]
     fn test_role_not_found() {
         let temp_dir = TempDir::new().unwrap();
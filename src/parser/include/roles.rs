@@ -1,68 +1,76 @@
+use crate::parser::collections::split_fqcn;
 use crate::parser::error::ParseError;
-use crate::parser::include::{IncludeContext, RoleIncludeSpec};
+use crate::parser::include::{
+    FileLoader, IncludeContext, InheritedKeywords, RoleArgumentSpecs, RoleIncludeSpec,
+};
 use crate::types::parsed::{ParsedRole, ParsedTask};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
-use tokio::fs;
 
 /// Handler for role include/import functionality
 pub struct RoleIncludeProcessor;
 
 impl RoleIncludeProcessor {
-    /// Process include_role directive
-    pub async fn include_role(
+    /// Load the variables of the role at `role_path` with `loader` and find its
+    /// task and handler files. `inherited` is recorded for every task and handler.
+    async fn load_role(
         role_spec: &RoleIncludeSpec,
+        role_path: &Path,
         context: &IncludeContext,
+        loader: &FileLoader,
+        inherited: InheritedKeywords,
     ) -> Result<RoleIncludeResult, ParseError> {
         Self::validate_role_spec(role_spec)?;
 
-        let role_path = Self::resolve_role_path(&role_spec.name, &context.current_file)?;
-
-        let mut result = RoleIncludeResult::new(role_spec.name.clone());
-
-        // Load role components based on specification
-        if let Some(tasks_from) = &role_spec.tasks_from {
-            let tasks = Self::load_role_tasks(&role_path, tasks_from, context).await?;
-            result.tasks = tasks;
-        } else {
-            // Load default main.yml if no specific tasks_from specified
-            let tasks = Self::load_default_role_tasks(&role_path, context).await?;
-            result.tasks = tasks;
-        }
-
-        // Load role variables
-        if let Some(vars_from) = &role_spec.vars_from {
-            let vars = Self::load_role_vars(&role_path, vars_from, context).await?;
-            result.vars.extend(vars);
-        } else {
-            // Load default vars/main.yml
-            let vars = Self::load_default_role_vars(&role_path, context).await?;
-            result.vars.extend(vars);
+        let mut result = RoleIncludeResult::new(role_spec.name.clone(), role_path, inherited);
+
+        // A `tasks_from` file must exist, the default `main` file need not
+        let tasks_from = role_spec.tasks_from.as_deref().unwrap_or("main");
+        result.tasks_file = Self::role_file(role_path, "tasks", tasks_from);
+        if result.tasks_file.is_none() && role_spec.tasks_from.is_some() {
+            return Err(ParseError::IncludeFileNotFound {
+                file: role_path
+                    .join("tasks")
+                    .join(format!("{tasks_from}.yml"))
+                    .to_string_lossy()
+                    .to_string(),
+            });
         }
-
-        // Load role defaults
-        if let Some(defaults_from) = &role_spec.defaults_from {
-            let defaults = Self::load_role_defaults(&role_path, defaults_from, context).await?;
-            // Defaults have lower precedence than vars
-            for (key, value) in defaults {
-                result.vars.entry(key).or_insert(value);
+        let handlers_from = role_spec.handlers_from.as_deref().unwrap_or("main");
+        result.handlers_file = Self::role_file(role_path, "handlers", handlers_from);
+
+        // The role's variables and defaults are read concurrently
+        let vars = async {
+            match &role_spec.vars_from {
+                Some(vars_from) => {
+                    Self::load_role_vars(role_path, vars_from, context, loader).await
+                }
+                None => Self::load_default_role_vars(role_path, context, loader).await,
             }
-        } else {
-            // Load default defaults/main.yml
-            let defaults = Self::load_default_role_defaults(&role_path, context).await?;
-            for (key, value) in defaults {
-                result.vars.entry(key).or_insert(value);
+        };
+        let defaults = async {
+            match &role_spec.defaults_from {
+                Some(defaults_from) => {
+                    Self::load_role_defaults(role_path, defaults_from, context, loader).await
+                }
+                None => Self::load_default_role_defaults(role_path, context, loader).await,
             }
-        }
-
-        // Load role handlers if specified
-        if let Some(handlers_from) = &role_spec.handlers_from {
-            let handlers = Self::load_role_handlers(&role_path, handlers_from, context).await?;
-            result.handlers = handlers;
-        } else {
-            // Load default handlers/main.yml
-            let handlers = Self::load_default_role_handlers(&role_path, context).await?;
-            result.handlers = handlers;
+        };
+        let (vars, defaults) = tokio::try_join!(vars, defaults)?;
+
+        // The role's inputs: its defaults, below the variables where it is
+        // used, then its vars and the task's own `vars`
+        result.task_vars = defaults.clone();
+        result.task_vars.extend(context.variables.clone());
+        result.task_vars.extend(vars.clone());
+        result
+            .task_vars
+            .extend(role_spec.vars.clone().unwrap_or_default());
+
+        result.vars.extend(vars);
+        // Defaults have lower precedence than vars
+        for (key, value) in defaults {
+            result.vars.entry(key).or_insert(value);
         }
 
         // Merge role spec variables (highest precedence)
@@ -70,46 +78,47 @@ impl RoleIncludeProcessor {
             result.vars.extend(spec_vars.clone());
         }
 
-        // Apply tags
+        // Tags on the include or import itself are recorded for the role
         if let Some(tags) = &role_spec.tags {
             result.tags.extend(tags.clone());
         }
 
-        // Apply when conditions to all tasks and handlers
-        if let Some(when_condition) = &role_spec.when_condition {
-            result.tasks = Self::apply_when_to_tasks(result.tasks, when_condition);
-            result.handlers = Self::apply_when_to_tasks(result.handlers, when_condition);
-        }
-
-        // Apply apply block if present
-        if let Some(apply_spec) = &role_spec.apply {
-            if let Some(apply_tags) = &apply_spec.tags {
-                result.tags.extend(apply_tags.clone());
-                // Apply tags to all tasks and handlers
-                for task in &mut result.tasks {
-                    task.tags.extend(apply_tags.clone());
-                }
-                for handler in &mut result.handlers {
-                    handler.tags.extend(apply_tags.clone());
-                }
-            }
-
-            if let Some(apply_when) = &apply_spec.when_condition {
-                result.tasks = Self::apply_when_to_tasks(result.tasks, apply_when);
-                result.handlers = Self::apply_when_to_tasks(result.handlers, apply_when);
-            }
+        // Check the role's inputs against the spec of the entry point being run
+        if let Some(specs) = Self::load_argument_specs(role_path, loader).await? {
+            specs.validate(&role_spec.name, tasks_from, &result.task_vars)?;
         }
 
         Ok(result)
     }
 
-    /// Process import_role directive
+    /// Process include_role directive: only `apply` keywords, behind the
+    /// include's `when`, reach the role's tasks
+    pub async fn include_role(
+        role_spec: &RoleIncludeSpec,
+        role_path: &Path,
+        context: &IncludeContext,
+        loader: &FileLoader,
+    ) -> Result<RoleIncludeResult, ParseError> {
+        Self::load_role(role_spec, role_path, context, loader, role_spec.included()?).await
+    }
+
+    /// Process import_role directive: every keyword of the import is
+    /// inherited by each of the role's tasks
     pub async fn import_role(
         role_spec: &RoleIncludeSpec,
+        role_path: &Path,
         context: &IncludeContext,
+        loader: &FileLoader,
     ) -> Result<RoleIncludeResult, ParseError> {
-        // Import role is similar to include role but processed at parse time
-        Self::include_role(role_spec, context).await
+        if role_spec.apply.is_some() {
+            return Err(ParseError::InvalidIncludeDirective {
+                message: format!(
+                    "import_role of '{}' cannot use apply, which is only valid on include_role",
+                    role_spec.name
+                ),
+            });
+        }
+        Self::load_role(role_spec, role_path, context, loader, role_spec.imported()?).await
     }
 
     /// Validate role specification
@@ -123,38 +132,44 @@ impl RoleIncludeProcessor {
         // Validate role name format
         if !Self::is_valid_role_name(&role_spec.name) {
             return Err(ParseError::InvalidIncludeDirective {
-                message: format!("Invalid role name '{}'. Role names must contain only letters, numbers, underscores, and hyphens.", role_spec.name),
+                message: format!("Invalid role name '{}'. Role names must contain only letters, numbers, underscores, and hyphens, with dots separating a collection name.", role_spec.name),
             });
         }
 
         Ok(())
     }
 
-    /// Check if role name is valid
+    /// Check if role name is valid, either a plain name or `namespace.collection.role`
     fn is_valid_role_name(name: &str) -> bool {
-        !name.is_empty()
-            && name
-                .chars()
-                .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+        let is_plain = |name: &str| {
+            !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+        };
+        is_plain(name) || split_fqcn(name).is_some()
     }
 
     /// Resolve role path from role name
-    fn resolve_role_path(role_name: &str, current_file: &Path) -> Result<PathBuf, ParseError> {
+    /// Find a role next to `current_file`, or else in `roles_path`
+    pub(crate) fn resolve_role_path(
+        role_name: &str,
+        current_file: &Path,
+        roles_path: &[PathBuf],
+    ) -> Result<PathBuf, ParseError> {
         let current_dir = current_file.parent().unwrap_or_else(|| Path::new("."));
 
         // Try multiple locations for roles following Ansible conventions
-        let search_paths = vec![
+        let mut search_paths = vec![
             // Local roles directory relative to current file
             current_dir.join("roles"),
             // Parent directory roles (common in Ansible project structures)
             current_dir.join("..").join("roles"),
             // Current directory (for role-specific layouts)
             current_dir.to_path_buf(),
-            // System-wide Ansible roles
-            PathBuf::from("/etc/ansible/roles"),
-            // User Ansible roles
-            PathBuf::from("~/.ansible/roles"), // TODO: Expand ~ properly
         ];
+        // Configured roles path (`roles_path` / `ANSIBLE_ROLES_PATH`)
+        search_paths.extend(roles_path.iter().cloned());
 
         for search_path in &search_paths {
             let role_path = search_path.join(role_name);
@@ -192,41 +207,28 @@ impl RoleIncludeProcessor {
         })
     }
 
-    /// Load role tasks from specific file
-    async fn load_role_tasks(
-        role_path: &Path,
-        tasks_from: &str,
-        context: &IncludeContext,
-    ) -> Result<Vec<ParsedTask>, ParseError> {
-        let tasks_path = role_path.join("tasks").join(format!("{tasks_from}.yml"));
-        if !tasks_path.exists() {
-            let tasks_path_yaml = role_path.join("tasks").join(format!("{tasks_from}.yaml"));
-            if !tasks_path_yaml.exists() {
-                return Err(ParseError::IncludeFileNotFound {
-                    file: tasks_path.to_string_lossy().to_string(),
-                });
-            }
-            return Self::parse_tasks_file(&tasks_path_yaml, context).await;
-        }
-        Self::parse_tasks_file(&tasks_path, context).await
+    /// Path of `<dir>/<name>.yml` (or `.yaml`) inside a role, if it exists
+    pub(crate) fn role_file(role_path: &Path, dir: &str, name: &str) -> Option<PathBuf> {
+        ["yml", "yaml"]
+            .iter()
+            .map(|extension| role_path.join(dir).join(format!("{name}.{extension}")))
+            .find(|path| path.is_file())
     }
 
-    /// Load default role tasks (tasks/main.yml)
-    async fn load_default_role_tasks(
+    /// Load a role's argument specs from `meta/argument_specs.yml`, or from the
+    /// `argument_specs` key of `meta/main.yml`
+    pub(crate) async fn load_argument_specs(
         role_path: &Path,
-        context: &IncludeContext,
-    ) -> Result<Vec<ParsedTask>, ParseError> {
-        let main_tasks = role_path.join("tasks").join("main.yml");
-        if main_tasks.exists() {
-            Self::parse_tasks_file(&main_tasks, context).await
-        } else {
-            let main_tasks_yaml = role_path.join("tasks").join("main.yaml");
-            if main_tasks_yaml.exists() {
-                Self::parse_tasks_file(&main_tasks_yaml, context).await
-            } else {
-                Ok(Vec::new()) // No tasks file found, return empty
-            }
-        }
+        loader: &FileLoader,
+    ) -> Result<Option<RoleArgumentSpecs>, ParseError> {
+        let Some(path) = Self::role_file(role_path, "meta", "argument_specs")
+            .or_else(|| Self::role_file(role_path, "meta", "main"))
+        else {
+            return Ok(None);
+        };
+
+        let specs: Option<RoleArgumentSpecs> = loader.load(&path).await?.deserialize()?;
+        Ok(specs.filter(|specs| !specs.argument_specs.is_empty()))
     }
 
     /// Load role variables from specific file
@@ -234,6 +236,7 @@ impl RoleIncludeProcessor {
         role_path: &Path,
         vars_from: &str,
         _context: &IncludeContext,
+        loader: &FileLoader,
     ) -> Result<HashMap<String, serde_json::Value>, ParseError> {
         let vars_path = role_path.join("vars").join(format!("{vars_from}.yml"));
         if !vars_path.exists() {
@@ -241,23 +244,24 @@ impl RoleIncludeProcessor {
             if !vars_path_yaml.exists() {
                 return Ok(HashMap::new());
             }
-            return Self::parse_vars_file(&vars_path_yaml).await;
+            return Self::parse_vars_file(&vars_path_yaml, loader).await;
         }
-        Self::parse_vars_file(&vars_path).await
+        Self::parse_vars_file(&vars_path, loader).await
     }
 
     /// Load default role variables (vars/main.yml)
     async fn load_default_role_vars(
         role_path: &Path,
         _context: &IncludeContext,
+        loader: &FileLoader,
     ) -> Result<HashMap<String, serde_json::Value>, ParseError> {
         let main_vars = role_path.join("vars").join("main.yml");
         if main_vars.exists() {
-            Self::parse_vars_file(&main_vars).await
+            Self::parse_vars_file(&main_vars, loader).await
         } else {
             let main_vars_yaml = role_path.join("vars").join("main.yaml");
             if main_vars_yaml.exists() {
-                Self::parse_vars_file(&main_vars_yaml).await
+                Self::parse_vars_file(&main_vars_yaml, loader).await
             } else {
                 Ok(HashMap::new())
             }
@@ -269,6 +273,7 @@ impl RoleIncludeProcessor {
         role_path: &Path,
         defaults_from: &str,
         _context: &IncludeContext,
+        loader: &FileLoader,
     ) -> Result<HashMap<String, serde_json::Value>, ParseError> {
         let defaults_path = role_path
             .join("defaults")
@@ -280,158 +285,82 @@ impl RoleIncludeProcessor {
             if !defaults_path_yaml.exists() {
                 return Ok(HashMap::new());
             }
-            return Self::parse_vars_file(&defaults_path_yaml).await;
+            return Self::parse_vars_file(&defaults_path_yaml, loader).await;
         }
-        Self::parse_vars_file(&defaults_path).await
+        Self::parse_vars_file(&defaults_path, loader).await
     }
 
     /// Load default role defaults (defaults/main.yml)
     async fn load_default_role_defaults(
         role_path: &Path,
         _context: &IncludeContext,
+        loader: &FileLoader,
     ) -> Result<HashMap<String, serde_json::Value>, ParseError> {
         let main_defaults = role_path.join("defaults").join("main.yml");
         if main_defaults.exists() {
-            Self::parse_vars_file(&main_defaults).await
+            Self::parse_vars_file(&main_defaults, loader).await
         } else {
             let main_defaults_yaml = role_path.join("defaults").join("main.yaml");
             if main_defaults_yaml.exists() {
-                Self::parse_vars_file(&main_defaults_yaml).await
+                Self::parse_vars_file(&main_defaults_yaml, loader).await
             } else {
                 Ok(HashMap::new())
             }
         }
     }
 
-    /// Load role handlers from specific file
-    async fn load_role_handlers(
-        role_path: &Path,
-        handlers_from: &str,
-        context: &IncludeContext,
-    ) -> Result<Vec<ParsedTask>, ParseError> {
-        let handlers_path = role_path
-            .join("handlers")
-            .join(format!("{handlers_from}.yml"));
-        if !handlers_path.exists() {
-            let handlers_path_yaml = role_path
-                .join("handlers")
-                .join(format!("{handlers_from}.yaml"));
-            if !handlers_path_yaml.exists() {
-                return Ok(Vec::new());
-            }
-            return Self::parse_tasks_file(&handlers_path_yaml, context).await;
-        }
-        Self::parse_tasks_file(&handlers_path, context).await
-    }
-
-    /// Load default role handlers (handlers/main.yml)
-    async fn load_default_role_handlers(
-        role_path: &Path,
-        context: &IncludeContext,
-    ) -> Result<Vec<ParsedTask>, ParseError> {
-        let main_handlers = role_path.join("handlers").join("main.yml");
-        if main_handlers.exists() {
-            Self::parse_tasks_file(&main_handlers, context).await
-        } else {
-            let main_handlers_yaml = role_path.join("handlers").join("main.yaml");
-            if main_handlers_yaml.exists() {
-                Self::parse_tasks_file(&main_handlers_yaml, context).await
-            } else {
-                Ok(Vec::new())
-            }
-        }
-    }
-
-    /// Parse tasks from a YAML file
-    async fn parse_tasks_file(
-        file_path: &Path,
-        _context: &IncludeContext,
-    ) -> Result<Vec<ParsedTask>, ParseError> {
-        let content =
-            fs::read_to_string(file_path)
-                .await
-                .map_err(|_| ParseError::IncludeFileNotFound {
-                    file: file_path.to_string_lossy().to_string(),
-                })?;
-
-        // Parse as YAML array of tasks
-        let raw_tasks: Vec<serde_yaml::Value> =
-            serde_yaml::from_str(&content).map_err(ParseError::Yaml)?;
-
-        let mut parsed_tasks = Vec::new();
-        for (index, _raw_task_value) in raw_tasks.into_iter().enumerate() {
-            // This is a simplified task parser - in a full implementation,
-            // this would use the full task parsing logic
-            let task = ParsedTask {
-                id: format!("role_task_{index}"),
-                name: format!("Role task {index}"),
-                module: "placeholder".to_string(),
-                args: HashMap::new(),
-                vars: HashMap::new(),
-                when: None,
-                loop_items: None,
-                tags: Vec::new(),
-                notify: Vec::new(),
-                changed_when: None,
-                failed_when: None,
-                ignore_errors: false,
-                delegate_to: None,
-                dependencies: Vec::new(),
-            };
-            parsed_tasks.push(task);
-        }
-
-        Ok(parsed_tasks)
-    }
-
     /// Parse variables from a YAML file
     async fn parse_vars_file(
         file_path: &Path,
+        loader: &FileLoader,
     ) -> Result<HashMap<String, serde_json::Value>, ParseError> {
-        let content =
-            fs::read_to_string(file_path)
-                .await
-                .map_err(|_| ParseError::IncludeFileNotFound {
-                    file: file_path.to_string_lossy().to_string(),
-                })?;
-
-        let vars: HashMap<String, serde_json::Value> =
-            serde_yaml::from_str(&content).map_err(ParseError::Yaml)?;
-
-        Ok(vars)
+        let vars: Option<HashMap<String, serde_json::Value>> =
+            loader.load(file_path).await?.deserialize()?;
+        Ok(vars.unwrap_or_default())
     }
 
-    /// Apply when condition to a list of tasks
-    fn apply_when_to_tasks(mut tasks: Vec<ParsedTask>, when_condition: &str) -> Vec<ParsedTask> {
+    /// Pass inherited keywords on to a list of tasks
+    pub(crate) fn inherit_keywords(
+        mut tasks: Vec<ParsedTask>,
+        inherited: &InheritedKeywords,
+    ) -> Vec<ParsedTask> {
         for task in &mut tasks {
-            if let Some(existing_when) = &task.when {
-                task.when = Some(format!("({existing_when}) and ({when_condition})"));
-            } else {
-                task.when = Some(when_condition.to_string());
-            }
+            inherited.apply_to(task);
         }
         tasks
     }
 }
 
-/// Result of role include processing
+/// Result of role include processing. The role's task and handler files are
+/// parsed by the caller, like any other included task file.
 #[derive(Debug, Clone)]
 pub struct RoleIncludeResult {
     pub name: String,
-    pub tasks: Vec<ParsedTask>,
-    pub handlers: Vec<ParsedTask>,
+    /// Directory the role was loaded from
+    pub path: PathBuf,
+    /// Task file of the entry point, if the role has one
+    pub tasks_file: Option<PathBuf>,
+    pub handlers_file: Option<PathBuf>,
+    /// Role defaults, vars and parameters
     pub vars: HashMap<String, serde_json::Value>,
+    /// Variables the role's tasks are parsed with
+    pub task_vars: HashMap<String, serde_json::Value>,
     pub tags: Vec<String>,
+    /// Keywords passed on to every task and handler of the role
+    pub inherited: InheritedKeywords,
 }
 
 impl RoleIncludeResult {
-    fn new(name: String) -> Self {
+    fn new(name: String, path: &Path, inherited: InheritedKeywords) -> Self {
         Self {
             name,
-            tasks: Vec::new(),
-            handlers: Vec::new(),
+            path: path.to_path_buf(),
+            tasks_file: None,
+            handlers_file: None,
             vars: HashMap::new(),
+            task_vars: HashMap::new(),
             tags: Vec::new(),
+            inherited,
         }
     }
 
@@ -443,6 +372,13 @@ impl RoleIncludeResult {
             version: None,
             vars: self.vars.clone(),
             tags: self.tags.clone(),
+            source: None,
+            when: Vec::new(),
+            path: Some(self.path.to_string_lossy().to_string()),
+            defaults: HashMap::new(),
+            role_vars: HashMap::new(),
+            dependencies: Vec::new(),
+            deduplicated: false,
         }
     }
 }
@@ -450,6 +386,9 @@ impl RoleIncludeResult {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::parser::condition::parse_condition;
+    use crate::parser::task_id::TaskPath;
+    use crate::types::parsed::TaskKeywords;
     use std::fs;
     use tempfile::TempDir;
 
@@ -466,6 +405,9 @@ mod tests {
             when_condition: None,
             tags: None,
             apply: None,
+            delegate_to: None,
+            ignore_errors: None,
+            keywords: TaskKeywords::default(),
         };
         assert!(RoleIncludeProcessor::validate_role_spec(&valid_spec).is_ok());
 
@@ -480,6 +422,9 @@ mod tests {
             when_condition: None,
             tags: None,
             apply: None,
+            delegate_to: None,
+            ignore_errors: None,
+            keywords: TaskKeywords::default(),
         };
         assert!(RoleIncludeProcessor::validate_role_spec(&invalid_spec).is_err());
 
@@ -494,6 +439,9 @@ mod tests {
             when_condition: None,
             tags: None,
             apply: None,
+            delegate_to: None,
+            ignore_errors: None,
+            keywords: TaskKeywords::default(),
         };
         assert!(RoleIncludeProcessor::validate_role_spec(&invalid_char_spec).is_err());
     }
@@ -535,10 +483,23 @@ mod tests {
 
         let current_file = temp_dir.path().join("playbook.yml");
         let resolved =
-            RoleIncludeProcessor::resolve_role_path("web_server", &current_file).unwrap();
+            RoleIncludeProcessor::resolve_role_path("web_server", &current_file, &[]).unwrap();
 
         assert!(resolved.ends_with("roles/web_server"));
         assert!(resolved.join("tasks").exists());
+
+        // Roles outside the playbook's directories are found through the roles path
+        let shared = TempDir::new().unwrap();
+        fs::create_dir_all(shared.path().join("common/tasks")).unwrap();
+        fs::write(shared.path().join("common/tasks/main.yml"), "").unwrap();
+        assert!(RoleIncludeProcessor::resolve_role_path("common", &current_file, &[]).is_err());
+        let resolved = RoleIncludeProcessor::resolve_role_path(
+            "common",
+            &current_file,
+            &[shared.path().to_path_buf()],
+        )
+        .unwrap();
+        assert_eq!(resolved, shared.path().join("common"));
     }
 
     #[tokio::test]
@@ -560,18 +521,23 @@ mod tests {
             include_depth: 0,
             tags: Vec::new(),
             when_condition: None,
+            task_path: TaskPath::default(),
         };
 
-        let vars = RoleIncludeProcessor::load_default_role_vars(&role_path, &context)
-            .await
-            .unwrap();
+        let vars = RoleIncludeProcessor::load_default_role_vars(
+            &role_path,
+            &context,
+            &FileLoader::default(),
+        )
+        .await
+        .unwrap();
 
         assert_eq!(vars["web_server_port"], serde_json::json!(80));
         assert_eq!(vars["web_server_name"], serde_json::json!("nginx"));
     }
 
     #[test]
-    fn test_apply_when_to_tasks() {
+    fn test_inherit_keywords() {
         let tasks = vec![
             ParsedTask {
                 id: "task1".to_string(),
@@ -579,15 +545,22 @@ mod tests {
                 module: "debug".to_string(),
                 args: HashMap::new(),
                 vars: HashMap::new(),
-                when: None,
+                when: Vec::new(),
                 loop_items: None,
                 tags: Vec::new(),
                 notify: Vec::new(),
+                listen: Vec::new(),
                 changed_when: None,
                 failed_when: None,
-                ignore_errors: false,
+                ignore_errors: false.into(),
                 delegate_to: None,
                 dependencies: Vec::new(),
+                block: None,
+                source: None,
+                register: None,
+                statically_skipped: false,
+                deferred_include: None,
+                keywords: TaskKeywords::default(),
             },
             ParsedTask {
                 id: "task2".to_string(),
@@ -595,24 +568,48 @@ mod tests {
                 module: "debug".to_string(),
                 args: HashMap::new(),
                 vars: HashMap::new(),
-                when: Some("existing_condition".to_string()),
+                when: vec![parse_condition("existing_condition", "test").unwrap()],
                 loop_items: None,
                 tags: Vec::new(),
                 notify: Vec::new(),
+                listen: Vec::new(),
                 changed_when: None,
                 failed_when: None,
-                ignore_errors: false,
+                ignore_errors: false.into(),
                 delegate_to: None,
                 dependencies: Vec::new(),
+                block: None,
+                source: None,
+                register: None,
+                statically_skipped: false,
+                deferred_include: None,
+                keywords: TaskKeywords::default(),
             },
         ];
 
-        let result = RoleIncludeProcessor::apply_when_to_tasks(tasks, "role_condition");
+        let inherited = InheritedKeywords {
+            when: vec![parse_condition("role_condition", "test").unwrap()],
+            keywords: TaskKeywords {
+                become_user: Some("deploy".to_string()),
+                ..TaskKeywords::default()
+            },
+            ..InheritedKeywords::default()
+        };
+        let result = RoleIncludeProcessor::inherit_keywords(tasks, &inherited);
 
-        assert_eq!(result[0].when, Some("role_condition".to_string()));
+        let expressions = |task: &ParsedTask| {
+            task.when
+                .iter()
+                .map(|c| c.expression.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(expressions(&result[0]), vec!["role_condition"]);
         assert_eq!(
-            result[1].when,
-            Some("(existing_condition) and (role_condition)".to_string())
+            expressions(&result[1]),
+            vec!["role_condition", "existing_condition"]
         );
+        assert!(result
+            .iter()
+            .all(|task| task.keywords.become_user.as_deref() == Some("deploy")));
     }
 }
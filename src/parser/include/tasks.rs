@@ -100,44 +100,7 @@ impl TaskIncludeProcessor {
         mut task: ParsedTask,
         include_spec: &IncludeSpec,
     ) -> Result<ParsedTask, ParseError> {
-        // Apply include-level delegate_to
-        if let Some(delegate_to) = &include_spec.delegate_to {
-            task.delegate_to = Some(delegate_to.clone());
-        }
-
-        // Apply include-level tags
-        if let Some(include_tags) = &include_spec.tags {
-            task.tags.extend(include_tags.clone());
-        }
-
-        // Apply when condition logic
-        if let Some(include_when) = &include_spec.when_condition {
-            if let Some(existing_when) = &task.when {
-                // Combine with AND logic
-                task.when = Some(format!("({existing_when}) and ({include_when})"));
-            } else {
-                task.when = Some(include_when.clone());
-            }
-        }
-
-        // Apply apply block if present
-        if let Some(apply_spec) = &include_spec.apply {
-            if let Some(apply_tags) = &apply_spec.tags {
-                task.tags.extend(apply_tags.clone());
-            }
-
-            if let Some(apply_when) = &apply_spec.when_condition {
-                if let Some(existing_when) = &task.when {
-                    task.when = Some(format!("({existing_when}) and ({apply_when})"));
-                } else {
-                    task.when = Some(apply_when.clone());
-                }
-            }
-
-            // Apply become settings (would need to extend ParsedTask to support these)
-            // For now, these are noted but not implemented in the task structure
-        }
-
+        include_spec.inherited()?.apply_to(&mut task);
         Ok(task)
     }
 
@@ -169,6 +132,9 @@ pub struct TaskIncludeMetadata {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::parser::include::ApplySpec;
+    use crate::parser::task_id::TaskPath;
+    use crate::types::parsed::{Condition, TaskKeywords};
     use std::path::PathBuf;
 
     #[test]
@@ -183,6 +149,7 @@ mod tests {
             delegate_to: None,
             delegate_facts: None,
             run_once: None,
+            name: None,
         };
         assert!(TaskIncludeProcessor::validate_task_include_spec(&valid_spec).is_ok());
 
@@ -196,6 +163,7 @@ mod tests {
             delegate_to: None,
             delegate_facts: None,
             run_once: None,
+            name: None,
         };
         assert!(TaskIncludeProcessor::validate_task_include_spec(&invalid_spec).is_err());
 
@@ -209,6 +177,7 @@ mod tests {
             delegate_to: Some("localhost".to_string()),
             delegate_facts: None,
             run_once: Some(true),
+            name: None,
         };
         assert!(TaskIncludeProcessor::validate_task_include_spec(&invalid_combo_spec).is_err());
     }
@@ -245,39 +214,60 @@ mod tests {
             module: "debug".to_string(),
             args: HashMap::new(),
             vars: HashMap::new(),
-            when: Some("existing_condition".to_string()),
+            when: vec![Condition {
+                expression: "existing_condition".to_string(),
+                ast: None,
+            }],
             loop_items: None,
             tags: vec!["original".to_string()],
             notify: Vec::new(),
+            listen: Vec::new(),
             changed_when: None,
             failed_when: None,
-            ignore_errors: false,
+            ignore_errors: false.into(),
             delegate_to: None,
             dependencies: Vec::new(),
+            block: None,
+            source: None,
+            register: None,
+            statically_skipped: false,
+            deferred_include: None,
+            keywords: TaskKeywords::default(),
         };
 
         let include_spec = IncludeSpec {
             file: "tasks/test.yml".to_string(),
             vars: None,
-            when_condition: Some("include_condition".to_string()),
+            when_condition: Some(vec!["include_condition".to_string()]),
             tags: Some(vec!["include_tag".to_string()]),
-            apply: None,
+            apply: Some(ApplySpec {
+                tags: Some(vec!["apply_tag".to_string()]),
+                delegate_to: Some("apply_host".to_string()),
+                keywords: TaskKeywords {
+                    r#become: Some(true.into()),
+                    ..TaskKeywords::default()
+                },
+                ..ApplySpec::default()
+            }),
             delegate_to: Some("test_host".to_string()),
             delegate_facts: None,
             run_once: None,
+            name: None,
         };
 
         let transformed =
             TaskIncludeProcessor::apply_task_transformations(task, &include_spec).unwrap();
 
-        // Check transformations
-        assert_eq!(transformed.delegate_to, Some("test_host".to_string()));
-        assert!(transformed.tags.contains(&"original".to_string()));
-        assert!(transformed.tags.contains(&"include_tag".to_string()));
-        assert_eq!(
-            transformed.when,
-            Some("(existing_condition) and (include_condition)".to_string())
-        );
+        // Keywords of the include itself stay on the include; `apply` ones are inherited
+        assert_eq!(transformed.delegate_to, Some("apply_host".to_string()));
+        assert_eq!(transformed.tags, vec!["original", "apply_tag"]);
+        assert_eq!(transformed.keywords.r#become, Some(true.into()));
+        let conditions: Vec<_> = transformed
+            .when
+            .iter()
+            .map(|c| c.expression.as_str())
+            .collect();
+        assert_eq!(conditions, vec!["include_condition", "existing_condition"]);
     }
 
     #[test]
@@ -300,6 +290,7 @@ mod tests {
             include_depth: 2,
             tags: Vec::new(),
             when_condition: None,
+            task_path: TaskPath::default(),
         };
 
         let include_spec = IncludeSpec {
@@ -311,6 +302,7 @@ mod tests {
             delegate_to: Some("test_host".to_string()),
             delegate_facts: Some(true),
             run_once: Some(false),
+            name: None,
         };
 
         let metadata = TaskIncludeProcessor::extract_task_metadata(&context, &include_spec);
@@ -1,12 +1,15 @@
 use crate::parser::error::ParseError;
+use crate::parser::include::loader::load_concurrently;
 use crate::parser::include::{IncludeContext, IncludeVarsSpec};
 use crate::parser::template::TemplateEngine;
+use crate::parser::yaml;
 use regex::Regex;
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use tokio::fs;
 
 /// Handler for include_vars functionality
+#[derive(Clone)]
 pub struct VariableIncludeProcessor {
     template_engine: TemplateEngine,
 }
@@ -86,33 +89,43 @@ impl VariableIncludeProcessor {
 
         let ignore_files = vars_spec.ignore_files.as_ref().cloned().unwrap_or_default();
 
-        self.load_vars_recursive(
+        let mut files = Vec::new();
+        Self::collect_vars_files(
             &resolved_dir,
-            &mut all_vars,
+            &mut files,
             0,
             max_depth,
             &extensions,
             &files_matching,
             &ignore_files,
-            context,
         )
         .await?;
 
+        // Files are read and parsed concurrently, then merged in path order
+        // so that later files override earlier ones deterministically
+        files.sort();
+        let loaded = load_concurrently(files, |path| {
+            let processor = self.clone();
+            let context = context.clone();
+            async move { processor.load_vars_from_path(&path, &context).await }
+        })
+        .await;
+        for file_vars in loaded {
+            all_vars.extend(file_vars?);
+        }
+
         Ok(all_vars)
     }
 
-    /// Recursively load variables from directory
-    #[allow(clippy::too_many_arguments)]
-    fn load_vars_recursive<'a>(
-        &'a self,
+    /// Recursively find the variable files in a directory
+    fn collect_vars_files<'a>(
         dir: &'a Path,
-        vars: &'a mut HashMap<String, serde_json::Value>,
+        files: &'a mut Vec<PathBuf>,
         current_depth: usize,
         max_depth: usize,
         extensions: &'a [String],
         files_matching: &'a Option<Regex>,
         ignore_files: &'a [String],
-        context: &'a IncludeContext,
     ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<(), ParseError>> + 'a>> {
         Box::pin(async move {
             if current_depth >= max_depth {
@@ -131,15 +144,14 @@ impl VariableIncludeProcessor {
                 }
 
                 if path.is_dir() && current_depth + 1 < max_depth {
-                    self.load_vars_recursive(
+                    Self::collect_vars_files(
                         &path,
-                        vars,
+                        files,
                         current_depth + 1,
                         max_depth,
                         extensions,
                         files_matching,
                         ignore_files,
-                        context,
                     )
                     .await?;
                 } else if path.is_file() {
@@ -157,11 +169,7 @@ impl VariableIncludeProcessor {
                         }
                     }
 
-                    // Load variables from this file
-                    let file_vars = self.load_vars_from_path(&path, context).await?;
-
-                    // Merge variables (later files override earlier ones)
-                    vars.extend(file_vars);
+                    files.push(path);
                 }
             }
 
@@ -204,7 +212,7 @@ impl VariableIncludeProcessor {
             }
             "yml" | "yaml" => {
                 // YAML variables file
-                serde_yaml::from_str(content).map_err(ParseError::Yaml)?
+                yaml::from_str(content)?
             }
             _ => {
                 return Err(ParseError::InvalidIncludeDirective {
@@ -335,6 +343,7 @@ impl VariableIncludeProcessor {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::parser::task_id::TaskPath;
     use std::fs;
     use tempfile::TempDir;
 
@@ -357,6 +366,7 @@ debug_mode: true
             include_depth: 0,
             tags: Vec::new(),
             when_condition: None,
+            task_path: TaskPath::default(),
         };
 
         let vars = processor
@@ -400,6 +410,7 @@ debug_mode: true
             include_depth: 0,
             tags: Vec::new(),
             when_condition: None,
+            task_path: TaskPath::default(),
         };
 
         let vars = processor
@@ -447,6 +458,7 @@ debug_mode: true
             include_depth: 0,
             tags: Vec::new(),
             when_condition: None,
+            task_path: TaskPath::default(),
         };
 
         let vars = processor
@@ -457,6 +469,8 @@ debug_mode: true
         // Should include files matching pattern but not config.yml
         assert!(vars.contains_key("env"));
         assert!(!vars.contains_key("type"));
+        // Files are merged in name order, so prod_vars.yml wins
+        assert_eq!(vars["env"], "production");
     }
 
     #[test]
@@ -501,6 +515,7 @@ debug_mode: true
             include_depth: 0,
             tags: Vec::new(),
             when_condition: None,
+            task_path: TaskPath::default(),
         };
 
         assert!(processor.should_process_vars(&vars_spec, &context).unwrap());
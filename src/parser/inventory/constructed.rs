@@ -0,0 +1,398 @@
+use crate::parser::error::ParseError;
+use crate::parser::inventory::sources::extend_unique;
+use crate::parser::template::TemplateEngine;
+use crate::parser::yaml::{self, YamlLimits};
+use crate::types::parsed::{ParsedGroup, ParsedInventory};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use tracing::debug;
+
+type Variables = HashMap<String, serde_json::Value>;
+
+/// Names the `constructed` plugin may be given as in an inventory source
+const PLUGIN_NAMES: [&str; 2] = ["constructed", "ansible.builtin.constructed"];
+
+/// Extensions of files that may configure an inventory plugin
+const PLUGIN_EXTENSIONS: [&str; 3] = ["yml", "yaml", "config"];
+
+/// An inventory source configuring Ansible's `constructed` plugin, which
+/// derives groups and variables from the hosts the other sources define
+#[derive(Debug, Clone, Deserialize)]
+pub struct ConstructedConfig {
+    pub plugin: String,
+    /// Fail on expressions that cannot be evaluated instead of skipping them
+    #[serde(default)]
+    pub strict: bool,
+    /// Host variables to set, from Jinja expressions
+    #[serde(default)]
+    pub compose: BTreeMap<String, String>,
+    /// Groups to add hosts to, when the Jinja condition holds
+    #[serde(default)]
+    pub groups: BTreeMap<String, String>,
+    /// Groups named after the value of a Jinja expression
+    #[serde(default)]
+    pub keyed_groups: Vec<KeyedGroup>,
+    /// Start keyed group names without a prefix with the separator
+    #[serde(default = "default_true")]
+    pub leading_separator: bool,
+    /// Evaluate expressions with `group_vars/` and `host_vars/` loaded
+    #[serde(default)]
+    pub use_vars_plugins: bool,
+    #[serde(skip)]
+    pub file: String,
+}
+
+/// One entry of `keyed_groups`
+#[derive(Debug, Clone, Deserialize)]
+pub struct KeyedGroup {
+    pub key: String,
+    #[serde(default)]
+    pub prefix: String,
+    #[serde(default = "default_separator")]
+    pub separator: String,
+    pub parent_group: Option<String>,
+    /// Group name used when the key evaluates to an empty string
+    pub default_value: Option<String>,
+    /// Keep the separator after a dictionary key whose value is empty
+    pub trailing_separator: Option<bool>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_separator() -> String {
+    "_".to_string()
+}
+
+impl ConstructedConfig {
+    /// Read `path` if it configures the `constructed` plugin. Other YAML
+    /// files are left to the inventory parser; other plugins are unsupported.
+    pub fn from_file(path: &Path, limits: &YamlLimits) -> Result<Option<Self>, ParseError> {
+        let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or("");
+        if !PLUGIN_EXTENSIONS.contains(&extension) {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(path)?;
+        let document = yaml::load_with_limits(&content, limits)?;
+        let Some(plugin) = document.get("plugin").and_then(|plugin| plugin.as_str()) else {
+            return Ok(None);
+        };
+        if !PLUGIN_NAMES.contains(&plugin) {
+            return Err(ParseError::UnsupportedFeature {
+                feature: format!("inventory plugin '{plugin}' in {}", path.display()),
+            });
+        }
+
+        let mut config: Self =
+            serde_yaml::from_value(document).map_err(|e| ParseError::InventoryPlugin {
+                file: path.display().to_string(),
+                message: e.to_string(),
+            })?;
+        config.file = path.display().to_string();
+        for keyed in &config.keyed_groups {
+            if keyed.default_value.is_some() && keyed.trailing_separator.is_some() {
+                return Err(config.error(
+                    "default_value and trailing_separator of a keyed group are mutually exclusive"
+                        .to_string(),
+                ));
+            }
+        }
+        Ok(Some(config))
+    }
+
+    /// Add the composed variables and constructed groups to `inventory`,
+    /// evaluating expressions against each host's variables in `resolved`
+    pub fn apply(
+        &self,
+        inventory: &mut ParsedInventory,
+        resolved: &ParsedInventory,
+        template_engine: &TemplateEngine,
+    ) -> Result<(), ParseError> {
+        let mut host_names: Vec<&String> = resolved.hosts.keys().collect();
+        host_names.sort();
+
+        for name in host_names {
+            let host = &resolved.hosts[name];
+            let mut variables = host.vars.clone();
+            variables.insert("inventory_hostname".to_string(), name.as_str().into());
+            variables.insert(
+                "inventory_hostname_short".to_string(),
+                name.split('.').next().unwrap_or(name).into(),
+            );
+            let mut group_names: Vec<String> = host
+                .groups
+                .iter()
+                .filter(|group| group.as_str() != "all")
+                .cloned()
+                .collect();
+            group_names.sort();
+            variables.insert("group_names".to_string(), group_names.into());
+
+            // Every composed variable sees the same variables; groups see the results
+            let mut composed = Variables::new();
+            for (var, expression) in &self.compose {
+                if let Some(value) = self.evaluate(expression, &variables, name, template_engine)? {
+                    composed.insert(var.clone(), value);
+                }
+            }
+            variables.extend(composed.clone());
+            if let Some(host) = inventory.hosts.get_mut(name) {
+                host.vars.extend(composed);
+            }
+
+            for (group, condition) in &self.groups {
+                let holds = self
+                    .evaluate(condition, &variables, name, template_engine)?
+                    .is_some_and(|value| is_truthy(&value));
+                if holds {
+                    add_host_to_group(inventory, group, name);
+                }
+            }
+
+            for keyed in &self.keyed_groups {
+                self.add_keyed_groups(inventory, keyed, &variables, name, template_engine)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn add_keyed_groups(
+        &self,
+        inventory: &mut ParsedInventory,
+        keyed: &KeyedGroup,
+        variables: &Variables,
+        host: &str,
+        template_engine: &TemplateEngine,
+    ) -> Result<(), ParseError> {
+        let Some(key) = self.evaluate(&keyed.key, variables, host, template_engine)? else {
+            return Ok(());
+        };
+        let empty = |value: &serde_json::Value| value.as_str() == Some("");
+        let value_name = |value: &serde_json::Value| match value {
+            serde_json::Value::String(s) => s.clone(),
+            other => other.to_string(),
+        };
+
+        // An empty key names no group, unless it has a default
+        let named = is_truthy(&key) || (empty(&key) && keyed.default_value.is_some());
+        if !named {
+            if self.strict {
+                return Err(self.error(format!(
+                    "keyed group key '{}' is empty for host '{host}'",
+                    keyed.key
+                )));
+            }
+            return Ok(());
+        }
+
+        let bare_names: Vec<String> = match &key {
+            serde_json::Value::Array(items) => items
+                .iter()
+                .map(|item| match &keyed.default_value {
+                    Some(default) if empty(item) => default.clone(),
+                    _ => value_name(item),
+                })
+                .collect(),
+            serde_json::Value::Object(entries) => entries
+                .iter()
+                .map(|(name, value)| match &keyed.default_value {
+                    Some(default) if empty(value) => {
+                        format!("{name}{}{default}", keyed.separator)
+                    }
+                    None if empty(value) && keyed.trailing_separator == Some(false) => name.clone(),
+                    _ => format!("{name}{}{}", keyed.separator, value_name(value)),
+                })
+                .collect(),
+            other => match &keyed.default_value {
+                Some(default) if empty(other) => vec![default.clone()],
+                _ => vec![value_name(other)],
+            },
+        };
+
+        let separator = if keyed.prefix.is_empty() && !self.leading_separator {
+            ""
+        } else {
+            keyed.separator.as_str()
+        };
+        for bare_name in bare_names {
+            let group = format!("{}{separator}{bare_name}", keyed.prefix);
+            add_host_to_group(inventory, &group, host);
+            if let Some(parent) = &keyed.parent_group {
+                add_child_group(inventory, parent, &group);
+            }
+        }
+        Ok(())
+    }
+
+    /// Evaluate `expression` for `host`; failures are skipped unless strict
+    fn evaluate(
+        &self,
+        expression: &str,
+        variables: &Variables,
+        host: &str,
+        template_engine: &TemplateEngine,
+    ) -> Result<Option<serde_json::Value>, ParseError> {
+        match template_engine.evaluate_expression(expression, variables) {
+            Ok(value) => Ok(Some(value)),
+            Err(e) if self.strict => Err(self.error(format!(
+                "could not evaluate '{expression}' for host '{host}': {e}"
+            ))),
+            Err(e) => {
+                debug!("Skipping '{expression}' for host '{host}': {e}");
+                Ok(None)
+            }
+        }
+    }
+
+    fn error(&self, message: String) -> ParseError {
+        ParseError::InventoryPlugin {
+            file: self.file.clone(),
+            message,
+        }
+    }
+}
+
+/// Jinja truthiness of an evaluated condition
+fn is_truthy(value: &serde_json::Value) -> bool {
+    match value {
+        serde_json::Value::Null => false,
+        serde_json::Value::Bool(b) => *b,
+        serde_json::Value::Number(n) => n.as_f64() != Some(0.0),
+        serde_json::Value::String(s) => !s.is_empty(),
+        serde_json::Value::Array(items) => !items.is_empty(),
+        serde_json::Value::Object(entries) => !entries.is_empty(),
+    }
+}
+
+fn group_entry<'a>(inventory: &'a mut ParsedInventory, name: &str) -> &'a mut ParsedGroup {
+    inventory
+        .groups
+        .entry(name.to_string())
+        .or_insert_with(|| ParsedGroup {
+            name: name.to_string(),
+            hosts: Vec::new(),
+            children: Vec::new(),
+            vars: HashMap::new(),
+            source: None,
+        })
+}
+
+fn add_host_to_group(inventory: &mut ParsedInventory, group: &str, host: &str) {
+    extend_unique(
+        &mut group_entry(inventory, group).hosts,
+        vec![host.to_string()],
+    );
+    if let Some(host) = inventory.hosts.get_mut(host) {
+        extend_unique(&mut host.groups, vec![group.to_string()]);
+    }
+}
+
+fn add_child_group(inventory: &mut ParsedInventory, parent: &str, child: &str) {
+    extend_unique(
+        &mut group_entry(inventory, parent).children,
+        vec![child.to_string()],
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::parsed::ParsedHost;
+
+    fn host(name: &str, vars: serde_json::Value) -> ParsedHost {
+        ParsedHost {
+            name: name.to_string(),
+            address: None,
+            port: None,
+            user: None,
+            vars: serde_json::from_value(vars).unwrap(),
+            groups: vec!["all".to_string()],
+            connection: None,
+            ssh_private_key_file: None,
+            ssh_common_args: None,
+            ssh_extra_args: None,
+            ssh_pipelining: None,
+            connection_timeout: None,
+            ansible_become: None,
+            become_method: None,
+            become_user: None,
+            become_flags: None,
+            source: None,
+        }
+    }
+
+    fn config(yaml: &str) -> ConstructedConfig {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    fn constructed(config: &ConstructedConfig) -> Result<ParsedInventory, ParseError> {
+        let mut inventory = ParsedInventory {
+            hosts: HashMap::from([
+                (
+                    "web1".to_string(),
+                    host(
+                        "web1",
+                        serde_json::json!({"role": "web", "tags": {"env": "prod", "team": ""}}),
+                    ),
+                ),
+                (
+                    "db1".to_string(),
+                    host("db1", serde_json::json!({"role": "db", "tags": {}})),
+                ),
+            ]),
+            groups: HashMap::new(),
+            variables: HashMap::new(),
+        };
+        let resolved = inventory.clone();
+        config.apply(&mut inventory, &resolved, &TemplateEngine::new())?;
+        Ok(inventory)
+    }
+
+    #[test]
+    fn test_keyed_group_names() {
+        let inventory = constructed(&config(
+            r#"
+plugin: constructed
+keyed_groups:
+  - key: role
+    prefix: role
+    parent_group: roles
+  - key: tags
+    prefix: tag
+    trailing_separator: false
+  - key: tags.team
+    default_value: unowned
+  - key: missing
+"#,
+        ))
+        .unwrap();
+
+        let mut groups: Vec<&String> = inventory.groups.keys().collect();
+        groups.sort();
+        assert_eq!(
+            groups,
+            vec![
+                "_unowned",
+                "role_db",
+                "role_web",
+                "roles",
+                "tag_env_prod",
+                "tag_team"
+            ]
+        );
+        assert_eq!(inventory.groups["roles"].children.len(), 2);
+        assert!(inventory.hosts["web1"]
+            .groups
+            .contains(&"tag_team".to_string()));
+    }
+
+    #[test]
+    fn test_strict_fails_on_undefined_keys() {
+        let result = constructed(&config(
+            "plugin: constructed\nstrict: true\ncompose:\n  ansible_host: private_ip\n",
+        ));
+        assert!(matches!(result, Err(ParseError::InventoryPlugin { .. })));
+    }
+}
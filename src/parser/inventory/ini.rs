@@ -1,8 +1,11 @@
+use crate::parser::config::{HashBehaviour, TransformInvalidGroupChars};
 use crate::parser::error::ParseError;
 use crate::parser::inventory::patterns::HostPattern;
 use crate::parser::template::TemplateEngine;
+use crate::parser::yaml::YamlLimits;
 use crate::types::parsed::*;
 use std::collections::{HashMap, HashSet};
+use std::time::Duration;
 
 /// Internal structure for parsing INI sections
 #[derive(Debug)]
@@ -10,6 +13,7 @@ struct IniSection {
     name: String,
     section_type: SectionType,
     entries: Vec<IniEntry>,
+    line: usize,
 }
 
 #[derive(Debug, PartialEq)]
@@ -24,16 +28,22 @@ struct IniEntry {
     key: String,
     value: Option<String>,
     variables: HashMap<String, String>,
+    line: usize,
+    column: usize,
 }
 
 /// Configuration for INI inventory parsing
 #[derive(Debug, Clone)]
 pub struct InventoryParserConfig {
-    pub strict_mode: bool,            // Fail on warnings
-    pub expand_patterns: bool,        // Enable host pattern expansion
-    pub max_pattern_expansion: usize, // Limit pattern expansion size
-    pub validate_hosts: bool,         // Validate host connectivity
-    pub resolve_dns: bool,            // Resolve hostnames to IPs
+    pub strict_mode: bool,             // Fail on warnings
+    pub expand_patterns: bool,         // Enable host pattern expansion
+    pub max_pattern_expansion: usize,  // Limit pattern expansion size
+    pub validate_hosts: bool,          // Validate host connectivity
+    pub resolve_dns: bool,             // Resolve hostnames to IPs
+    pub yaml_limits: YamlLimits,       // Limits for YAML inventories
+    pub hash_behaviour: HashBehaviour, // How group and host dictionaries combine
+    pub transform_invalid_group_chars: TransformInvalidGroupChars, // Invalid group names
+    pub script_timeout: Duration,      // Longest an inventory script may run
 }
 
 impl Default for InventoryParserConfig {
@@ -44,6 +54,10 @@ impl Default for InventoryParserConfig {
             max_pattern_expansion: 1000,
             validate_hosts: false,
             resolve_dns: false,
+            yaml_limits: YamlLimits::default(),
+            hash_behaviour: HashBehaviour::default(),
+            transform_invalid_group_chars: TransformInvalidGroupChars::default(),
+            script_timeout: Duration::from_secs(30),
         }
     }
 }
@@ -54,6 +68,7 @@ pub struct IniInventoryParser<'a> {
     template_engine: &'a TemplateEngine,
     extra_vars: &'a HashMap<String, serde_json::Value>,
     config: InventoryParserConfig,
+    source_file: Option<String>,
 }
 
 impl<'a> IniInventoryParser<'a> {
@@ -65,6 +80,7 @@ impl<'a> IniInventoryParser<'a> {
             template_engine,
             extra_vars,
             config: InventoryParserConfig::default(),
+            source_file: None,
         }
     }
 
@@ -77,9 +93,24 @@ impl<'a> IniInventoryParser<'a> {
             template_engine,
             extra_vars,
             config,
+            source_file: None,
         }
     }
 
+    /// Record the inventory file name so hosts and groups carry their source location
+    pub fn with_source_file(mut self, file: &str) -> Self {
+        self.source_file = Some(file.to_string());
+        self
+    }
+
+    fn location(&self, line: usize, column: usize) -> Option<SourceLocation> {
+        self.source_file.as_ref().map(|file| SourceLocation {
+            file: file.clone(),
+            line,
+            column,
+        })
+    }
+
     /// Parse INI inventory with complete feature support
     pub async fn parse_ini_inventory(&self, content: &str) -> Result<ParsedInventory, ParseError> {
         let mut inventory = ParsedInventory {
@@ -142,11 +173,13 @@ impl<'a> IniInventoryParser<'a> {
                     name: section_name,
                     section_type,
                     entries: Vec::new(),
+                    line: line_num + 1,
                 });
             } else if let Some(ref mut section) = current_section {
                 // Parse entry within a section
-                let entry =
+                let mut entry =
                     self.parse_ansible_ini_line(trimmed, &section.section_type, line_num)?;
+                entry.column = line.len() - line.trim_start().len() + 1;
                 section.entries.push(entry);
             } else {
                 return Err(ParseError::IniParsing {
@@ -172,7 +205,7 @@ impl<'a> IniInventoryParser<'a> {
         &self,
         line: &str,
         section_type: &SectionType,
-        _line_num: usize,
+        line_num: usize,
     ) -> Result<IniEntry, ParseError> {
         match section_type {
             SectionType::Hosts => {
@@ -189,6 +222,8 @@ impl<'a> IniInventoryParser<'a> {
                         Some(variables_str)
                     },
                     variables,
+                    line: line_num + 1,
+                    column: 1,
                 })
             }
             SectionType::GroupVars | SectionType::GroupChildren => {
@@ -198,12 +233,16 @@ impl<'a> IniInventoryParser<'a> {
                         key: key.trim().to_string(),
                         value: Some(value.trim().to_string()),
                         variables: HashMap::new(),
+                        line: line_num + 1,
+                        column: 1,
                     })
                 } else {
                     Ok(IniEntry {
                         key: line.trim().to_string(),
                         value: None,
                         variables: HashMap::new(),
+                        line: line_num + 1,
+                        column: 1,
                     })
                 }
             }
@@ -366,6 +405,7 @@ impl<'a> IniInventoryParser<'a> {
                         become_method: conn.become_method,
                         become_user: conn.become_user,
                         become_flags: conn.become_flags,
+                        source: self.location(entry.line, entry.column),
                     };
                     inventory.hosts.insert(hostname.clone(), host);
                 }
@@ -379,6 +419,7 @@ impl<'a> IniInventoryParser<'a> {
             hosts: group_hosts,
             children: Vec::new(),
             vars: HashMap::new(),
+            source: self.location(section.line, 1),
         };
 
         inventory.groups.insert(group_name, group);
@@ -402,6 +443,7 @@ impl<'a> IniInventoryParser<'a> {
                     hosts: Vec::new(),
                     children: Vec::new(),
                     vars: HashMap::new(),
+                    source: self.location(section.line, 1),
                 },
             );
         }
@@ -435,6 +477,7 @@ impl<'a> IniInventoryParser<'a> {
                     hosts: Vec::new(),
                     children: Vec::new(),
                     vars: HashMap::new(),
+                    source: self.location(section.line, 1),
                 },
             );
         }
@@ -724,6 +767,7 @@ impl<'a> IniInventoryParser<'a> {
                 hosts: all_hosts,
                 children: Vec::new(),
                 vars: HashMap::new(),
+                source: None,
             };
             inventory.groups.insert("all".to_string(), all_group);
         }
@@ -828,6 +872,10 @@ mod tests {
             max_pattern_expansion: 100,
             validate_hosts: true,
             resolve_dns: true,
+            yaml_limits: YamlLimits::default(),
+            hash_behaviour: HashBehaviour::default(),
+            transform_invalid_group_chars: TransformInvalidGroupChars::default(),
+            script_timeout: Duration::from_secs(30),
         };
 
         let parser = IniInventoryParser::with_config(&template_engine, &extra_vars, config);
@@ -1,19 +1,36 @@
+use crate::parser::config::TransformInvalidGroupChars;
 use crate::parser::error::ParseError;
+use crate::parser::include::{FileLoader, IncludeConfig};
+use crate::parser::inventory::constructed::ConstructedConfig;
 use crate::parser::inventory::ini::{IniInventoryParser, InventoryParserConfig};
+use crate::parser::inventory::script::{is_executable, InventoryScript, ScriptCache};
+use crate::parser::inventory::selection::HostSelector;
+use crate::parser::inventory::sources::{self, extend_unique};
 use crate::parser::inventory::validation::InventoryValidator;
 use crate::parser::inventory::variables::VariableInheritanceResolver;
+use crate::parser::inventory::vars_files::VarsDirectories;
+use crate::parser::source_map::{SourceMap, SourceNode};
 use crate::parser::template::TemplateEngine;
+use crate::parser::yaml;
 use crate::types::parsed::*;
-use regex;
+use once_cell::sync::Lazy;
+use regex::{self, Regex};
 use serde::Deserialize;
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use tokio::fs;
+use tracing::warn;
+
+/// Characters Ansible does not allow in group names, including a leading digit
+static INVALID_GROUP_CHARS: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[\d\W]|[^\w]").unwrap());
 
 pub struct InventoryParser<'a> {
     template_engine: &'a TemplateEngine,
     extra_vars: &'a HashMap<String, serde_json::Value>,
     config: InventoryParserConfig,
+    playbook_dir: Option<PathBuf>,
+    vault_password: Option<&'a str>,
+    script_cache: ScriptCache,
 }
 
 impl<'a> InventoryParser<'a> {
@@ -25,6 +42,9 @@ impl<'a> InventoryParser<'a> {
             template_engine,
             extra_vars,
             config: InventoryParserConfig::default(),
+            playbook_dir: None,
+            vault_password: None,
+            script_cache: ScriptCache::default(),
         }
     }
 
@@ -37,10 +57,78 @@ impl<'a> InventoryParser<'a> {
             template_engine,
             extra_vars,
             config,
+            playbook_dir: None,
+            vault_password: None,
+            script_cache: ScriptCache::default(),
         }
     }
 
+    /// Also load `group_vars/` and `host_vars/` next to the playbook, over the
+    /// ones next to the inventory
+    pub fn with_playbook_dir(mut self, playbook_dir: &Path) -> Self {
+        self.playbook_dir = Some(playbook_dir.to_path_buf());
+        self
+    }
+
+    /// Password for encrypted `group_vars/` and `host_vars/` files
+    pub fn with_vault_password(mut self, password: Option<&'a str>) -> Self {
+        self.vault_password = password;
+        self
+    }
+
+    /// Share inventory script output with other parsers, so each script runs once
+    pub fn with_script_cache(mut self, cache: ScriptCache) -> Self {
+        self.script_cache = cache;
+        self
+    }
+
+    /// Parse an inventory file, or every inventory file of a directory
     pub async fn parse(&self, path: &Path) -> Result<ParsedInventory, ParseError> {
+        self.parse_sources(&[path.to_path_buf()]).await
+    }
+
+    /// Parse several inventory files and directories into one inventory, as
+    /// repeated `-i` options do. Later sources override the variables of
+    /// earlier ones, and each host keeps the location it was first defined at.
+    pub async fn parse_sources(&self, sources: &[PathBuf]) -> Result<ParsedInventory, ParseError> {
+        let mut inventory = ParsedInventory {
+            hosts: HashMap::new(),
+            groups: HashMap::new(),
+            variables: self.extra_vars.clone(),
+        };
+        let mut vars_dirs = Vec::new();
+        let mut constructed = Vec::new();
+        for source in sources {
+            for file in sources::inventory_files(source)? {
+                if let Some(config) = ConstructedConfig::from_file(&file, &self.config.yaml_limits)?
+                {
+                    constructed.push(config);
+                    continue;
+                }
+                let parsed = self.parse_file(&file).await?;
+                sources::merge_inventory(&mut inventory, parsed, self.config.hash_behaviour);
+            }
+            // group_vars/ and host_vars/ sit in a directory source, or next to a file
+            let vars_dir = if source.is_dir() {
+                source.as_path()
+            } else {
+                source.parent().unwrap_or_else(|| Path::new("."))
+            };
+            if !vars_dirs.iter().any(|dir: &PathBuf| dir == vars_dir) {
+                vars_dirs.push(vars_dir.to_path_buf());
+            }
+        }
+
+        self.finish_inventory(&mut inventory, vars_dirs, &constructed)
+            .await?;
+        Ok(inventory)
+    }
+
+    async fn parse_file(&self, path: &Path) -> Result<ParsedInventory, ParseError> {
+        if is_executable(path) {
+            return self.parse_script(path).await;
+        }
+
         let content = fs::read_to_string(path).await.map_err(|e| {
             if e.kind() == std::io::ErrorKind::NotFound {
                 ParseError::FileNotFound {
@@ -51,100 +139,53 @@ impl<'a> InventoryParser<'a> {
             }
         })?;
 
+        let file = path.to_string_lossy();
+
         // Detect format based on file extension and content
         let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or("");
 
         match extension {
-            "ini" => self.parse_ini_inventory(&content).await,
-            "yml" | "yaml" => self.parse_yaml_inventory(&content).await,
-            "json" => self.parse_json_inventory(&content).await,
+            "ini" => self.parse_ini_inventory(&content, &file).await,
+            "yml" | "yaml" => self.parse_yaml_inventory(&content, &file).await,
+            "json" => self.parse_json_inventory(&content, &file).await,
             _ => {
                 // Try to auto-detect format
                 if content.trim_start().starts_with('{') {
-                    self.parse_json_inventory(&content).await
+                    self.parse_json_inventory(&content, &file).await
                 } else if content.contains("---") || content.trim_start().starts_with("all:") {
-                    self.parse_yaml_inventory(&content).await
+                    self.parse_yaml_inventory(&content, &file).await
                 } else if content.contains('[') && content.contains(']') {
                     // INI format sections like [webservers]
-                    self.parse_ini_inventory(&content).await
+                    self.parse_ini_inventory(&content, &file).await
                 } else {
                     // Default to INI for simple host lists
-                    self.parse_ini_inventory(&content).await
+                    self.parse_ini_inventory(&content, &file).await
                 }
             }
         }
     }
 
-    /// Filter inventory based on a limit pattern (similar to Ansible's --limit)
+    /// Run an executable inventory and read its JSON like a JSON inventory
+    async fn parse_script(&self, path: &Path) -> Result<ParsedInventory, ParseError> {
+        let output =
+            InventoryScript::new(path, self.config.script_timeout, self.script_cache.clone())
+                .list()
+                .await?;
+        let content = serde_json::to_string_pretty(&output)?;
+        self.parse_json_inventory(&content, &path.to_string_lossy())
+            .await
+    }
+
+    /// Keep only the hosts a limit pattern selects, as Ansible's `--limit` does
     pub fn filter_inventory(
         &self,
         inventory: &mut ParsedInventory,
         limit_pattern: &str,
     ) -> Result<(), ParseError> {
-        // Parse comma-separated patterns
-        let patterns: Vec<&str> = limit_pattern.split(',').map(|s| s.trim()).collect();
-
-        let mut matching_hosts = std::collections::HashSet::new();
-
-        for pattern in patterns {
-            // Check if pattern is a group name (prefixed with ':' or just a group name)
-            let is_group_pattern = pattern.starts_with(':');
-            let pattern_name = if is_group_pattern {
-                &pattern[1..]
-            } else {
-                pattern
-            };
-
-            if is_group_pattern || inventory.groups.contains_key(pattern_name) {
-                // It's a group pattern - add all hosts from the group
-                if let Some(group) = inventory.groups.get(pattern_name) {
-                    for host in &group.hosts {
-                        matching_hosts.insert(host.clone());
-                    }
-                    // Also add hosts from child groups
-                    Self::collect_hosts_from_children(
-                        &inventory.groups,
-                        pattern_name,
-                        &mut matching_hosts,
-                    );
-                }
-            } else if pattern.contains('[') && pattern.contains(']') {
-                // It's a host pattern with ranges - expand it
-                match self.expand_host_pattern(pattern) {
-                    Ok(expanded_hosts) => {
-                        for host in expanded_hosts {
-                            if inventory.hosts.contains_key(&host) {
-                                matching_hosts.insert(host);
-                            }
-                        }
-                    }
-                    Err(_) => {
-                        // If pattern expansion fails, treat as literal
-                        if inventory.hosts.contains_key(pattern) {
-                            matching_hosts.insert(pattern.to_string());
-                        }
-                    }
-                }
-            } else {
-                // It's a literal host name or glob pattern
-                if pattern.contains('*') || pattern.contains('?') {
-                    // Simple glob pattern matching
-                    let regex_pattern = pattern.replace("*", ".*").replace("?", ".");
-                    if let Ok(re) = regex::Regex::new(&format!("^{regex_pattern}$")) {
-                        for host_name in inventory.hosts.keys() {
-                            if re.is_match(host_name) {
-                                matching_hosts.insert(host_name.clone());
-                            }
-                        }
-                    }
-                } else {
-                    // Literal host name
-                    if inventory.hosts.contains_key(pattern) {
-                        matching_hosts.insert(pattern.to_string());
-                    }
-                }
-            }
-        }
+        let matching_hosts: std::collections::HashSet<String> = HostSelector::new(inventory)
+            .select_limit(limit_pattern)?
+            .into_iter()
+            .collect();
 
         // Filter hosts to only include matching ones
         inventory
@@ -170,45 +211,20 @@ impl<'a> InventoryParser<'a> {
         Ok(())
     }
 
-    /// Recursively collect hosts from child groups
-    fn collect_hosts_from_children(
-        groups: &HashMap<String, ParsedGroup>,
-        group_name: &str,
-        matching_hosts: &mut std::collections::HashSet<String>,
-    ) {
-        if let Some(group) = groups.get(group_name) {
-            for child_name in &group.children {
-                if let Some(child_group) = groups.get(child_name) {
-                    for host in &child_group.hosts {
-                        matching_hosts.insert(host.clone());
-                    }
-                    // Recurse into child's children
-                    Self::collect_hosts_from_children(groups, child_name, matching_hosts);
-                }
-            }
-        }
-    }
-
-    async fn parse_ini_inventory(&self, content: &str) -> Result<ParsedInventory, ParseError> {
+    async fn parse_ini_inventory(
+        &self,
+        content: &str,
+        file: &str,
+    ) -> Result<ParsedInventory, ParseError> {
         // Use the new comprehensive INI parser
         let ini_parser = IniInventoryParser::with_config(
             self.template_engine,
             self.extra_vars,
             self.config.clone(),
-        );
-
-        let mut inventory = ini_parser.parse_ini_inventory(content).await?;
-
-        // Ensure 'all' group exists and contains all hosts
-        Self::ensure_all_group(&mut inventory);
-
-        // Resolve variable inheritance
-        VariableInheritanceResolver::resolve_group_inheritance(&mut inventory)?;
-
-        // Validate the final inventory
-        InventoryValidator::validate_inventory(&inventory)?;
+        )
+        .with_source_file(file);
 
-        Ok(inventory)
+        ini_parser.parse_ini_inventory(content).await
     }
 
     /// Parse host patterns like web[01:05] into individual hosts
@@ -251,8 +267,13 @@ impl<'a> InventoryParser<'a> {
         InventoryValidator::validate_inventory(inventory)
     }
 
-    async fn parse_yaml_inventory(&self, content: &str) -> Result<ParsedInventory, ParseError> {
-        let yaml_value: serde_yaml::Value = serde_yaml::from_str(content)?;
+    async fn parse_yaml_inventory(
+        &self,
+        content: &str,
+        file: &str,
+    ) -> Result<ParsedInventory, ParseError> {
+        let yaml_value = yaml::load_with_limits(content, &self.config.yaml_limits)?;
+        let source_map = SourceMap::parse(file, content);
 
         let mut hosts = HashMap::new();
         let mut groups = HashMap::new();
@@ -284,39 +305,41 @@ impl<'a> InventoryParser<'a> {
                                 || all_data
                                     .contains_key(serde_yaml::Value::String("children".to_string()))
                             {
-                                self.process_group(group_name, &value, &mut hosts, &mut groups)?;
+                                self.process_group(
+                                    group_name,
+                                    &value,
+                                    &source_map.root().key(group_name),
+                                    &mut hosts,
+                                    &mut groups,
+                                )?;
                             }
                         }
                     } else {
                         // Process regular groups
-                        self.process_group(group_name, &value, &mut hosts, &mut groups)?;
+                        self.process_group(
+                            group_name,
+                            &value,
+                            &source_map.root().key(group_name),
+                            &mut hosts,
+                            &mut groups,
+                        )?;
                     }
                 }
             }
         }
 
-        let mut inventory = ParsedInventory {
+        Ok(ParsedInventory {
             hosts,
             groups,
             variables,
-        };
-
-        // Ensure 'all' group exists and contains all hosts
-        Self::ensure_all_group(&mut inventory);
-
-        // Resolve variable inheritance
-        VariableInheritanceResolver::resolve_group_inheritance(&mut inventory)?;
-
-        // Validate the final inventory
-        InventoryValidator::validate_inventory(&inventory)?;
-
-        Ok(inventory)
+        })
     }
 
     fn process_group(
         &self,
         group_name: &str,
         group_value: &serde_yaml::Value,
+        group_node: &SourceNode<'_>,
         hosts: &mut HashMap<String, ParsedHost>,
         groups: &mut HashMap<String, ParsedGroup>,
     ) -> Result<(), ParseError> {
@@ -361,6 +384,7 @@ impl<'a> InventoryParser<'a> {
                             become_method: conn.become_method,
                             become_user: conn.become_user,
                             become_flags: conn.become_flags,
+                            source: group_node.key("hosts").key(hostname).location(),
                         };
 
                         hosts.insert(hostname.to_string(), host);
@@ -377,7 +401,13 @@ impl<'a> InventoryParser<'a> {
                     if let Some(child_name) = child_name_val.as_str() {
                         children.push(child_name.to_string());
                         // Recursively process child group
-                        self.process_group(child_name, child_data_val, hosts, groups)?;
+                        self.process_group(
+                            child_name,
+                            child_data_val,
+                            &group_node.key("children").key(child_name),
+                            hosts,
+                            groups,
+                        )?;
                     }
                 }
             }
@@ -398,6 +428,7 @@ impl<'a> InventoryParser<'a> {
                     hosts: parsed_hosts,
                     children,
                     vars: group_vars,
+                    source: group_node.location(),
                 },
             );
         }
@@ -405,8 +436,14 @@ impl<'a> InventoryParser<'a> {
         Ok(())
     }
 
-    async fn parse_json_inventory(&self, content: &str) -> Result<ParsedInventory, ParseError> {
+    async fn parse_json_inventory(
+        &self,
+        content: &str,
+        file: &str,
+    ) -> Result<ParsedInventory, ParseError> {
         let raw_inventory: serde_json::Value = serde_json::from_str(content)?;
+        // JSON is valid YAML, so the YAML scanner can locate its nodes as well
+        let source_map = SourceMap::parse(file, content);
 
         let mut hosts = HashMap::new();
         let mut groups = HashMap::new();
@@ -443,6 +480,12 @@ impl<'a> InventoryParser<'a> {
                                         become_method: conn.become_method,
                                         become_user: conn.become_user,
                                         become_flags: conn.become_flags,
+                                        source: source_map
+                                            .root()
+                                            .key("_meta")
+                                            .key("hostvars")
+                                            .key(hostname)
+                                            .location(),
                                     };
 
                                     hosts.insert(hostname.clone(), host);
@@ -451,7 +494,13 @@ impl<'a> InventoryParser<'a> {
                         }
                     }
                 } else {
-                    // Handle group sections
+                    // Handle group sections; a bare list is shorthand for the group's hosts
+                    let value = match value {
+                        serde_json::Value::Array(group_hosts) => {
+                            serde_json::json!({ "hosts": group_hosts })
+                        }
+                        value => value,
+                    };
                     if let serde_json::Value::Object(group_data) = value {
                         let group_hosts: Vec<String> = group_data
                             .get("hosts")
@@ -488,11 +537,12 @@ impl<'a> InventoryParser<'a> {
                                 hosts: group_hosts.clone(),
                                 children,
                                 vars: group_vars,
+                                source: source_map.root().key(&key).location(),
                             },
                         );
 
                         // Update host group memberships and create hosts if they don't exist
-                        for hostname in group_hosts {
+                        for (index, hostname) in group_hosts.into_iter().enumerate() {
                             if let Some(host) = hosts.get_mut(&hostname) {
                                 host.groups.push(key.clone());
                             } else {
@@ -514,6 +564,12 @@ impl<'a> InventoryParser<'a> {
                                     become_method: None,
                                     become_user: None,
                                     become_flags: None,
+                                    source: source_map
+                                        .root()
+                                        .key(&key)
+                                        .key("hosts")
+                                        .index(index)
+                                        .location(),
                                 };
                                 hosts.insert(hostname.clone(), host);
                             }
@@ -523,22 +579,11 @@ impl<'a> InventoryParser<'a> {
             }
         }
 
-        let mut inventory = ParsedInventory {
+        Ok(ParsedInventory {
             hosts,
             groups,
             variables,
-        };
-
-        // Ensure 'all' group exists and contains all hosts
-        Self::ensure_all_group(&mut inventory);
-
-        // Resolve variable inheritance
-        VariableInheritanceResolver::resolve_group_inheritance(&mut inventory)?;
-
-        // Validate the final inventory
-        InventoryValidator::validate_inventory(&inventory)?;
-
-        Ok(inventory)
+        })
     }
 
     #[allow(dead_code)]
@@ -582,6 +627,112 @@ impl<'a> InventoryParser<'a> {
         vars
     }
 
+    /// Steps shared by every inventory format once its hosts and groups are read
+    async fn finish_inventory(
+        &self,
+        inventory: &mut ParsedInventory,
+        vars_dirs: Vec<PathBuf>,
+        constructed: &[ConstructedConfig],
+    ) -> Result<(), ParseError> {
+        // Ensure 'all' group exists and contains all hosts
+        Self::ensure_all_group(inventory);
+
+        let dirs = vars_dirs
+            .into_iter()
+            .chain(self.playbook_dir.clone())
+            .collect();
+        let loader = FileLoader::new(&IncludeConfig {
+            yaml_limits: self.config.yaml_limits,
+            ..IncludeConfig::default()
+        });
+        let vars_directories = VarsDirectories::new(dirs, loader)
+            .with_hash_behaviour(self.config.hash_behaviour)
+            .with_vault_password(self.vault_password);
+
+        // Constructed sources run once every other source is read, seeing
+        // each host's variables as inherited from its groups
+        for config in constructed {
+            let mut resolved = inventory.clone();
+            if config.use_vars_plugins {
+                vars_directories.apply(&mut resolved).await?;
+            }
+            VariableInheritanceResolver::resolve_group_inheritance_with(
+                &mut resolved,
+                self.config.hash_behaviour,
+            )?;
+            config.apply(inventory, &resolved, self.template_engine)?;
+        }
+
+        Self::check_group_names(inventory, self.config.transform_invalid_group_chars);
+
+        // group_vars/ and host_vars/ override the inventory's own variables
+        vars_directories.apply(inventory).await?;
+
+        // Resolve variable inheritance
+        VariableInheritanceResolver::resolve_group_inheritance_with(
+            inventory,
+            self.config.hash_behaviour,
+        )?;
+
+        // Validate the final inventory
+        InventoryValidator::validate_inventory(inventory)
+    }
+
+    /// Report, and with `always` or `silently` rename, groups whose names are
+    /// not valid variable names, as Ansible's `TRANSFORM_INVALID_GROUP_CHARS` does
+    fn check_group_names(inventory: &mut ParsedInventory, mode: TransformInvalidGroupChars) {
+        let mut invalid: Vec<String> = inventory
+            .groups
+            .keys()
+            .filter(|name| INVALID_GROUP_CHARS.is_match(name))
+            .cloned()
+            .collect();
+        invalid.sort();
+
+        for name in invalid {
+            let valid = INVALID_GROUP_CHARS.replace_all(&name, "_").into_owned();
+            if !mode.transforms() {
+                if mode.warns() {
+                    warn!("Invalid characters were found in group name '{name}'");
+                }
+                continue;
+            }
+            if mode.warns() {
+                warn!("Replacing invalid characters in group name '{name}' with '{valid}'");
+            }
+
+            let Some(mut group) = inventory.groups.remove(&name) else {
+                continue;
+            };
+            group.name = valid.clone();
+            match inventory.groups.get_mut(&valid) {
+                Some(existing) => {
+                    extend_unique(&mut existing.hosts, group.hosts);
+                    extend_unique(&mut existing.children, group.children);
+                    existing.vars.extend(group.vars);
+                }
+                None => {
+                    inventory.groups.insert(valid.clone(), group);
+                }
+            }
+
+            let rename = |names: &mut Vec<String>| {
+                if names.contains(&name) {
+                    names.retain(|n| *n != name);
+                    if !names.contains(&valid) {
+                        names.push(valid.clone());
+                    }
+                }
+            };
+            for group in inventory.groups.values_mut() {
+                rename(&mut group.children);
+            }
+            for host in inventory.hosts.values_mut() {
+                rename(&mut host.groups);
+            }
+        }
+    }
+
     /// Ensure the 'all' group exists and contains all hosts
     fn ensure_all_group(inventory: &mut ParsedInventory) {
         let all_host_names: Vec<String> = inventory.hosts.keys().cloned().collect();
@@ -611,6 +762,7 @@ impl<'a> InventoryParser<'a> {
                     hosts: all_host_names,
                     children: Vec::new(),
                     vars: HashMap::new(),
+                    source: None,
                 },
             );
         }
@@ -704,6 +856,7 @@ impl<'a> InventoryParser<'a> {
     }
 }
 
+/// Append the entries of `extra` that `names` does not already hold
 // Raw data structures for YAML inventory parsing
 #[derive(Debug, Deserialize)]
 struct RawYamlInventory {
@@ -1,13 +1,23 @@
+pub mod constructed;
 pub mod ini;
 pub mod patterns;
+pub mod script;
+pub mod selection;
+pub mod sources;
 pub mod validation;
 pub mod variables;
+pub mod vars_files;
 
 // Re-export the main inventory parser
 pub mod main;
 pub use main::*;
 
+pub use constructed::*;
 pub use ini::*;
 pub use patterns::*;
+pub use script::*;
+pub use selection::*;
+pub use sources::*;
 pub use validation::*;
 pub use variables::*;
+pub use vars_files::*;
@@ -0,0 +1,289 @@
+use crate::parser::error::ParseError;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::process::Command;
+
+/// A script and the arguments it was run with
+type ScriptRun = (PathBuf, Vec<String>);
+
+/// Output of inventory scripts by script and arguments. Clones share the
+/// outputs, so a script runs once per run however often it is parsed.
+#[derive(Debug, Clone, Default)]
+pub struct ScriptCache {
+    outputs: Arc<Mutex<HashMap<ScriptRun, String>>>,
+}
+
+impl ScriptCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn get(&self, key: &ScriptRun) -> Option<String> {
+        self.lock().get(key).cloned()
+    }
+
+    fn insert(&self, key: ScriptRun, output: String) {
+        self.lock().insert(key, output);
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<ScriptRun, String>> {
+        self.outputs.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// An executable inventory, run as Ansible's `script` inventory plugin does
+pub struct InventoryScript {
+    path: PathBuf,
+    timeout: Duration,
+    cache: ScriptCache,
+}
+
+impl InventoryScript {
+    /// A bare relative `path` is the script in the current directory, not one
+    /// looked up on `PATH`
+    pub fn new(path: &Path, timeout: Duration, cache: ScriptCache) -> Self {
+        let path = path.canonicalize().unwrap_or_else(|_| {
+            std::env::current_dir()
+                .map(|dir| dir.join(path))
+                .unwrap_or_else(|_| path.to_path_buf())
+        });
+        Self {
+            path,
+            timeout,
+            cache,
+        }
+    }
+
+    /// The JSON inventory the script prints for `--list`, with
+    /// `_meta.hostvars` filled in from `--host <name>` when the script
+    /// does not provide it
+    pub async fn list(&self) -> Result<serde_json::Value, ParseError> {
+        let mut inventory = self.run_json(&["--list"]).await?;
+        let Some(root) = inventory.as_object_mut() else {
+            return Err(self.error("--list did not return a JSON object".to_string()));
+        };
+        if root
+            .get("_meta")
+            .and_then(|meta| meta.get("hostvars"))
+            .is_some()
+        {
+            return Ok(inventory);
+        }
+
+        let mut host_names: Vec<String> = root
+            .iter()
+            .filter(|(group, _)| group.as_str() != "_meta")
+            .flat_map(|(_, group)| group_hosts(group))
+            .collect();
+        host_names.sort();
+        host_names.dedup();
+
+        let mut hostvars = serde_json::Map::new();
+        for name in host_names {
+            let vars = self.run_json(&["--host", &name]).await?;
+            hostvars.insert(name, vars);
+        }
+        root.insert(
+            "_meta".to_string(),
+            serde_json::json!({ "hostvars": hostvars }),
+        );
+        Ok(inventory)
+    }
+
+    async fn run_json(&self, args: &[&str]) -> Result<serde_json::Value, ParseError> {
+        let output = self.run(args).await?;
+        serde_json::from_str(&output)
+            .map_err(|e| self.error(format!("{} did not return valid JSON: {e}", args.join(" "))))
+    }
+
+    async fn run(&self, args: &[&str]) -> Result<String, ParseError> {
+        let key = (
+            self.path.clone(),
+            args.iter().map(|arg| arg.to_string()).collect(),
+        );
+        if let Some(output) = self.cache.get(&key) {
+            return Ok(output);
+        }
+
+        let child = Command::new(&self.path)
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| self.error(e.to_string()))?;
+        let output = tokio::time::timeout(self.timeout, child.wait_with_output())
+            .await
+            .map_err(|_| self.error(format!("timed out after {:?}", self.timeout)))?
+            .map_err(|e| self.error(e.to_string()))?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(self.error(format!("{}: {}", output.status, stderr.trim())));
+        }
+        let stdout = String::from_utf8(output.stdout)
+            .map_err(|_| self.error("output is not valid UTF-8".to_string()))?;
+        self.cache.insert(key, stdout.clone());
+        Ok(stdout)
+    }
+
+    fn error(&self, message: String) -> ParseError {
+        ParseError::InventoryScript {
+            script: self.path.to_string_lossy().to_string(),
+            message,
+        }
+    }
+}
+
+/// Host names a group lists, in either the full or the list shorthand form
+fn group_hosts(group: &serde_json::Value) -> Vec<String> {
+    let hosts = match group {
+        serde_json::Value::Array(hosts) => Some(hosts),
+        serde_json::Value::Object(group) => group.get("hosts").and_then(|h| h.as_array()),
+        _ => None,
+    };
+    hosts
+        .into_iter()
+        .flatten()
+        .filter_map(|host| host.as_str().map(str::to_string))
+        .collect()
+}
+
+/// Whether `path` is a file Ansible would run rather than read
+#[cfg(unix)]
+pub fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .map(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+pub fn is_executable(_path: &Path) -> bool {
+    false
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+    use tempfile::TempDir;
+
+    fn write_script(dir: &Path, name: &str, body: &str) -> PathBuf {
+        let path = dir.join(name);
+        std::fs::write(&path, format!("#!/bin/sh\n{body}")).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    #[tokio::test]
+    async fn test_list_fills_hostvars_from_host_calls() {
+        let temp_dir = TempDir::new().unwrap();
+        let calls = temp_dir.path().join("calls");
+        let script = write_script(
+            temp_dir.path(),
+            "inventory.sh",
+            &format!(
+                r#"echo "$@" >> {calls}
+if [ "$1" = "--list" ]; then
+  echo '{{"web": ["web1", "web2"]}}'
+else
+  echo "{{\"name\": \"$2\"}}"
+fi
+"#,
+                calls = calls.display()
+            ),
+        );
+
+        let cache = ScriptCache::new();
+        let inventory = InventoryScript::new(&script, Duration::from_secs(10), cache.clone())
+            .list()
+            .await
+            .unwrap();
+        assert_eq!(
+            inventory["_meta"]["hostvars"]["web2"],
+            serde_json::json!({"name": "web2"})
+        );
+        assert_eq!(cache.len(), 3);
+
+        // A second run is answered from the cache
+        InventoryScript::new(&script, Duration::from_secs(10), cache)
+            .list()
+            .await
+            .unwrap();
+        let calls = std::fs::read_to_string(calls).unwrap();
+        assert_eq!(calls.lines().count(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_bare_relative_script_name() {
+        let script = tempfile::Builder::new()
+            .suffix(".sh")
+            .tempfile_in(".")
+            .unwrap();
+        std::fs::write(
+            script.path(),
+            "#!/bin/sh\necho '{\"web\": {\"hosts\": [\"web1\"]}, \"_meta\": {\"hostvars\": {}}}'\n",
+        )
+        .unwrap();
+        let script = script.into_temp_path();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let name = Path::new(script.file_name().unwrap());
+        assert_eq!(name.components().count(), 1);
+
+        let cache = ScriptCache::new();
+        let inventory = InventoryScript::new(name, Duration::from_secs(10), cache.clone())
+            .list()
+            .await
+            .unwrap();
+        assert_eq!(inventory["web"]["hosts"][0], "web1");
+
+        // `./name` is the same script and shares its output
+        InventoryScript::new(
+            &Path::new(".").join(name),
+            Duration::from_secs(10),
+            cache.clone(),
+        )
+        .list()
+        .await
+        .unwrap();
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_script_failures() {
+        let temp_dir = TempDir::new().unwrap();
+        let failing = write_script(temp_dir.path(), "failing.sh", "echo broken >&2\nexit 3\n");
+        let slow = write_script(temp_dir.path(), "slow.sh", "sleep 5\n");
+        let garbage = write_script(temp_dir.path(), "garbage.sh", "echo not json\n");
+
+        for (script, message) in [
+            (&failing, "broken"),
+            (&slow, "timed out"),
+            (&garbage, "valid JSON"),
+        ] {
+            let result =
+                InventoryScript::new(script, Duration::from_millis(200), ScriptCache::new())
+                    .list()
+                    .await;
+            match result {
+                Err(ParseError::InventoryScript { message: error, .. }) => {
+                    assert!(error.contains(message), "{error}")
+                }
+                other => panic!("unexpected result: {other:?}"),
+            }
+        }
+    }
+}
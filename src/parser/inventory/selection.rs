@@ -0,0 +1,476 @@
+use crate::parser::error::ParseError;
+use crate::parser::inventory::patterns::HostPattern;
+use crate::types::parsed::ParsedInventory;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::collections::HashSet;
+use std::path::Path;
+
+/// `name[index]`, `name[start:end]`, `name[start:]` and the removed `name[start-end]`
+static SUBSCRIPT: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(.+)\[(?:(-?[0-9]+)|([0-9]+)([:-])([0-9]+)?)\]$").unwrap());
+
+/// Patterns separated by `:`, where a bracketed subscript or range may itself hold colons
+static COLON_SEPARATED: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?:[^\s:\[\]]|\[[^\]]*\])+").unwrap());
+
+/// `host:port`, which is one pattern rather than two
+static HOST_AND_PORT: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[^:\[\]]+:[0-9]+$").unwrap());
+
+/// Selects inventory hosts with Ansible's host pattern language, as used by
+/// `--limit` and a play's `hosts`.
+///
+/// Patterns are unions (`a:b` or `a,b`), intersections (`&a`), exclusions
+/// (`!a`), regular expressions (`~web\d+`), globs (`web*`), group subscripts
+/// (`web[0]`, `web[-1]`, `web[1:3]`) and `all`/`*`. Unions are evaluated
+/// first, then intersections, then exclusions, whatever order they are
+/// written in.
+pub struct HostSelector<'a> {
+    inventory: &'a ParsedInventory,
+}
+
+impl<'a> HostSelector<'a> {
+    pub fn new(inventory: &'a ParsedInventory) -> Self {
+        Self { inventory }
+    }
+
+    /// Hosts selected by `pattern`, in inventory order
+    pub fn select(&self, pattern: &str) -> Result<Vec<String>, ParseError> {
+        self.select_patterns(&split_host_pattern(pattern))
+    }
+
+    /// Hosts selected by a `--limit` value, which may also name `@file`s
+    /// listing one pattern per line, such as Ansible's retry files
+    pub fn select_limit(&self, limit: &str) -> Result<Vec<String>, ParseError> {
+        let mut patterns = Vec::new();
+        for pattern in split_host_pattern(limit) {
+            match pattern.strip_prefix('@') {
+                Some(file) => patterns.extend(read_limit_file(Path::new(file))?),
+                None => patterns.push(pattern),
+            }
+        }
+        self.select_patterns(&patterns)
+    }
+
+    /// Hosts selected by several patterns, each of which may be a union
+    pub fn select_patterns(&self, patterns: &[String]) -> Result<Vec<String>, ParseError> {
+        let patterns: Vec<String> = patterns
+            .iter()
+            .flat_map(|pattern| split_host_pattern(pattern))
+            .collect();
+
+        let (mut unions, mut intersections, mut exclusions) = (Vec::new(), Vec::new(), Vec::new());
+        for pattern in &patterns {
+            if let Some(pattern) = pattern.strip_prefix('&') {
+                intersections.push(pattern);
+            } else if let Some(pattern) = pattern.strip_prefix('!') {
+                exclusions.push(pattern);
+            } else {
+                unions.push(pattern.as_str());
+            }
+        }
+        if patterns.is_empty() {
+            return Ok(Vec::new());
+        }
+        // Only intersections and exclusions narrow down every host
+        if unions.is_empty() {
+            unions.push("all");
+        }
+
+        let mut hosts: Vec<String> = Vec::new();
+        for pattern in unions {
+            for host in self.match_one(pattern)? {
+                if !hosts.contains(&host) {
+                    hosts.push(host);
+                }
+            }
+        }
+        for pattern in intersections {
+            let matched: HashSet<String> = self.match_one(pattern)?.into_iter().collect();
+            hosts.retain(|host| matched.contains(host));
+        }
+        for pattern in exclusions {
+            let matched: HashSet<String> = self.match_one(pattern)?.into_iter().collect();
+            hosts.retain(|host| !matched.contains(host));
+        }
+        Ok(hosts)
+    }
+
+    /// Hosts of a single pattern, with its subscript applied
+    fn match_one(&self, pattern: &str) -> Result<Vec<String>, ParseError> {
+        if pattern.starts_with('~') {
+            return self.enumerate(pattern);
+        }
+        let Some(captures) = SUBSCRIPT.captures(pattern) else {
+            return self.enumerate(pattern);
+        };
+
+        let base = &captures[1];
+        let hosts = self.enumerate(base)?;
+        if hosts.is_empty() {
+            // Not a group or host: a host range such as `web[01:03]`
+            return Ok(match HostPattern::new(pattern) {
+                Ok(range) => self.existing_hosts(range.expanded),
+                Err(_) => Vec::new(),
+            });
+        }
+
+        let invalid = |message: &str| ParseError::InvalidHostPattern {
+            pattern: pattern.to_string(),
+            line: 0,
+            message: message.to_string(),
+        };
+        let len = hosts.len() as i64;
+        if let Some(index) = captures.get(2) {
+            let index: i64 = index
+                .as_str()
+                .parse()
+                .map_err(|_| invalid("invalid subscript"))?;
+            let index = if index < 0 { len + index } else { index };
+            return Ok(usize::try_from(index)
+                .ok()
+                .and_then(|index| hosts.get(index))
+                .cloned()
+                .into_iter()
+                .collect());
+        }
+
+        if &captures[4] == "-" {
+            return Err(invalid(
+                "use [x:y] inclusive subscripts instead of [x-y], which Ansible removed",
+            ));
+        }
+        let start: usize = captures[3]
+            .parse()
+            .map_err(|_| invalid("invalid subscript"))?;
+        let end = match captures.get(5) {
+            Some(end) => end
+                .as_str()
+                .parse::<usize>()
+                .map_err(|_| invalid("invalid subscript"))?
+                .saturating_add(1)
+                .min(hosts.len()),
+            None => hosts.len(),
+        };
+        Ok(hosts
+            .get(start..end)
+            .map(<[String]>::to_vec)
+            .unwrap_or_default())
+    }
+
+    /// Hosts of the groups matching `pattern`, and the hosts matching it when
+    /// no group does or the pattern is a regex or glob
+    fn enumerate(&self, pattern: &str) -> Result<Vec<String>, ParseError> {
+        if pattern == "all" || pattern == "*" {
+            return Ok(self.all_hosts());
+        }
+        let matcher = Matcher::new(pattern)?;
+
+        let mut group_names: Vec<&String> = self
+            .inventory
+            .groups
+            .keys()
+            .filter(|group| matcher.matches(group))
+            .collect();
+        group_names.sort();
+
+        let mut hosts = Vec::new();
+        let mut seen = HashSet::new();
+        for group in &group_names {
+            self.collect_group_hosts(group, &mut hosts, &mut seen);
+        }
+
+        let is_wildcard = pattern.starts_with('~') || pattern.contains(['.', '?', '*', '[']);
+        if group_names.is_empty() || is_wildcard {
+            let mut host_names: Vec<&String> = self
+                .inventory
+                .hosts
+                .keys()
+                .filter(|host| matcher.matches(host))
+                .collect();
+            host_names.sort();
+            for host in host_names {
+                if seen.insert(host.clone()) {
+                    hosts.push(host.clone());
+                }
+            }
+        }
+        Ok(hosts)
+    }
+
+    /// A group's own hosts, then those of its children
+    fn collect_group_hosts(
+        &self,
+        group: &str,
+        hosts: &mut Vec<String>,
+        seen: &mut HashSet<String>,
+    ) {
+        let Some(group) = self.inventory.groups.get(group) else {
+            return;
+        };
+        if group.name == "all" {
+            for host in self.all_hosts() {
+                if seen.insert(host.clone()) {
+                    hosts.push(host);
+                }
+            }
+            return;
+        }
+        for host in &group.hosts {
+            if self.inventory.hosts.contains_key(host) && seen.insert(host.clone()) {
+                hosts.push(host.clone());
+            }
+        }
+        for child in &group.children {
+            self.collect_group_hosts(child, hosts, seen);
+        }
+    }
+
+    fn all_hosts(&self) -> Vec<String> {
+        let mut hosts: Vec<String> = self.inventory.hosts.keys().cloned().collect();
+        hosts.sort();
+        hosts
+    }
+
+    fn existing_hosts(&self, names: Vec<String>) -> Vec<String> {
+        names
+            .into_iter()
+            .filter(|host| self.inventory.hosts.contains_key(host))
+            .collect()
+    }
+}
+
+/// A glob, or with a leading `~` a regular expression matched from the
+/// start of the name
+enum Matcher {
+    Exact(String),
+    Regex(Regex),
+}
+
+impl Matcher {
+    fn new(pattern: &str) -> Result<Self, ParseError> {
+        let invalid = |e: regex::Error| ParseError::InvalidHostPattern {
+            pattern: pattern.to_string(),
+            line: 0,
+            message: e.to_string(),
+        };
+        if let Some(regex) = pattern.strip_prefix('~') {
+            return Ok(Self::Regex(
+                Regex::new(&format!("^(?:{regex})")).map_err(invalid)?,
+            ));
+        }
+        if !pattern.contains(['*', '?', '[']) {
+            return Ok(Self::Exact(pattern.to_string()));
+        }
+
+        let mut regex = String::from("^");
+        let mut chars = pattern.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '*' => regex.push_str(".*"),
+                '?' => regex.push('.'),
+                '[' => {
+                    // A character class, as in fnmatch
+                    let mut class = String::new();
+                    for c in chars.by_ref() {
+                        if c == ']' {
+                            break;
+                        }
+                        class.push(c);
+                    }
+                    let class = match class.strip_prefix('!') {
+                        Some(negated) => format!("^{}", regex::escape(negated)),
+                        None => regex::escape(&class),
+                    };
+                    regex.push_str(&format!("[{}]", class.replace("\\-", "-")));
+                }
+                c => regex.push_str(&regex::escape(&c.to_string())),
+            }
+        }
+        regex.push('$');
+        Ok(Self::Regex(Regex::new(&regex).map_err(invalid)?))
+    }
+
+    fn matches(&self, name: &str) -> bool {
+        match self {
+            Self::Exact(pattern) => pattern == name,
+            Self::Regex(regex) => regex.is_match(name),
+        }
+    }
+}
+
+/// Split a host pattern into the patterns of its union: on commas if it has
+/// any, otherwise on colons outside brackets, keeping IPv6 addresses and
+/// `host:port` whole
+pub fn split_host_pattern(pattern: &str) -> Vec<String> {
+    let parts: Vec<&str> = if pattern.contains(',') {
+        // `:group` was accepted in comma-separated limits before colons
+        // separated patterns
+        pattern
+            .split(',')
+            .map(|part| part.trim().strip_prefix(':').unwrap_or(part.trim()))
+            .collect()
+    } else if pattern.trim().parse::<std::net::Ipv6Addr>().is_ok()
+        || HOST_AND_PORT.is_match(pattern.trim())
+    {
+        vec![pattern]
+    } else {
+        COLON_SEPARATED
+            .find_iter(pattern)
+            .map(|part| part.as_str())
+            .collect()
+    };
+    parts
+        .into_iter()
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// The patterns of a limit file, one per line
+fn read_limit_file(path: &Path) -> Result<Vec<String>, ParseError> {
+    let content = std::fs::read_to_string(path).map_err(|e| {
+        if e.kind() == std::io::ErrorKind::NotFound {
+            ParseError::FileNotFound {
+                path: path.to_string_lossy().to_string(),
+            }
+        } else {
+            ParseError::Io(e)
+        }
+    })?;
+    Ok(content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::parsed::{ParsedGroup, ParsedHost};
+    use std::collections::HashMap;
+
+    fn inventory() -> ParsedInventory {
+        let host = |name: &str| ParsedHost {
+            name: name.to_string(),
+            address: None,
+            port: None,
+            user: None,
+            vars: HashMap::new(),
+            groups: Vec::new(),
+            connection: None,
+            ssh_private_key_file: None,
+            ssh_common_args: None,
+            ssh_extra_args: None,
+            ssh_pipelining: None,
+            connection_timeout: None,
+            ansible_become: None,
+            become_method: None,
+            become_user: None,
+            become_flags: None,
+            source: None,
+        };
+        let group = |name: &str, hosts: &[&str], children: &[&str]| ParsedGroup {
+            name: name.to_string(),
+            hosts: hosts.iter().map(|h| h.to_string()).collect(),
+            children: children.iter().map(|c| c.to_string()).collect(),
+            vars: HashMap::new(),
+            source: None,
+        };
+        let hosts = [
+            "web3",
+            "web1",
+            "web2",
+            "db1",
+            "db2",
+            "app01",
+            "app02",
+            "lb.example.com",
+        ];
+        ParsedInventory {
+            hosts: hosts.iter().map(|h| (h.to_string(), host(h))).collect(),
+            groups: [
+                group("all", &hosts, &[]),
+                group("web", &["web3", "web1", "web2"], &[]),
+                group("db", &["db1", "db2"], &[]),
+                group("staging", &["web1", "db1"], &[]),
+                group("apps", &[], &["web", "db"]),
+            ]
+            .into_iter()
+            .map(|g| (g.name.clone(), g))
+            .collect(),
+            variables: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_split_host_pattern() {
+        assert_eq!(split_host_pattern("web:db"), vec!["web", "db"]);
+        assert_eq!(split_host_pattern("web, :db"), vec!["web", "db"]);
+        assert_eq!(
+            split_host_pattern("web[1:2]:&staging:!db"),
+            vec!["web[1:2]", "&staging", "!db"]
+        );
+        assert_eq!(split_host_pattern("fe80::1"), vec!["fe80::1"]);
+        assert_eq!(split_host_pattern("web1:22"), vec!["web1:22"]);
+    }
+
+    #[test]
+    fn test_select() {
+        let inventory = inventory();
+        let selector = HostSelector::new(&inventory);
+        let select = |pattern: &str| selector.select(pattern).unwrap();
+
+        assert_eq!(select("all").len(), 8);
+        assert_eq!(select("*").len(), 8);
+        assert_eq!(select("web"), vec!["web3", "web1", "web2"]);
+        assert_eq!(select("apps"), vec!["web3", "web1", "web2", "db1", "db2"]);
+        // Exclusions and intersections apply after every union
+        assert_eq!(select("!db:apps"), vec!["web3", "web1", "web2"]);
+        assert_eq!(select("apps:&staging"), vec!["web1", "db1"]);
+        assert_eq!(select("!apps"), vec!["app01", "app02", "lb.example.com"]);
+        assert_eq!(select("~web[12]"), vec!["web1", "web2"]);
+        assert_eq!(select("db?"), vec!["db1", "db2"]);
+        assert_eq!(select("*.example.com"), vec!["lb.example.com"]);
+        assert_eq!(select("web[0]"), vec!["web3"]);
+        assert_eq!(select("web[-1]"), vec!["web2"]);
+        assert_eq!(select("web[1:2]"), vec!["web1", "web2"]);
+        assert_eq!(select("web[1:]"), vec!["web1", "web2"]);
+        assert_eq!(select("web[5]"), Vec::<String>::new());
+        assert_eq!(select("db[1:2]"), vec!["db2"]);
+        // Names that are not groups or hosts are host ranges
+        assert_eq!(select("app[01:02]"), vec!["app01", "app02"]);
+        assert!(select("missing").is_empty());
+
+        assert!(matches!(
+            selector.select("web[0-1]"),
+            Err(ParseError::InvalidHostPattern { .. })
+        ));
+        assert!(matches!(
+            selector.select("~web("),
+            Err(ParseError::InvalidHostPattern { .. })
+        ));
+    }
+
+    #[test]
+    fn test_select_limit_reads_retry_files() {
+        let inventory = inventory();
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let retry = temp_dir.path().join("site.retry");
+        std::fs::write(&retry, "web2\ndb1\n\n").unwrap();
+
+        let selector = HostSelector::new(&inventory);
+        let limit = format!("@{},lb.example.com", retry.display());
+        assert_eq!(
+            selector.select_limit(&limit).unwrap(),
+            vec!["web2", "db1", "lb.example.com"]
+        );
+        assert!(matches!(
+            selector.select_limit("@missing.retry"),
+            Err(ParseError::FileNotFound { .. })
+        ));
+    }
+}
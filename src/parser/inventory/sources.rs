@@ -0,0 +1,183 @@
+use crate::parser::config::HashBehaviour;
+use crate::parser::error::ParseError;
+use crate::parser::inventory::variables::merge_hashes;
+use crate::types::parsed::{ParsedGroup, ParsedHost, ParsedInventory};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// File name endings skipped in inventory directories, Ansible's default
+/// `INVENTORY_IGNORE_EXTS`
+const IGNORED_EXTENSIONS: [&str; 12] = [
+    ".pyc", ".pyo", ".swp", ".bak", "~", ".rpm", ".md", ".txt", ".rst", ".orig", ".cfg", ".retry",
+];
+
+/// Entries of an inventory directory that hold variables rather than inventory
+const VARS_DIRECTORIES: [&str; 3] = ["group_vars", "host_vars", "vars_plugins"];
+
+/// The inventory files of `source`: the file itself, or every file of the
+/// directory and its subdirectories in name order, without hidden files,
+/// ignored extensions and vars directories
+pub fn inventory_files(source: &Path) -> Result<Vec<PathBuf>, ParseError> {
+    if !source.exists() {
+        return Err(ParseError::FileNotFound {
+            path: source.to_string_lossy().to_string(),
+        });
+    }
+    let mut files = Vec::new();
+    if source.is_dir() {
+        collect_dir(source, &mut files)?;
+    } else {
+        files.push(source.to_path_buf());
+    }
+    Ok(files)
+}
+
+fn collect_dir(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), ParseError> {
+    let mut paths = std::fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    paths.sort();
+
+    for path in paths {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        if name.starts_with('.') {
+            continue;
+        }
+        if path.is_dir() {
+            if !VARS_DIRECTORIES.contains(&name.as_str()) {
+                collect_dir(&path, files)?;
+            }
+        } else if !IGNORED_EXTENSIONS.iter().any(|ext| name.ends_with(ext)) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Merge `other`, read from a later inventory source, into `inventory`.
+///
+/// Hosts and groups defined by both sources keep their members from each;
+/// variables and connection settings of the later source win. A host keeps
+/// the source location it was first defined at.
+pub fn merge_inventory(
+    inventory: &mut ParsedInventory,
+    other: ParsedInventory,
+    hash_behaviour: HashBehaviour,
+) {
+    for (name, host) in other.hosts {
+        match inventory.hosts.get_mut(&name) {
+            Some(existing) => merge_host(existing, host, hash_behaviour),
+            None => {
+                inventory.hosts.insert(name, host);
+            }
+        }
+    }
+    for (name, group) in other.groups {
+        match inventory.groups.get_mut(&name) {
+            Some(existing) => merge_group(existing, group, hash_behaviour),
+            None => {
+                inventory.groups.insert(name, group);
+            }
+        }
+    }
+    merge_vars(&mut inventory.variables, other.variables, hash_behaviour);
+}
+
+fn merge_host(host: &mut ParsedHost, other: ParsedHost, hash_behaviour: HashBehaviour) {
+    merge_vars(&mut host.vars, other.vars, hash_behaviour);
+    extend_unique(&mut host.groups, other.groups);
+
+    host.address = other.address.or(host.address.take());
+    host.port = other.port.or(host.port);
+    host.user = other.user.or(host.user.take());
+    host.connection = other.connection.or(host.connection.take());
+    host.ssh_private_key_file = other
+        .ssh_private_key_file
+        .or(host.ssh_private_key_file.take());
+    host.ssh_common_args = other.ssh_common_args.or(host.ssh_common_args.take());
+    host.ssh_extra_args = other.ssh_extra_args.or(host.ssh_extra_args.take());
+    host.ssh_pipelining = other.ssh_pipelining.or(host.ssh_pipelining);
+    host.connection_timeout = other.connection_timeout.or(host.connection_timeout);
+    host.ansible_become = other.ansible_become.or(host.ansible_become);
+    host.become_method = other.become_method.or(host.become_method.take());
+    host.become_user = other.become_user.or(host.become_user.take());
+    host.become_flags = other.become_flags.or(host.become_flags.take());
+    host.source = host.source.take().or(other.source);
+}
+
+fn merge_group(group: &mut ParsedGroup, other: ParsedGroup, hash_behaviour: HashBehaviour) {
+    merge_vars(&mut group.vars, other.vars, hash_behaviour);
+    extend_unique(&mut group.hosts, other.hosts);
+    extend_unique(&mut group.children, other.children);
+    group.source = group.source.take().or(other.source);
+}
+
+/// Set `overrides` in `vars`, combining dictionaries as `hash_behaviour` says
+pub(crate) fn merge_vars(
+    vars: &mut HashMap<String, serde_json::Value>,
+    overrides: HashMap<String, serde_json::Value>,
+    hash_behaviour: HashBehaviour,
+) {
+    for (key, value) in overrides {
+        let value = match (hash_behaviour, vars.get(&key)) {
+            (HashBehaviour::Merge, Some(existing)) => merge_hashes(existing, &value),
+            _ => value,
+        };
+        vars.insert(key, value);
+    }
+}
+
+pub(crate) fn extend_unique(items: &mut Vec<String>, others: Vec<String>) {
+    for item in others {
+        if !items.contains(&item) {
+            items.push(item);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_inventory_files_skip_ignored_entries() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path();
+        for file in [
+            "01-hosts.ini",
+            "02-cloud.yml",
+            "README.md",
+            "hosts.ini.bak",
+            "hosts~",
+            ".hidden",
+            "group_vars/all.yml",
+            "host_vars/web1.yml",
+            "nested/extra",
+        ] {
+            let path = dir.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, "").unwrap();
+        }
+
+        assert_eq!(
+            inventory_files(dir).unwrap(),
+            vec![
+                dir.join("01-hosts.ini"),
+                dir.join("02-cloud.yml"),
+                dir.join("nested/extra"),
+            ]
+        );
+        assert_eq!(
+            inventory_files(&dir.join("README.md")).unwrap(),
+            vec![dir.join("README.md")]
+        );
+        assert!(matches!(
+            inventory_files(&dir.join("missing")),
+            Err(ParseError::FileNotFound { .. })
+        ));
+    }
+}
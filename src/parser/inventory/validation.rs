@@ -552,6 +552,7 @@ mod tests {
                 become_method: None,
                 become_user: None,
                 become_flags: None,
+                source: None,
             },
         );
 
@@ -562,6 +563,7 @@ mod tests {
                 hosts: vec!["web1".to_string()],
                 children: Vec::new(),
                 vars: HashMap::new(),
+                source: None,
             },
         );
 
@@ -572,6 +574,7 @@ mod tests {
                 hosts: vec!["web1".to_string()],
                 children: Vec::new(),
                 vars: HashMap::new(),
+                source: None,
             },
         );
 
@@ -636,6 +639,7 @@ mod tests {
                 hosts: Vec::new(),
                 children: vec!["group2".to_string()],
                 vars: HashMap::new(),
+                source: None,
             },
         );
 
@@ -646,6 +650,7 @@ mod tests {
                 hosts: Vec::new(),
                 children: vec!["group1".to_string()],
                 vars: HashMap::new(),
+                source: None,
             },
         );
 
@@ -656,6 +661,7 @@ mod tests {
                 hosts: Vec::new(),
                 children: Vec::new(),
                 vars: HashMap::new(),
+                source: None,
             },
         );
 
@@ -1,3 +1,4 @@
+use crate::parser::config::HashBehaviour;
 use crate::parser::error::ParseError;
 use crate::types::parsed::{ParsedHost, ParsedInventory};
 use petgraph::{Direction, Graph};
@@ -15,6 +16,15 @@ pub struct VariableInheritanceResolver;
 impl VariableInheritanceResolver {
     /// Resolve group inheritance and variable precedence for the entire inventory
     pub fn resolve_group_inheritance(inventory: &mut ParsedInventory) -> Result<(), ParseError> {
+        Self::resolve_group_inheritance_with(inventory, HashBehaviour::Replace)
+    }
+
+    /// Resolve group inheritance, combining dictionaries defined at several
+    /// levels as `hash_behaviour` says
+    pub fn resolve_group_inheritance_with(
+        inventory: &mut ParsedInventory,
+        hash_behaviour: HashBehaviour,
+    ) -> Result<(), ParseError> {
         // Build group dependency graph
         let (graph, group_indices) = Self::build_group_graph(inventory)?;
 
@@ -31,10 +41,19 @@ impl VariableInheritanceResolver {
             }
         })?;
 
-        // Apply group variables to hosts in dependency order (children first, then parents)
-        for node_index in topo_order.iter().rev() {
-            let group_name = &graph[*node_index];
-            Self::apply_group_variables_to_hosts(inventory, group_name)?;
+        // Apply group variables to hosts in dependency order (children first, then parents),
+        // with `all` last whether or not it lists the other groups as children
+        let mut apply_order: Vec<&String> = topo_order
+            .iter()
+            .rev()
+            .map(|node_index| &graph[*node_index])
+            .filter(|group_name| group_name.as_str() != "all")
+            .collect();
+        if group_indices.contains_key("all") {
+            apply_order.push(&graph[group_indices["all"]]);
+        }
+        for group_name in apply_order {
+            Self::apply_group_variables_to_hosts(inventory, group_name, hash_behaviour)?;
         }
 
         // Apply variables from parent groups to child groups
@@ -121,6 +140,7 @@ impl VariableInheritanceResolver {
     fn apply_group_variables_to_hosts(
         inventory: &mut ParsedInventory,
         group_name: &str,
+        hash_behaviour: HashBehaviour,
     ) -> Result<(), ParseError> {
         let group = match inventory.groups.get(group_name) {
             Some(g) => g.clone(),  // Clone to avoid borrow checker issues
@@ -130,7 +150,7 @@ impl VariableInheritanceResolver {
         // Apply group variables to direct member hosts
         for host_name in &group.hosts {
             if let Some(host) = inventory.hosts.get_mut(host_name) {
-                Self::apply_group_vars_to_host(host, &group.vars);
+                Self::apply_group_vars_to_host(host, &group.vars, hash_behaviour);
             }
         }
 
@@ -140,6 +160,7 @@ impl VariableInheritanceResolver {
                 inventory,
                 child_group_name,
                 &group.vars,
+                hash_behaviour,
             )?;
         }
 
@@ -150,13 +171,20 @@ impl VariableInheritanceResolver {
     fn apply_group_vars_to_host(
         host: &mut ParsedHost,
         group_vars: &HashMap<String, serde_json::Value>,
+        hash_behaviour: HashBehaviour,
     ) {
         for (key, value) in group_vars {
             // Group variables have lower precedence than host variables
             // Only set if the host doesn't already have this variable
-            host.vars
-                .entry(key.clone())
-                .or_insert_with(|| value.clone());
+            match host.vars.get_mut(key) {
+                Some(existing) if hash_behaviour == HashBehaviour::Merge => {
+                    *existing = merge_hashes(value, existing);
+                }
+                Some(_) => {}
+                None => {
+                    host.vars.insert(key.clone(), value.clone());
+                }
+            }
         }
     }
 
@@ -165,6 +193,7 @@ impl VariableInheritanceResolver {
         inventory: &mut ParsedInventory,
         child_group_name: &str,
         parent_vars: &HashMap<String, serde_json::Value>,
+        hash_behaviour: HashBehaviour,
     ) -> Result<(), ParseError> {
         let child_group = match inventory.groups.get(child_group_name) {
             Some(g) => g.clone(),
@@ -174,7 +203,7 @@ impl VariableInheritanceResolver {
         // Apply to direct hosts in child group
         for host_name in &child_group.hosts {
             if let Some(host) = inventory.hosts.get_mut(host_name) {
-                Self::apply_group_vars_to_host(host, parent_vars);
+                Self::apply_group_vars_to_host(host, parent_vars, hash_behaviour);
             }
         }
 
@@ -184,6 +213,7 @@ impl VariableInheritanceResolver {
                 inventory,
                 grandchild_name,
                 parent_vars,
+                hash_behaviour,
             )?;
         }
 
@@ -324,6 +354,25 @@ impl VariableInheritanceResolver {
     }
 }
 
+/// Merge `higher` over `lower` the way `hash_behaviour = merge` does: dictionaries
+/// are merged key by key, recursively, and any other value from `higher` wins
+pub fn merge_hashes(lower: &serde_json::Value, higher: &serde_json::Value) -> serde_json::Value {
+    match (lower, higher) {
+        (serde_json::Value::Object(lower), serde_json::Value::Object(higher)) => {
+            let mut merged = lower.clone();
+            for (key, value) in higher {
+                let value = match merged.get(key) {
+                    Some(existing) => merge_hashes(existing, value),
+                    None => value.clone(),
+                };
+                merged.insert(key.clone(), value);
+            }
+            serde_json::Value::Object(merged)
+        }
+        _ => higher.clone(),
+    }
+}
+
 /// Variable precedence levels in Ansible inventory (from lowest to highest)
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub enum VariablePrecedence {
@@ -395,6 +444,7 @@ mod tests {
                 become_method: None,
                 become_user: None,
                 become_flags: None,
+                source: None,
             },
         );
 
@@ -417,6 +467,7 @@ mod tests {
                 become_method: None,
                 become_user: None,
                 become_flags: None,
+                source: None,
             },
         );
 
@@ -439,6 +490,7 @@ mod tests {
                     );
                     vars
                 },
+                source: None,
             },
         );
 
@@ -460,6 +512,7 @@ mod tests {
                     );
                     vars
                 },
+                source: None,
             },
         );
 
@@ -477,6 +530,7 @@ mod tests {
                     );
                     vars
                 },
+                source: None,
             },
         );
 
@@ -553,6 +607,7 @@ mod tests {
                 hosts: Vec::new(),
                 children: vec!["group2".to_string()],
                 vars: HashMap::new(),
+                source: None,
             },
         );
 
@@ -563,6 +618,7 @@ mod tests {
                 hosts: Vec::new(),
                 children: vec!["group1".to_string()],
                 vars: HashMap::new(),
+                source: None,
             },
         );
 
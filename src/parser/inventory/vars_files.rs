@@ -0,0 +1,314 @@
+use crate::parser::config::HashBehaviour;
+use crate::parser::error::ParseError;
+use crate::parser::include::FileLoader;
+use crate::parser::inventory::sources::merge_vars;
+use crate::parser::vault::VaultDecryptor;
+use crate::types::parsed::ParsedInventory;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+type Variables = HashMap<String, serde_json::Value>;
+
+/// Extensions Ansible accepts for files in `group_vars/` and `host_vars/`
+const VARS_EXTENSIONS: [&str; 3] = ["yml", "yaml", "json"];
+
+/// Loads `group_vars/<group>` and `host_vars/<host>` from directories next to
+/// the inventory and the playbook.
+///
+/// Each entry may be a file, with or without a YAML/JSON extension, or a
+/// directory whose files are read recursively in name order. Later
+/// directories override earlier ones, and all of them override variables
+/// written in the inventory itself.
+pub struct VarsDirectories<'a> {
+    dirs: Vec<PathBuf>,
+    loader: FileLoader,
+    hash_behaviour: HashBehaviour,
+    vault_password: Option<&'a str>,
+}
+
+impl<'a> VarsDirectories<'a> {
+    /// `dirs` in increasing precedence, e.g. the inventory's then the playbook's directory
+    pub fn new(dirs: Vec<PathBuf>, loader: FileLoader) -> Self {
+        Self {
+            dirs,
+            loader,
+            hash_behaviour: HashBehaviour::default(),
+            vault_password: None,
+        }
+    }
+
+    pub fn with_hash_behaviour(mut self, hash_behaviour: HashBehaviour) -> Self {
+        self.hash_behaviour = hash_behaviour;
+        self
+    }
+
+    /// Password for vars files encrypted with `ansible-vault`
+    pub fn with_vault_password(mut self, password: Option<&'a str>) -> Self {
+        self.vault_password = password;
+        self
+    }
+
+    /// Merge the vars files of every group and host of `inventory` into their
+    /// `vars`, before group inheritance is resolved
+    pub async fn apply(&self, inventory: &mut ParsedInventory) -> Result<(), ParseError> {
+        let mut group_names: Vec<String> = inventory.groups.keys().cloned().collect();
+        group_names.sort();
+        let mut host_names: Vec<String> = inventory.hosts.keys().cloned().collect();
+        host_names.sort();
+
+        for name in group_names {
+            let vars = self.load_vars("group_vars", &name).await?;
+            if let Some(group) = inventory.groups.get_mut(&name) {
+                self.merge_into(&mut group.vars, vars);
+            }
+        }
+        for name in host_names {
+            let vars = self.load_vars("host_vars", &name).await?;
+            if let Some(host) = inventory.hosts.get_mut(&name) {
+                self.merge_into(&mut host.vars, vars);
+            }
+        }
+        Ok(())
+    }
+
+    /// The variables of `name` from every directory, read concurrently and
+    /// merged in precedence order
+    async fn load_vars(&self, kind: &str, name: &str) -> Result<Variables, ParseError> {
+        let files: Vec<PathBuf> = self
+            .dirs
+            .iter()
+            .flat_map(|dir| vars_files(&dir.join(kind), name))
+            .collect();
+
+        let mut vars = Variables::new();
+        for (file, loaded) in files.iter().zip(self.loader.load_all(files.clone()).await) {
+            let loaded = loaded?;
+            let document = if is_vaulted(&loaded.content) {
+                match self.decrypt(file, &loaded.content)? {
+                    Some(document) => document,
+                    None => continue,
+                }
+            } else {
+                loaded.document.clone()
+            };
+            self.merge_into(&mut vars, document_vars(file, document)?);
+        }
+        Ok(vars)
+    }
+
+    /// Decrypt a vaulted vars file and parse it like any other. Without a
+    /// password the file is skipped with a warning.
+    fn decrypt(&self, file: &Path, content: &str) -> Result<Option<serde_yaml::Value>, ParseError> {
+        let Some(password) = self.vault_password else {
+            warn!(
+                "{} is encrypted and no vault password was given, its variables are not loaded",
+                file.display()
+            );
+            return Ok(None);
+        };
+        let plaintext = VaultDecryptor::new(password.to_string())
+            .decrypt(content)
+            .map_err(|e| match e {
+                ParseError::VaultDecryption { message } => ParseError::VaultDecryption {
+                    message: format!("{}: {message}", file.display()),
+                },
+                other => other,
+            })?;
+        Ok(Some(self.loader.parse(file, plaintext)?.document))
+    }
+
+    fn merge_into(&self, vars: &mut Variables, overrides: Variables) {
+        merge_vars(vars, overrides, self.hash_behaviour);
+    }
+}
+
+/// The files holding the variables of `name` under `dir`, in load order
+fn vars_files(dir: &Path, name: &str) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let bare = dir.join(name);
+    if bare.is_dir() {
+        collect_dir(&bare, &mut files);
+    } else if bare.is_file() {
+        files.push(bare);
+    }
+    for extension in VARS_EXTENSIONS {
+        let file = dir.join(format!("{name}.{extension}"));
+        if file.is_file() {
+            files.push(file);
+        }
+    }
+    files
+}
+
+/// Every vars file under `dir`, recursively, sorted by name
+fn collect_dir(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    let mut paths: Vec<PathBuf> = entries.flatten().map(|entry| entry.path()).collect();
+    paths.sort();
+    for path in paths {
+        let hidden = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_none_or(|name| name.starts_with('.') || name.ends_with('~'));
+        if hidden {
+            continue;
+        }
+        if path.is_dir() {
+            collect_dir(&path, files);
+        } else if has_vars_extension(&path) {
+            files.push(path);
+        }
+    }
+}
+
+fn has_vars_extension(path: &Path) -> bool {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some(extension) => VARS_EXTENSIONS.contains(&extension),
+        None => true,
+    }
+}
+
+fn is_vaulted(content: &str) -> bool {
+    content.trim_start().starts_with("$ANSIBLE_VAULT;")
+}
+
+/// The top-level mapping of a vars file; an empty file defines nothing
+fn document_vars(file: &Path, document: serde_yaml::Value) -> Result<Variables, ParseError> {
+    match document {
+        serde_yaml::Value::Null => Ok(Variables::new()),
+        serde_yaml::Value::Mapping(_) => Ok(serde_yaml::from_value(document)?),
+        _ => Err(ParseError::InvalidStructure {
+            message: format!("{} must contain a mapping of variables", file.display()),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::fixtures::{VAULT_ENCRYPTED_CONTENT, VAULT_PASSWORD};
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    fn write(path: &Path, content: &str) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_vars_files_order() {
+        let temp_dir = TempDir::new().unwrap();
+        let group_vars = temp_dir.path().join("group_vars");
+        write(&group_vars.join("web/b.yml"), "");
+        write(&group_vars.join("web/a/nested.json"), "");
+        write(&group_vars.join("web/.hidden.yml"), "");
+        write(&group_vars.join("web/notes.txt"), "");
+        write(&group_vars.join("web/backup.yml~"), "");
+        write(&group_vars.join("web/plain"), "");
+        write(&group_vars.join("web.yml"), "");
+        write(&group_vars.join("db"), "");
+
+        assert_eq!(
+            vars_files(&group_vars, "web"),
+            vec![
+                group_vars.join("web/a/nested.json"),
+                group_vars.join("web/b.yml"),
+                group_vars.join("web/plain"),
+                group_vars.join("web.yml"),
+            ]
+        );
+        assert_eq!(vars_files(&group_vars, "db"), vec![group_vars.join("db")]);
+        assert!(vars_files(&group_vars, "missing").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_vaulted_files() {
+        let temp_dir = TempDir::new().unwrap();
+        write(
+            &temp_dir.path().join("group_vars/all/vault.yml"),
+            VAULT_ENCRYPTED_CONTENT,
+        );
+        write(
+            &temp_dir.path().join("group_vars/all/vars.yml"),
+            "db_user: app\n",
+        );
+        let vars_dirs =
+            VarsDirectories::new(vec![temp_dir.path().to_path_buf()], FileLoader::default());
+
+        // Without a password the vaulted file is skipped
+        let vars = vars_dirs.load_vars("group_vars", "all").await.unwrap();
+        assert_eq!(vars, HashMap::from([("db_user".to_string(), json!("app"))]));
+
+        let vars = vars_dirs
+            .with_vault_password(Some(VAULT_PASSWORD))
+            .load_vars("group_vars", "all")
+            .await
+            .unwrap();
+        assert_eq!(vars["db_password"], json!("s3cret"));
+        assert_eq!(vars["db_user"], json!("app"));
+    }
+
+    #[tokio::test]
+    async fn test_vaulted_file_with_wrong_password() {
+        let temp_dir = TempDir::new().unwrap();
+        write(
+            &temp_dir.path().join("host_vars/web1.yml"),
+            VAULT_ENCRYPTED_CONTENT,
+        );
+        let vars_dirs =
+            VarsDirectories::new(vec![temp_dir.path().to_path_buf()], FileLoader::default())
+                .with_vault_password(Some("wrong"));
+
+        let result = vars_dirs.load_vars("host_vars", "web1").await;
+        assert!(matches!(
+            result,
+            Err(ParseError::VaultDecryption { message }) if message.contains("web1.yml")
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_non_mapping_file_is_rejected() {
+        let temp_dir = TempDir::new().unwrap();
+        write(&temp_dir.path().join("group_vars/all.yml"), "- a\n- b\n");
+        let vars_dirs =
+            VarsDirectories::new(vec![temp_dir.path().to_path_buf()], FileLoader::default());
+
+        let result = vars_dirs.load_vars("group_vars", "all").await;
+        assert!(matches!(result, Err(ParseError::InvalidStructure { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_merge_hash_behaviour() {
+        let temp_dir = TempDir::new().unwrap();
+        let inventory_dir = temp_dir.path().join("inventory");
+        let playbook_dir = temp_dir.path().join("playbook");
+        write(
+            &inventory_dir.join("group_vars/web.yml"),
+            "app: {port: 80, user: www}\n",
+        );
+        write(
+            &playbook_dir.join("group_vars/web.yml"),
+            "app: {port: 8080}\n",
+        );
+        let dirs = vec![inventory_dir, playbook_dir];
+
+        let replaced = VarsDirectories::new(dirs.clone(), FileLoader::default())
+            .load_vars("group_vars", "web")
+            .await
+            .unwrap();
+        assert_eq!(replaced["app"], serde_json::json!({"port": 8080}));
+
+        let merged = VarsDirectories::new(dirs, FileLoader::default())
+            .with_hash_behaviour(HashBehaviour::Merge)
+            .load_vars("group_vars", "web")
+            .await
+            .unwrap();
+        assert_eq!(
+            merged["app"],
+            serde_json::json!({"port": 8080, "user": "www"})
+        );
+    }
+}
@@ -0,0 +1,361 @@
+//! Tolerant deserializers for play, task and role keywords.
+//!
+//! Ansible coerces keyword values rather than rejecting them: `tags: web` is a
+//! one element list, `become: "yes"` is a boolean and `serial: "5"` an integer.
+//! These helpers apply the same coercions so raw structures can be typed strictly.
+//! Values that are templates are kept as written, to be rendered at runtime.
+
+use crate::types::parsed::{BooleanOrString, Serial};
+use serde::de::{Error, Unexpected};
+use serde::{Deserialize, Deserializer};
+use serde_yaml::Value;
+
+/// Interpret a string the way Ansible's `boolean()` does
+pub fn parse_bool(s: &str) -> Option<bool> {
+    match s.trim().to_lowercase().as_str() {
+        "y" | "yes" | "on" | "1" | "true" | "t" => Some(true),
+        "n" | "no" | "off" | "0" | "false" | "f" => Some(false),
+        _ => None,
+    }
+}
+
+/// Render a scalar as a string, or `None` for sequences and mappings
+fn scalar_to_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+/// A string keyword that may have been written as a number or boolean, e.g. `name: 2024`
+pub fn deserialize_string<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Option::<Value>::deserialize(deserializer)? {
+        None | Some(Value::Null) => Ok(None),
+        Some(value) => scalar_to_string(&value)
+            .map(Some)
+            .ok_or_else(|| Error::invalid_type(Unexpected::Other("collection"), &"a string")),
+    }
+}
+
+/// Like [`deserialize_string`] for keywords that are required
+pub fn deserialize_required_string<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserialize_string(deserializer)?.ok_or_else(|| Error::custom("expected a string, found null"))
+}
+
+/// A single string or a list of strings, as used by `notify` and `listen`.
+/// Numbers and booleans become strings.
+pub fn deserialize_string_list<'de, D>(deserializer: D) -> Result<Option<Vec<String>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Option::<Value>::deserialize(deserializer)? {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::Sequence(items)) => items
+            .iter()
+            .map(|item| {
+                scalar_to_string(item).ok_or_else(|| {
+                    Error::invalid_type(Unexpected::Other("collection in array"), &"string")
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(Some),
+        Some(value) => scalar_to_string(&value)
+            .map(|s| Some(vec![s]))
+            .ok_or_else(|| {
+                Error::invalid_type(Unexpected::Other("mapping"), &"string or array of strings")
+            }),
+    }
+}
+
+/// Tags given as a list, a single tag or a comma-separated string (`tags: "web,db"`)
+pub fn deserialize_tags<'de, D>(deserializer: D) -> Result<Option<Vec<String>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let split = |s: &str| -> Vec<String> {
+        s.split(',')
+            .map(str::trim)
+            .filter(|tag| !tag.is_empty())
+            .map(str::to_string)
+            .collect()
+    };
+
+    match Option::<Value>::deserialize(deserializer)? {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::Sequence(items)) => items
+            .iter()
+            .map(|item| {
+                scalar_to_string(item)
+                    .ok_or_else(|| Error::invalid_type(Unexpected::Other("collection"), &"a tag"))
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(Some),
+        Some(value) => scalar_to_string(&value)
+            .map(|s| Some(split(&s)))
+            .ok_or_else(|| {
+                Error::invalid_type(Unexpected::Other("mapping"), &"a tag or list of tags")
+            }),
+    }
+}
+
+/// Booleans written in any YAML 1.1 or Ansible form (`yes`, `On`, `1`, ...)
+pub fn deserialize_yaml_bool<'de, D>(deserializer: D) -> Result<Option<bool>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Option::<Value>::deserialize(deserializer)? {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::Bool(b)) => Ok(Some(b)),
+        Some(Value::String(s)) => parse_bool(&s)
+            .map(Some)
+            .ok_or_else(|| Error::custom(format!("Invalid boolean string: {s}"))),
+        Some(Value::Number(n)) => match n.as_f64() {
+            Some(1.0) => Ok(Some(true)),
+            Some(0.0) => Ok(Some(false)),
+            _ => Err(Error::custom(format!("Invalid boolean number: {n}"))),
+        },
+        Some(_) => Err(Error::custom("Expected boolean or boolean string")),
+    }
+}
+
+/// Booleans as [`deserialize_yaml_bool`] reads them, or templates such as
+/// `"{{ use_become | default(false) }}"` that are only known at runtime
+pub fn deserialize_bool_or_template<'de, D>(
+    deserializer: D,
+) -> Result<Option<BooleanOrString>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Option::<Value>::deserialize(deserializer)? {
+        Some(Value::String(s)) if is_template(&s) => Ok(Some(BooleanOrString::String(s))),
+        value => deserialize_yaml_bool(value.unwrap_or(Value::Null))
+            .map(|b| b.map(BooleanOrString::Boolean))
+            .map_err(Error::custom),
+    }
+}
+
+/// A play's `serial`: a host count, a percentage such as `"30%"`, a template,
+/// or a list of those giving successive batch sizes
+pub fn deserialize_serial<'de, D>(deserializer: D) -> Result<Option<Serial>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Option::<Value>::deserialize(deserializer)? {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::Sequence(items)) => items
+            .into_iter()
+            .map(|item| match item {
+                Value::Sequence(_) => Err(Error::custom("serial batches cannot be nested")),
+                item => serial_batch(item),
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(|batches| Some(Serial::Batches(batches))),
+        Some(value) => serial_batch(value).map(Some),
+    }
+}
+
+fn serial_batch<E: Error>(value: Value) -> Result<Serial, E> {
+    if let Value::String(s) = &value {
+        let trimmed = s.trim();
+        if is_template(trimmed) {
+            return Ok(Serial::Expression(s.clone()));
+        }
+        if let Some(percentage) = trimmed.strip_suffix('%') {
+            return percentage
+                .trim()
+                .parse::<f32>()
+                .ok()
+                .filter(|percentage| *percentage >= 0.0)
+                .map(|_| Serial::Expression(trimmed.to_string()))
+                .ok_or_else(|| E::custom(format!("Invalid serial percentage: '{s}'")));
+        }
+    }
+    deserialize_u32(value)
+        .map_err(E::custom)?
+        .map(Serial::Count)
+        .ok_or_else(|| E::custom("Expected a serial batch size"))
+}
+
+fn is_template(s: &str) -> bool {
+    s.contains("{{") || s.contains("{%")
+}
+
+/// Non-negative integers that may be quoted, e.g. `serial: "5"`
+pub fn deserialize_u32<'de, D>(deserializer: D) -> Result<Option<u32>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Option::<Value>::deserialize(deserializer)? {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::Number(n)) => n
+            .as_u64()
+            .and_then(|n| u32::try_from(n).ok())
+            .map(Some)
+            .ok_or_else(|| Error::custom(format!("Expected a non-negative integer, found {n}"))),
+        Some(Value::String(s)) => s
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(|_| Error::custom(format!("Expected a non-negative integer, found '{s}'"))),
+        Some(_) => Err(Error::custom("Expected a non-negative integer")),
+    }
+}
+
+/// Percentages written as numbers or strings, with or without a trailing `%`
+pub fn deserialize_percentage<'de, D>(deserializer: D) -> Result<Option<f32>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Option::<Value>::deserialize(deserializer)? {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::Number(n)) => n
+            .as_f64()
+            .map(|f| Some(f as f32))
+            .ok_or_else(|| Error::custom(format!("Invalid percentage: {n}"))),
+        Some(Value::String(s)) => s
+            .trim()
+            .trim_end_matches('%')
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(|_| Error::custom(format!("Invalid percentage: '{s}'"))),
+        Some(_) => Err(Error::custom("Expected a percentage")),
+    }
+}
+
+/// Boolean-or-expression keywords like `changed_when` and `failed_when`
+pub fn deserialize_boolean_or_string<'de, D>(
+    deserializer: D,
+) -> Result<Option<BooleanOrString>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Option::<Value>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(Value::Bool(b)) => Ok(Some(BooleanOrString::Boolean(b))),
+        Some(Value::String(s)) => {
+            // Try to parse as boolean first for string representations
+            match s.to_lowercase().as_str() {
+                "true" | "yes" | "on" => Ok(Some(BooleanOrString::Boolean(true))),
+                "false" | "no" | "off" => Ok(Some(BooleanOrString::Boolean(false))),
+                _ => Ok(Some(BooleanOrString::String(s))),
+            }
+        },
+        Some(Value::Number(_)) => Err(D::Error::custom(
+            "numeric values not supported for boolean/string fields, use 'true'/'false' or a string expression"
+        )),
+        Some(Value::Sequence(_)) => Err(D::Error::custom(
+            "arrays not supported for boolean/string fields"
+        )),
+        Some(other) => Err(D::Error::invalid_type(
+            Unexpected::Other(&format!("{other:?}")),
+            &"boolean literal (true/false) or string expression"
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Deserialize)]
+    struct Keywords {
+        #[serde(deserialize_with = "deserialize_string", default)]
+        name: Option<String>,
+        #[serde(deserialize_with = "deserialize_tags", default)]
+        tags: Option<Vec<String>>,
+        #[serde(deserialize_with = "deserialize_yaml_bool", default)]
+        r#become: Option<bool>,
+        #[serde(deserialize_with = "deserialize_bool_or_template", default)]
+        become_template: Option<BooleanOrString>,
+        #[serde(deserialize_with = "deserialize_serial", default)]
+        serial: Option<Serial>,
+        #[serde(deserialize_with = "deserialize_string_list", default)]
+        notify: Option<Vec<String>>,
+        #[serde(deserialize_with = "deserialize_percentage", default)]
+        max_fail_percentage: Option<f32>,
+    }
+
+    fn parse(yaml: &str) -> Keywords {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn test_tags_forms() {
+        assert_eq!(parse("tags: web").tags, Some(vec!["web".to_string()]));
+        assert_eq!(
+            parse("tags: 'web, db'").tags,
+            Some(vec!["web".to_string(), "db".to_string()])
+        );
+        assert_eq!(
+            parse("tags: [web, 2024]").tags,
+            Some(vec!["web".to_string(), "2024".to_string()])
+        );
+        assert_eq!(parse("tags: 42").tags, Some(vec!["42".to_string()]));
+        assert!(serde_yaml::from_str::<Keywords>("tags: {a: b}").is_err());
+    }
+
+    #[test]
+    fn test_scalar_coercions() {
+        let keywords = parse("name: 2024\nbecome: 'Yes'\nserial: '5'\nmax_fail_percentage: '25%'");
+        assert_eq!(keywords.name, Some("2024".to_string()));
+        assert_eq!(keywords.r#become, Some(true));
+        assert_eq!(keywords.serial, Some(Serial::Count(5)));
+        assert_eq!(keywords.max_fail_percentage, Some(25.0));
+
+        assert_eq!(parse("become: 0").r#become, Some(false));
+        assert_eq!(parse("become: n").r#become, Some(false));
+        assert!(serde_yaml::from_str::<Keywords>("become: maybe").is_err());
+        assert!(serde_yaml::from_str::<Keywords>("serial: -1").is_err());
+        assert!(serde_yaml::from_str::<Keywords>("name: [a]").is_err());
+    }
+
+    #[test]
+    fn test_templates_and_serial_forms() {
+        assert_eq!(
+            parse("become_template: \"{{ use_become | default(false) }}\"").become_template,
+            Some(BooleanOrString::String(
+                "{{ use_become | default(false) }}".to_string()
+            ))
+        );
+        assert_eq!(
+            parse("become_template: 'yes'").become_template,
+            Some(BooleanOrString::Boolean(true))
+        );
+        assert!(serde_yaml::from_str::<Keywords>("become_template: maybe").is_err());
+
+        assert_eq!(
+            parse("serial: '30%'").serial,
+            Some(Serial::Expression("30%".to_string()))
+        );
+        assert_eq!(
+            parse("serial: [1, '5', '20%', \"{{ batch }}\"]").serial,
+            Some(Serial::Batches(vec![
+                Serial::Count(1),
+                Serial::Count(5),
+                Serial::Expression("20%".to_string()),
+                Serial::Expression("{{ batch }}".to_string()),
+            ]))
+        );
+        assert!(serde_yaml::from_str::<Keywords>("serial: 'x%'").is_err());
+        assert!(serde_yaml::from_str::<Keywords>("serial: [[1]]").is_err());
+
+        assert_eq!(
+            parse("notify: [1, true, restart]").notify,
+            Some(vec![
+                "1".to_string(),
+                "true".to_string(),
+                "restart".to_string()
+            ])
+        );
+        assert_eq!(parse("notify: 2").notify, Some(vec!["2".to_string()]));
+    }
+}
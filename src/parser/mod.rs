@@ -1,28 +1,51 @@
 pub mod cache;
+pub mod collections;
+pub mod condition;
+pub mod config;
 pub mod dependency;
 pub mod error;
 pub mod include;
 pub mod inventory;
+pub mod keywords;
 pub mod playbook;
+pub mod requirements;
+pub mod source_map;
+pub mod static_conditions;
+pub mod tags;
+pub mod task;
+pub mod task_id;
 pub mod template;
 pub mod validator;
 pub mod vault;
+pub mod yaml;
 
+pub use collections::CollectionIndex;
+pub use config::{AnsibleConfig, ParserConfig};
 pub use error::ParseError;
 pub use include::IncludeHandler;
-pub use inventory::InventoryParser;
+pub use inventory::{InventoryParser, ScriptCache};
 pub use playbook::PlaybookParser;
+pub use requirements::Requirements;
+pub use static_conditions::StaticConditionMode;
+pub use tags::TagSelection;
 pub use template::TemplateEngine;
+pub use yaml::YamlLimits;
 
-use crate::types::parsed::{ParsedInventory, ParsedPlaybook};
+use crate::parser::inventory::HostSelector;
+use crate::types::parsed::{HostPattern, ParsedInventory, ParsedPlaybook};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use tracing::warn;
 
 pub struct Parser {
     vault_password: Option<String>,
     extra_vars: HashMap<String, serde_json::Value>,
     template_engine: TemplateEngine,
     cache: Option<cache::ParseCache>,
+    tag_selection: Option<TagSelection>,
+    static_conditions: Option<StaticConditionMode>,
+    config: ParserConfig,
+    script_cache: ScriptCache,
 }
 
 impl Parser {
@@ -32,6 +55,10 @@ impl Parser {
             extra_vars: HashMap::new(),
             template_engine: TemplateEngine::new(),
             cache: None,
+            tag_selection: None,
+            static_conditions: None,
+            config: ParserConfig::default(),
+            script_cache: ScriptCache::default(),
         }
     }
 
@@ -50,22 +77,164 @@ impl Parser {
         self
     }
 
+    /// Only keep tasks selected by `--tags` / `--skip-tags` style tag selection
+    pub fn with_tag_selection(mut self, tag_selection: TagSelection) -> Self {
+        self.tag_selection = Some(tag_selection);
+        self
+    }
+
+    /// Evaluate `when` conditions that are fully known at parse time
+    pub fn with_static_conditions(mut self, mode: StaticConditionMode) -> Self {
+        self.static_conditions = Some(mode);
+        self
+    }
+
+    /// Limits on document size, nesting and alias expansion for loaded YAML
+    pub fn with_yaml_limits(mut self, limits: YamlLimits) -> Self {
+        self.config.yaml_limits = limits;
+        self
+    }
+
+    /// Directories holding `ansible_collections` trees, searched after the
+    /// `collections` directory next to the playbook
+    pub fn with_collections_paths(mut self, paths: Vec<PathBuf>) -> Self {
+        self.config.collections_paths = paths;
+        self
+    }
+
+    /// Fail on fully qualified modules missing from their installed collection
+    pub fn with_strict_modules(mut self, strict: bool) -> Self {
+        self.config.strict_modules = strict;
+        self
+    }
+
+    /// Directories searched for roles not found next to the playbook
+    pub fn with_roles_path(mut self, paths: Vec<PathBuf>) -> Self {
+        self.config.include.role_search_paths = paths;
+        self
+    }
+
+    /// Use the settings of an `ansible.cfg` and the `ANSIBLE_*` environment
+    pub fn with_ansible_config(mut self, config: &AnsibleConfig) -> Self {
+        config.apply_to(&mut self.config);
+        self.template_engine = self.config.template_engine();
+        self
+    }
+
+    /// Replace every include, inventory, template and YAML setting
+    pub fn with_config(mut self, config: ParserConfig) -> Self {
+        self.template_engine = config.template_engine();
+        self.config = config;
+        self
+    }
+
+    pub fn config(&self) -> &ParserConfig {
+        &self.config
+    }
+
     pub async fn parse_playbook(&self, path: &Path) -> Result<ParsedPlaybook, ParseError> {
-        let parser = PlaybookParser::new(&self.template_engine, &self.extra_vars);
-        parser.parse_with_includes(path).await
+        let collections = self.collection_index(path)?;
+        let mut parser = PlaybookParser::new(&self.template_engine, &self.extra_vars)
+            .with_include_config(self.config.include_config())
+            .with_collections(&collections);
+        if let Some(tag_selection) = &self.tag_selection {
+            parser = parser.with_tag_selection(tag_selection);
+        }
+        let mut playbook = parser.parse_with_includes(path).await?;
+        self.apply_static_conditions(&mut playbook);
+        self.apply_tag_selection(&mut playbook);
+        Ok(playbook)
     }
 
     pub async fn parse_playbook_without_includes(
         &self,
         path: &Path,
     ) -> Result<ParsedPlaybook, ParseError> {
-        let parser = PlaybookParser::new(&self.template_engine, &self.extra_vars);
-        parser.parse(path).await
+        let collections = self.collection_index(path)?;
+        let parser = PlaybookParser::new(&self.template_engine, &self.extra_vars)
+            .with_include_config(self.config.include_config())
+            .with_collections(&collections);
+        let mut playbook = parser.parse(path).await?;
+        self.apply_static_conditions(&mut playbook);
+        self.apply_tag_selection(&mut playbook);
+        Ok(playbook)
+    }
+
+    /// Collections installed for a playbook
+    pub fn collection_index(&self, playbook: &Path) -> Result<CollectionIndex, ParseError> {
+        Ok(
+            CollectionIndex::load(&collections::playbook_collections_paths(
+                playbook,
+                &self.config.collections_paths,
+            ))?
+            .with_strict_modules(self.config.strict_modules),
+        )
+    }
+
+    /// Evaluate static conditions with the configured mode, if any.
+    ///
+    /// Parsing already does this; call it again after attaching an inventory to
+    /// the playbook so that variables shared by all hosts are taken into account.
+    pub fn apply_static_conditions(&self, playbook: &mut ParsedPlaybook) {
+        if let Some(mode) = self.static_conditions {
+            static_conditions::StaticConditions::new(mode, &self.extra_vars).apply(playbook);
+        }
+    }
+
+    fn apply_tag_selection(&self, playbook: &mut ParsedPlaybook) {
+        if let Some(tag_selection) = &self.tag_selection {
+            tag_selection.filter_playbook(playbook);
+        }
     }
 
     pub async fn parse_inventory(&self, path: &Path) -> Result<ParsedInventory, ParseError> {
-        let parser = InventoryParser::new(&self.template_engine, &self.extra_vars);
-        parser.parse(path).await
+        self.inventory_parser().parse(path).await
+    }
+
+    /// Parse several inventory files and directories into one inventory
+    pub async fn parse_inventories(
+        &self,
+        sources: &[PathBuf],
+    ) -> Result<ParsedInventory, ParseError> {
+        self.inventory_parser().parse_sources(sources).await
+    }
+
+    /// Parse inventory sources for a playbook, loading the `group_vars/` and
+    /// `host_vars/` next to the playbook over the inventory's own
+    pub async fn parse_inventory_for_playbook(
+        &self,
+        sources: &[PathBuf],
+        playbook: &Path,
+    ) -> Result<ParsedInventory, ParseError> {
+        let playbook_dir = playbook.parent().unwrap_or_else(|| Path::new("."));
+        self.inventory_parser()
+            .with_playbook_dir(playbook_dir)
+            .parse_sources(sources)
+            .await
+    }
+
+    fn inventory_parser(&self) -> InventoryParser<'_> {
+        InventoryParser::with_config(
+            &self.template_engine,
+            &self.extra_vars,
+            self.config.inventory_config(),
+        )
+        .with_vault_password(self.vault_password.as_deref())
+        .with_script_cache(self.script_cache.clone())
+    }
+
+    /// Parse a `requirements.yml`
+    pub async fn parse_requirements(&self, path: &Path) -> Result<Requirements, ParseError> {
+        let content = tokio::fs::read_to_string(path).await.map_err(|e| {
+            if e.kind() == std::io::ErrorKind::NotFound {
+                ParseError::FileNotFound {
+                    path: path.to_string_lossy().to_string(),
+                }
+            } else {
+                ParseError::Io(e)
+            }
+        })?;
+        content.parse()
     }
 
     pub async fn validate_syntax(&self, path: &Path) -> Result<(), ParseError> {
@@ -103,6 +272,7 @@ impl Parser {
             become_method: None,
             become_user: None,
             become_flags: None,
+            source: None,
         };
         hosts.insert("localhost".to_string(), localhost);
 
@@ -112,6 +282,7 @@ impl Parser {
             hosts: vec!["localhost".to_string()],
             children: vec![],
             vars: HashMap::new(),
+            source: None,
         };
         groups.insert("all".to_string(), all_group);
 
@@ -127,8 +298,35 @@ impl Parser {
         inventory: &mut ParsedInventory,
         limit_pattern: &str,
     ) -> Result<(), ParseError> {
-        let parser = InventoryParser::new(&self.template_engine, &self.extra_vars);
-        parser.filter_inventory(inventory, limit_pattern)
+        self.inventory_parser()
+            .filter_inventory(inventory, limit_pattern)
+    }
+
+    /// Resolve each play's `hosts` pattern against the playbook's inventory.
+    ///
+    /// Call it after attaching and limiting the inventory. Patterns whose
+    /// variables were undefined at parse time render empty and are left
+    /// unresolved.
+    pub fn resolve_play_hosts(&self, playbook: &mut ParsedPlaybook) -> Result<(), ParseError> {
+        let selector = HostSelector::new(&playbook.inventory);
+        for play in &mut playbook.plays {
+            let patterns = match &play.hosts {
+                HostPattern::All => vec!["all".to_string()],
+                HostPattern::Single(pattern) => vec![pattern.clone()],
+                HostPattern::Multiple(patterns) => patterns.clone(),
+            };
+            if patterns.iter().all(|pattern| pattern.trim().is_empty()) {
+                play.matched_hosts = None;
+                continue;
+            }
+
+            let hosts = selector.select_patterns(&patterns)?;
+            if hosts.is_empty() {
+                warn!("No hosts matched the pattern of play '{}'", play.name);
+            }
+            play.matched_hosts = Some(hosts);
+        }
+        Ok(())
     }
 }
 
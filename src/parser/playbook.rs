@@ -1,17 +1,38 @@
+use crate::parser::collections::CollectionIndex;
+use crate::parser::condition::{deserialize_conditions, inherit_conditions};
+use crate::parser::dependency::resolve_notifications;
 use crate::parser::error::ParseError;
-use crate::parser::include::{ImportSpec, IncludeContext, IncludeHandler, IncludeSpec};
+use crate::parser::include::{
+    handler::MAX_INCLUDE_CANDIDATES,
+    roles::{RoleIncludeProcessor, RoleIncludeResult},
+    ApplySpec, FileLoader, ImportSpec, IncludeConfig, IncludeContext, IncludeHandler, IncludeSpec,
+    InheritedKeywords, RoleIncludeSpec,
+};
+use crate::parser::keywords::{deserialize_tags, deserialize_yaml_bool};
+use crate::parser::source_map::{SourceMap, SourceNode};
+use crate::parser::tags::{inherit_tags, TagSelection};
+use crate::parser::task::{RawPlay, RawRole, RawTask, TaskParser};
+use crate::parser::task_id::TaskPath;
 use crate::parser::template::TemplateEngine;
+use crate::parser::yaml::{self, YamlLimits};
 use crate::types::parsed::*;
 use chrono::Utc;
-use serde::{Deserialize, Deserializer};
+use serde::Deserialize;
 use sha2::{Digest, Sha256};
-use std::collections::HashMap;
-use std::path::Path;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use tokio::fs;
+use tracing::{debug, warn};
 
 pub struct PlaybookParser<'a> {
     template_engine: &'a TemplateEngine,
     extra_vars: &'a HashMap<String, serde_json::Value>,
+    tag_selection: Option<&'a TagSelection>,
+    collections: Option<&'a CollectionIndex>,
+    /// Include depth, caching, file access, role search paths and YAML limits
+    include_config: IncludeConfig,
+    /// Reads include and role files, shared with the include handler
+    loader: FileLoader,
 }
 
 impl<'a> PlaybookParser<'a> {
@@ -22,16 +43,69 @@ impl<'a> PlaybookParser<'a> {
         Self {
             template_engine,
             extra_vars,
+            tag_selection: None,
+            collections: None,
+            include_config: IncludeConfig::default(),
+            loader: FileLoader::default(),
         }
     }
 
+    /// Skip dynamic includes whose own tags are not selected, as Ansible does at runtime
+    pub fn with_tag_selection(mut self, tag_selection: &'a TagSelection) -> Self {
+        self.tag_selection = Some(tag_selection);
+        self
+    }
+
+    /// Limits applied to every YAML file loaded for the playbook, includes too
+    pub fn with_yaml_limits(mut self, yaml_limits: YamlLimits) -> Self {
+        self.include_config.yaml_limits = yaml_limits;
+        self.loader = FileLoader::new(&self.include_config);
+        self
+    }
+
+    /// Settings for includes, imports and roles, passed on to the include handler
+    pub fn with_include_config(mut self, include_config: IncludeConfig) -> Self {
+        self.loader = FileLoader::new(&include_config);
+        self.include_config = include_config;
+        self
+    }
+
+    /// Share `loader`, and so its cache, with an include handler
+    pub(crate) fn with_loader(mut self, loader: FileLoader) -> Self {
+        self.loader = loader;
+        self
+    }
+
+    /// Installed collections, for `namespace.collection.role` roles, the play
+    /// `collections` keyword and checking fully qualified module names
+    pub fn with_collections(mut self, collections: &'a CollectionIndex) -> Self {
+        self.collections = Some(collections);
+        self
+    }
+
+    /// Directories searched for roles not found next to the playbook, like
+    /// Ansible's `roles_path`
+    pub fn with_roles_path(mut self, roles_path: Vec<PathBuf>) -> Self {
+        self.include_config.role_search_paths = roles_path;
+        self
+    }
+
+    fn task_parser(&self) -> TaskParser<'_> {
+        TaskParser::new(self.template_engine).with_collections(self.collections)
+    }
+
     /// Parse playbook with include/import support
     pub async fn parse_with_includes(&self, path: &Path) -> Result<ParsedPlaybook, ParseError> {
         let base_path = path
             .parent()
             .unwrap_or_else(|| Path::new("."))
             .to_path_buf();
-        let mut include_handler = IncludeHandler::new(base_path, self.template_engine.clone());
+        let mut include_handler = IncludeHandler::new(base_path, self.template_engine.clone())
+            .with_config(self.include_config.clone())
+            .with_loader(self.loader.clone());
+        if let Some(collections) = self.collections {
+            include_handler = include_handler.with_collections(collections.clone());
+        }
 
         self.parse_playbook_recursive(path, &mut include_handler)
             .await
@@ -55,7 +129,9 @@ impl<'a> PlaybookParser<'a> {
         let checksum = format!("{:x}", hasher.finalize());
 
         // Parse YAML - Ansible playbooks are arrays of plays
-        let raw_plays: Vec<RawPlay> = serde_yaml::from_str(&content)?;
+        let raw_plays: Vec<RawPlay> =
+            yaml::from_str_with_limits(&content, &self.include_config.yaml_limits)?;
+        let source_map = SourceMap::parse(path.to_string_lossy(), &content);
 
         // Transform to parsed format
         let mut parsed_plays = Vec::new();
@@ -67,8 +143,13 @@ impl<'a> PlaybookParser<'a> {
         playbook_vars.extend(self.extra_vars.clone());
 
         // Process each play
-        for raw_play in raw_plays {
-            let parsed_play = self.parse_play(raw_play, &playbook_vars).await?;
+        let playbook_path = playbook_task_path(path);
+        for (index, raw_play) in raw_plays.into_iter().enumerate() {
+            let play_path = playbook_path.child("play", index);
+            let play_node = source_map.root().index(index);
+            let parsed_play = self
+                .parse_play(raw_play, &playbook_vars, path, &play_path, &play_node)
+                .await?;
 
             // Check if any task requires facts
             if parsed_play
@@ -127,7 +208,8 @@ impl<'a> PlaybookParser<'a> {
         let checksum = format!("{:x}", hasher.finalize());
 
         // Parse YAML - Ansible playbooks can be arrays of plays or include directives
-        let yaml_content: serde_yaml::Value = serde_yaml::from_str(&content)?;
+        let yaml_content = yaml::load_with_limits(&content, &self.include_config.yaml_limits)?;
+        let source_map = SourceMap::parse(path.to_string_lossy(), &content);
 
         // Process each item in the playbook
         let items = if let serde_yaml::Value::Sequence(seq) = yaml_content {
@@ -136,14 +218,10 @@ impl<'a> PlaybookParser<'a> {
             vec![yaml_content]
         };
 
-        // Transform to parsed format
-        let mut parsed_plays = Vec::new();
-        let mut playbook_vars = HashMap::new();
-        let mut facts_required = false;
         let vault_ids = Vec::new(); // TODO: Extract vault IDs from content
 
         // Merge extra vars
-        playbook_vars.extend(self.extra_vars.clone());
+        let playbook_vars = self.extra_vars.clone();
 
         // Create include context
         let include_context = IncludeContext {
@@ -152,44 +230,26 @@ impl<'a> PlaybookParser<'a> {
             include_depth: 0,
             tags: Vec::new(),
             when_condition: None,
+            task_path: TaskPath::default(),
         };
 
-        // Process each item in the playbook
-        for item in items {
-            // Check if this is a playbook include directive
-            if let Some(map) = item.as_mapping() {
-                if map.contains_key("include_playbook") || map.contains_key("import_playbook") {
-                    // This is a playbook include directive
-                    let included_plays = self
-                        .handle_playbook_include_directive(item, include_handler, &include_context)
-                        .await?;
-                    parsed_plays.extend(included_plays);
-                    continue;
-                }
-            }
-
-            // Otherwise, parse as a regular play
-            let raw_play: RawPlay = serde_yaml::from_value(item)?;
-            let parsed_play = self
-                .parse_play_with_includes(
-                    raw_play,
-                    &playbook_vars,
-                    include_handler,
-                    &include_context,
-                )
-                .await?;
-
-            // Check if any task requires facts
-            if parsed_play
-                .tasks
+        let parsed_plays = self
+            .parse_plays(
+                items,
+                &source_map,
+                &playbook_task_path(path),
+                "play",
+                include_handler,
+                &include_context,
+            )
+            .await?;
+
+        // Check if any task requires facts
+        let facts_required = parsed_plays.iter().any(|play| {
+            play.tasks
                 .iter()
                 .any(|t| t.module == "setup" || t.module == "gather_facts")
-            {
-                facts_required = true;
-            }
-
-            parsed_plays.push(parsed_play);
-        }
+        });
 
         let metadata = PlaybookMetadata {
             file_path: path.to_string_lossy().to_string(),
@@ -212,104 +272,245 @@ impl<'a> PlaybookParser<'a> {
         })
     }
 
-    async fn parse_play(
-        &self,
-        raw_play: RawPlay,
-        global_vars: &HashMap<String, serde_json::Value>,
-    ) -> Result<ParsedPlay, ParseError> {
-        let mut play_vars = global_vars.clone();
-
-        // Merge play vars and render any templates in them
-        if let Some(vars) = raw_play.vars {
-            // First pass: add all raw variables
-            for (key, value) in &vars {
-                play_vars.insert(key.clone(), value.clone());
-            }
+    /// Parse the plays of a playbook file, expanding its `include_playbook` and
+    /// `import_playbook` entries in place. Every play, whether in the top-level
+    /// playbook or an included one, is parsed by [`Self::parse_play_with_includes`].
+    fn parse_plays<'b>(
+        &'b self,
+        items: Vec<serde_yaml::Value>,
+        source_map: &'b SourceMap,
+        parent: &'b TaskPath,
+        section: &'b str,
+        include_handler: &'b mut IncludeHandler,
+        include_context: &'b IncludeContext,
+    ) -> std::pin::Pin<
+        Box<dyn std::future::Future<Output = Result<Vec<ParsedPlay>, ParseError>> + 'b>,
+    > {
+        Box::pin(async move {
+            // Included playbooks are read concurrently, then expanded in order
+            let included: Vec<&str> = items
+                .iter()
+                .filter_map(|item| {
+                    let map = item.as_mapping()?;
+                    map.get("include_playbook")
+                        .or_else(|| map.get("import_playbook"))?
+                        .as_str()
+                })
+                .collect();
+            include_handler.prefetch(&included, include_context).await;
+
+            let mut parsed_plays = Vec::new();
+            for (index, item) in items.into_iter().enumerate() {
+                let play_path = parent.child(section, index);
+                let mut context = include_context.clone();
+                context.task_path = play_path.clone();
+
+                // Check if this is a playbook include directive
+                if let Some(map) = item.as_mapping() {
+                    if map.contains_key("include_playbook") || map.contains_key("import_playbook") {
+                        let included_plays = self
+                            .handle_playbook_include_directive(item, include_handler, &context)
+                            .await?;
+                        parsed_plays.extend(included_plays);
+                        continue;
+                    }
+                }
 
-            // Second pass: render templates that may reference other variables
-            for (key, value) in vars {
-                let rendered_value = self.template_engine.render_value(&value, &play_vars)?;
-                play_vars.insert(key, rendered_value);
+                // Otherwise, parse as a regular play
+                let raw_play: RawPlay = serde_yaml::from_value(item)?;
+                let parsed_play = self
+                    .parse_play_with_includes(
+                        raw_play,
+                        &include_context.variables,
+                        include_handler,
+                        &context,
+                        &play_path,
+                        &source_map.root().index(index),
+                    )
+                    .await?;
+                parsed_plays.push(parsed_play);
             }
-        }
+            Ok(parsed_plays)
+        })
+    }
 
-        // Parse hosts pattern and render templates
-        let hosts = match raw_play.hosts {
-            Some(RawHostPattern::Single(host)) => {
-                let rendered_host = if host.contains("{{") && host.contains("}}") {
-                    self.template_engine.render_string(&host, &play_vars)?
-                } else {
-                    host
-                };
+    /// Expand an `include_playbook`. Its `vars` become play vars of the
+    /// included plays, and its tags, `when` and `apply` keywords reach every
+    /// task and handler in them.
+    pub(crate) async fn include_playbook(
+        &self,
+        include_spec: &IncludeSpec,
+        include_handler: &mut IncludeHandler,
+        include_context: &IncludeContext,
+    ) -> Result<Vec<ParsedPlay>, ParseError> {
+        let mut inherited = include_spec.inherited()?;
+        inherited.vars.clear();
+        inherited
+            .tags
+            .splice(0..0, include_spec.tags.clone().unwrap_or_default());
+        self.parse_included_playbook(
+            &include_spec.file,
+            include_spec.vars.as_ref(),
+            &inherited,
+            include_handler,
+            include_context,
+        )
+        .await
+    }
 
-                if rendered_host == "all" {
-                    HostPattern::All
-                } else {
-                    HostPattern::Single(rendered_host)
-                }
-            }
-            Some(RawHostPattern::Multiple(hosts)) => {
-                let mut rendered_hosts = Vec::new();
-                for host in hosts {
-                    let rendered_host = if host.contains("{{") && host.contains("}}") {
-                        self.template_engine.render_string(&host, &play_vars)?
-                    } else {
-                        host
-                    };
-                    rendered_hosts.push(rendered_host);
-                }
-                HostPattern::Multiple(rendered_hosts)
-            }
-            Some(RawHostPattern::All) => HostPattern::All,
-            None => HostPattern::Single("localhost".to_string()),
+    /// Expand an `import_playbook`. Its `vars` become play vars of the
+    /// imported plays, and its tags and `when` hold for every task and handler.
+    pub(crate) async fn import_playbook(
+        &self,
+        import_spec: &ImportSpec,
+        include_handler: &mut IncludeHandler,
+        include_context: &IncludeContext,
+    ) -> Result<Vec<ParsedPlay>, ParseError> {
+        let imported = import_spec.inherited()?;
+        let inherited = InheritedKeywords {
+            tags: imported.tags,
+            when: imported.when,
+            ..InheritedKeywords::default()
         };
+        self.parse_included_playbook(
+            &import_spec.file,
+            import_spec.vars.as_ref(),
+            &inherited,
+            include_handler,
+            include_context,
+        )
+        .await
+    }
 
-        // Parse tasks
-        let mut tasks = Vec::new();
-        if let Some(raw_tasks) = raw_play.tasks {
-            for (index, raw_task) in raw_tasks.into_iter().enumerate() {
-                let task = self.parse_task(raw_task, &play_vars, index).await?;
-                tasks.push(task);
+    /// Parse the plays of the playbook `file` names, with `vars` over the
+    /// variables in scope, and pass `inherited` on to their tasks and handlers
+    async fn parse_included_playbook(
+        &self,
+        file: &str,
+        vars: Option<&HashMap<String, serde_json::Value>>,
+        inherited: &InheritedKeywords,
+        include_handler: &mut IncludeHandler,
+        include_context: &IncludeContext,
+    ) -> Result<Vec<ParsedPlay>, ParseError> {
+        let path = include_handler.resolve_path(file, include_context)?;
+        let included = include_handler
+            .open_playbook(path, vars, include_context)
+            .await?;
+        let plays = self
+            .parse_plays(
+                included.items,
+                &included.source_map,
+                &include_context.task_path,
+                file,
+                include_handler,
+                &included.context,
+            )
+            .await;
+        include_handler.close();
+
+        let mut plays = plays?;
+        for play in &mut plays {
+            for task in play.tasks.iter_mut().chain(play.handlers.iter_mut()) {
+                inherited.apply_to(task);
             }
         }
+        Ok(plays)
+    }
 
-        // Parse handlers
-        let mut handlers = Vec::new();
-        if let Some(raw_handlers) = raw_play.handlers {
-            for (index, raw_handler) in raw_handlers.into_iter().enumerate() {
-                let handler = self.parse_task(raw_handler, &play_vars, index).await?;
-                handlers.push(handler);
-            }
+    async fn parse_play(
+        &self,
+        raw_play: RawPlay,
+        global_vars: &HashMap<String, serde_json::Value>,
+        play_file: &Path,
+        play_path: &TaskPath,
+        play_node: &SourceNode<'_>,
+    ) -> Result<ParsedPlay, ParseError> {
+        let parser = self.task_parser();
+        let (play_vars, var_sources) =
+            parser.play_vars(raw_play.vars, global_vars, &play_node.key("vars"))?;
+        let play_tags = raw_play.tags.unwrap_or_default();
+        let hosts = parser.parse_hosts(raw_play.hosts, &play_vars)?;
+
+        // Tasks run as pre_tasks, then role tasks, then tasks and post_tasks
+        let mut tasks = self
+            .parse_task_list(
+                raw_play.pre_tasks,
+                &play_vars,
+                play_path,
+                "pre_tasks",
+                &play_node.key("pre_tasks"),
+                None,
+            )
+            .await?;
+        let collections = raw_play.collections.unwrap_or_default();
+        let expanded = self
+            .expand_roles(
+                raw_play.roles.unwrap_or_default(),
+                RoleExpansion::new(&play_vars, &collections, play_file),
+                play_path,
+                play_node,
+                None,
+            )
+            .await?;
+        tasks.extend(expanded.tasks);
+        for (section, raw_tasks) in [
+            ("tasks", raw_play.tasks),
+            ("post_tasks", raw_play.post_tasks),
+        ] {
+            let section_tasks = self
+                .parse_task_list(
+                    raw_tasks,
+                    &play_vars,
+                    play_path,
+                    section,
+                    &play_node.key(section),
+                    None,
+                )
+                .await?;
+            tasks.extend(section_tasks);
         }
 
-        // Parse roles
-        let mut roles = Vec::new();
-        if let Some(raw_roles) = raw_play.roles {
-            for raw_role in raw_roles {
-                let role = self.parse_role(raw_role)?;
-                roles.push(role);
-            }
+        // Role handlers come before the play's own
+        let mut handlers = expanded.handlers;
+        let play_handlers = self
+            .parse_task_list(
+                raw_play.handlers,
+                &play_vars,
+                play_path,
+                "handlers",
+                &play_node.key("handlers"),
+                None,
+            )
+            .await?;
+        handlers.extend(play_handlers);
+        let roles = expanded.roles;
+
+        // Tasks and handlers inherit the play's tags
+        for task in tasks.iter_mut().chain(handlers.iter_mut()) {
+            inherit_tags(task, &play_tags);
         }
 
-        // Render play name if it contains templates
-        let play_name = match raw_play.name {
-            Some(name) if name.contains("{{") && name.contains("}}") => {
-                self.template_engine.render_string(&name, &play_vars)?
-            }
-            Some(name) => name,
-            None => "Unnamed play".to_string(),
-        };
+        let notifications = self.resolve_handler_notifications(&tasks, &handlers);
+
+        let play_name = parser.play_name(raw_play.name, &play_vars)?;
 
         Ok(ParsedPlay {
             name: play_name,
             hosts,
+            tags: play_tags,
             vars: play_vars,
+            vars_files: parser.vars_files(raw_play.vars_files),
             tasks,
             handlers,
             roles,
+            collections,
             strategy: raw_play.strategy.unwrap_or_default(),
             serial: raw_play.serial,
             max_fail_percentage: raw_play.max_fail_percentage,
+            notifications,
+            source: play_node.location(),
+            var_sources,
+            matched_hosts: None,
         })
     }
 
@@ -319,115 +520,490 @@ impl<'a> PlaybookParser<'a> {
         global_vars: &HashMap<String, serde_json::Value>,
         include_handler: &mut IncludeHandler,
         include_context: &IncludeContext,
+        play_path: &TaskPath,
+        play_node: &SourceNode<'_>,
     ) -> Result<ParsedPlay, ParseError> {
-        let mut play_vars = global_vars.clone();
+        let parser = self.task_parser();
+        let (play_vars, var_sources) =
+            parser.play_vars(raw_play.vars, global_vars, &play_node.key("vars"))?;
+        let play_tags = raw_play.tags.unwrap_or_default();
+        let hosts = parser.parse_hosts(raw_play.hosts, &play_vars)?;
+
+        // Includes inside the play are resolved with the play's variables
+        let mut play_include_context = include_context.clone();
+        play_include_context.variables = play_vars.clone();
+        let mut included_roles = ExpandedRoles::default();
+        let mut includes = Some(TaskIncludes {
+            handler: include_handler,
+            context: &play_include_context,
+            inherited_tags: &play_tags,
+            roles: &mut included_roles,
+        });
 
-        // Merge play vars and render any templates in them
-        if let Some(vars) = raw_play.vars {
-            // First pass: add all raw variables
-            for (key, value) in &vars {
-                play_vars.insert(key.clone(), value.clone());
-            }
+        // Tasks run as pre_tasks, then role tasks, then tasks and post_tasks
+        let mut tasks = self
+            .parse_task_list(
+                raw_play.pre_tasks,
+                &play_vars,
+                play_path,
+                "pre_tasks",
+                &play_node.key("pre_tasks"),
+                TaskIncludes::reborrow(&mut includes),
+            )
+            .await?;
+        let collections = raw_play.collections.unwrap_or_default();
+        let expanded = self
+            .expand_roles(
+                raw_play.roles.unwrap_or_default(),
+                RoleExpansion::new(&play_vars, &collections, &include_context.current_file),
+                play_path,
+                play_node,
+                TaskIncludes::reborrow(&mut includes),
+            )
+            .await?;
+        tasks.extend(expanded.tasks);
+        for (section, raw_tasks) in [
+            ("tasks", raw_play.tasks),
+            ("post_tasks", raw_play.post_tasks),
+        ] {
+            let section_tasks = self
+                .parse_task_list(
+                    raw_tasks,
+                    &play_vars,
+                    play_path,
+                    section,
+                    &play_node.key(section),
+                    TaskIncludes::reborrow(&mut includes),
+                )
+                .await?;
+            tasks.extend(section_tasks);
+        }
 
-            // Second pass: render templates that may reference other variables
-            for (key, value) in vars {
-                let rendered_value = self.template_engine.render_value(&value, &play_vars)?;
-                play_vars.insert(key, rendered_value);
-            }
+        // Role handlers come before the play's own
+        let mut handlers = expanded.handlers;
+        handlers.extend(included_roles.handlers);
+        let play_handlers = self
+            .parse_task_list(
+                raw_play.handlers,
+                &play_vars,
+                play_path,
+                "handlers",
+                &play_node.key("handlers"),
+                None,
+            )
+            .await?;
+        handlers.extend(play_handlers);
+        let mut roles = expanded.roles;
+        roles.extend(included_roles.roles);
+
+        // Tasks and handlers inherit the play's tags
+        for task in tasks.iter_mut().chain(handlers.iter_mut()) {
+            inherit_tags(task, &play_tags);
         }
 
-        // Parse hosts pattern and render templates
-        let hosts = match raw_play.hosts {
-            Some(RawHostPattern::Single(host)) => {
-                let rendered_host = if host.contains("{{") && host.contains("}}") {
-                    self.template_engine.render_string(&host, &play_vars)?
-                } else {
-                    host
-                };
+        let notifications = self.resolve_handler_notifications(&tasks, &handlers);
 
-                if rendered_host == "all" {
-                    HostPattern::All
-                } else {
-                    HostPattern::Single(rendered_host)
+        let play_name = parser.play_name(raw_play.name, &play_vars)?;
+
+        Ok(ParsedPlay {
+            name: play_name,
+            hosts,
+            tags: play_tags,
+            vars: play_vars,
+            vars_files: parser.vars_files(raw_play.vars_files),
+            tasks,
+            handlers,
+            roles,
+            collections,
+            strategy: raw_play.strategy.unwrap_or_default(),
+            serial: raw_play.serial,
+            max_fail_percentage: raw_play.max_fail_percentage,
+            notifications,
+            source: play_node.location(),
+            var_sources,
+            matched_hosts: None,
+        })
+    }
+
+    /// Parse the `section` list of tasks below `parent`, such as a play's `tasks`.
+    ///
+    /// With `includes`, `include_tasks`, `import_tasks`, `include_role` and
+    /// `import_role` are expanded in place; without, they are kept as ordinary tasks.
+    async fn parse_task_list(
+        &self,
+        raw_tasks: Option<Vec<RawTask>>,
+        vars: &HashMap<String, serde_json::Value>,
+        parent: &TaskPath,
+        section: &str,
+        list_node: &SourceNode<'_>,
+        mut includes: Option<TaskIncludes<'_>>,
+    ) -> Result<Vec<ParsedTask>, ParseError> {
+        let raw_tasks = raw_tasks.unwrap_or_default();
+
+        // The files of sibling includes are read concurrently, then expanded in order
+        if let Some(includes) = &includes {
+            let files: Vec<&str> = raw_tasks
+                .iter()
+                .filter(|raw_task| {
+                    self.is_dynamic_include_selected(raw_task, includes.inherited_tags)
+                })
+                .filter_map(|raw_task| {
+                    let value = raw_task
+                        .module_args
+                        .get("include_tasks")
+                        .or_else(|| raw_task.module_args.get("import_tasks"))?;
+                    value.as_str().or_else(|| {
+                        value
+                            .get("file")
+                            .or_else(|| value.get("_raw_params"))?
+                            .as_str()
+                    })
+                })
+                .collect();
+            includes.handler.prefetch(&files, includes.context).await;
+        }
+
+        let identities: Vec<Option<String>> = raw_tasks.iter().map(RawTask::identity).collect();
+        let task_paths = parent.children(section, &identities);
+
+        let mut tasks = Vec::new();
+        for ((index, raw_task), task_path) in raw_tasks.into_iter().enumerate().zip(task_paths) {
+            if let Some(includes) = includes
+                .as_mut()
+                .filter(|_| self.is_include_task(&raw_task))
+            {
+                if !self.is_dynamic_include_selected(&raw_task, includes.inherited_tags) {
+                    continue;
                 }
-            }
-            Some(RawHostPattern::Multiple(hosts)) => {
-                let mut rendered_hosts = Vec::new();
-                for host in hosts {
-                    let rendered_host = if host.contains("{{") && host.contains("}}") {
-                        self.template_engine.render_string(&host, &play_vars)?
-                    } else {
-                        host
-                    };
-                    rendered_hosts.push(rendered_host);
+                let mut context = includes.context.clone();
+                context.task_path = task_path.clone();
+                let include = TaskIncludes {
+                    context: &context,
+                    ..includes.by_ref()
+                };
+                // Like play roles, roles that cannot be found are kept as the task itself
+                match self.process_task_include(&raw_task, include).await {
+                    Err(ParseError::RoleNotFound { role, .. }) => {
+                        warn!("Role '{role}' not found, its tasks are not expanded");
+                    }
+                    included => {
+                        tasks.extend(included?);
+                        continue;
+                    }
                 }
-                HostPattern::Multiple(rendered_hosts)
             }
-            Some(RawHostPattern::All) => HostPattern::All,
-            None => HostPattern::Single("localhost".to_string()),
-        };
 
-        // Parse tasks with include support
-        let mut tasks = Vec::new();
-        if let Some(raw_tasks) = raw_play.tasks {
-            // Create a new include context with play vars
-            let mut play_include_context = include_context.clone();
-            play_include_context.variables = play_vars.clone();
-
-            for (index, raw_task) in raw_tasks.into_iter().enumerate() {
-                // Check if this is an include directive
-                if self.is_include_task(&raw_task) {
-                    let included_tasks = self
-                        .process_task_include(&raw_task, include_handler, &play_include_context)
-                        .await?;
-                    tasks.extend(included_tasks);
-                } else {
-                    let task = self.parse_task(raw_task, &play_vars, index).await?;
-                    tasks.push(task);
+            let task_node = list_node.index(index);
+            tasks.push(
+                self.task_parser()
+                    .parse_task(raw_task, vars, &task_path, &task_node)?,
+            );
+        }
+        Ok(tasks)
+    }
+
+    /// Load the roles of a play together with their tasks and handlers.
+    ///
+    /// Role tasks are rendered with the role's defaults below the play vars, and
+    /// its `vars/main.yml` and parameters above them. Every task and handler of
+    /// a role gets the role's tags and conditions. Roles that cannot be found are
+    /// kept unexpanded with a warning.
+    async fn expand_roles(
+        &self,
+        raw_roles: Vec<RawRole>,
+        mut expansion: RoleExpansion,
+        play_path: &TaskPath,
+        play_node: &SourceNode<'_>,
+        mut includes: Option<TaskIncludes<'_>>,
+    ) -> Result<ExpandedRoles, ParseError> {
+        self.prefetch_roles(&raw_roles, &expansion).await;
+        for (index, raw_role) in raw_roles.into_iter().enumerate() {
+            let source = play_node.key("roles").index(index).location();
+            let role = self
+                .expand_role(
+                    raw_role,
+                    source,
+                    play_path.child("roles", index),
+                    (Vec::new(), Vec::new()),
+                    &mut expansion,
+                    TaskIncludes::reborrow(&mut includes),
+                )
+                .await?;
+            expansion.expanded.roles.push(role);
+        }
+        Ok(expansion.expanded)
+    }
+
+    /// Expand one role invocation, after the dependencies from its `meta/main.yml`.
+    ///
+    /// `inherited` holds the tags and conditions of the roles depending on this one.
+    fn expand_role<'b>(
+        &'b self,
+        raw_role: RawRole,
+        source: Option<SourceLocation>,
+        role_task_path: TaskPath,
+        inherited: (Vec<String>, Vec<Condition>),
+        expansion: &'b mut RoleExpansion,
+        mut includes: Option<TaskIncludes<'b>>,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<ParsedRole, ParseError>> + 'b>>
+    {
+        Box::pin(async move {
+            let mut role = self.task_parser().parse_role(raw_role, source)?;
+
+            let Some((collection, role_path)) = self.locate_role(&role.name, expansion) else {
+                warn!("Role '{}' not found, its tasks are not expanded", role.name);
+                return Ok(role);
+            };
+            role.path = Some(role_path.to_string_lossy().to_string());
+
+            if let Some(start) = expansion.chain.iter().position(|name| *name == role.name) {
+                let mut cycle = expansion.chain[start..].to_vec();
+                cycle.push(role.name.clone());
+                return Err(ParseError::CircularDependency {
+                    cycle: cycle.join(" -> "),
+                });
+            }
+
+            // The same role with the same parameters only runs once per play
+            let (meta, meta_map) = self.load_role_meta(&role_path).await?;
+            let invocation = format!(
+                "{} {}",
+                role.name,
+                serde_json::to_value(&role.vars).unwrap_or_default()
+            );
+            if !expansion.seen.insert(invocation) && !meta.allow_duplicates.unwrap_or(false) {
+                role.deduplicated = true;
+                return Ok(role);
+            }
+
+            // Dependencies and tasks get the tags and conditions of this role
+            // and of every role depending on it
+            let (mut tags, mut when) = inherited;
+            for tag in &role.tags {
+                if !tags.contains(tag) {
+                    tags.push(tag.clone());
                 }
             }
-        }
+            when.extend(role.when.iter().cloned());
 
-        // Parse handlers
-        let mut handlers = Vec::new();
-        if let Some(raw_handlers) = raw_play.handlers {
-            for (index, raw_handler) in raw_handlers.into_iter().enumerate() {
-                let handler = self.parse_task(raw_handler, &play_vars, index).await?;
-                handlers.push(handler);
+            expansion.chain.push(role.name.clone());
+            if let Some(collection) = &collection {
+                expansion.collections.insert(0, collection.clone());
             }
-        }
+            let dependencies_node = meta_map.root().key("dependencies");
+            let dependencies = meta.dependencies.unwrap_or_default();
+            self.prefetch_roles(&dependencies, expansion).await;
+            for (index, raw_dependency) in dependencies.into_iter().enumerate() {
+                let dependency = self
+                    .expand_role(
+                        raw_dependency,
+                        dependencies_node.index(index).location(),
+                        role_task_path.child("dependencies", index),
+                        (tags.clone(), when.clone()),
+                        expansion,
+                        TaskIncludes::reborrow(&mut includes),
+                    )
+                    .await?;
+                role.dependencies.push(dependency);
+            }
+            if collection.is_some() {
+                expansion.collections.remove(0);
+            }
+            expansion.chain.pop();
+
+            (role.defaults, role.role_vars) = tokio::try_join!(
+                self.load_role_vars_file(&role_path, "defaults"),
+                self.load_role_vars_file(&role_path, "vars"),
+            )?;
+
+            let mut vars = role.defaults.clone();
+            vars.extend(expansion.play_vars.clone());
+            vars.extend(role.role_vars.clone());
+            for (key, value) in &role.vars {
+                let rendered = self.template_engine.render_value(value, &vars)?;
+                vars.insert(key.clone(), rendered);
+            }
+            vars.extend(self.extra_vars.clone());
 
-        // Parse roles
-        let mut roles = Vec::new();
-        if let Some(raw_roles) = raw_play.roles {
-            for raw_role in raw_roles {
-                let role = self.parse_role(raw_role)?;
-                roles.push(role);
+            if let Some(specs) =
+                RoleIncludeProcessor::load_argument_specs(&role_path, &self.loader).await?
+            {
+                specs.validate(&role.name, "main", &vars)?;
             }
-        }
 
-        // Render play name if it contains templates
-        let play_name = match raw_play.name {
-            Some(name) if name.contains("{{") && name.contains("}}") => {
-                self.template_engine.render_string(&name, &play_vars)?
+            let mut tasks = Vec::new();
+            if let Some(file) = RoleIncludeProcessor::role_file(&role_path, "tasks", "main") {
+                let (raw_tasks, source_map) = self.load_task_file(&file).await?;
+                let inherited_tags = includes
+                    .as_ref()
+                    .map(|includes| [includes.inherited_tags, &tags].concat())
+                    .unwrap_or_default();
+                let context = includes.as_ref().map(|includes| IncludeContext {
+                    variables: vars.clone(),
+                    current_file: file.clone(),
+                    ..includes.context.clone()
+                });
+                let role_includes = match (includes.as_mut(), &context) {
+                    (Some(includes), Some(context)) => Some(TaskIncludes {
+                        handler: &mut *includes.handler,
+                        context,
+                        inherited_tags: &inherited_tags,
+                        roles: &mut *includes.roles,
+                    }),
+                    _ => None,
+                };
+                tasks = self
+                    .parse_task_list(
+                        Some(raw_tasks),
+                        &vars,
+                        &role_task_path,
+                        "tasks",
+                        &source_map.root(),
+                        role_includes,
+                    )
+                    .await?;
             }
-            Some(name) => name,
-            None => "Unnamed play".to_string(),
-        };
 
-        Ok(ParsedPlay {
-            name: play_name,
-            hosts,
-            vars: play_vars,
-            tasks,
-            handlers,
-            roles,
-            strategy: raw_play.strategy.unwrap_or_default(),
-            serial: raw_play.serial,
-            max_fail_percentage: raw_play.max_fail_percentage,
+            let mut handlers = Vec::new();
+            if let Some(file) = RoleIncludeProcessor::role_file(&role_path, "handlers", "main") {
+                let (raw_handlers, source_map) = self.load_task_file(&file).await?;
+                handlers = self
+                    .parse_task_list(
+                        Some(raw_handlers),
+                        &vars,
+                        &role_task_path,
+                        "handlers",
+                        &source_map.root(),
+                        None,
+                    )
+                    .await?;
+            }
+
+            for task in tasks.iter_mut().chain(handlers.iter_mut()) {
+                inherit_tags(task, &tags);
+                inherit_conditions(&mut task.when, &when);
+            }
+            expansion.expanded.tasks.extend(tasks);
+            expansion.expanded.handlers.extend(handlers);
+            Ok(role)
+        })
+    }
+
+    /// Find a role in the play's collections, or else in the `roles`
+    /// directories next to the playbook and the roles path. Returns the collection it is from, if any.
+    fn locate_role(
+        &self,
+        name: &str,
+        expansion: &RoleExpansion,
+    ) -> Option<(Option<String>, PathBuf)> {
+        let collection_role = self
+            .collections
+            .and_then(|index| index.resolve_role(name, &expansion.collections))
+            .map(|(collection, path)| (Some(collection.fqcn()), path));
+        collection_role.or_else(|| {
+            RoleIncludeProcessor::resolve_role_path(
+                name,
+                &expansion.play_file,
+                &self.include_config.role_search_paths,
+            )
+            .ok()
+            .map(|path| (None, path))
         })
     }
 
+    /// Read the files of every role in `raw_roles` concurrently, ahead of
+    /// expanding the roles in order
+    async fn prefetch_roles(&self, raw_roles: &[RawRole], expansion: &RoleExpansion) {
+        let files = raw_roles
+            .iter()
+            .filter_map(|raw_role| self.locate_role(raw_role.name(), expansion))
+            .flat_map(|(_, role_path)| {
+                [
+                    ("meta", "main"),
+                    ("meta", "argument_specs"),
+                    ("defaults", "main"),
+                    ("vars", "main"),
+                    ("tasks", "main"),
+                    ("handlers", "main"),
+                ]
+                .into_iter()
+                .filter_map(move |(dir, name)| {
+                    RoleIncludeProcessor::role_file(&role_path, dir, name)
+                })
+            })
+            .collect();
+        self.loader.prefetch(files).await;
+    }
+
+    /// Read a role's `meta/main.yml`, if it has one
+    async fn load_role_meta(
+        &self,
+        role_path: &Path,
+    ) -> Result<(RawRoleMeta, SourceMap), ParseError> {
+        let Some(path) = RoleIncludeProcessor::role_file(role_path, "meta", "main") else {
+            return Ok((RawRoleMeta::default(), SourceMap::parse("", "")));
+        };
+        let loaded = self.loader.load(&path).await?;
+        let meta: Option<RawRoleMeta> = loaded.deserialize()?;
+        Ok((meta.unwrap_or_default(), loaded.source_map.clone()))
+    }
+
+    /// Read a task list file, such as a role's `tasks/main.yml`
+    async fn load_task_file(&self, path: &Path) -> Result<(Vec<RawTask>, SourceMap), ParseError> {
+        let loaded = self.loader.load(path).await?;
+        let raw_tasks: Option<Vec<RawTask>> = loaded.deserialize()?;
+        Ok((raw_tasks.unwrap_or_default(), loaded.source_map.clone()))
+    }
+
+    /// Read a role's `<dir>/main.yml` variables file, if it has one
+    async fn load_role_vars_file(
+        &self,
+        role_path: &Path,
+        dir: &str,
+    ) -> Result<HashMap<String, serde_json::Value>, ParseError> {
+        let Some(path) = RoleIncludeProcessor::role_file(role_path, dir, "main") else {
+            return Ok(HashMap::new());
+        };
+        let vars: Option<HashMap<String, serde_json::Value>> =
+            self.loader.load(&path).await?.deserialize()?;
+        Ok(vars.unwrap_or_default())
+    }
+
+    /// Resolve task and handler `notify` entries, warning about unknown targets
+    fn resolve_handler_notifications(
+        &self,
+        tasks: &[ParsedTask],
+        handlers: &[ParsedTask],
+    ) -> NotificationMap {
+        let notifications = resolve_notifications(tasks, handlers);
+        for unresolved in &notifications.unresolved {
+            warn!(
+                "Task '{}' notifies '{}', which matches no handler name or listen topic",
+                unresolved.task_id, unresolved.target
+            );
+        }
+        notifications
+    }
+
+    /// Check whether a dynamic `include_*` task is selected by its own and inherited tags.
+    ///
+    /// Tags on a dynamic include apply only to the include itself, so an
+    /// unselected include never loads its file. Static imports are always expanded.
+    fn is_dynamic_include_selected(&self, raw_task: &RawTask, inherited_tags: &[String]) -> bool {
+        let Some(selection) = self.tag_selection else {
+            return true;
+        };
+        if !raw_task.module_args.contains_key("include_tasks")
+            && !raw_task.module_args.contains_key("include_role")
+        {
+            return true;
+        }
+
+        let mut tags = raw_task.tags.clone().unwrap_or_default();
+        tags.extend(inherited_tags.iter().cloned());
+        selection.should_run(&tags)
+    }
+
     /// Check if a raw task is an include directive
     fn is_include_task(&self, raw_task: &RawTask) -> bool {
         let include_keys = [
@@ -435,7 +1011,6 @@ impl<'a> PlaybookParser<'a> {
             "import_tasks",
             "include_playbook",
             "import_playbook",
-            "include_vars",
             "include_role",
             "import_role",
         ];
@@ -479,30 +1054,15 @@ impl<'a> PlaybookParser<'a> {
             serde_yaml::from_value::<HashMap<String, serde_json::Value>>(v.clone()).ok()
         });
 
-        // For when conditions, get the raw YAML value as a string
-        let when_condition = map.get("when").map(|v| {
-            // Convert YAML value to string representation
-            match v {
-                serde_yaml::Value::String(s) => s.clone(),
-                serde_yaml::Value::Bool(b) => b.to_string(),
-                serde_yaml::Value::Number(n) => n.to_string(),
-                _ => format!("{v:?}"),
-            }
-        });
+        let when_condition = match map.get("when") {
+            Some(when) => deserialize_conditions(when.clone())?,
+            None => None,
+        };
 
-        let tags = map.get("tags").and_then(|v| {
-            if let Some(arr) = v.as_sequence() {
-                Some(
-                    arr.iter()
-                        .filter_map(|t| t.as_str().map(|s| s.to_string()))
-                        .collect(),
-                )
-            } else if let Some(s) = v.as_str() {
-                Some(vec![s.to_string()])
-            } else {
-                None
-            }
-        });
+        let tags = match map.get("tags") {
+            Some(tags) => deserialize_tags(tags.clone())?,
+            None => None,
+        };
 
         if is_include {
             // Create IncludeSpec
@@ -515,10 +1075,10 @@ impl<'a> PlaybookParser<'a> {
                 delegate_to: None,
                 delegate_facts: None,
                 run_once: None,
+                name: None,
             };
 
-            include_handler
-                .include_playbook(&include_spec, include_context)
+            self.include_playbook(&include_spec, include_handler, include_context)
                 .await
         } else {
             // Create ImportSpec
@@ -527,10 +1087,12 @@ impl<'a> PlaybookParser<'a> {
                 vars,
                 when_condition,
                 tags,
+                delegate_to: None,
+                ignore_errors: None,
+                keywords: TaskKeywords::default(),
             };
 
-            include_handler
-                .import_playbook(&import_spec, include_context)
+            self.import_playbook(&import_spec, include_handler, include_context)
                 .await
         }
     }
@@ -542,30 +1104,265 @@ impl<'a> PlaybookParser<'a> {
             || raw_task.module_args.contains_key("import_playbook")
     }
 
-    /// Process task-level include directives
-    async fn process_task_include(
+    /// Process task-level include directives. Included and imported roles are
+    /// recorded in `includes.roles`, with their handlers.
+    fn process_task_include<'b>(
+        &'b self,
+        raw_task: &'b RawTask,
+        mut includes: TaskIncludes<'b>,
+    ) -> std::pin::Pin<
+        Box<dyn std::future::Future<Output = Result<Vec<ParsedTask>, ParseError>> + 'b>,
+    > {
+        Box::pin(async move {
+            // Convert raw task to include specification
+            if let Some(include_tasks_value) = raw_task.module_args.get("include_tasks") {
+                let include_spec = self.parse_include_tasks_spec(include_tasks_value, raw_task)?;
+                self.include_tasks(&include_spec, includes).await
+            } else if let Some(import_tasks_value) = raw_task.module_args.get("import_tasks") {
+                let import_spec = self.parse_import_tasks_spec(import_tasks_value, raw_task)?;
+                self.import_tasks(&import_spec, includes).await
+            } else if let Some(include_role_value) = raw_task.module_args.get("include_role") {
+                let role_spec =
+                    self.parse_role_include_spec("include_role", include_role_value, raw_task)?;
+                let role = includes
+                    .handler
+                    .include_role(&role_spec, includes.context)
+                    .await?;
+                self.parse_role_tasks(role, includes.by_ref()).await
+            } else if let Some(import_role_value) = raw_task.module_args.get("import_role") {
+                let role_spec =
+                    self.parse_role_include_spec("import_role", import_role_value, raw_task)?;
+                let role = includes
+                    .handler
+                    .import_role(&role_spec, includes.context)
+                    .await?;
+                self.parse_role_tasks(role, includes.by_ref()).await
+            } else {
+                // include_playbook and import_playbook are handled at play level
+                Ok(Vec::new())
+            }
+        })
+    }
+
+    /// Expand an `include_tasks`. Only `apply` keywords, behind the include's
+    /// `when`, reach the included tasks.
+    async fn include_tasks(
         &self,
-        raw_task: &RawTask,
-        include_handler: &mut IncludeHandler,
-        include_context: &IncludeContext,
+        include_spec: &IncludeSpec,
+        includes: TaskIncludes<'_>,
     ) -> Result<Vec<ParsedTask>, ParseError> {
-        // Convert raw task to include specification
-        if let Some(include_tasks_value) = raw_task.module_args.get("include_tasks") {
-            let include_spec = self.parse_include_tasks_spec(include_tasks_value, raw_task)?;
-            include_handler
-                .include_tasks(&include_spec, include_context)
-                .await
-        } else if let Some(import_tasks_value) = raw_task.module_args.get("import_tasks") {
-            let import_spec = self.parse_import_tasks_spec(import_tasks_value, raw_task)?;
-            include_handler
-                .import_tasks(&import_spec, include_context)
-                .await
-        } else {
-            // For now, only support include_tasks and import_tasks at task level
-            // include_playbook and import_playbook should be handled at play level
-            // Other include types would be implemented here
-            Ok(Vec::new())
+        // File names that need runtime variables are resolved when the play runs
+        let variables = self
+            .template_engine
+            .undefined_variables(&include_spec.file, &includes.context.variables)?;
+        if !variables.is_empty() {
+            return self.defer_include(include_spec, variables, includes).await;
+        }
+        let file = self
+            .template_engine
+            .render_string(&include_spec.file, &includes.context.variables)?;
+        let path = includes.handler.resolve_path(&file, includes.context)?;
+
+        self.parse_included_tasks(
+            path,
+            &file,
+            include_spec.vars.as_ref(),
+            &include_spec.inherited()?,
+            includes,
+        )
+        .await
+    }
+
+    /// Expand an `import_tasks`, whose keywords, `when` included, are inherited
+    /// by each imported task rather than evaluated once
+    async fn import_tasks(
+        &self,
+        import_spec: &ImportSpec,
+        includes: TaskIncludes<'_>,
+    ) -> Result<Vec<ParsedTask>, ParseError> {
+        // Imports are expanded at parse time, so their file name must be known
+        let variables = self
+            .template_engine
+            .undefined_variables(&import_spec.file, &includes.context.variables)?;
+        if !variables.is_empty() {
+            return Err(ParseError::InvalidIncludeDirective {
+                message: format!(
+                    "import_tasks file '{}' depends on undefined variables ({}); use include_tasks for files chosen at runtime",
+                    import_spec.file,
+                    variables.join(", ")
+                ),
+            });
+        }
+        let file = self
+            .template_engine
+            .render_string(&import_spec.file, &includes.context.variables)?;
+        let path = includes.handler.resolve_path(&file, includes.context)?;
+
+        self.parse_included_tasks(
+            path,
+            &file,
+            import_spec.vars.as_ref(),
+            &import_spec.inherited()?,
+            includes,
+        )
+        .await
+    }
+
+    /// Keep an include whose file name needs runtime `variables` as a single
+    /// marker task, with every file on disk the name can match parsed as a candidate
+    async fn defer_include(
+        &self,
+        include_spec: &IncludeSpec,
+        variables: Vec<String>,
+        mut includes: TaskIncludes<'_>,
+    ) -> Result<Vec<ParsedTask>, ParseError> {
+        let inherited = include_spec.inherited()?;
+        let files = includes
+            .handler
+            .include_candidates(&include_spec.file, includes.context)
+            .await;
+
+        let mut candidates = Vec::new();
+        for file in files {
+            if candidates.len() == MAX_INCLUDE_CANDIDATES {
+                warn!(
+                    "Include '{}' matches more than {MAX_INCLUDE_CANDIDATES} files, the rest are not parsed",
+                    include_spec.file
+                );
+                break;
+            }
+            // Files that merely match the name, such as the including file
+            // itself or a vars file, are not task files the include could load
+            let tasks = match includes.handler.resolve_path(&file, includes.context) {
+                Ok(path) => {
+                    self.parse_included_tasks(
+                        path,
+                        &file,
+                        include_spec.vars.as_ref(),
+                        &inherited,
+                        includes.by_ref(),
+                    )
+                    .await
+                }
+                Err(e) => Err(e),
+            };
+            match tasks {
+                Ok(tasks) => candidates.push(IncludeCandidate { file, tasks }),
+                Err(e) => debug!("Skipping include candidate '{file}': {e}"),
+            }
+        }
+
+        Ok(vec![IncludeHandler::deferred_include(
+            include_spec,
+            includes.context,
+            variables,
+            candidates,
+        )?])
+    }
+
+    /// Parse the task file at `path` through [`Self::parse_task_list`], so the
+    /// includes in it are expanded too, and pass `inherited` on to every task.
+    /// `section` names the file in the ids of its tasks.
+    async fn parse_included_tasks(
+        &self,
+        path: PathBuf,
+        section: &str,
+        vars: Option<&HashMap<String, serde_json::Value>>,
+        inherited: &InheritedKeywords,
+        includes: TaskIncludes<'_>,
+    ) -> Result<Vec<ParsedTask>, ParseError> {
+        let included = includes
+            .handler
+            .open_tasks(path, vars, includes.context)
+            .await?;
+        // Nested dynamic includes are selected by the tags passed on to them
+        let inherited_tags = [includes.inherited_tags, &inherited.tags].concat();
+        let tasks = self
+            .parse_task_list(
+                Some(included.raw_tasks),
+                &included.context.variables,
+                &includes.context.task_path,
+                section,
+                &included.source_map.root(),
+                Some(TaskIncludes {
+                    handler: &mut *includes.handler,
+                    context: &included.context,
+                    inherited_tags: &inherited_tags,
+                    roles: &mut *includes.roles,
+                }),
+            )
+            .await;
+        includes.handler.close();
+
+        let mut tasks = tasks?;
+        for task in &mut tasks {
+            inherited.apply_to(task);
+        }
+        Ok(tasks)
+    }
+
+    /// Parse the tasks and handlers of a role loaded by `include_role` or
+    /// `import_role`, recording the role and its handlers in `includes.roles`
+    async fn parse_role_tasks(
+        &self,
+        role: RoleIncludeResult,
+        includes: TaskIncludes<'_>,
+    ) -> Result<Vec<ParsedTask>, ParseError> {
+        let mut context = includes.context.clone();
+        context.variables = role.task_vars.clone();
+        context.variables.extend(self.extra_vars.clone());
+        // Identify the files by their path within the roles directory, e.g. `web/tasks/main.yml`
+        let section = |file: &Path| {
+            let parts: Vec<String> = file
+                .iter()
+                .rev()
+                .take(3)
+                .map(|part| part.to_string_lossy().to_string())
+                .collect();
+            parts.into_iter().rev().collect::<Vec<_>>().join("/")
+        };
+
+        let mut tasks = Vec::new();
+        if let Some(file) = &role.tasks_file {
+            tasks = self
+                .parse_included_tasks(
+                    file.clone(),
+                    &section(file),
+                    None,
+                    &role.inherited,
+                    TaskIncludes {
+                        handler: &mut *includes.handler,
+                        context: &context,
+                        inherited_tags: includes.inherited_tags,
+                        roles: &mut *includes.roles,
+                    },
+                )
+                .await?;
         }
+
+        if let Some(file) = &role.handlers_file {
+            let (raw_handlers, source_map) = self.load_task_file(file).await?;
+            let handlers = self
+                .parse_task_list(
+                    Some(raw_handlers),
+                    &context.variables,
+                    &includes.context.task_path,
+                    &section(file),
+                    &source_map.root(),
+                    None,
+                )
+                .await?;
+            includes
+                .roles
+                .handlers
+                .extend(RoleIncludeProcessor::inherit_keywords(
+                    handlers,
+                    &role.inherited,
+                ));
+        }
+
+        includes.roles.roles.push(role.to_parsed_role());
+        Ok(tasks)
     }
 
     /// Process playbook-level include directives
@@ -580,27 +1377,40 @@ impl<'a> PlaybookParser<'a> {
         if let Some(include_playbook_value) = raw_task.module_args.get("include_playbook") {
             let include_spec =
                 self.parse_include_playbook_spec(include_playbook_value, raw_task)?;
-            include_handler
-                .include_playbook(&include_spec, include_context)
+            self.include_playbook(&include_spec, include_handler, include_context)
                 .await
         } else if let Some(import_playbook_value) = raw_task.module_args.get("import_playbook") {
             let import_spec = self.parse_import_playbook_spec(import_playbook_value, raw_task)?;
-            include_handler
-                .import_playbook(&import_spec, include_context)
+            self.import_playbook(&import_spec, include_handler, include_context)
                 .await
         } else {
             Ok(Vec::new())
         }
     }
 
-    /// Parse include_tasks specification from raw task
+    /// Parse include_tasks specification from raw task, either `include_tasks: file`
+    /// or `include_tasks: {file: ..., apply: {...}}`
     fn parse_include_tasks_spec(
         &self,
         include_value: &serde_json::Value,
         raw_task: &RawTask,
-    ) -> Result<crate::parser::include::IncludeSpec, ParseError> {
-        let file = match include_value {
-            serde_json::Value::String(file_path) => file_path.clone(),
+    ) -> Result<IncludeSpec, ParseError> {
+        let (file, apply) = match include_value {
+            serde_json::Value::String(file_path) => (file_path.clone(), None),
+            serde_json::Value::Object(args) => {
+                let file = args
+                    .get("file")
+                    .or_else(|| args.get("_raw_params"))
+                    .and_then(|file| file.as_str())
+                    .ok_or_else(|| ParseError::InvalidIncludeDirective {
+                        message: "include_tasks must specify a file path".to_string(),
+                    })?;
+                let apply = args
+                    .get("apply")
+                    .map(|apply| serde_json::from_value::<ApplySpec>(apply.clone()))
+                    .transpose()?;
+                (file.to_string(), apply)
+            }
             _ => {
                 return Err(ParseError::InvalidIncludeDirective {
                     message: "include_tasks must specify a file path".to_string(),
@@ -608,12 +1418,13 @@ impl<'a> PlaybookParser<'a> {
             }
         };
 
-        Ok(crate::parser::include::IncludeSpec {
+        Ok(IncludeSpec {
             file,
+            name: raw_task.name.clone(),
             vars: raw_task.vars.clone(),
             when_condition: raw_task.when.clone(),
             tags: raw_task.tags.clone(),
-            apply: None, // TODO: Parse apply block from raw task
+            apply,
             delegate_to: raw_task.delegate_to.clone(),
             delegate_facts: None, // TODO: Extract from raw task if present
             run_once: None,       // TODO: Extract from raw task if present
@@ -640,9 +1451,41 @@ impl<'a> PlaybookParser<'a> {
             vars: raw_task.vars.clone(),
             when_condition: raw_task.when.clone(),
             tags: raw_task.tags.clone(),
+            delegate_to: raw_task.delegate_to.clone(),
+            ignore_errors: raw_task.ignore_errors.clone(),
+            keywords: raw_task.keywords.clone(),
         })
     }
 
+    /// Parse an `include_role` or `import_role` specification from raw task.
+    /// The role options come from the directive, every other keyword from the task.
+    fn parse_role_include_spec(
+        &self,
+        directive: &str,
+        role_value: &serde_json::Value,
+        raw_task: &RawTask,
+    ) -> Result<RoleIncludeSpec, ParseError> {
+        let serde_json::Value::Object(args) = role_value else {
+            return Err(ParseError::InvalidIncludeDirective {
+                message: format!("{directive} must specify a role name"),
+            });
+        };
+        let mut role_spec: RoleIncludeSpec =
+            serde_json::from_value(serde_json::Value::Object(args.clone())).map_err(|e| {
+                ParseError::InvalidIncludeDirective {
+                    message: format!("invalid {directive}: {e}"),
+                }
+            })?;
+
+        role_spec.vars = raw_task.vars.clone();
+        role_spec.when_condition = raw_task.when.clone();
+        role_spec.tags = raw_task.tags.clone();
+        role_spec.delegate_to = raw_task.delegate_to.clone();
+        role_spec.ignore_errors = raw_task.ignore_errors.clone();
+        role_spec.keywords = raw_task.keywords.clone();
+        Ok(role_spec)
+    }
+
     /// Parse include_playbook specification from raw task
     #[allow(dead_code)]
     fn parse_include_playbook_spec(
@@ -661,6 +1504,7 @@ impl<'a> PlaybookParser<'a> {
 
         Ok(crate::parser::include::IncludeSpec {
             file,
+            name: raw_task.name.clone(),
             vars: raw_task.vars.clone(),
             when_condition: raw_task.when.clone(),
             tags: raw_task.tags.clone(),
@@ -692,476 +1536,100 @@ impl<'a> PlaybookParser<'a> {
             vars: raw_task.vars.clone(),
             when_condition: raw_task.when.clone(),
             tags: raw_task.tags.clone(),
+            delegate_to: raw_task.delegate_to.clone(),
+            ignore_errors: raw_task.ignore_errors.clone(),
+            keywords: raw_task.keywords.clone(),
         })
     }
+}
 
-    async fn parse_task(
-        &self,
-        raw_task: RawTask,
-        vars: &HashMap<String, serde_json::Value>,
-        index: usize,
-    ) -> Result<ParsedTask, ParseError> {
-        let id = raw_task
-            .id
-            .clone()
-            .unwrap_or_else(|| format!("task_{index}"));
-        let name = raw_task
-            .name
-            .clone()
-            .unwrap_or_else(|| "Unnamed task".to_string());
-
-        // Find the module and its arguments
-        let (module, args) = self.extract_module_and_args(&raw_task)?;
-
-        // Render templates in args
-        let rendered_args = self.render_task_args(args, vars).await?;
-
-        // Process boolean-or-string fields with template resolution
-        let changed_when = self.convert_boolean_or_string_field(raw_task.changed_when, vars)?;
-        let failed_when = self.convert_boolean_or_string_field(raw_task.failed_when, vars)?;
-
-        Ok(ParsedTask {
-            id,
-            name,
-            module,
-            args: rendered_args,
-            vars: raw_task.vars.unwrap_or_default(),
-            when: raw_task.when,
-            loop_items: raw_task.loop_items,
-            tags: raw_task.tags.unwrap_or_default(),
-            notify: raw_task.notify.unwrap_or_default(),
-            changed_when,
-            failed_when,
-            ignore_errors: raw_task.ignore_errors.unwrap_or(false),
-            delegate_to: raw_task.delegate_to,
-            dependencies: Vec::new(), // TODO: Extract dependencies from task relationships
-        })
-    }
-
-    /// Convert boolean-or-string field with template resolution
-    fn convert_boolean_or_string_field(
-        &self,
-        field: Option<BooleanOrString>,
-        vars: &HashMap<String, serde_json::Value>,
-    ) -> Result<Option<BooleanOrString>, ParseError> {
-        match field {
-            None => Ok(None),
-            Some(BooleanOrString::Boolean(b)) => Ok(Some(BooleanOrString::Boolean(b))),
-            Some(BooleanOrString::String(s)) => {
-                let resolved = self.template_engine.render_string(&s, vars)?;
-                // Try to parse resolved template as boolean
-                match resolved.to_lowercase().as_str() {
-                    "true" | "yes" | "on" => Ok(Some(BooleanOrString::Boolean(true))),
-                    "false" | "no" | "off" => Ok(Some(BooleanOrString::Boolean(false))),
-                    _ => Ok(Some(BooleanOrString::String(resolved))),
-                }
-            }
-        }
-    }
-
-    fn parse_role(&self, raw_role: RawRole) -> Result<ParsedRole, ParseError> {
-        match raw_role {
-            RawRole::String(name) => Ok(ParsedRole {
-                name,
-                src: None,
-                version: None,
-                vars: HashMap::new(),
-                tags: Vec::new(),
-            }),
-            RawRole::Object(role_obj) => Ok(ParsedRole {
-                name: role_obj.name,
-                src: role_obj.src,
-                version: role_obj.version,
-                vars: role_obj.vars.unwrap_or_default(),
-                tags: role_obj.tags.unwrap_or_default(),
-            }),
-        }
-    }
-
-    fn extract_module_and_args(
-        &self,
-        raw_task: &RawTask,
-    ) -> Result<(String, HashMap<String, serde_json::Value>), ParseError> {
-        // Look for known module keys
-        let module_keys = [
-            "shell",
-            "command",
-            "copy",
-            "file",
-            "template",
-            "service",
-            "package",
-            "yum",
-            "apt",
-            "git",
-            "debug",
-            "set_fact",
-            "include",
-            "include_tasks",
-            "import_tasks",
-            "block",
-            "rescue",
-            "always",
-            "meta",
-            "setup",
-            "gather_facts",
-            "ping",
-            "uri",
-            "get_url",
-            "unarchive",
-            "lineinfile",
-            "replace",
-            "stat",
-            "find",
-            "user",
-            "group",
-            "cron",
-            "systemd",
-            "assert",
-            "postgresql_db",
-            "wait_for",
-        ];
-
-        for &key in &module_keys {
-            if let Some(value) = raw_task.module_args.get(key) {
-                let args = match value {
-                    serde_json::Value::String(s) => {
-                        let mut args = HashMap::new();
-                        args.insert(
-                            "_raw_params".to_string(),
-                            serde_json::Value::String(s.clone()),
-                        );
-                        args
-                    }
-                    serde_json::Value::Object(obj) => {
-                        obj.iter().map(|(k, v)| (k.clone(), v.clone())).collect()
-                    }
-                    _ => {
-                        let mut args = HashMap::new();
-                        args.insert("_raw_params".to_string(), value.clone());
-                        args
-                    }
-                };
-                return Ok((key.to_string(), args));
-            }
-        }
-
-        // Provide more context about the failed task
-        let available_modules: Vec<String> = raw_task.module_args.keys().cloned().collect();
-        let task_name = raw_task.name.as_deref().unwrap_or("unnamed");
-        Err(ParseError::InvalidStructure {
-            message: format!("No valid module found in task '{}'. Available keys: {:?}. Known modules: include_tasks={}, import_tasks={}", 
-                task_name, available_modules,
-                module_keys.contains(&"include_tasks"), 
-                module_keys.contains(&"import_tasks")),
-        })
-    }
-
-    async fn render_task_args(
-        &self,
-        args: HashMap<String, serde_json::Value>,
-        vars: &HashMap<String, serde_json::Value>,
-    ) -> Result<HashMap<String, serde_json::Value>, ParseError> {
-        let mut rendered_args = HashMap::new();
+/// Root of the task paths for a playbook file. Only the file name is used so
+/// that ids do not depend on where the project is checked out.
+fn playbook_task_path(path: &Path) -> TaskPath {
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string_lossy().to_string());
+    TaskPath::new(&file_name)
+}
 
-        for (key, value) in args {
-            let normalized_value = self.normalize_yaml_value(value);
-            let rendered_value = self.template_engine.render_value(&normalized_value, vars)?;
-            rendered_args.insert(key, rendered_value);
-        }
+/// How a task list expands `include_tasks`, `import_tasks`, `include_role`
+/// and `import_role`
+struct TaskIncludes<'a> {
+    handler: &'a mut IncludeHandler,
+    context: &'a IncludeContext,
+    /// Tags the list's tasks inherit, used to select dynamic includes
+    inherited_tags: &'a [String],
+    /// Roles loaded by `include_role` and `import_role`, with their handlers
+    roles: &'a mut ExpandedRoles,
+}
 
-        Ok(rendered_args)
+impl TaskIncludes<'_> {
+    fn reborrow<'b>(includes: &'b mut Option<TaskIncludes<'_>>) -> Option<TaskIncludes<'b>> {
+        includes.as_mut().map(TaskIncludes::by_ref)
     }
 
-    fn normalize_yaml_value(&self, value: serde_json::Value) -> serde_json::Value {
-        match value {
-            serde_json::Value::String(s) => {
-                // Convert YAML boolean strings to actual booleans
-                match s.to_lowercase().as_str() {
-                    "yes" | "true" | "on" => serde_json::Value::Bool(true),
-                    "no" | "false" | "off" => serde_json::Value::Bool(false),
-                    _ => serde_json::Value::String(s),
-                }
-            }
-            _ => value,
+    fn by_ref(&mut self) -> TaskIncludes<'_> {
+        TaskIncludes {
+            handler: &mut *self.handler,
+            context: self.context,
+            inherited_tags: self.inherited_tags,
+            roles: &mut *self.roles,
         }
     }
 }
 
-// Raw data structures for YAML parsing
-#[derive(Debug, Deserialize)]
-struct RawPlay {
-    name: Option<String>,
-    hosts: Option<RawHostPattern>,
-    vars: Option<HashMap<String, serde_json::Value>>,
-    tasks: Option<Vec<RawTask>>,
-    handlers: Option<Vec<RawTask>>,
-    roles: Option<Vec<RawRole>>,
-    strategy: Option<ExecutionStrategy>,
-    serial: Option<u32>,
-    max_fail_percentage: Option<f32>,
+/// A play's roles with the tasks and handlers they contribute
+#[derive(Default)]
+struct ExpandedRoles {
+    roles: Vec<ParsedRole>,
+    tasks: Vec<ParsedTask>,
+    handlers: Vec<ParsedTask>,
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(untagged)]
-enum RawHostPattern {
-    Single(String),
-    Multiple(Vec<String>),
-    All,
+/// State while expanding the roles of one play
+struct RoleExpansion {
+    play_vars: HashMap<String, serde_json::Value>,
+    play_file: PathBuf,
+    /// Collections searched for short role names. A collection role's
+    /// dependencies search its own collection first.
+    collections: Vec<String>,
+    /// Roles whose dependencies are being expanded, outermost first
+    chain: Vec<String>,
+    /// Role invocations (name and parameters) that already ran
+    seen: HashSet<String>,
+    expanded: ExpandedRoles,
 }
 
-#[derive(Debug, Deserialize)]
-struct RawTask {
-    id: Option<String>,
-    name: Option<String>,
-    vars: Option<HashMap<String, serde_json::Value>>,
-    when: Option<String>,
-    #[serde(rename = "loop")]
-    loop_items: Option<serde_json::Value>,
-    tags: Option<Vec<String>>,
-    #[serde(deserialize_with = "deserialize_notify", default)]
-    notify: Option<Vec<String>>,
-    #[serde(deserialize_with = "deserialize_boolean_or_string", default)]
-    changed_when: Option<BooleanOrString>,
-    #[serde(deserialize_with = "deserialize_boolean_or_string", default)]
-    failed_when: Option<BooleanOrString>,
-    #[serde(deserialize_with = "deserialize_yaml_bool", default)]
-    ignore_errors: Option<bool>,
-    delegate_to: Option<String>,
-    #[serde(rename = "become", deserialize_with = "deserialize_yaml_bool", default)]
-    #[allow(dead_code)]
-    r#become: Option<bool>,
-    #[allow(dead_code)]
-    become_user: Option<String>,
-    #[allow(dead_code)]
-    become_method: Option<String>,
-    #[allow(dead_code)]
-    register: Option<String>,
-    #[serde(flatten)]
-    module_args: HashMap<String, serde_json::Value>,
-}
-
-#[derive(Debug, Deserialize)]
-#[serde(untagged)]
-enum RawRole {
-    String(String),
-    Object(RawRoleObject),
-}
-
-#[derive(Debug, Deserialize)]
-struct RawRoleObject {
-    name: String,
-    src: Option<String>,
-    version: Option<String>,
-    vars: Option<HashMap<String, serde_json::Value>>,
-    tags: Option<Vec<String>>,
-}
-
-// Custom deserializer for notify field that accepts both string and array
-fn deserialize_notify<'de, D>(deserializer: D) -> Result<Option<Vec<String>>, D::Error>
-where
-    D: Deserializer<'de>,
-{
-    use serde::de::{Error, Unexpected};
-
-    let value: Option<serde_json::Value> = Option::deserialize(deserializer)?;
-    match value {
-        None => Ok(None),
-        Some(serde_json::Value::String(s)) => Ok(Some(vec![s])),
-        Some(serde_json::Value::Array(arr)) => {
-            let strings: Result<Vec<String>, _> = arr
-                .into_iter()
-                .map(|v| match v {
-                    serde_json::Value::String(s) => Ok(s),
-                    _ => Err(Error::invalid_type(
-                        Unexpected::Other("non-string in array"),
-                        &"string",
-                    )),
-                })
-                .collect();
-            strings.map(Some)
+impl RoleExpansion {
+    fn new(
+        play_vars: &HashMap<String, serde_json::Value>,
+        collections: &[String],
+        play_file: &Path,
+    ) -> Self {
+        Self {
+            play_vars: play_vars.clone(),
+            play_file: play_file.to_path_buf(),
+            collections: collections.to_vec(),
+            chain: Vec::new(),
+            seen: HashSet::new(),
+            expanded: ExpandedRoles::default(),
         }
-        Some(_) => Err(Error::invalid_type(
-            Unexpected::Other("non-string/array"),
-            &"string or array of strings",
-        )),
     }
 }
 
-// Custom deserializer for boolean fields that handles YAML boolean strings
-fn deserialize_yaml_bool<'de, D>(deserializer: D) -> Result<Option<bool>, D::Error>
-where
-    D: Deserializer<'de>,
-{
-    use serde::de::Error;
-
-    let value: Option<serde_yaml::Value> = Option::deserialize(deserializer)?;
-    match value {
-        None => Ok(None),
-        Some(serde_yaml::Value::Bool(b)) => Ok(Some(b)),
-        Some(serde_yaml::Value::String(s)) => match s.to_lowercase().as_str() {
-            "yes" | "true" | "on" => Ok(Some(true)),
-            "no" | "false" | "off" => Ok(Some(false)),
-            _ => Err(Error::custom(format!("Invalid boolean string: {s}"))),
-        },
-        Some(_) => Err(Error::custom("Expected boolean or boolean string")),
-    }
-}
-
-// Custom deserializer for boolean-or-string fields like changed_when and failed_when
-pub fn deserialize_boolean_or_string<'de, D>(
-    deserializer: D,
-) -> Result<Option<BooleanOrString>, D::Error>
-where
-    D: Deserializer<'de>,
-{
-    use serde::de::Error;
-
-    let value: Option<serde_yaml::Value> = Option::deserialize(deserializer)?;
-    match value {
-        None => Ok(None),
-        Some(serde_yaml::Value::Bool(b)) => Ok(Some(BooleanOrString::Boolean(b))),
-        Some(serde_yaml::Value::String(s)) => {
-            // Try to parse as boolean first for string representations
-            match s.to_lowercase().as_str() {
-                "true" | "yes" | "on" => Ok(Some(BooleanOrString::Boolean(true))),
-                "false" | "no" | "off" => Ok(Some(BooleanOrString::Boolean(false))),
-                _ => Ok(Some(BooleanOrString::String(s))),
-            }
-        },
-        Some(serde_yaml::Value::Number(_)) => Err(D::Error::custom(
-            "numeric values not supported for boolean/string fields, use 'true'/'false' or a string expression"
-        )),
-        Some(serde_yaml::Value::Sequence(_)) => Err(D::Error::custom(
-            "arrays not supported for boolean/string fields"
-        )),
-        Some(other) => Err(D::Error::invalid_type(
-            serde::de::Unexpected::Other(&format!("{other:?}")),
-            &"boolean literal (true/false) or string expression"
-        )),
-    }
+/// The parts of a role's `meta/main.yml` that affect how it runs
+#[derive(Debug, Default, Deserialize)]
+struct RawRoleMeta {
+    #[serde(default)]
+    dependencies: Option<Vec<RawRole>>,
+    #[serde(deserialize_with = "deserialize_yaml_bool", default)]
+    allow_duplicates: Option<bool>,
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::parser::template::TemplateEngine;
-    use std::collections::HashMap;
-
-    fn create_test_parser() -> (TemplateEngine, HashMap<String, serde_json::Value>) {
-        (TemplateEngine::default(), HashMap::new())
-    }
-
-    #[test]
-    fn test_normalize_yaml_value_boolean_strings() {
-        let (template_engine, extra_vars) = create_test_parser();
-        let parser = PlaybookParser::new(&template_engine, &extra_vars);
-
-        // Test "yes" variations
-        assert_eq!(
-            parser.normalize_yaml_value(serde_json::Value::String("yes".to_string())),
-            serde_json::Value::Bool(true)
-        );
-        assert_eq!(
-            parser.normalize_yaml_value(serde_json::Value::String("YES".to_string())),
-            serde_json::Value::Bool(true)
-        );
-        assert_eq!(
-            parser.normalize_yaml_value(serde_json::Value::String("Yes".to_string())),
-            serde_json::Value::Bool(true)
-        );
-
-        // Test "true" variations
-        assert_eq!(
-            parser.normalize_yaml_value(serde_json::Value::String("true".to_string())),
-            serde_json::Value::Bool(true)
-        );
-        assert_eq!(
-            parser.normalize_yaml_value(serde_json::Value::String("TRUE".to_string())),
-            serde_json::Value::Bool(true)
-        );
-
-        // Test "on" variations
-        assert_eq!(
-            parser.normalize_yaml_value(serde_json::Value::String("on".to_string())),
-            serde_json::Value::Bool(true)
-        );
-        assert_eq!(
-            parser.normalize_yaml_value(serde_json::Value::String("ON".to_string())),
-            serde_json::Value::Bool(true)
-        );
-
-        // Test "no" variations
-        assert_eq!(
-            parser.normalize_yaml_value(serde_json::Value::String("no".to_string())),
-            serde_json::Value::Bool(false)
-        );
-        assert_eq!(
-            parser.normalize_yaml_value(serde_json::Value::String("NO".to_string())),
-            serde_json::Value::Bool(false)
-        );
-
-        // Test "false" variations
-        assert_eq!(
-            parser.normalize_yaml_value(serde_json::Value::String("false".to_string())),
-            serde_json::Value::Bool(false)
-        );
-        assert_eq!(
-            parser.normalize_yaml_value(serde_json::Value::String("FALSE".to_string())),
-            serde_json::Value::Bool(false)
-        );
-
-        // Test "off" variations
-        assert_eq!(
-            parser.normalize_yaml_value(serde_json::Value::String("off".to_string())),
-            serde_json::Value::Bool(false)
-        );
-        assert_eq!(
-            parser.normalize_yaml_value(serde_json::Value::String("OFF".to_string())),
-            serde_json::Value::Bool(false)
-        );
-    }
-
-    #[test]
-    fn test_normalize_yaml_value_non_boolean_strings() {
-        let (template_engine, extra_vars) = create_test_parser();
-        let parser = PlaybookParser::new(&template_engine, &extra_vars);
-
-        // Test regular strings that should not be converted
-        let test_cases = vec![
-            "hello", "world", "maybe", "nope", "truthy", "falsy", "1", "0", "",
-        ];
-
-        for test_case in test_cases {
-            assert_eq!(
-                parser.normalize_yaml_value(serde_json::Value::String(test_case.to_string())),
-                serde_json::Value::String(test_case.to_string()),
-                "String '{test_case}' should not be normalized"
-            );
-        }
-    }
-
-    #[test]
-    fn test_normalize_yaml_value_non_string_types() {
-        let (template_engine, extra_vars) = create_test_parser();
-        let parser = PlaybookParser::new(&template_engine, &extra_vars);
-
-        // Test that non-string values are passed through unchanged
-        let test_cases = vec![
-            serde_json::Value::Bool(true),
-            serde_json::Value::Bool(false),
-            serde_json::Value::Number(serde_json::Number::from(42)),
-            serde_json::Value::Number(serde_json::Number::from_f64(std::f64::consts::PI).unwrap()),
-            serde_json::Value::Null,
-            serde_json::json!({"key": "value"}),
-            serde_json::json!(["item1", "item2"]),
-        ];
-
-        for test_case in test_cases {
-            let result = parser.normalize_yaml_value(test_case.clone());
-            assert_eq!(result, test_case, "Non-string value should be unchanged");
-        }
-    }
 
     #[test]
     fn test_deserialize_notify_valid_cases() {
@@ -1204,22 +1672,21 @@ name: Test task
     }
 
     #[test]
-    fn test_deserialize_notify_error_cases() {
-        // Test invalid type (number)
+    fn test_deserialize_notify_coercions_and_errors() {
+        // Numbers and booleans become strings, as Ansible converts them
         let yaml_number = r#"
 name: Test task
 notify: 123
 "#;
-        let task: Result<RawTask, _> = serde_yaml::from_str(yaml_number);
-        assert!(task.is_err());
+        let task: RawTask = serde_yaml::from_str(yaml_number).unwrap();
+        assert_eq!(task.notify, Some(vec!["123".to_string()]));
 
-        // Test invalid type (boolean)
         let yaml_bool = r#"
 name: Test task
 notify: true
 "#;
-        let task: Result<RawTask, _> = serde_yaml::from_str(yaml_bool);
-        assert!(task.is_err());
+        let task: RawTask = serde_yaml::from_str(yaml_bool).unwrap();
+        assert_eq!(task.notify, Some(vec!["true".to_string()]));
 
         // Test invalid type (object)
         let yaml_object = r#"
@@ -1231,7 +1698,7 @@ notify:
         let task: Result<RawTask, _> = serde_yaml::from_str(yaml_object);
         assert!(task.is_err());
 
-        // Test array with non-string elements
+        // Test array with non-string scalar elements
         let yaml_mixed_array = r#"
 name: Test task
 notify:
@@ -1239,8 +1706,15 @@ notify:
   - 123
   - reload_config
 "#;
-        let task: Result<RawTask, _> = serde_yaml::from_str(yaml_mixed_array);
-        assert!(task.is_err());
+        let task: RawTask = serde_yaml::from_str(yaml_mixed_array).unwrap();
+        assert_eq!(
+            task.notify,
+            Some(vec![
+                "restart_service".to_string(),
+                "123".to_string(),
+                "reload_config".to_string()
+            ])
+        );
 
         // Test array with nested objects
         let yaml_nested_array = r#"
@@ -0,0 +1,515 @@
+//! `requirements.yml` files, as read by `ansible-galaxy install -r`.
+//!
+//! The roles and collections a project pins are compared with those its
+//! playbooks reference, and resolved against what is installed locally to
+//! produce a lockfile.
+
+use crate::parser::collections::{is_builtin_collection, split_fqcn, CollectionIndex};
+use crate::parser::error::ParseError;
+use crate::parser::include::roles::RoleIncludeProcessor;
+use crate::types::parsed::{ParsedPlaybook, ParsedRole, ParsedTask};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Roles and collections pinned by a `requirements.yml`
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Requirements {
+    pub roles: Vec<RoleRequirement>,
+    pub collections: Vec<CollectionRequirement>,
+}
+
+/// A role entry, from Galaxy (`src: namespace.role`) or a source repository
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RoleRequirement {
+    /// Name the role is installed under
+    pub name: String,
+    pub src: Option<String>,
+    pub scm: Option<String>,
+    pub version: Option<String>,
+}
+
+/// A collection entry
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CollectionRequirement {
+    /// `namespace.collection`, or a path or URL for non-Galaxy sources
+    pub name: String,
+    pub version: Option<String>,
+    pub source: Option<String>,
+    /// `galaxy`, `git`, `url`, `file`, `dir` or `subdirs`
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum RawRequirementsFile {
+    // Files without sections list roles only
+    Roles(Vec<RawRoleRequirement>),
+    Sections {
+        #[serde(default)]
+        roles: Option<Vec<RawRoleRequirement>>,
+        #[serde(default)]
+        collections: Option<Vec<RawCollectionRequirement>>,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum RawRoleRequirement {
+    String(String),
+    Object {
+        name: Option<String>,
+        src: Option<String>,
+        scm: Option<String>,
+        #[serde(
+            default,
+            deserialize_with = "crate::parser::keywords::deserialize_string"
+        )]
+        version: Option<String>,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum RawCollectionRequirement {
+    String(String),
+    Object {
+        name: String,
+        #[serde(
+            default,
+            deserialize_with = "crate::parser::keywords::deserialize_string"
+        )]
+        version: Option<String>,
+        source: Option<String>,
+        #[serde(rename = "type")]
+        kind: Option<String>,
+    },
+}
+
+impl FromStr for Requirements {
+    type Err = ParseError;
+
+    fn from_str(content: &str) -> Result<Self, ParseError> {
+        let raw: Option<RawRequirementsFile> = crate::parser::yaml::from_str(content)?;
+        let (roles, collections) = match raw {
+            None => (Vec::new(), Vec::new()),
+            Some(RawRequirementsFile::Roles(roles)) => (roles, Vec::new()),
+            Some(RawRequirementsFile::Sections { roles, collections }) => {
+                (roles.unwrap_or_default(), collections.unwrap_or_default())
+            }
+        };
+
+        Ok(Self {
+            roles: roles
+                .into_iter()
+                .map(RoleRequirement::from_raw)
+                .collect::<Result<_, _>>()?,
+            collections: collections
+                .into_iter()
+                .map(|raw| match raw {
+                    RawCollectionRequirement::String(name) => CollectionRequirement {
+                        name,
+                        ..CollectionRequirement::default()
+                    },
+                    RawCollectionRequirement::Object {
+                        name,
+                        version,
+                        source,
+                        kind,
+                    } => CollectionRequirement {
+                        name,
+                        version,
+                        source,
+                        kind,
+                    },
+                })
+                .collect(),
+        })
+    }
+}
+
+impl Requirements {
+    /// Compare the requirements with the roles and collections `playbook` uses.
+    ///
+    /// Roles that belong to the project, found below `project_dir` and not
+    /// installed by `ansible-galaxy`, need no requirement.
+    pub fn check(&self, playbook: &ParsedPlaybook, project_dir: &Path) -> RequirementsReport {
+        let used = UsedContent::of(playbook);
+
+        let required_roles: BTreeSet<&str> =
+            self.roles.iter().map(|role| role.name.as_str()).collect();
+        let required_collections: BTreeSet<&str> = self
+            .collections
+            .iter()
+            .map(|collection| collection.name.as_str())
+            .collect();
+
+        let missing_roles = used
+            .roles
+            .iter()
+            .filter(|(name, _)| !required_roles.contains(name.as_str()))
+            .filter(|(_, path)| !is_project_role(path.as_deref(), project_dir))
+            .map(|(name, _)| name.clone())
+            .collect();
+        let used_role_names: BTreeSet<&str> =
+            used.roles.iter().map(|(name, _)| name.as_str()).collect();
+
+        RequirementsReport {
+            missing_roles,
+            missing_collections: used
+                .collections
+                .iter()
+                .filter(|name| !required_collections.contains(name.as_str()))
+                .cloned()
+                .collect(),
+            unused_roles: required_roles
+                .iter()
+                .filter(|name| !used_role_names.contains(*name))
+                .map(|name| name.to_string())
+                .collect(),
+            // Collections installed from a URL or path have no name to match
+            unused_collections: required_collections
+                .iter()
+                .filter(|name| !name.contains('/') && !used.collections.contains(**name))
+                .map(|name| name.to_string())
+                .collect(),
+        }
+    }
+
+    /// Resolve every requirement against the roles next to `playbook` or in
+    /// `role_search_paths`, and the installed `collections`
+    pub fn lock(
+        &self,
+        playbook: &Path,
+        role_search_paths: &[PathBuf],
+        collections: &CollectionIndex,
+    ) -> Lockfile {
+        let roles = self
+            .roles
+            .iter()
+            .map(|role| {
+                let path = RoleIncludeProcessor::resolve_role_path(
+                    &role.name,
+                    playbook,
+                    role_search_paths,
+                )
+                .ok();
+                LockedRole {
+                    name: role.name.clone(),
+                    src: role.src.clone(),
+                    required_version: role.version.clone(),
+                    installed_version: path.as_deref().and_then(installed_role_version),
+                    path: path.map(|path| path.to_string_lossy().to_string()),
+                }
+            })
+            .collect();
+
+        let collections = self
+            .collections
+            .iter()
+            .map(|requirement| {
+                let installed = collections.get(&requirement.name);
+                LockedCollection {
+                    name: requirement.name.clone(),
+                    required_version: requirement.version.clone(),
+                    installed_version: installed.and_then(|found| found.version.clone()),
+                    path: installed.map(|found| found.path.to_string_lossy().to_string()),
+                }
+            })
+            .collect();
+
+        Lockfile { roles, collections }
+    }
+}
+
+impl RoleRequirement {
+    fn from_raw(raw: RawRoleRequirement) -> Result<Self, ParseError> {
+        let (name, src, scm, version) = match raw {
+            // `src[,version[,name]]`
+            RawRoleRequirement::String(spec) => {
+                let mut parts = spec.split(',').map(str::trim);
+                let src = parts.next().unwrap_or_default().to_string();
+                let version = parts.next().filter(|v| !v.is_empty()).map(String::from);
+                let name = parts.next().filter(|n| !n.is_empty()).map(String::from);
+                (name, Some(src), None, version)
+            }
+            RawRoleRequirement::Object {
+                name,
+                src,
+                scm,
+                version,
+            } => (name, src, scm, version),
+        };
+
+        // `git+https://...` names the SCM in the source
+        let (scm, src) = match src.as_deref().and_then(|src| src.split_once('+')) {
+            Some((prefix, url)) if scm.is_none() && url.contains("://") => {
+                (Some(prefix.to_string()), Some(url.to_string()))
+            }
+            _ => (scm, src),
+        };
+
+        let name = match (name, src.as_deref()) {
+            (Some(name), _) => name,
+            (None, Some(src)) => role_name_from_src(src),
+            (None, None) => {
+                return Err(ParseError::InvalidStructure {
+                    message: "Role requirement needs a name or src".to_string(),
+                })
+            }
+        };
+
+        Ok(Self {
+            name,
+            src,
+            scm,
+            version,
+        })
+    }
+}
+
+/// Name `ansible-galaxy` installs a role from `src` under: the repository
+/// name for URLs, the source itself for Galaxy roles
+fn role_name_from_src(src: &str) -> String {
+    if !src.contains("://") && !src.contains('@') {
+        return src.to_string();
+    }
+    let trailing = src.rsplit('/').next().unwrap_or(src);
+    let trailing = trailing.split(',').next().unwrap_or(trailing);
+    let trailing = trailing.strip_suffix(".git").unwrap_or(trailing);
+    trailing
+        .strip_suffix(".tar.gz")
+        .unwrap_or(trailing)
+        .to_string()
+}
+
+/// Version recorded by `ansible-galaxy` when it installed the role
+fn installed_role_version(role_path: &Path) -> Option<String> {
+    #[derive(Deserialize)]
+    struct InstallInfo {
+        #[serde(
+            default,
+            deserialize_with = "crate::parser::keywords::deserialize_string"
+        )]
+        version: Option<String>,
+    }
+
+    let content =
+        std::fs::read_to_string(role_path.join("meta").join(".galaxy_install_info")).ok()?;
+    crate::parser::yaml::from_str::<InstallInfo>(&content)
+        .ok()?
+        .version
+}
+
+fn is_project_role(path: Option<&str>, project_dir: &Path) -> bool {
+    let Some(path) = path.map(Path::new) else {
+        return false;
+    };
+    let project_dir = project_dir
+        .canonicalize()
+        .unwrap_or(project_dir.to_path_buf());
+    let path = path.canonicalize().unwrap_or(path.to_path_buf());
+    path.starts_with(&project_dir)
+        && !path
+            .components()
+            .any(|part| part.as_os_str() == "ansible_collections")
+        && !path.join("meta").join(".galaxy_install_info").is_file()
+}
+
+/// Requirements that do not match what a playbook uses
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RequirementsReport {
+    /// Roles the playbook uses that are neither required nor part of the project
+    pub missing_roles: Vec<String>,
+    /// Collections the playbook uses that are not required
+    pub missing_collections: Vec<String>,
+    pub unused_roles: Vec<String>,
+    pub unused_collections: Vec<String>,
+}
+
+impl RequirementsReport {
+    pub fn is_clean(&self) -> bool {
+        self.missing_roles.is_empty()
+            && self.missing_collections.is_empty()
+            && self.unused_roles.is_empty()
+            && self.unused_collections.is_empty()
+    }
+}
+
+/// Requirements resolved to what is installed on disk
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Lockfile {
+    pub roles: Vec<LockedRole>,
+    pub collections: Vec<LockedCollection>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LockedRole {
+    pub name: String,
+    pub src: Option<String>,
+    pub required_version: Option<String>,
+    /// `None` if the role is not installed
+    pub path: Option<String>,
+    pub installed_version: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LockedCollection {
+    pub name: String,
+    pub required_version: Option<String>,
+    /// `None` if the collection is not installed
+    pub path: Option<String>,
+    pub installed_version: Option<String>,
+}
+
+/// Roles, with the path they were loaded from, and collections a playbook uses
+#[derive(Default)]
+struct UsedContent {
+    roles: BTreeSet<(String, Option<String>)>,
+    collections: BTreeSet<String>,
+}
+
+impl UsedContent {
+    fn of(playbook: &ParsedPlaybook) -> Self {
+        let mut used = Self::default();
+        for play in &playbook.plays {
+            for collection in &play.collections {
+                used.add_collection(collection);
+            }
+            for role in &play.roles {
+                used.add_role(role);
+            }
+            for task in play.tasks.iter().chain(&play.handlers) {
+                used.add_task(task);
+            }
+        }
+        used
+    }
+
+    fn add_role(&mut self, role: &ParsedRole) {
+        match split_fqcn(&role.name) {
+            Some((collection, _)) => self.add_collection(collection),
+            None => {
+                self.roles.insert((role.name.clone(), role.path.clone()));
+            }
+        }
+        for dependency in &role.dependencies {
+            self.add_role(dependency);
+        }
+    }
+
+    fn add_task(&mut self, task: &ParsedTask) {
+        if let Some((collection, _)) = split_fqcn(&task.module) {
+            self.add_collection(collection);
+        }
+        let is_role_include = matches!(task.module.as_str(), "include_role" | "import_role")
+            || task.module.ends_with(".include_role")
+            || task.module.ends_with(".import_role");
+        if let Some(name) = task.args.get("name").and_then(|name| name.as_str()) {
+            if is_role_include && !name.contains("{{") {
+                match split_fqcn(name) {
+                    Some((collection, _)) => self.add_collection(collection),
+                    None => {
+                        self.roles.insert((name.to_string(), None));
+                    }
+                }
+            }
+        }
+        if let Some(block) = &task.block {
+            for task in block.block.iter().chain(&block.rescue).chain(&block.always) {
+                self.add_task(task);
+            }
+        }
+    }
+
+    fn add_collection(&mut self, collection: &str) {
+        if !is_builtin_collection(collection) {
+            self.collections.insert(collection.to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_requirements() {
+        let requirements = Requirements::from_str(
+            r#"
+roles:
+  - src: geerlingguy.nginx
+    version: 3.1.4
+  - src: git+https://github.com/acme/ansible-role-base.git
+    version: v1.0
+  - name: web
+    src: https://github.com/acme/web-role
+    scm: git
+  - https://example.com/roles/db.tar.gz,2.0,database
+collections:
+  - community.general
+  - name: acme.web
+    version: ">=1.0.0,<2.0.0"
+    source: https://galaxy.example.com
+  - name: https://github.com/acme/collection.git
+    type: git
+    version: 1.0
+"#,
+        )
+        .unwrap();
+
+        let roles: Vec<(&str, Option<&str>, Option<&str>)> = requirements
+            .roles
+            .iter()
+            .map(|r| (r.name.as_str(), r.scm.as_deref(), r.version.as_deref()))
+            .collect();
+        assert_eq!(
+            roles,
+            vec![
+                ("geerlingguy.nginx", None, Some("3.1.4")),
+                ("ansible-role-base", Some("git"), Some("v1.0")),
+                ("web", Some("git"), None),
+                ("database", None, Some("2.0")),
+            ]
+        );
+        assert_eq!(
+            requirements.roles[1].src.as_deref(),
+            Some("https://github.com/acme/ansible-role-base.git")
+        );
+
+        assert_eq!(requirements.collections[0].name, "community.general");
+        assert_eq!(
+            requirements.collections[1].version.as_deref(),
+            Some(">=1.0.0,<2.0.0")
+        );
+        assert_eq!(requirements.collections[2].kind.as_deref(), Some("git"));
+        assert_eq!(requirements.collections[2].version.as_deref(), Some("1.0"));
+    }
+
+    #[test]
+    fn test_parse_legacy_role_list() {
+        let requirements =
+            Requirements::from_str("- geerlingguy.mysql\n- src: acme.base\n  name: base\n")
+                .unwrap();
+        assert_eq!(requirements.roles[0].name, "geerlingguy.mysql");
+        assert_eq!(requirements.roles[1].name, "base");
+        assert!(requirements.collections.is_empty());
+        assert!(Requirements::from_str("").unwrap().roles.is_empty());
+        assert!(Requirements::from_str("roles:\n  - version: 1\n").is_err());
+    }
+
+    #[test]
+    fn test_role_name_from_src() {
+        assert_eq!(role_name_from_src("geerlingguy.nginx"), "geerlingguy.nginx");
+        assert_eq!(
+            role_name_from_src("git@github.com:acme/role-x.git"),
+            "role-x"
+        );
+        assert_eq!(
+            role_name_from_src("https://example.com/roles/db.tar.gz"),
+            "db"
+        );
+    }
+}
@@ -0,0 +1,250 @@
+use crate::types::parsed::SourceLocation;
+use std::collections::HashMap;
+use yaml_rust2::parser::{Event, MarkedEventReceiver, Parser as YamlParser};
+use yaml_rust2::scanner::Marker;
+
+/// One step from a YAML node to one of its children
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum NodeKey {
+    Index(usize),
+    Key(String),
+}
+
+/// Line and column of every node in a YAML document, addressed by the keys and
+/// indices leading to it from the document root.
+///
+/// serde_yaml does not expose positions, so the document is scanned a second
+/// time with yaml-rust2's event parser. Mapping values are located at their key
+/// and sequence items at their first character.
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    file: String,
+    nodes: HashMap<Vec<NodeKey>, (usize, usize)>,
+}
+
+impl SourceMap {
+    /// Scan `content`. Documents that fail to scan yield an empty map; the
+    /// actual syntax error is reported by the serde_yaml pass.
+    pub fn parse(file: impl Into<String>, content: &str) -> Self {
+        let mut builder = SourceMapBuilder::default();
+        if YamlParser::new_from_str(content)
+            .load(&mut builder, false)
+            .is_err()
+        {
+            builder.nodes.clear();
+        }
+
+        Self {
+            file: file.into(),
+            nodes: builder.nodes,
+        }
+    }
+
+    pub fn file(&self) -> &str {
+        &self.file
+    }
+
+    pub fn root(&self) -> SourceNode<'_> {
+        SourceNode {
+            map: self,
+            path: Vec::new(),
+        }
+    }
+
+    fn location(&self, path: &[NodeKey]) -> Option<SourceLocation> {
+        self.nodes.get(path).map(|&(line, column)| SourceLocation {
+            file: self.file.clone(),
+            line,
+            column,
+        })
+    }
+}
+
+/// Cursor pointing at a node of a [`SourceMap`]
+#[derive(Debug, Clone)]
+pub struct SourceNode<'m> {
+    map: &'m SourceMap,
+    path: Vec<NodeKey>,
+}
+
+impl<'m> SourceNode<'m> {
+    pub fn index(&self, index: usize) -> Self {
+        self.child(NodeKey::Index(index))
+    }
+
+    pub fn key(&self, key: &str) -> Self {
+        self.child(NodeKey::Key(key.to_string()))
+    }
+
+    fn child(&self, key: NodeKey) -> Self {
+        let mut path = self.path.clone();
+        path.push(key);
+        Self {
+            map: self.map,
+            path,
+        }
+    }
+
+    pub fn location(&self) -> Option<SourceLocation> {
+        self.map.location(&self.path)
+    }
+}
+
+enum Frame {
+    Sequence {
+        next: usize,
+    },
+    Mapping {
+        pending_key: Option<(NodeKey, Marker)>,
+        /// Set when the mapping's own position should be taken from its first key
+        locate_at_first_key: bool,
+    },
+}
+
+#[derive(Default)]
+struct SourceMapBuilder {
+    nodes: HashMap<Vec<NodeKey>, (usize, usize)>,
+    path: Vec<NodeKey>,
+    frames: Vec<Frame>,
+    done: bool,
+}
+
+impl SourceMapBuilder {
+    fn record(&mut self, path: &[NodeKey], mark: Marker) {
+        // yaml-rust2 lines are 1-based and columns 0-based
+        self.nodes
+            .insert(path.to_vec(), (mark.line(), mark.col() + 1));
+    }
+
+    /// Handle the start of a scalar, alias or collection node
+    fn enter_node(&mut self, mark: Marker, scalar: Option<&str>, container: bool) {
+        let (key, location) = match self.frames.last_mut() {
+            None => (None, mark),
+            Some(Frame::Sequence { next }) => {
+                *next += 1;
+                (Some(NodeKey::Index(*next - 1)), mark)
+            }
+            Some(Frame::Mapping { pending_key, .. }) if pending_key.is_some() => {
+                let (key, key_mark) = pending_key.take().unwrap();
+                (Some(key), key_mark)
+            }
+            Some(Frame::Mapping {
+                pending_key,
+                locate_at_first_key,
+            }) => {
+                // This node is a key of the mapping
+                let relocate = std::mem::take(locate_at_first_key);
+                *pending_key = Some((NodeKey::Key(scalar.unwrap_or_default().to_string()), mark));
+                if relocate {
+                    let path = self.path.clone();
+                    self.record(&path, mark);
+                }
+                if container {
+                    // Complex keys are rare; their children are tracked under an empty key
+                    self.path.push(NodeKey::Key(String::new()));
+                }
+                return;
+            }
+        };
+
+        let mut path = self.path.clone();
+        path.extend(key);
+        self.record(&path, location);
+        if container {
+            self.path = path;
+        }
+    }
+}
+
+impl MarkedEventReceiver for SourceMapBuilder {
+    fn on_event(&mut self, event: Event, mark: Marker) {
+        if self.done {
+            return;
+        }
+
+        match event {
+            Event::Scalar(value, ..) => self.enter_node(mark, Some(&value), false),
+            Event::Alias(_) => self.enter_node(mark, None, false),
+            Event::SequenceStart(..) => {
+                self.enter_node(mark, None, true);
+                self.frames.push(Frame::Sequence { next: 0 });
+            }
+            Event::MappingStart(..) => {
+                let is_value = matches!(
+                    self.frames.last(),
+                    Some(Frame::Mapping {
+                        pending_key: Some(_),
+                        ..
+                    })
+                );
+                self.enter_node(mark, None, true);
+                self.frames.push(Frame::Mapping {
+                    pending_key: None,
+                    locate_at_first_key: !is_value,
+                });
+            }
+            Event::SequenceEnd | Event::MappingEnd => {
+                self.frames.pop();
+                if !self.frames.is_empty() {
+                    self.path.pop();
+                }
+            }
+            Event::DocumentEnd => self.done = true,
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locates_nested_nodes() {
+        let content = "\
+- name: play
+  hosts: all
+  tasks:
+    - name: first
+      debug:
+        msg: hi
+    -   name: second
+  vars:
+    port: 80
+";
+        let map = SourceMap::parse("site.yml", content);
+        let play = map.root().index(0);
+
+        let location = play.location().unwrap();
+        assert_eq!((location.line, location.column), (1, 3));
+        assert_eq!(location.file, "site.yml");
+
+        let tasks = play.key("tasks");
+        let first = tasks.index(0).location().unwrap();
+        assert_eq!((first.line, first.column), (4, 7));
+        let second = tasks.index(1).location().unwrap();
+        assert_eq!((second.line, second.column), (7, 9));
+
+        let msg = tasks.index(0).key("debug").key("msg").location().unwrap();
+        assert_eq!((msg.line, msg.column), (6, 9));
+
+        let port = play.key("vars").key("port").location().unwrap();
+        assert_eq!((port.line, port.column), (9, 5));
+
+        assert!(tasks.index(2).location().is_none());
+    }
+
+    #[test]
+    fn test_flow_style_and_json() {
+        let map = SourceMap::parse("hosts.json", "{\"web\": {\"hosts\": [\"h1\",\n \"h2\"]}}");
+        let hosts = map.root().key("web").key("hosts");
+        let h2 = hosts.index(1).location().unwrap();
+        assert_eq!((h2.line, h2.column), (2, 2));
+    }
+
+    #[test]
+    fn test_invalid_yaml_yields_empty_map() {
+        let map = SourceMap::parse("bad.yml", "key: [unclosed");
+        assert!(map.root().location().is_none());
+    }
+}
@@ -0,0 +1,764 @@
+use crate::types::parsed::{
+    BinaryOp, CallArg, ConditionExpr, ParsedInventory, ParsedPlay, ParsedPlaybook, ParsedRole,
+    ParsedTask, UnaryOp,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+
+/// What to do with tasks whose `when` conditions are false at parse time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StaticConditionMode {
+    /// Keep such tasks but mark them as `statically_skipped`
+    Flag,
+    /// Remove such tasks and emptied blocks, and drop conditions that always hold
+    Prune,
+}
+
+/// Evaluates `when` conditions whose inputs are all known at parse time.
+///
+/// Known inputs are extra vars, play vars, task and block vars, and inventory
+/// variables that have the same value on every host. Variables a play may set at
+/// runtime (`register`, `set_fact`, `include_vars`) are treated as unknown unless
+/// overridden by extra vars, as are facts and magic variables. So are the
+/// parameters, vars and defaults of the play's roles, which take precedence over
+/// play vars in their own tasks, and every play and inventory variable of a play
+/// with `vars_files`. Conditions that depend on anything unknown are left intact.
+#[derive(Debug, Clone)]
+pub struct StaticConditions<'a> {
+    mode: StaticConditionMode,
+    extra_vars: &'a HashMap<String, Value>,
+}
+
+impl<'a> StaticConditions<'a> {
+    pub fn new(mode: StaticConditionMode, extra_vars: &'a HashMap<String, Value>) -> Self {
+        Self { mode, extra_vars }
+    }
+
+    /// Evaluate the conditions of every task in the playbook. Handlers are left alone.
+    pub fn apply(&self, playbook: &mut ParsedPlaybook) {
+        let inventory_vars = common_host_vars(&playbook.inventory);
+        // Facts set by one play stay set for the plays after it
+        let runtime = runtime_vars(&playbook.plays);
+        for play in &mut playbook.plays {
+            let unknown = runtime.with_names(role_var_names(&play.roles));
+            let vars = self.play_vars(play, &inventory_vars, &unknown);
+            self.apply_to_tasks(&mut play.tasks, &vars, &unknown);
+        }
+    }
+
+    /// Variables known for a whole play, in increasing precedence
+    fn play_vars(
+        &self,
+        play: &ParsedPlay,
+        inventory_vars: &HashMap<String, Value>,
+        runtime: &RuntimeVars,
+    ) -> HashMap<String, Value> {
+        // Files loaded when the play runs may override any play or inventory variable
+        if !play.vars_files.is_empty() {
+            return self.extra_vars.clone();
+        }
+
+        let mut vars = inventory_vars.clone();
+        vars.extend(
+            play.vars
+                .iter()
+                .filter(|(_, value)| !is_templated(value))
+                .map(|(k, v)| (k.clone(), v.clone())),
+        );
+
+        vars.retain(|name, _| !runtime.contains(name));
+
+        vars.extend(self.extra_vars.clone());
+        vars
+    }
+
+    fn apply_to_tasks(
+        &self,
+        tasks: &mut Vec<ParsedTask>,
+        vars: &HashMap<String, Value>,
+        runtime: &RuntimeVars,
+    ) {
+        for task in tasks.iter_mut() {
+            let task_vars = self.task_vars(task, vars, runtime);
+            let results: Vec<Option<bool>> = task
+                .when
+                .iter()
+                .map(|condition| {
+                    condition
+                        .ast
+                        .as_ref()
+                        .and_then(|ast| evaluate(ast, &task_vars).truthy())
+                })
+                .collect();
+
+            if results.contains(&Some(false)) {
+                task.statically_skipped = true;
+                continue;
+            }
+
+            if self.mode == StaticConditionMode::Prune {
+                let mut results = results.into_iter();
+                task.when.retain(|_| results.next() != Some(Some(true)));
+            }
+
+            if let Some(block) = &mut task.block {
+                self.apply_to_tasks(&mut block.block, &task_vars, runtime);
+                self.apply_to_tasks(&mut block.rescue, &task_vars, runtime);
+                self.apply_to_tasks(&mut block.always, &task_vars, runtime);
+            }
+        }
+
+        if self.mode == StaticConditionMode::Prune {
+            // Blocks go too once none of their sections has tasks left
+            tasks.retain(|task| {
+                !task.statically_skipped
+                    && task.block.as_ref().is_none_or(|block| {
+                        !(block.block.is_empty()
+                            && block.rescue.is_empty()
+                            && block.always.is_empty())
+                    })
+            });
+        }
+    }
+
+    /// Overlay a task's own `vars` on the variables known around it
+    fn task_vars(
+        &self,
+        task: &ParsedTask,
+        vars: &HashMap<String, Value>,
+        runtime: &RuntimeVars,
+    ) -> HashMap<String, Value> {
+        let mut task_vars = vars.clone();
+        for (name, value) in &task.vars {
+            if self.extra_vars.contains_key(name) || runtime.contains(name) {
+                continue;
+            }
+            if is_templated(value) {
+                task_vars.remove(name);
+            } else {
+                task_vars.insert(name.clone(), value.clone());
+            }
+        }
+        if task.loop_items.is_some() {
+            task_vars.remove("item");
+        }
+        task_vars
+    }
+}
+
+/// Names a play may assign while it runs
+#[derive(Clone)]
+enum RuntimeVars {
+    Names(HashSet<String>),
+    /// The play loads variables whose names cannot be known statically
+    Unknown,
+}
+
+impl RuntimeVars {
+    fn contains(&self, name: &str) -> bool {
+        match self {
+            RuntimeVars::Names(names) => names.contains(name),
+            RuntimeVars::Unknown => true,
+        }
+    }
+
+    /// These names and `more`
+    fn with_names(&self, more: HashSet<String>) -> RuntimeVars {
+        match self {
+            RuntimeVars::Names(names) => RuntimeVars::Names(names.union(&more).cloned().collect()),
+            RuntimeVars::Unknown => RuntimeVars::Unknown,
+        }
+    }
+}
+
+/// Names set by the parameters, vars and defaults of `roles` and their dependencies
+fn role_var_names(roles: &[ParsedRole]) -> HashSet<String> {
+    let mut names = HashSet::new();
+    for role in roles {
+        names.extend(role.vars.keys().cloned());
+        names.extend(role.role_vars.keys().cloned());
+        names.extend(role.defaults.keys().cloned());
+        names.extend(role_var_names(&role.dependencies));
+    }
+    names
+}
+
+/// Names assigned by the tasks and handlers of any play, including the tasks
+/// of every file a deferred include may load
+fn runtime_vars(plays: &[ParsedPlay]) -> RuntimeVars {
+    fn collect(tasks: &[ParsedTask], names: &mut HashSet<String>) -> bool {
+        for task in tasks {
+            if let Some(register) = &task.register {
+                names.insert(register.clone());
+            }
+            match task.module.rsplit('.').next().unwrap_or_default() {
+                "set_fact" => {
+                    for (key, value) in &task.args {
+                        match (key.as_str(), value) {
+                            ("cacheable", _) => {}
+                            ("_raw_params", Value::String(params)) => {
+                                names.extend(raw_param_keys(params))
+                            }
+                            ("_raw_params", _) => return false,
+                            _ => {
+                                names.insert(key.clone());
+                            }
+                        }
+                    }
+                }
+                "include_vars" => match task.args.get("name") {
+                    Some(Value::String(name)) => {
+                        names.insert(name.clone());
+                    }
+                    _ => return false,
+                },
+                _ => {}
+            }
+            if let Some(block) = &task.block {
+                if !collect(&block.block, names)
+                    || !collect(&block.rescue, names)
+                    || !collect(&block.always, names)
+                {
+                    return false;
+                }
+            }
+            if let Some(deferred) = &task.deferred_include {
+                if !deferred
+                    .candidates
+                    .iter()
+                    .all(|candidate| collect(&candidate.tasks, names))
+                {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    let mut names = HashSet::new();
+    let known = plays
+        .iter()
+        .all(|play| collect(&play.tasks, &mut names) && collect(&play.handlers, &mut names));
+    if known {
+        RuntimeVars::Names(names)
+    } else {
+        RuntimeVars::Unknown
+    }
+}
+
+/// Keys of free-form `k=v` arguments, e.g. `set_fact: a=1 b="two words"`
+fn raw_param_keys(params: &str) -> Vec<String> {
+    let mut keys = Vec::new();
+    let mut token = String::new();
+    let mut quote = None;
+    for c in params.chars().chain(std::iter::once(' ')) {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c.is_whitespace() => {
+                if let Some((key, _)) = token.split_once('=') {
+                    keys.push(key.to_string());
+                }
+                token.clear();
+                continue;
+            }
+            None => {}
+        }
+        token.push(c);
+    }
+    keys
+}
+
+/// Inventory variables that have the same value on every host
+fn common_host_vars(inventory: &ParsedInventory) -> HashMap<String, Value> {
+    let mut hosts = inventory.hosts.values();
+    let Some(first) = hosts.next() else {
+        return HashMap::new();
+    };
+
+    let mut common = first.vars.clone();
+    for host in hosts {
+        common.retain(|name, value| host.vars.get(name) == Some(value));
+    }
+    common.retain(|_, value| !is_templated(value));
+    common
+}
+
+fn is_templated(value: &Value) -> bool {
+    match value {
+        Value::String(s) => s.contains("{{") || s.contains("{%"),
+        Value::Array(items) => items.iter().any(is_templated),
+        Value::Object(map) => map.values().any(is_templated),
+        _ => false,
+    }
+}
+
+/// Result of evaluating an expression with partial knowledge
+#[derive(Debug, Clone, PartialEq)]
+pub enum StaticValue {
+    Known(Value),
+    /// Known not to exist, e.g. a missing key of a known mapping
+    Undefined,
+    /// Depends on data only available at runtime
+    Unknown,
+}
+
+impl StaticValue {
+    /// Jinja truthiness, if it can be decided
+    pub fn truthy(&self) -> Option<bool> {
+        match self {
+            StaticValue::Known(value) => Some(is_truthy(value)),
+            StaticValue::Undefined | StaticValue::Unknown => None,
+        }
+    }
+
+    fn known(self) -> Option<Value> {
+        match self {
+            StaticValue::Known(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+impl From<Option<Value>> for StaticValue {
+    fn from(value: Option<Value>) -> Self {
+        value.map_or(StaticValue::Unknown, StaticValue::Known)
+    }
+}
+
+/// Evaluate a condition expression against the variables known at parse time
+pub fn evaluate(expr: &ConditionExpr, vars: &HashMap<String, Value>) -> StaticValue {
+    use StaticValue::*;
+
+    match expr {
+        ConditionExpr::Literal { value } => Known(value.clone()),
+        ConditionExpr::Name { name } => vars.get(name).cloned().into(),
+        ConditionExpr::GetAttr { object, attr } => match evaluate(object, vars) {
+            Known(Value::Object(map)) => map.get(attr).cloned().map_or(Undefined, Known),
+            Known(_) | Undefined => Unknown,
+            Unknown => Unknown,
+        },
+        ConditionExpr::GetItem { object, index } => {
+            match (evaluate(object, vars), evaluate(index, vars)) {
+                (Known(Value::Object(map)), Known(Value::String(key))) => {
+                    map.get(&key).cloned().map_or(Undefined, Known)
+                }
+                (Known(Value::Array(items)), Known(Value::Number(n))) => {
+                    let len = items.len() as i64;
+                    match n.as_i64() {
+                        Some(i) if (-len..len).contains(&i) => {
+                            Known(items[i.rem_euclid(len) as usize].clone())
+                        }
+                        Some(_) => Undefined,
+                        None => Unknown,
+                    }
+                }
+                _ => Unknown,
+            }
+        }
+        ConditionExpr::List { items } => items
+            .iter()
+            .map(|item| evaluate(item, vars).known())
+            .collect::<Option<Vec<_>>>()
+            .map(Value::Array)
+            .into(),
+        ConditionExpr::Dict { entries } => entries
+            .iter()
+            .map(|(key, value)| match evaluate(key, vars).known()? {
+                Value::String(key) => Some((key, evaluate(value, vars).known()?)),
+                _ => None,
+            })
+            .collect::<Option<serde_json::Map<_, _>>>()
+            .map(Value::Object)
+            .into(),
+        ConditionExpr::Unary { op, operand } => {
+            let operand = evaluate(operand, vars);
+            match op {
+                UnaryOp::Not => operand.truthy().map(|b| Value::Bool(!b)).into(),
+                UnaryOp::Neg => operand
+                    .known()
+                    .and_then(|v| arithmetic(&Value::from(0), &v, BinaryOp::Sub))
+                    .into(),
+                UnaryOp::Pos => operand.known().filter(Value::is_number).into(),
+            }
+        }
+        ConditionExpr::Binary { op, left, right } => {
+            evaluate_binary(*op, evaluate(left, vars), || evaluate(right, vars))
+        }
+        ConditionExpr::Test {
+            operand,
+            name,
+            args,
+            negated,
+        } => match evaluate_test(name, evaluate(operand, vars), args, vars) {
+            Some(result) => Known(Value::Bool(result != *negated)),
+            None => Unknown,
+        },
+        ConditionExpr::Filter {
+            operand,
+            name,
+            args,
+        } => evaluate_filter(name, evaluate(operand, vars), args, vars),
+        ConditionExpr::IfElse {
+            condition,
+            then,
+            otherwise,
+        } => match evaluate(condition, vars).truthy() {
+            Some(true) => evaluate(then, vars),
+            Some(false) => match otherwise {
+                Some(otherwise) => evaluate(otherwise, vars),
+                None => Undefined,
+            },
+            None => Unknown,
+        },
+        ConditionExpr::Call { .. } => Unknown,
+    }
+}
+
+fn evaluate_binary(
+    op: BinaryOp,
+    left: StaticValue,
+    right: impl FnOnce() -> StaticValue,
+) -> StaticValue {
+    use StaticValue::*;
+
+    // `and` / `or` can be decided by one side alone
+    match op {
+        BinaryOp::And => {
+            return match (left.truthy(), right().truthy()) {
+                (Some(false), _) | (_, Some(false)) => Known(Value::Bool(false)),
+                (Some(true), Some(true)) => Known(Value::Bool(true)),
+                _ => Unknown,
+            }
+        }
+        BinaryOp::Or => {
+            return match (left.truthy(), right().truthy()) {
+                (Some(true), _) | (_, Some(true)) => Known(Value::Bool(true)),
+                (Some(false), Some(false)) => Known(Value::Bool(false)),
+                _ => Unknown,
+            }
+        }
+        _ => {}
+    }
+
+    let (Known(left), Known(right)) = (left, right()) else {
+        return Unknown;
+    };
+
+    let result = match op {
+        BinaryOp::Eq => Some(Value::Bool(values_equal(&left, &right))),
+        BinaryOp::Ne => Some(Value::Bool(!values_equal(&left, &right))),
+        BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge => {
+            compare(&left, &right).map(|ordering| {
+                Value::Bool(match op {
+                    BinaryOp::Lt => ordering.is_lt(),
+                    BinaryOp::Le => ordering.is_le(),
+                    BinaryOp::Gt => ordering.is_gt(),
+                    _ => ordering.is_ge(),
+                })
+            })
+        }
+        BinaryOp::In => contains(&right, &left).map(Value::Bool),
+        BinaryOp::NotIn => contains(&right, &left).map(|found| Value::Bool(!found)),
+        BinaryOp::Concat => Some(Value::String(format!(
+            "{}{}",
+            to_jinja_string(&left),
+            to_jinja_string(&right)
+        ))),
+        BinaryOp::Add => match (&left, &right) {
+            (Value::String(a), Value::String(b)) => Some(Value::String(format!("{a}{b}"))),
+            (Value::Array(a), Value::Array(b)) => {
+                Some(Value::Array(a.iter().chain(b).cloned().collect()))
+            }
+            _ => arithmetic(&left, &right, op),
+        },
+        _ => arithmetic(&left, &right, op),
+    };
+    result.into()
+}
+
+fn evaluate_test(
+    name: &str,
+    operand: StaticValue,
+    args: &[CallArg],
+    vars: &HashMap<String, Value>,
+) -> Option<bool> {
+    let name = name.rsplit('.').next().unwrap_or(name);
+    match (name, &operand) {
+        ("defined", StaticValue::Known(_)) | ("undefined", StaticValue::Undefined) => {
+            return Some(true)
+        }
+        ("defined", StaticValue::Undefined) | ("undefined", StaticValue::Known(_)) => {
+            return Some(false)
+        }
+        _ => {}
+    }
+
+    let value = operand.known()?;
+    let arg = |i: usize| {
+        args.get(i)
+            .filter(|arg| arg.name.is_none())
+            .and_then(|arg| evaluate(&arg.value, vars).known())
+    };
+
+    Some(match name {
+        "none" => value.is_null(),
+        "true" => value == Value::Bool(true),
+        "false" => value == Value::Bool(false),
+        "boolean" => value.is_boolean(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "float" => value.is_f64(),
+        "mapping" => value.is_object(),
+        "sequence" | "iterable" => value.is_array() || value.is_object() || value.is_string(),
+        "truthy" => is_truthy(&value),
+        "falsy" => !is_truthy(&value),
+        "even" | "odd" => {
+            let n = value.as_i64()?;
+            (n % 2 == 0) == (name == "even")
+        }
+        "divisibleby" => {
+            let divisor = arg(0)?.as_i64().filter(|d| *d != 0)?;
+            value.as_i64()? % divisor == 0
+        }
+        "eq" | "equalto" | "sameas" | "==" => values_equal(&value, &arg(0)?),
+        "ne" | "!=" => !values_equal(&value, &arg(0)?),
+        "lt" | "lessthan" | "<" => compare(&value, &arg(0)?)?.is_lt(),
+        "le" | "<=" => compare(&value, &arg(0)?)?.is_le(),
+        "gt" | "greaterthan" | ">" => compare(&value, &arg(0)?)?.is_gt(),
+        "ge" | ">=" => compare(&value, &arg(0)?)?.is_ge(),
+        "in" => contains(&arg(0)?, &value)?,
+        _ => return None,
+    })
+}
+
+fn evaluate_filter(
+    name: &str,
+    operand: StaticValue,
+    args: &[CallArg],
+    vars: &HashMap<String, Value>,
+) -> StaticValue {
+    let name = name.rsplit('.').next().unwrap_or(name);
+    let arg = |i: usize| {
+        args.get(i)
+            .filter(|arg| arg.name.is_none())
+            .map(|arg| evaluate(&arg.value, vars))
+            .unwrap_or(StaticValue::Unknown)
+    };
+
+    if matches!(name, "default" | "d") {
+        let use_default = match &operand {
+            StaticValue::Undefined => Some(true),
+            StaticValue::Known(value) => match args.get(1) {
+                // `default(value, true)` also replaces falsy values
+                Some(_) => arg(1).truthy().map(|boolean| boolean && !is_truthy(value)),
+                None => Some(false),
+            },
+            StaticValue::Unknown => None,
+        };
+        return match use_default {
+            Some(true) if args.is_empty() => StaticValue::Known(Value::String(String::new())),
+            Some(true) => arg(0),
+            Some(false) => operand,
+            None => StaticValue::Unknown,
+        };
+    }
+
+    let Some(value) = operand.known() else {
+        return StaticValue::Unknown;
+    };
+
+    let result = match name {
+        "bool" => Some(Value::Bool(match &value {
+            Value::Bool(b) => *b,
+            Value::Number(n) => n.as_f64() == Some(1.0),
+            Value::String(s) => matches!(
+                s.trim().to_lowercase().as_str(),
+                "yes" | "on" | "1" | "true" | "y" | "t"
+            ),
+            _ => false,
+        })),
+        "length" | "count" => match &value {
+            Value::String(s) => Some(Value::from(s.chars().count())),
+            Value::Array(items) => Some(Value::from(items.len())),
+            Value::Object(map) => Some(Value::from(map.len())),
+            _ => None,
+        },
+        "lower" => value.as_str().map(|s| Value::String(s.to_lowercase())),
+        "upper" => value.as_str().map(|s| Value::String(s.to_uppercase())),
+        "trim" => value.as_str().map(|s| Value::String(s.trim().to_string())),
+        "string" => Some(Value::String(to_jinja_string(&value))),
+        "int" => match &value {
+            Value::Number(n) => n
+                .as_i64()
+                .or_else(|| n.as_f64().map(|f| f as i64))
+                .map(Value::from),
+            Value::String(s) => Some(Value::from(s.trim().parse::<i64>().unwrap_or(0))),
+            Value::Bool(b) => Some(Value::from(*b as i64)),
+            _ => None,
+        },
+        "float" => match &value {
+            Value::Number(n) => n.as_f64().map(Value::from),
+            Value::String(s) => Some(Value::from(s.trim().parse::<f64>().unwrap_or(0.0))),
+            _ => None,
+        },
+        _ => None,
+    };
+    result.into()
+}
+
+/// Jinja truthiness of a value
+fn is_truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Number(n) => n.as_f64().is_some_and(|f| f != 0.0),
+        Value::String(s) => !s.is_empty(),
+        Value::Array(items) => !items.is_empty(),
+        Value::Object(map) => !map.is_empty(),
+    }
+}
+
+fn values_equal(left: &Value, right: &Value) -> bool {
+    match (left, right) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64() == b.as_f64(),
+        _ => left == right,
+    }
+}
+
+fn compare(left: &Value, right: &Value) -> Option<std::cmp::Ordering> {
+    match (left, right) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64()?.partial_cmp(&b.as_f64()?),
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        _ => None,
+    }
+}
+
+fn contains(container: &Value, item: &Value) -> Option<bool> {
+    match (container, item) {
+        (Value::Array(items), _) => Some(items.iter().any(|i| values_equal(i, item))),
+        (Value::Object(map), Value::String(key)) => Some(map.contains_key(key)),
+        (Value::String(haystack), Value::String(needle)) => {
+            Some(haystack.contains(needle.as_str()))
+        }
+        _ => None,
+    }
+}
+
+fn arithmetic(left: &Value, right: &Value, op: BinaryOp) -> Option<Value> {
+    let (Value::Number(a), Value::Number(b)) = (left, right) else {
+        return None;
+    };
+
+    if let (Some(a), Some(b)) = (a.as_i64(), b.as_i64()) {
+        let result = match op {
+            BinaryOp::Add => a.checked_add(b),
+            BinaryOp::Sub => a.checked_sub(b),
+            BinaryOp::Mul => a.checked_mul(b),
+            BinaryOp::FloorDiv if b != 0 => Some(a.div_euclid(b)),
+            BinaryOp::Rem if b != 0 => Some(a.rem_euclid(b)),
+            BinaryOp::Pow => u32::try_from(b).ok().and_then(|b| a.checked_pow(b)),
+            _ => None,
+        };
+        if let Some(result) = result {
+            return Some(Value::from(result));
+        }
+    }
+
+    let (a, b) = (a.as_f64()?, b.as_f64()?);
+    let result = match op {
+        BinaryOp::Add => a + b,
+        BinaryOp::Sub => a - b,
+        BinaryOp::Mul => a * b,
+        BinaryOp::Div if b != 0.0 => a / b,
+        BinaryOp::FloorDiv if b != 0.0 => (a / b).floor(),
+        BinaryOp::Rem if b != 0.0 => a.rem_euclid(b),
+        BinaryOp::Pow => a.powf(b),
+        _ => return None,
+    };
+    serde_json::Number::from_f64(result).map(Value::Number)
+}
+
+fn to_jinja_string(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null => "None".to_string(),
+        Value::Bool(true) => "True".to_string(),
+        Value::Bool(false) => "False".to_string(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::condition::parse_condition;
+    use serde_json::json;
+
+    fn eval(expression: &str, vars: &HashMap<String, Value>) -> Option<bool> {
+        let condition = parse_condition(expression, "test").unwrap();
+        evaluate(condition.ast.as_ref().unwrap(), vars).truthy()
+    }
+
+    fn vars() -> HashMap<String, Value> {
+        HashMap::from([
+            ("env".to_string(), json!("prod")),
+            ("enabled".to_string(), json!("yes")),
+            ("ports".to_string(), json!([80, 443])),
+            ("app".to_string(), json!({"name": "web", "replicas": 3})),
+        ])
+    }
+
+    #[test]
+    fn test_raw_param_keys() {
+        assert_eq!(
+            raw_param_keys("a=1 b=\"two words=x\" c='{{ d }}'"),
+            vec!["a", "b", "c"]
+        );
+    }
+
+    #[test]
+    fn test_known_conditions() {
+        let vars = vars();
+        assert_eq!(eval("env == 'prod'", &vars), Some(true));
+        assert_eq!(eval("env != 'prod'", &vars), Some(false));
+        assert_eq!(eval("enabled | bool", &vars), Some(true));
+        assert_eq!(eval("443 in ports", &vars), Some(true));
+        assert_eq!(
+            eval("app.replicas > 2 and app.name == 'web'", &vars),
+            Some(true)
+        );
+        assert_eq!(eval("ports | length == 3", &vars), Some(false));
+        assert_eq!(eval("app.missing is defined", &vars), Some(false));
+        assert_eq!(eval("app.missing | default(1) == 1", &vars), Some(true));
+        assert_eq!(eval("false", &vars), Some(false));
+    }
+
+    #[test]
+    fn test_runtime_conditions_stay_unknown() {
+        let vars = vars();
+        assert_eq!(eval("ansible_os_family == 'Debian'", &vars), None);
+        assert_eq!(eval("result.rc == 0", &vars), None);
+        assert_eq!(eval("undefined_var is defined", &vars), None);
+        assert_eq!(eval("lookup('env', 'HOME') == '/root'", &vars), None);
+        assert_eq!(eval("env == 'prod' and result is changed", &vars), None);
+    }
+
+    #[test]
+    fn test_partial_logic_short_circuits() {
+        let vars = vars();
+        assert_eq!(
+            eval("env == 'dev' and ansible_os_family == 'Debian'", &vars),
+            Some(false)
+        );
+        assert_eq!(
+            eval("env == 'prod' or ansible_os_family == 'Debian'", &vars),
+            Some(true)
+        );
+        assert_eq!(eval("not (env == 'dev' and unknown)", &vars), Some(true));
+    }
+}
@@ -0,0 +1,266 @@
+use crate::types::parsed::{ParsedPlay, ParsedPlaybook, ParsedTask};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+const ALWAYS: &str = "always";
+const NEVER: &str = "never";
+const ALL: &str = "all";
+const TAGGED: &str = "tagged";
+const UNTAGGED: &str = "untagged";
+
+/// Tag selection equivalent to `ansible-playbook --tags` / `--skip-tags`.
+///
+/// Tags are evaluated against a task's effective tags, i.e. its own tags plus
+/// those inherited from the play, roles, blocks and static imports around it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TagSelection {
+    pub only_tags: Vec<String>,
+    pub skip_tags: Vec<String>,
+}
+
+impl Default for TagSelection {
+    fn default() -> Self {
+        Self {
+            only_tags: vec![ALL.to_string()],
+            skip_tags: Vec::new(),
+        }
+    }
+}
+
+impl TagSelection {
+    /// Build a selection from `--tags` and `--skip-tags` values. Each value may
+    /// itself be a comma-separated list, as on the Ansible command line.
+    pub fn new<I, J>(only_tags: I, skip_tags: J) -> Self
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+        J: IntoIterator,
+        J::Item: AsRef<str>,
+    {
+        let mut only_tags = split_tag_list(only_tags);
+        if only_tags.is_empty() {
+            only_tags.push(ALL.to_string());
+        }
+
+        Self {
+            only_tags,
+            skip_tags: split_tag_list(skip_tags),
+        }
+    }
+
+    /// Whether this selection runs every task except those tagged `never`
+    pub fn is_default(&self) -> bool {
+        self.only_tags.iter().all(|t| t == ALL) && self.skip_tags.is_empty()
+    }
+
+    /// Decide whether a task with the given effective tags would run.
+    ///
+    /// Mirrors Ansible's `Taggable.evaluate_tags`: `always` runs unless skipped
+    /// explicitly, `never` only runs when requested by another of its tags, and
+    /// `tagged`/`untagged`/`all` select by the presence of tags.
+    pub fn should_run(&self, tags: &[String]) -> bool {
+        let untagged = tags.is_empty();
+        let has = |tag: &str| tags.iter().any(|t| t == tag);
+        let has_untagged = untagged || has(UNTAGGED);
+        let selected_by = |list: &[String]| {
+            list.iter()
+                .any(|t| has(t) || (t == UNTAGGED && has_untagged))
+        };
+
+        let mut should_run = has(ALWAYS)
+            || (self.only_tags.iter().any(|t| t == ALL) && !has(NEVER))
+            || selected_by(&self.only_tags)
+            || (self.only_tags.iter().any(|t| t == TAGGED) && !untagged && !has(NEVER));
+
+        if should_run && !self.skip_tags.is_empty() {
+            if self.skip_tags.iter().any(|t| t == ALL) {
+                if !has(ALWAYS) || self.skip_tags.iter().any(|t| t == ALWAYS) {
+                    should_run = false;
+                }
+            } else if selected_by(&self.skip_tags)
+                || (self.skip_tags.iter().any(|t| t == TAGGED) && !untagged)
+            {
+                should_run = false;
+            }
+        }
+
+        should_run
+    }
+
+    /// Remove every task that would not run under this selection.
+    ///
+    /// Blocks are kept as long as any of their `block`, `rescue` or `always`
+    /// sections still has tasks. Handlers only run when notified and are never
+    /// filtered by tags.
+    pub fn filter_playbook(&self, playbook: &mut ParsedPlaybook) {
+        for play in &mut playbook.plays {
+            self.filter_tasks(&mut play.tasks);
+        }
+    }
+
+    fn filter_tasks(&self, tasks: &mut Vec<ParsedTask>) {
+        tasks.retain_mut(|task| match &mut task.block {
+            Some(block) => {
+                self.filter_tasks(&mut block.block);
+                self.filter_tasks(&mut block.rescue);
+                self.filter_tasks(&mut block.always);
+                !(block.block.is_empty() && block.rescue.is_empty() && block.always.is_empty())
+            }
+            None => self.should_run(&task.tags),
+        });
+    }
+}
+
+/// Tags found in a single play, as reported by `--list-tags`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlayTags {
+    pub play: String,
+    pub play_tags: Vec<String>,
+    pub task_tags: BTreeSet<String>,
+}
+
+/// Collect the effective task tags of every play in the playbook
+pub fn list_tags(playbook: &ParsedPlaybook) -> Vec<PlayTags> {
+    playbook
+        .plays
+        .iter()
+        .map(|play: &ParsedPlay| {
+            let mut task_tags = BTreeSet::new();
+            collect_task_tags(&play.tasks, &mut task_tags);
+            PlayTags {
+                play: play.name.clone(),
+                play_tags: play.tags.clone(),
+                task_tags,
+            }
+        })
+        .collect()
+}
+
+fn collect_task_tags(tasks: &[ParsedTask], tags: &mut BTreeSet<String>) {
+    for task in tasks {
+        tags.extend(task.tags.iter().cloned());
+        if let Some(block) = &task.block {
+            collect_task_tags(&block.block, tags);
+            collect_task_tags(&block.rescue, tags);
+            collect_task_tags(&block.always, tags);
+        }
+    }
+}
+
+/// Add inherited tags to a task and, for blocks, to every task inside it
+pub(crate) fn inherit_tags(task: &mut ParsedTask, inherited: &[String]) {
+    for tag in inherited {
+        if !task.tags.contains(tag) {
+            task.tags.push(tag.clone());
+        }
+    }
+
+    if let Some(block) = &mut task.block {
+        for child in block
+            .block
+            .iter_mut()
+            .chain(block.rescue.iter_mut())
+            .chain(block.always.iter_mut())
+        {
+            inherit_tags(child, inherited);
+        }
+    }
+}
+
+fn split_tag_list<I>(values: I) -> Vec<String>
+where
+    I: IntoIterator,
+    I::Item: AsRef<str>,
+{
+    let mut tags = Vec::new();
+    for value in values {
+        for tag in value.as_ref().split(',') {
+            let tag = tag.trim();
+            if !tag.is_empty() && !tags.iter().any(|t| t == tag) {
+                tags.push(tag.to_string());
+            }
+        }
+    }
+    tags
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags(values: &[&str]) -> Vec<String> {
+        values.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_default_selection_runs_everything_but_never() {
+        let selection = TagSelection::default();
+        assert!(selection.is_default());
+        assert!(selection.should_run(&[]));
+        assert!(selection.should_run(&tags(&["web"])));
+        assert!(selection.should_run(&tags(&["always"])));
+        assert!(!selection.should_run(&tags(&["never"])));
+        assert!(!selection.should_run(&tags(&["never", "debug"])));
+    }
+
+    #[test]
+    fn test_only_tags() {
+        let selection = TagSelection::new(["web,db"], Vec::<String>::new());
+        assert_eq!(selection.only_tags, tags(&["web", "db"]));
+        assert!(selection.should_run(&tags(&["web"])));
+        assert!(selection.should_run(&tags(&["db", "other"])));
+        assert!(!selection.should_run(&tags(&["other"])));
+        assert!(!selection.should_run(&[]));
+        assert!(selection.should_run(&tags(&["always"])));
+    }
+
+    #[test]
+    fn test_never_tag_runs_only_when_requested() {
+        let selection = TagSelection::new(["debug"], Vec::<String>::new());
+        assert!(selection.should_run(&tags(&["never", "debug"])));
+        assert!(!selection.should_run(&tags(&["never", "other"])));
+
+        let selection = TagSelection::new(["never"], Vec::<String>::new());
+        assert!(selection.should_run(&tags(&["never"])));
+    }
+
+    #[test]
+    fn test_tagged_and_untagged() {
+        let tagged = TagSelection::new(["tagged"], Vec::<String>::new());
+        assert!(tagged.should_run(&tags(&["web"])));
+        assert!(!tagged.should_run(&[]));
+        assert!(!tagged.should_run(&tags(&["never"])));
+
+        let untagged = TagSelection::new(["untagged"], Vec::<String>::new());
+        assert!(untagged.should_run(&[]));
+        assert!(!untagged.should_run(&tags(&["web"])));
+        assert!(untagged.should_run(&tags(&["always"])));
+    }
+
+    #[test]
+    fn test_skip_tags() {
+        let selection = TagSelection::new(Vec::<String>::new(), ["web"]);
+        assert!(!selection.should_run(&tags(&["web"])));
+        assert!(selection.should_run(&tags(&["db"])));
+        assert!(selection.should_run(&[]));
+
+        let skip_tagged = TagSelection::new(Vec::<String>::new(), ["tagged"]);
+        assert!(!skip_tagged.should_run(&tags(&["db"])));
+        assert!(skip_tagged.should_run(&[]));
+
+        let skip_untagged = TagSelection::new(Vec::<String>::new(), ["untagged"]);
+        assert!(!skip_untagged.should_run(&[]));
+        assert!(skip_untagged.should_run(&tags(&["db"])));
+    }
+
+    #[test]
+    fn test_skip_always() {
+        let skip_all = TagSelection::new(Vec::<String>::new(), ["all"]);
+        assert!(skip_all.should_run(&tags(&["always"])));
+        assert!(!skip_all.should_run(&tags(&["web"])));
+
+        let skip_always = TagSelection::new(Vec::<String>::new(), ["always"]);
+        assert!(!skip_always.should_run(&tags(&["always"])));
+        assert!(skip_always.should_run(&tags(&["web"])));
+    }
+}
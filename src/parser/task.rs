@@ -0,0 +1,636 @@
+//! Raw play, task and role structures and the parser that turns them into
+//! their parsed form.
+//!
+//! The playbook parser, the include handler and the role include processor all
+//! go through [`TaskParser`], so a task parses the same way whether it is in a
+//! playbook, an included file or a role.
+
+use crate::parser::collections::{split_fqcn, CollectionIndex, ModuleLookup};
+use crate::parser::condition::{condition_location, deserialize_conditions, parse_conditions};
+use crate::parser::error::ParseError;
+use crate::parser::keywords::{
+    deserialize_bool_or_template, deserialize_boolean_or_string, deserialize_percentage,
+    deserialize_required_string, deserialize_serial, deserialize_string, deserialize_string_list,
+    deserialize_tags,
+};
+use crate::parser::source_map::SourceNode;
+use crate::parser::tags::inherit_tags;
+use crate::parser::task_id::TaskPath;
+use crate::parser::template::TemplateEngine;
+use crate::types::parsed::*;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use tracing::warn;
+
+/// Module names recognised without a collection prefix
+const MODULE_KEYS: &[&str] = &[
+    "shell",
+    "command",
+    "copy",
+    "file",
+    "template",
+    "service",
+    "package",
+    "yum",
+    "apt",
+    "git",
+    "debug",
+    "set_fact",
+    "include",
+    "include_tasks",
+    "import_tasks",
+    "include_role",
+    "import_role",
+    "include_vars",
+    "block",
+    "rescue",
+    "always",
+    "meta",
+    "setup",
+    "gather_facts",
+    "ping",
+    "uri",
+    "get_url",
+    "unarchive",
+    "lineinfile",
+    "replace",
+    "stat",
+    "find",
+    "user",
+    "group",
+    "cron",
+    "systemd",
+    "assert",
+    "postgresql_db",
+    "wait_for",
+];
+
+/// Keywords that may appear in a play's role entry without being role parameters
+const ROLE_KEYWORDS: &[&str] = &[
+    "become",
+    "become_method",
+    "become_user",
+    "check_mode",
+    "collections",
+    "connection",
+    "delegate_to",
+    "diff",
+    "environment",
+    "ignore_errors",
+    "no_log",
+    "port",
+    "remote_user",
+    "run_once",
+];
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct RawPlay {
+    #[serde(deserialize_with = "deserialize_string", default)]
+    pub name: Option<String>,
+    pub hosts: Option<RawHostPattern>,
+    #[serde(deserialize_with = "deserialize_tags", default)]
+    pub tags: Option<Vec<String>>,
+    pub vars: Option<HashMap<String, serde_json::Value>>,
+    pub vars_files: Option<serde_json::Value>,
+    pub pre_tasks: Option<Vec<RawTask>>,
+    pub tasks: Option<Vec<RawTask>>,
+    pub post_tasks: Option<Vec<RawTask>>,
+    pub handlers: Option<Vec<RawTask>>,
+    pub roles: Option<Vec<RawRole>>,
+    #[serde(deserialize_with = "deserialize_string_list", default)]
+    pub collections: Option<Vec<String>>,
+    pub strategy: Option<ExecutionStrategy>,
+    #[serde(deserialize_with = "deserialize_serial", default)]
+    pub serial: Option<Serial>,
+    #[serde(deserialize_with = "deserialize_percentage", default)]
+    pub max_fail_percentage: Option<f32>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub(crate) enum RawHostPattern {
+    Single(String),
+    Multiple(Vec<String>),
+    All,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct RawTask {
+    #[serde(deserialize_with = "deserialize_string", default)]
+    pub id: Option<String>,
+    #[serde(deserialize_with = "deserialize_string", default)]
+    pub name: Option<String>,
+    pub vars: Option<HashMap<String, serde_json::Value>>,
+    #[serde(deserialize_with = "deserialize_conditions", default)]
+    pub when: Option<Vec<String>>,
+    #[serde(rename = "loop")]
+    pub loop_items: Option<serde_json::Value>,
+    #[serde(deserialize_with = "deserialize_tags", default)]
+    pub tags: Option<Vec<String>>,
+    #[serde(deserialize_with = "deserialize_string_list", default)]
+    pub notify: Option<Vec<String>>,
+    #[serde(deserialize_with = "deserialize_string_list", default)]
+    pub listen: Option<Vec<String>>,
+    #[serde(deserialize_with = "deserialize_boolean_or_string", default)]
+    pub changed_when: Option<BooleanOrString>,
+    #[serde(deserialize_with = "deserialize_boolean_or_string", default)]
+    pub failed_when: Option<BooleanOrString>,
+    #[serde(deserialize_with = "deserialize_bool_or_template", default)]
+    pub ignore_errors: Option<BooleanOrString>,
+    #[serde(deserialize_with = "deserialize_string", default)]
+    pub delegate_to: Option<String>,
+    #[serde(deserialize_with = "deserialize_string", default)]
+    pub register: Option<String>,
+    /// Taken before `module_args`, which gets the remaining keys
+    #[serde(flatten)]
+    pub keywords: TaskKeywords,
+    #[serde(flatten)]
+    pub module_args: HashMap<String, serde_json::Value>,
+}
+
+impl RawTask {
+    /// What tells the task apart from its siblings: its name, or else its
+    /// module and arguments. Unnamed blocks have none.
+    pub(crate) fn identity(&self) -> Option<String> {
+        if let Some(name) = self.name.as_deref().filter(|name| !name.is_empty()) {
+            return Some(name.to_string());
+        }
+        if self.module_args.contains_key("block") {
+            return None;
+        }
+        let module_args: BTreeMap<&String, &serde_json::Value> = self.module_args.iter().collect();
+        serde_json::to_string(&module_args).ok()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub(crate) enum RawRole {
+    String(String),
+    Object(RawRoleObject),
+}
+
+impl RawRole {
+    pub fn name(&self) -> &str {
+        match self {
+            RawRole::String(name) => name,
+            RawRole::Object(role) => &role.name,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct RawRoleObject {
+    #[serde(alias = "role", deserialize_with = "deserialize_required_string")]
+    pub name: String,
+    #[serde(deserialize_with = "deserialize_string", default)]
+    pub src: Option<String>,
+    #[serde(deserialize_with = "deserialize_string", default)]
+    pub version: Option<String>,
+    pub vars: Option<HashMap<String, serde_json::Value>>,
+    #[serde(deserialize_with = "deserialize_tags", default)]
+    pub tags: Option<Vec<String>>,
+    #[serde(deserialize_with = "deserialize_conditions", default)]
+    pub when: Option<Vec<String>>,
+    /// Any other key is a role parameter, e.g. `- role: web` with `http_port: 80`
+    #[serde(flatten)]
+    pub params: HashMap<String, serde_json::Value>,
+}
+
+type Variables = HashMap<String, serde_json::Value>;
+
+/// Turns raw plays, tasks and roles into their parsed form
+pub(crate) struct TaskParser<'a> {
+    template_engine: &'a TemplateEngine,
+    collections: Option<&'a CollectionIndex>,
+}
+
+impl<'a> TaskParser<'a> {
+    pub fn new(template_engine: &'a TemplateEngine) -> Self {
+        Self {
+            template_engine,
+            collections: None,
+        }
+    }
+
+    /// Installed collections to check fully qualified module names against
+    pub fn with_collections(mut self, collections: Option<&'a CollectionIndex>) -> Self {
+        self.collections = collections;
+        self
+    }
+
+    /// Merge a play's `vars` over `global_vars`, rendering templates that refer
+    /// to other variables. Also returns where each play variable is defined.
+    pub fn play_vars(
+        &self,
+        vars: Option<HashMap<String, serde_json::Value>>,
+        global_vars: &HashMap<String, serde_json::Value>,
+        vars_node: &SourceNode<'_>,
+    ) -> Result<(Variables, HashMap<String, SourceLocation>), ParseError> {
+        let mut play_vars = global_vars.clone();
+        let mut var_sources = HashMap::new();
+        let Some(vars) = vars else {
+            return Ok((play_vars, var_sources));
+        };
+
+        for key in vars.keys() {
+            if let Some(location) = vars_node.key(key).location() {
+                var_sources.insert(key.clone(), location);
+            }
+        }
+
+        // First pass: add all raw variables
+        for (key, value) in &vars {
+            play_vars.insert(key.clone(), value.clone());
+        }
+
+        // Second pass: render templates that may reference other variables
+        for (key, value) in vars {
+            let rendered_value = self.template_engine.render_value(&value, &play_vars)?;
+            play_vars.insert(key, rendered_value);
+        }
+        Ok((play_vars, var_sources))
+    }
+
+    /// Parse a play's `hosts`, rendering templates in the pattern
+    pub fn parse_hosts(
+        &self,
+        hosts: Option<RawHostPattern>,
+        vars: &HashMap<String, serde_json::Value>,
+    ) -> Result<HostPattern, ParseError> {
+        let render = |host: String| {
+            if host.contains("{{") && host.contains("}}") {
+                self.template_engine.render_string(&host, vars)
+            } else {
+                Ok(host)
+            }
+        };
+
+        Ok(match hosts {
+            Some(RawHostPattern::Single(host)) => {
+                let rendered_host = render(host)?;
+                if rendered_host == "all" {
+                    HostPattern::All
+                } else {
+                    HostPattern::Single(rendered_host)
+                }
+            }
+            Some(RawHostPattern::Multiple(hosts)) => HostPattern::Multiple(
+                hosts
+                    .into_iter()
+                    .map(render)
+                    .collect::<Result<Vec<_>, _>>()?,
+            ),
+            Some(RawHostPattern::All) => HostPattern::All,
+            None => HostPattern::Single("localhost".to_string()),
+        })
+    }
+
+    /// Render a play's name if it contains templates
+    /// A play's `vars_files` as a list, which may also be written as a single file
+    pub fn vars_files(&self, vars_files: Option<serde_json::Value>) -> Vec<serde_json::Value> {
+        match vars_files {
+            None | Some(serde_json::Value::Null) => Vec::new(),
+            Some(serde_json::Value::Array(entries)) => entries,
+            Some(entry) => vec![entry],
+        }
+    }
+
+    pub fn play_name(
+        &self,
+        name: Option<String>,
+        vars: &HashMap<String, serde_json::Value>,
+    ) -> Result<String, ParseError> {
+        match name {
+            Some(name) if name.contains("{{") && name.contains("}}") => {
+                self.template_engine.render_string(&name, vars)
+            }
+            Some(name) => Ok(name),
+            None => Ok("Unnamed play".to_string()),
+        }
+    }
+
+    /// Parse a single task, or a `block` with its `rescue` and `always` sections
+    pub fn parse_task(
+        &self,
+        raw_task: RawTask,
+        vars: &HashMap<String, serde_json::Value>,
+        task_path: &TaskPath,
+        node: &SourceNode<'_>,
+    ) -> Result<ParsedTask, ParseError> {
+        if raw_task.module_args.contains_key("block") {
+            return self.parse_block(raw_task, vars, task_path, node);
+        }
+
+        let id = raw_task.id.clone().unwrap_or_else(|| task_path.task_id());
+        let name = raw_task
+            .name
+            .clone()
+            .unwrap_or_else(|| "Unnamed task".to_string());
+
+        // Find the module and its arguments
+        let (module, args) = extract_module_and_args(&raw_task)?;
+        self.check_module(&module, &name)?;
+
+        // Render templates in args
+        let rendered_args = args
+            .into_iter()
+            .map(|(key, value)| Ok((key, self.template_engine.render_value(&value, vars)?)))
+            .collect::<Result<HashMap<_, _>, ParseError>>()?;
+
+        // Process boolean-or-string fields with template resolution
+        let changed_when = self.convert_boolean_or_string_field(raw_task.changed_when, vars)?;
+        let failed_when = self.convert_boolean_or_string_field(raw_task.failed_when, vars)?;
+
+        let source = node.location();
+        let when = parse_conditions(raw_task.when, &condition_location(source.as_ref(), &name))?;
+
+        Ok(ParsedTask {
+            id,
+            name,
+            module,
+            args: rendered_args,
+            vars: raw_task.vars.unwrap_or_default(),
+            when,
+            loop_items: raw_task.loop_items,
+            tags: raw_task.tags.unwrap_or_default(),
+            notify: raw_task.notify.unwrap_or_default(),
+            listen: raw_task.listen.unwrap_or_default(),
+            changed_when,
+            failed_when,
+            ignore_errors: raw_task
+                .ignore_errors
+                .unwrap_or(BooleanOrString::Boolean(false)),
+            delegate_to: raw_task.delegate_to,
+            dependencies: Vec::new(), // TODO: Extract dependencies from task relationships
+            block: None,
+            source,
+            register: raw_task.register,
+            statically_skipped: false,
+            deferred_include: None,
+            keywords: raw_task.keywords,
+        })
+    }
+
+    /// Parse a `block` task together with its `rescue` and `always` sections
+    fn parse_block(
+        &self,
+        mut raw_task: RawTask,
+        vars: &HashMap<String, serde_json::Value>,
+        task_path: &TaskPath,
+        node: &SourceNode<'_>,
+    ) -> Result<ParsedTask, ParseError> {
+        let mut sections = ParsedBlock {
+            block: self.parse_block_section(
+                raw_task.module_args.remove("block"),
+                vars,
+                task_path,
+                &node.key("block"),
+                "block",
+            )?,
+            rescue: self.parse_block_section(
+                raw_task.module_args.remove("rescue"),
+                vars,
+                task_path,
+                &node.key("rescue"),
+                "rescue",
+            )?,
+            always: self.parse_block_section(
+                raw_task.module_args.remove("always"),
+                vars,
+                task_path,
+                &node.key("always"),
+                "always",
+            )?,
+        };
+
+        // Tasks inside the block inherit its tags
+        let tags = raw_task.tags.unwrap_or_default();
+        for task in sections
+            .block
+            .iter_mut()
+            .chain(sections.rescue.iter_mut())
+            .chain(sections.always.iter_mut())
+        {
+            inherit_tags(task, &tags);
+        }
+
+        let name = raw_task.name.unwrap_or_else(|| "Unnamed block".to_string());
+        let source = node.location();
+        let when = parse_conditions(raw_task.when, &condition_location(source.as_ref(), &name))?;
+
+        Ok(ParsedTask {
+            id: raw_task.id.unwrap_or_else(|| task_path.task_id()),
+            name,
+            module: "block".to_string(),
+            args: HashMap::new(),
+            vars: raw_task.vars.unwrap_or_default(),
+            when,
+            loop_items: raw_task.loop_items,
+            tags,
+            notify: raw_task.notify.unwrap_or_default(),
+            listen: raw_task.listen.unwrap_or_default(),
+            changed_when: None,
+            failed_when: None,
+            ignore_errors: raw_task
+                .ignore_errors
+                .unwrap_or(BooleanOrString::Boolean(false)),
+            delegate_to: raw_task.delegate_to,
+            dependencies: Vec::new(),
+            block: Some(sections),
+            source,
+            register: None,
+            statically_skipped: false,
+            deferred_include: None,
+            keywords: raw_task.keywords,
+        })
+    }
+
+    /// Parse the task list of a single block section (`block`, `rescue` or `always`)
+    fn parse_block_section(
+        &self,
+        section: Option<serde_json::Value>,
+        vars: &HashMap<String, serde_json::Value>,
+        block_path: &TaskPath,
+        section_node: &SourceNode<'_>,
+        section_name: &str,
+    ) -> Result<Vec<ParsedTask>, ParseError> {
+        let raw_tasks = match section {
+            None | Some(serde_json::Value::Null) => return Ok(Vec::new()),
+            Some(serde_json::Value::Array(items)) => items,
+            Some(_) => {
+                return Err(ParseError::InvalidStructure {
+                    message: "block, rescue and always sections must be lists of tasks".to_string(),
+                })
+            }
+        };
+
+        let raw_tasks = raw_tasks
+            .into_iter()
+            .map(serde_json::from_value)
+            .collect::<Result<Vec<RawTask>, _>>()?;
+        let identities: Vec<Option<String>> = raw_tasks.iter().map(RawTask::identity).collect();
+        let task_paths = block_path.children(section_name, &identities);
+
+        let mut tasks = Vec::new();
+        for ((index, raw_task), task_path) in raw_tasks.into_iter().enumerate().zip(task_paths) {
+            let task_node = section_node.index(index);
+            tasks.push(self.parse_task(raw_task, vars, &task_path, &task_node)?);
+        }
+        Ok(tasks)
+    }
+
+    /// Convert boolean-or-string field with template resolution
+    fn convert_boolean_or_string_field(
+        &self,
+        field: Option<BooleanOrString>,
+        vars: &HashMap<String, serde_json::Value>,
+    ) -> Result<Option<BooleanOrString>, ParseError> {
+        match field {
+            None => Ok(None),
+            Some(BooleanOrString::Boolean(b)) => Ok(Some(BooleanOrString::Boolean(b))),
+            Some(BooleanOrString::String(s)) => {
+                let resolved = self.template_engine.render_string(&s, vars)?;
+                // Try to parse resolved template as boolean
+                match resolved.to_lowercase().as_str() {
+                    "true" | "yes" | "on" => Ok(Some(BooleanOrString::Boolean(true))),
+                    "false" | "no" | "off" => Ok(Some(BooleanOrString::Boolean(false))),
+                    _ => Ok(Some(BooleanOrString::String(resolved))),
+                }
+            }
+        }
+    }
+
+    /// Parse a play's or role's role entry. Keys other than role keywords are
+    /// role parameters.
+    pub fn parse_role(
+        &self,
+        raw_role: RawRole,
+        source: Option<SourceLocation>,
+    ) -> Result<ParsedRole, ParseError> {
+        match raw_role {
+            RawRole::String(name) => Ok(ParsedRole {
+                name,
+                src: None,
+                version: None,
+                vars: HashMap::new(),
+                tags: Vec::new(),
+                source,
+                when: Vec::new(),
+                path: None,
+                defaults: HashMap::new(),
+                role_vars: HashMap::new(),
+                dependencies: Vec::new(),
+                deduplicated: false,
+            }),
+            RawRole::Object(role_obj) => {
+                let location = condition_location(source.as_ref(), &role_obj.name);
+                let mut vars: HashMap<String, serde_json::Value> = role_obj
+                    .params
+                    .into_iter()
+                    .filter(|(key, _)| !ROLE_KEYWORDS.contains(&key.as_str()))
+                    .collect();
+                vars.extend(role_obj.vars.unwrap_or_default());
+
+                Ok(ParsedRole {
+                    name: role_obj.name,
+                    src: role_obj.src,
+                    version: role_obj.version,
+                    vars,
+                    tags: role_obj.tags.unwrap_or_default(),
+                    when: parse_conditions(role_obj.when, &location)?,
+                    source,
+                    path: None,
+                    defaults: HashMap::new(),
+                    role_vars: HashMap::new(),
+                    dependencies: Vec::new(),
+                    deduplicated: false,
+                })
+            }
+        }
+    }
+
+    /// Check a fully qualified module name against the installed collections.
+    /// Modules of collections that are not installed cannot be checked.
+    fn check_module(&self, module: &str, task: &str) -> Result<(), ParseError> {
+        let Some(index) = self.collections else {
+            return Ok(());
+        };
+        if split_fqcn(module).is_none() {
+            return Ok(());
+        }
+        match index.lookup_module(module) {
+            ModuleLookup::Found => Ok(()),
+            ModuleLookup::Missing if index.strict_modules() => Err(ParseError::InvalidModule {
+                module: module.to_string(),
+                task: task.to_string(),
+            }),
+            ModuleLookup::Missing => {
+                warn!("Module '{module}' of task '{task}' is not in its installed collection");
+                Ok(())
+            }
+            ModuleLookup::Removed(tombstone) => {
+                warn!(
+                    "Module '{module}' has been removed: {}",
+                    tombstone
+                        .warning_text
+                        .as_deref()
+                        .unwrap_or("no replacement given")
+                );
+                Err(ParseError::InvalidModule {
+                    module: module.to_string(),
+                    task: task.to_string(),
+                })
+            }
+            ModuleLookup::UnknownCollection => {
+                warn!("Collection of module '{module}' is not installed, it cannot be checked");
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Find a task's module, by known name or fully qualified name, and its arguments
+pub(crate) fn extract_module_and_args(
+    raw_task: &RawTask,
+) -> Result<(String, HashMap<String, serde_json::Value>), ParseError> {
+    // Modules from collections are referenced by fully qualified name
+    let fqcn_key = raw_task
+        .module_args
+        .keys()
+        .map(String::as_str)
+        .find(|key| split_fqcn(key).is_some());
+
+    for key in MODULE_KEYS.iter().copied().chain(fqcn_key) {
+        if let Some(value) = raw_task.module_args.get(key) {
+            let args = match value {
+                serde_json::Value::String(s) => {
+                    let mut args = HashMap::new();
+                    args.insert(
+                        "_raw_params".to_string(),
+                        serde_json::Value::String(s.clone()),
+                    );
+                    args
+                }
+                serde_json::Value::Object(obj) => {
+                    obj.iter().map(|(k, v)| (k.clone(), v.clone())).collect()
+                }
+                _ => {
+                    let mut args = HashMap::new();
+                    args.insert("_raw_params".to_string(), value.clone());
+                    args
+                }
+            };
+            return Ok((key.to_string(), args));
+        }
+    }
+
+    // Provide more context about the failed task
+    let available_modules: Vec<String> = raw_task.module_args.keys().cloned().collect();
+    let task_name = raw_task.name.as_deref().unwrap_or("unnamed");
+    Err(ParseError::InvalidStructure {
+        message: format!(
+            "No valid module found in task '{task_name}'. Available keys: {available_modules:?}"
+        ),
+    })
+}
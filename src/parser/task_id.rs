@@ -0,0 +1,131 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Position of a play or task within a playbook, used to derive stable task ids.
+///
+/// A path is built from the playbook file name followed by one segment per
+/// level of nesting, e.g. `site.yml/play[0]/tasks["Setup"]/tasks/setup.yml[1]`.
+/// A task is keyed by its name, or its module and arguments, when no sibling
+/// shares it, so inserting, removing or reordering tasks leaves the ids of
+/// the others alone. Tasks without such an identity are keyed by index.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TaskPath(String);
+
+impl TaskPath {
+    pub fn new(file: &str) -> Self {
+        Self(file.to_string())
+    }
+
+    /// Path of the `index`-th element of the `section` list below this path
+    pub fn child(&self, section: &str, index: usize) -> Self {
+        self.segment(&format!("{section}[{index}]"))
+    }
+
+    /// Paths of the elements of the `section` list below this path, keyed by
+    /// their identity where it is unique among them and by index otherwise
+    pub fn children(&self, section: &str, identities: &[Option<String>]) -> Vec<Self> {
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for identity in identities.iter().flatten() {
+            *counts.entry(identity).or_default() += 1;
+        }
+        identities
+            .iter()
+            .enumerate()
+            .map(|(index, identity)| match identity {
+                Some(identity) if counts[identity.as_str()] == 1 => {
+                    self.segment(&format!("{section}[{identity:?}]"))
+                }
+                _ => self.child(section, index),
+            })
+            .collect()
+    }
+
+    fn segment(&self, segment: &str) -> Self {
+        if self.0.is_empty() {
+            Self(segment.to_string())
+        } else {
+            Self(format!("{}/{segment}", self.0))
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Deterministic task id for this path
+    pub fn task_id(&self) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.0.as_bytes());
+        let digest = format!("{:x}", hasher.finalize());
+        format!("task_{}", &digest[..16])
+    }
+}
+
+impl fmt::Display for TaskPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_task_path_segments() {
+        let path = TaskPath::new("site.yml")
+            .child("play", 0)
+            .child("tasks", 3)
+            .child("tasks/setup.yml", 1);
+        assert_eq!(
+            path.as_str(),
+            "site.yml/play[0]/tasks[3]/tasks/setup.yml[1]"
+        );
+        assert_eq!(TaskPath::default().child("tasks", 2).as_str(), "tasks[2]");
+    }
+
+    #[test]
+    fn test_children_keyed_by_unique_identity() {
+        let play = TaskPath::new("site.yml").child("play", 0);
+        let identities = [
+            Some("Install".to_string()),
+            None,
+            Some("Restart".to_string()),
+            Some("Restart".to_string()),
+        ];
+        let paths: Vec<String> = play
+            .children("tasks", &identities)
+            .iter()
+            .map(|path| path.to_string())
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                "site.yml/play[0]/tasks[\"Install\"]",
+                "site.yml/play[0]/tasks[1]",
+                "site.yml/play[0]/tasks[2]",
+                "site.yml/play[0]/tasks[3]",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_task_id_is_deterministic_and_distinct() {
+        let play = TaskPath::new("site.yml").child("play", 0);
+        let first = play.child("tasks", 0);
+
+        assert_eq!(first.task_id(), play.child("tasks", 0).task_id());
+        assert_ne!(first.task_id(), play.child("handlers", 0).task_id());
+        assert_ne!(
+            first.task_id(),
+            TaskPath::new("site.yml")
+                .child("play", 1)
+                .child("tasks", 0)
+                .task_id()
+        );
+        assert!(first.task_id().starts_with("task_"));
+        assert_eq!(first.task_id().len(), "task_".len() + 16);
+    }
+}
@@ -5,6 +5,7 @@ use std::collections::HashMap;
 #[derive(Clone)]
 pub struct TemplateEngine {
     env: Environment<'static>,
+    native_types: bool,
 }
 
 impl TemplateEngine {
@@ -12,8 +13,9 @@ impl TemplateEngine {
         let mut env = Environment::new();
 
         // Configure to be more lenient with undefined variables
-        // This prevents errors when variables are not yet defined during parsing
-        env.set_undefined_behavior(minijinja::UndefinedBehavior::Lenient);
+        // This prevents errors when variables (or attributes of registered results)
+        // are not yet defined during parsing
+        env.set_undefined_behavior(minijinja::UndefinedBehavior::Chainable);
 
         // Add Ansible-compatible filters
         env.add_filter("default", filters::default_filter);
@@ -23,7 +25,17 @@ impl TemplateEngine {
         env.add_filter("upper", filters::upper_filter);
         env.add_filter("trim", filters::trim_filter);
 
-        Self { env }
+        Self {
+            env,
+            native_types: false,
+        }
+    }
+
+    /// Render values that are a single `{{ expression }}` to the expression's
+    /// native type instead of a string, like Ansible's `jinja2_native`
+    pub fn with_native_types(mut self, native_types: bool) -> Self {
+        self.native_types = native_types;
+        self
     }
 
     pub fn render_string(
@@ -59,6 +71,85 @@ impl TemplateEngine {
             })
     }
 
+    /// Variables a template uses that `vars` does not define, sorted
+    pub fn undefined_variables(
+        &self,
+        template_str: &str,
+        vars: &HashMap<String, serde_json::Value>,
+    ) -> Result<Vec<String>, ParseError> {
+        let template =
+            self.env
+                .template_from_str(template_str)
+                .map_err(|e| ParseError::Template {
+                    file: "inline".to_string(),
+                    line: 0,
+                    message: e.to_string(),
+                })?;
+
+        let mut undefined: Vec<String> = template
+            .undeclared_variables(false)
+            .into_iter()
+            .filter(|name| !vars.contains_key(name))
+            .collect();
+        undefined.sort();
+        Ok(undefined)
+    }
+
+    /// Evaluate a bare Jinja expression such as `private_ip | default(ip)` to
+    /// its native value. An expression that evaluates to an undefined value
+    /// is an error.
+    pub fn evaluate_expression(
+        &self,
+        expression: &str,
+        vars: &HashMap<String, serde_json::Value>,
+    ) -> Result<serde_json::Value, ParseError> {
+        let minijinja_vars: HashMap<String, Value> = vars
+            .iter()
+            .map(|(k, v)| (k.clone(), serde_json_to_minijinja_value(v)))
+            .collect();
+        let value = self
+            .env
+            .compile_expression(expression)?
+            .eval(&minijinja_vars)?;
+        if value.is_undefined() {
+            return Err(ParseError::UndefinedVariable {
+                variable: expression.to_string(),
+            });
+        }
+        Ok(serde_json::to_value(&value)?)
+    }
+
+    /// Evaluate `template_str` to a native value if native types are enabled
+    /// and it is one expression that evaluates to a defined value
+    fn render_native(
+        &self,
+        template_str: &str,
+        vars: &HashMap<String, serde_json::Value>,
+    ) -> Option<serde_json::Value> {
+        if !self.native_types {
+            return None;
+        }
+        let expression = template_str.trim().strip_prefix("{{")?.strip_suffix("}}")?;
+        if expression.contains("{{") || expression.contains("}}") || expression.contains("{%") {
+            return None;
+        }
+
+        let minijinja_vars: HashMap<String, Value> = vars
+            .iter()
+            .map(|(k, v)| (k.clone(), serde_json_to_minijinja_value(v)))
+            .collect();
+        let value = self
+            .env
+            .compile_expression(expression)
+            .ok()?
+            .eval(&minijinja_vars)
+            .ok()?;
+        if value.is_undefined() {
+            return None;
+        }
+        serde_json::to_value(&value).ok()
+    }
+
     pub fn render_value(
         &self,
         value: &serde_json::Value,
@@ -67,6 +158,9 @@ impl TemplateEngine {
         match value {
             serde_json::Value::String(s) => {
                 if s.contains("{{") && s.contains("}}") {
+                    if let Some(native) = self.render_native(s, vars) {
+                        return Ok(native);
+                    }
                     let rendered = self.render_string(s, vars)?;
                     Ok(serde_json::Value::String(rendered))
                 } else {
@@ -1,7 +1,15 @@
 use crate::parser::error::ParseError;
+use aes::Aes256;
+use ctr::cipher::{KeyIvInit, StreamCipher};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
 
+/// PBKDF2 rounds `ansible-vault` uses to derive its keys
+const KDF_ITERATIONS: u32 = 10_000;
+
+/// Decrypts files and values encrypted with `ansible-vault` (format 1.1 and
+/// 1.2, AES256)
 pub struct VaultDecryptor {
-    #[allow(dead_code)]
     password: String,
 }
 
@@ -10,18 +18,72 @@ impl VaultDecryptor {
         Self { password }
     }
 
-    pub fn decrypt(&self, _encrypted_data: &str) -> Result<String, ParseError> {
-        // TODO: Implement vault decryption
-        Err(ParseError::UnsupportedFeature {
-            feature: "Vault decryption".to_string(),
-        })
+    /// Decrypt `$ANSIBLE_VAULT` text, returning the plaintext
+    pub fn decrypt(&self, encrypted_data: &str) -> Result<String, ParseError> {
+        let mut lines = encrypted_data.trim().lines();
+        let header = lines.next().unwrap_or_default().trim();
+        let fields: Vec<&str> = header.split(';').map(str::trim).collect();
+        match fields.as_slice() {
+            ["$ANSIBLE_VAULT", "1.1" | "1.2", "AES256", ..] => {}
+            ["$ANSIBLE_VAULT", version, cipher, ..] => {
+                return Err(vault_error(format!(
+                    "unsupported vault format {version} with cipher {cipher}"
+                )))
+            }
+            _ => return Err(vault_error("missing $ANSIBLE_VAULT header")),
+        }
+
+        // The body is the hex of three hex-encoded lines: salt, HMAC and ciphertext
+        let body: String = lines.map(str::trim).collect();
+        let body = hex_decode(&body, "body")?;
+        let body = String::from_utf8(body).map_err(|_| vault_error("malformed body"))?;
+        let [salt, expected_hmac, ciphertext] = body.lines().collect::<Vec<_>>()[..] else {
+            return Err(vault_error("malformed body"));
+        };
+        let salt = hex_decode(salt, "salt")?;
+        let expected_hmac = hex_decode(expected_hmac, "HMAC")?;
+        let mut data = hex_decode(ciphertext, "ciphertext")?;
+
+        // 32 bytes of AES key, 32 of HMAC key and a 16 byte counter block
+        let mut keys = [0u8; 80];
+        pbkdf2::pbkdf2_hmac::<Sha256>(self.password.as_bytes(), &salt, KDF_ITERATIONS, &mut keys);
+        let (cipher_key, rest) = keys.split_at(32);
+        let (hmac_key, iv) = rest.split_at(32);
+
+        let mut mac = Hmac::<Sha256>::new_from_slice(hmac_key)
+            .map_err(|_| vault_error("invalid HMAC key"))?;
+        mac.update(&data);
+        mac.verify_slice(&expected_hmac)
+            .map_err(|_| vault_error("HMAC mismatch, the vault password is probably wrong"))?;
+
+        let mut cipher = ctr::Ctr128BE::<Aes256>::new(cipher_key.into(), iv.into());
+        cipher.apply_keystream(&mut data);
+
+        // Strip the PKCS#7 padding
+        let padding = data.last().copied().unwrap_or_default() as usize;
+        if padding == 0 || padding > 16 || padding > data.len() {
+            return Err(vault_error("invalid padding"));
+        }
+        data.truncate(data.len() - padding);
+
+        String::from_utf8(data).map_err(|_| vault_error("plaintext is not valid UTF-8"))
+    }
+}
+
+fn hex_decode(text: &str, part: &str) -> Result<Vec<u8>, ParseError> {
+    hex::decode(text.trim()).map_err(|_| vault_error(format!("{part} is not valid hex")))
+}
+
+fn vault_error(message: impl Into<String>) -> ParseError {
+    ParseError::VaultDecryption {
+        message: message.into(),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::testing::fixtures::VAULT_ENCRYPTED_CONTENT;
+    use crate::testing::fixtures::{VAULT_ENCRYPTED_CONTENT, VAULT_PASSWORD, VAULT_PLAINTEXT};
 
     #[test]
     fn test_vault_decryptor_creation() {
@@ -31,15 +93,23 @@ mod tests {
     }
 
     #[test]
-    fn test_vault_decryptor_decrypt_returns_error() {
-        let decryptor = VaultDecryptor::new("password".to_string());
+    fn test_vault_decryptor_decrypts() {
+        let decryptor = VaultDecryptor::new(VAULT_PASSWORD.to_string());
+        let plaintext = decryptor.decrypt(VAULT_ENCRYPTED_CONTENT).unwrap();
+        assert_eq!(plaintext, VAULT_PLAINTEXT);
+    }
+
+    #[test]
+    fn test_vault_decryptor_wrong_password() {
+        let decryptor = VaultDecryptor::new("wrong".to_string());
         let result = decryptor.decrypt(VAULT_ENCRYPTED_CONTENT);
 
-        assert!(result.is_err());
-        assert!(matches!(
-            result.unwrap_err(),
-            ParseError::UnsupportedFeature { .. }
-        ));
+        match result.unwrap_err() {
+            ParseError::VaultDecryption { message } => {
+                assert!(message.contains("HMAC mismatch"));
+            }
+            other => panic!("Expected VaultDecryption error, got {other:?}"),
+        }
     }
 
     #[test]
@@ -47,10 +117,9 @@ mod tests {
         let decryptor = VaultDecryptor::new("password".to_string());
         let result = decryptor.decrypt("");
 
-        assert!(result.is_err());
         assert!(matches!(
             result.unwrap_err(),
-            ParseError::UnsupportedFeature { .. }
+            ParseError::VaultDecryption { .. }
         ));
     }
 
@@ -60,41 +129,22 @@ mod tests {
         let invalid_data = "this is not vault encrypted data";
         let result = decryptor.decrypt(invalid_data);
 
-        assert!(result.is_err());
         assert!(matches!(
             result.unwrap_err(),
-            ParseError::UnsupportedFeature { .. }
+            ParseError::VaultDecryption { .. }
         ));
     }
 
     #[test]
-    fn test_vault_decryptor_with_different_passwords() {
-        let passwords = vec!["pass1", "pass2", "very_long_password_123"];
-
-        for password in passwords {
-            let decryptor = VaultDecryptor::new(password.to_string());
-            assert_eq!(decryptor.password, password);
-
-            // All should return the same unsupported feature error
-            let result = decryptor.decrypt("test_data");
-            assert!(result.is_err());
-            assert!(matches!(
-                result.unwrap_err(),
-                ParseError::UnsupportedFeature { .. }
-            ));
-        }
-    }
-
-    #[test]
-    fn test_vault_decryptor_error_message() {
+    fn test_vault_decryptor_unsupported_format() {
         let decryptor = VaultDecryptor::new("password".to_string());
-        let result = decryptor.decrypt("test");
+        let result = decryptor.decrypt("$ANSIBLE_VAULT;1.0;AES\n00\n");
 
         match result.unwrap_err() {
-            ParseError::UnsupportedFeature { feature } => {
-                assert_eq!(feature, "Vault decryption");
+            ParseError::VaultDecryption { message } => {
+                assert!(message.contains("unsupported vault format 1.0"));
             }
-            _ => panic!("Expected UnsupportedFeature error"),
+            other => panic!("Expected VaultDecryption error, got {other:?}"),
         }
     }
 }
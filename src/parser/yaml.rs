@@ -0,0 +1,615 @@
+use crate::parser::error::ParseError;
+use serde::de::DeserializeOwned;
+use serde_yaml::value::{Tag, TaggedValue};
+use serde_yaml::{Mapping, Number, Value};
+use std::collections::HashMap;
+use yaml_rust2::parser::{Event, MarkedEventReceiver, Parser as YamlParser, Tag as YamlTag};
+use yaml_rust2::scanner::{Marker, TScalarStyle};
+
+/// Tag prefix that `!!` expands to
+const CORE_TAG_PREFIX: &str = "tag:yaml.org,2002:";
+
+/// Resource limits applied while loading YAML, to guard against
+/// billion-laughs style documents that expand aliases exponentially.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct YamlLimits {
+    /// Largest accepted document, in bytes
+    pub max_document_size: usize,
+    /// Deepest accepted nesting of sequences and mappings, aliases included
+    pub max_depth: usize,
+    /// Total number of nodes that aliases and merge keys may copy into a document
+    pub max_alias_nodes: usize,
+}
+
+impl Default for YamlLimits {
+    fn default() -> Self {
+        Self {
+            max_document_size: 10 * 1024 * 1024,
+            max_depth: 128,
+            max_alias_nodes: 100_000,
+        }
+    }
+}
+
+/// Load a YAML document with YAML 1.1 scalar resolution, as Ansible (PyYAML) does.
+///
+/// serde_yaml implements YAML 1.2, where `0644` is the integer 644 and `yes` is a
+/// string. Here unquoted scalars resolve like they do in Ansible: `0644` is octal
+/// 420, `yes`/`on`/`off` are booleans and `1:30` is sexagesimal 90, while quoted
+/// scalars always stay strings. Untagged mapping keys are always strings.
+///
+/// Aliases are expanded and `<<` merge keys applied, with keys written in the
+/// mapping itself taking precedence over merged ones. Uses the default [`YamlLimits`].
+pub fn load(content: &str) -> Result<Value, ParseError> {
+    load_with_limits(content, &YamlLimits::default())
+}
+
+/// Like [`load`], with explicit resource limits
+pub fn load_with_limits(content: &str, limits: &YamlLimits) -> Result<Value, ParseError> {
+    if content.len() > limits.max_document_size {
+        return Err(ParseError::YamlLimitExceeded {
+            message: format!(
+                "document is {} bytes, the limit is {}",
+                content.len(),
+                limits.max_document_size
+            ),
+        });
+    }
+
+    let mut builder = ValueBuilder::new(limits);
+    if YamlParser::new_from_str(content)
+        .load(&mut builder, false)
+        .is_err()
+    {
+        // serde_yaml reports syntax errors with their location
+        let mut value: Value = serde_yaml::from_str(content)?;
+        value.apply_merge()?;
+        return Ok(value);
+    }
+    if let Some(error) = builder.error {
+        return Err(error);
+    }
+    Ok(builder.document.unwrap_or(Value::Null))
+}
+
+/// Deserialize a YAML document loaded with [`load`]
+pub fn from_str<T: DeserializeOwned>(content: &str) -> Result<T, ParseError> {
+    from_str_with_limits(content, &YamlLimits::default())
+}
+
+/// Deserialize a YAML document loaded with [`load_with_limits`]
+pub fn from_str_with_limits<T: DeserializeOwned>(
+    content: &str,
+    limits: &YamlLimits,
+) -> Result<T, ParseError> {
+    Ok(serde_yaml::from_value(load_with_limits(content, limits)?)?)
+}
+
+/// Resolve an unquoted, untagged scalar the way YAML 1.1 does
+pub fn resolve_plain_scalar(scalar: &str) -> Value {
+    match scalar {
+        "" | "~" | "null" | "Null" | "NULL" => return Value::Null,
+        "yes" | "Yes" | "YES" | "true" | "True" | "TRUE" | "on" | "On" | "ON" => {
+            return Value::Bool(true)
+        }
+        "no" | "No" | "NO" | "false" | "False" | "FALSE" | "off" | "Off" | "OFF" => {
+            return Value::Bool(false)
+        }
+        _ => {}
+    }
+
+    resolve_int(scalar)
+        .or_else(|| resolve_float(scalar))
+        .unwrap_or_else(|| Value::String(scalar.to_string()))
+}
+
+fn resolve_int(scalar: &str) -> Option<Value> {
+    let (negative, digits) = match scalar.as_bytes().first()? {
+        b'-' => (true, &scalar[1..]),
+        b'+' => (false, &scalar[1..]),
+        _ => (false, scalar),
+    };
+    if digits.is_empty() || digits.starts_with('_') {
+        return None;
+    }
+
+    let parse = |body: &str, radix: u32| -> Option<i128> {
+        let body: String = body.chars().filter(|c| *c != '_').collect();
+        if body.is_empty() {
+            return None;
+        }
+        i128::from_str_radix(&body, radix).ok()
+    };
+
+    let magnitude = if let Some(body) = digits.strip_prefix("0b") {
+        parse(body, 2)?
+    } else if let Some(body) = digits.strip_prefix("0x") {
+        parse(body, 16)?
+    } else if digits.contains(':') {
+        sexagesimal(digits, |part| parse(part, 10))?
+    } else if digits.len() > 1 && digits.starts_with('0') {
+        parse(&digits[1..], 8)?
+    } else if digits.chars().all(|c| c.is_ascii_digit() || c == '_') {
+        parse(digits, 10)?
+    } else {
+        return None;
+    };
+
+    let value = if negative { -magnitude } else { magnitude };
+    if let Ok(value) = i64::try_from(value) {
+        Some(Value::Number(value.into()))
+    } else {
+        u64::try_from(value).ok().map(|v| Value::Number(v.into()))
+    }
+}
+
+fn resolve_float(scalar: &str) -> Option<Value> {
+    let (negative, body) = match scalar.as_bytes().first()? {
+        b'-' => (true, &scalar[1..]),
+        b'+' => (false, &scalar[1..]),
+        _ => (false, scalar),
+    };
+
+    let magnitude = match body {
+        ".inf" | ".Inf" | ".INF" => f64::INFINITY,
+        // `.nan` takes no sign
+        ".nan" | ".NaN" | ".NAN" if body.len() == scalar.len() => f64::NAN,
+        _ if body.contains(':') => {
+            let (whole, fraction) = body.split_once('.')?;
+            if !fraction.chars().all(|c| c.is_ascii_digit() || c == '_') {
+                return None;
+            }
+            let whole = sexagesimal(whole, |part| part.replace('_', "").parse::<f64>().ok())?;
+            let fraction = format!("0.{}0", fraction.replace('_', ""))
+                .parse::<f64>()
+                .ok()?;
+            whole + fraction
+        }
+        _ => {
+            // YAML 1.1 floats need a `.`, and an exponent needs an explicit sign
+            let (mantissa, exponent) = match body.find(['e', 'E']) {
+                Some(pos) => (&body[..pos], Some(&body[pos + 1..])),
+                None => (body, None),
+            };
+            let (whole, fraction) = mantissa.split_once('.')?;
+            let digits_ok = |s: &str| s.chars().all(|c| c.is_ascii_digit() || c == '_');
+            if !digits_ok(whole) || !digits_ok(fraction) || whole.starts_with('_') {
+                return None;
+            }
+            if whole.is_empty() && fraction.is_empty() {
+                return None;
+            }
+            if let Some(exponent) = exponent {
+                let unsigned = exponent.strip_prefix(['+', '-'])?;
+                if unsigned.is_empty() || !unsigned.chars().all(|c| c.is_ascii_digit()) {
+                    return None;
+                }
+            }
+            body.replace('_', "").parse::<f64>().ok()?
+        }
+    };
+
+    let value = if negative { -magnitude } else { magnitude };
+    Some(Value::Number(Number::from(value)))
+}
+
+/// Base 60 numbers like `1:30:00`; every part after the first must be below 60
+fn sexagesimal<T>(text: &str, parse: impl Fn(&str) -> Option<T>) -> Option<T>
+where
+    T: std::ops::Mul<Output = T> + std::ops::Add<Output = T> + From<u8> + Copy,
+{
+    let mut parts = text.split(':');
+    let first = parts.next()?;
+    if first.is_empty() || !first.starts_with(|c: char| c.is_ascii_digit() && c != '0') {
+        return None;
+    }
+    if !first.chars().all(|c| c.is_ascii_digit() || c == '_') {
+        return None;
+    }
+
+    let mut total = parse(first)?;
+    for part in parts {
+        let valid = matches!(part.len(), 1 | 2)
+            && part.chars().all(|c| c.is_ascii_digit())
+            && part.parse::<u8>().is_ok_and(|n| n < 60);
+        if !valid {
+            return None;
+        }
+        total = total * T::from(60) + parse(part)?;
+    }
+    Some(total)
+}
+
+/// Apply an explicit `!!type` tag, or keep unknown tags like `!vault` as tagged values
+fn apply_tag(tag: &YamlTag, scalar: String, style: TScalarStyle) -> Value {
+    let core_type = match tag.handle.as_str() {
+        "!!" => Some(tag.suffix.as_str()),
+        handle if handle == CORE_TAG_PREFIX => Some(tag.suffix.as_str()),
+        _ => tag.suffix.strip_prefix(CORE_TAG_PREFIX),
+    };
+
+    match core_type {
+        Some("str") => Value::String(scalar),
+        Some("null") | Some("bool") | Some("int") | Some("float") => resolve_plain_scalar(&scalar),
+        _ => Value::Tagged(Box::new(TaggedValue {
+            tag: Tag::new(format!("{}{}", tag.handle, tag.suffix)),
+            value: scalar_value(scalar, style),
+        })),
+    }
+}
+
+fn scalar_value(scalar: String, style: TScalarStyle) -> Value {
+    match style {
+        TScalarStyle::Plain => resolve_plain_scalar(&scalar),
+        _ => Value::String(scalar),
+    }
+}
+
+/// Size of a value: how many nodes it has and how deeply it nests
+#[derive(Clone, Copy, Default)]
+struct Extent {
+    nodes: usize,
+    depth: usize,
+}
+
+impl Extent {
+    fn add(&mut self, child: Extent) {
+        self.nodes += child.nodes;
+        self.depth = self.depth.max(child.depth + 1);
+    }
+}
+
+enum PendingKey {
+    Key(Value),
+    /// A plain `<<` key, whose value is merged into the mapping
+    Merge,
+}
+
+enum Frame {
+    Sequence {
+        items: Vec<Value>,
+        anchor: usize,
+        extent: Extent,
+    },
+    Mapping {
+        mapping: Mapping,
+        pending_key: Option<PendingKey>,
+        merged: Vec<Mapping>,
+        anchor: usize,
+        extent: Extent,
+    },
+}
+
+/// Builds a [`Value`] from parser events, resolving anchors as it goes
+struct ValueBuilder<'a> {
+    limits: &'a YamlLimits,
+    stack: Vec<Frame>,
+    anchors: HashMap<usize, (Value, Extent)>,
+    alias_nodes: usize,
+    document: Option<Value>,
+    error: Option<ParseError>,
+}
+
+impl<'a> ValueBuilder<'a> {
+    fn new(limits: &'a YamlLimits) -> Self {
+        Self {
+            limits,
+            stack: Vec::new(),
+            anchors: HashMap::new(),
+            alias_nodes: 0,
+            document: None,
+            error: None,
+        }
+    }
+
+    fn fail(&mut self, marker: Marker, message: String) {
+        if self.error.is_none() {
+            self.error = Some(ParseError::YamlLimitExceeded {
+                message: format!("{message} at line {}", marker.line()),
+            });
+        }
+    }
+
+    fn complete(&mut self, value: Value, anchor: usize, extent: Extent, marker: Marker) {
+        if anchor > 0 {
+            self.anchors.insert(anchor, (value.clone(), extent));
+        }
+
+        match self.stack.last_mut() {
+            None => {
+                if self.document.is_none() {
+                    self.document = Some(value);
+                }
+            }
+            Some(Frame::Sequence {
+                items,
+                extent: parent,
+                ..
+            }) => {
+                parent.add(extent);
+                items.push(value);
+            }
+            Some(Frame::Mapping {
+                mapping,
+                pending_key,
+                merged,
+                extent: parent,
+                ..
+            }) => {
+                parent.add(extent);
+                match pending_key.take() {
+                    None => *pending_key = Some(PendingKey::Key(value)),
+                    Some(PendingKey::Key(key)) => {
+                        mapping.insert(key, value);
+                    }
+                    Some(PendingKey::Merge) => match value {
+                        Value::Mapping(source) => merged.push(source),
+                        Value::Sequence(sources)
+                            if sources.iter().all(|source| source.is_mapping()) =>
+                        {
+                            merged.extend(sources.into_iter().filter_map(|source| match source {
+                                Value::Mapping(source) => Some(source),
+                                _ => None,
+                            }));
+                        }
+                        _ => {
+                            self.error.get_or_insert(ParseError::InvalidStructure {
+                                message: format!(
+                                    "merge key '<<' at line {} expects a mapping or a list of mappings",
+                                    marker.line()
+                                ),
+                            });
+                        }
+                    },
+                }
+            }
+        }
+    }
+
+    fn expecting_key(&self) -> bool {
+        matches!(
+            self.stack.last(),
+            Some(Frame::Mapping {
+                pending_key: None,
+                ..
+            })
+        )
+    }
+
+    fn push(&mut self, frame: Frame, marker: Marker) {
+        if self.stack.len() >= self.limits.max_depth {
+            let message = format!("nesting is deeper than {}", self.limits.max_depth);
+            self.fail(marker, message);
+        }
+        self.stack.push(frame);
+    }
+}
+
+/// Apply merged mappings; earlier sources win over later ones, and keys
+/// written in the mapping itself win over all of them
+fn merge_mapping(mapping: Mapping, merged: Vec<Mapping>) -> Mapping {
+    if merged.is_empty() {
+        return mapping;
+    }
+
+    let mut result = Mapping::new();
+    for source in merged {
+        for (key, value) in source {
+            if !mapping.contains_key(&key) && !result.contains_key(&key) {
+                result.insert(key, value);
+            }
+        }
+    }
+    result.extend(mapping);
+    result
+}
+
+impl MarkedEventReceiver for ValueBuilder<'_> {
+    fn on_event(&mut self, event: Event, marker: Marker) {
+        // Stop building once a limit is hit; the parser still drains its events
+        if self.error.is_some() {
+            return;
+        }
+
+        let leaf = Extent { nodes: 1, depth: 0 };
+        match event {
+            Event::Scalar(scalar, style, anchor, tag) => {
+                if self.expecting_key()
+                    && tag.is_none()
+                    && style == TScalarStyle::Plain
+                    && scalar == "<<"
+                {
+                    if let Some(Frame::Mapping { pending_key, .. }) = self.stack.last_mut() {
+                        *pending_key = Some(PendingKey::Merge);
+                    }
+                    return;
+                }
+
+                let is_key = self.expecting_key();
+                let value = match tag {
+                    Some(tag) => apply_tag(&tag, scalar, style),
+                    // Keys stay strings so that `on:` or `80:` can name variables
+                    None if is_key => Value::String(scalar),
+                    None => scalar_value(scalar, style),
+                };
+                self.complete(value, anchor, leaf, marker);
+            }
+            Event::Alias(anchor) => {
+                let (value, extent) = self.anchors.get(&anchor).cloned().unwrap_or_default();
+                self.alias_nodes += extent.nodes;
+                if self.alias_nodes > self.limits.max_alias_nodes {
+                    let message = format!(
+                        "aliases expand to more than {} nodes",
+                        self.limits.max_alias_nodes
+                    );
+                    return self.fail(marker, message);
+                }
+                if self.stack.len() + extent.depth > self.limits.max_depth {
+                    let message = format!("nesting is deeper than {}", self.limits.max_depth);
+                    return self.fail(marker, message);
+                }
+                self.complete(value, 0, extent, marker);
+            }
+            Event::SequenceStart(anchor, _) => {
+                let frame = Frame::Sequence {
+                    items: Vec::new(),
+                    anchor,
+                    extent: leaf,
+                };
+                self.push(frame, marker);
+            }
+            Event::MappingStart(anchor, _) => {
+                let frame = Frame::Mapping {
+                    mapping: Mapping::new(),
+                    pending_key: None,
+                    merged: Vec::new(),
+                    anchor,
+                    extent: leaf,
+                };
+                self.push(frame, marker);
+            }
+            Event::SequenceEnd => {
+                if let Some(Frame::Sequence {
+                    items,
+                    anchor,
+                    extent,
+                }) = self.stack.pop()
+                {
+                    self.complete(Value::Sequence(items), anchor, extent, marker);
+                }
+            }
+            Event::MappingEnd => {
+                if let Some(Frame::Mapping {
+                    mapping,
+                    merged,
+                    anchor,
+                    extent,
+                    ..
+                }) = self.stack.pop()
+                {
+                    let mapping = merge_mapping(mapping, merged);
+                    self.complete(Value::Mapping(mapping), anchor, extent, marker);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(yaml: &str) -> serde_json::Value {
+        serde_yaml::from_value(load(yaml).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_plain_scalar_resolution() {
+        assert_eq!(resolve_plain_scalar("0644"), Value::Number(420.into()));
+        assert_eq!(resolve_plain_scalar("0o644"), Value::String("0o644".into()));
+        assert_eq!(resolve_plain_scalar("0x1F"), Value::Number(31.into()));
+        assert_eq!(resolve_plain_scalar("0b101"), Value::Number(5.into()));
+        assert_eq!(resolve_plain_scalar("1_000"), Value::Number(1000.into()));
+        assert_eq!(resolve_plain_scalar("-42"), Value::Number((-42).into()));
+        assert_eq!(
+            resolve_plain_scalar("190:20:30"),
+            Value::Number(685230.into())
+        );
+        assert_eq!(resolve_plain_scalar("09"), Value::String("09".into()));
+        assert_eq!(resolve_plain_scalar("0"), Value::Number(0.into()));
+
+        assert_eq!(resolve_plain_scalar("1.5"), Value::Number(1.5.into()));
+        assert_eq!(resolve_plain_scalar("1.0e+3"), Value::Number(1000.0.into()));
+        assert_eq!(resolve_plain_scalar("1e3"), Value::String("1e3".into()));
+        assert_eq!(
+            resolve_plain_scalar("20:30.15"),
+            Value::Number(1230.15.into())
+        );
+        assert_eq!(resolve_plain_scalar("1.2.3"), Value::String("1.2.3".into()));
+
+        assert_eq!(resolve_plain_scalar("yes"), Value::Bool(true));
+        assert_eq!(resolve_plain_scalar("Off"), Value::Bool(false));
+        assert_eq!(resolve_plain_scalar("y"), Value::String("y".into()));
+        assert_eq!(resolve_plain_scalar("~"), Value::Null);
+    }
+
+    #[test]
+    fn test_quoted_scalars_stay_strings() {
+        let doc = value("a: 'yes'\nb: \"0644\"\nc: yes\nd: 0644\ne: !!str 0644\nf: |\n  on\n");
+        assert_eq!(doc["a"], "yes");
+        assert_eq!(doc["b"], "0644");
+        assert_eq!(doc["c"], true);
+        assert_eq!(doc["d"], 420);
+        assert_eq!(doc["e"], "0644");
+        assert_eq!(doc["f"], "on\n");
+    }
+
+    #[test]
+    fn test_keys_and_anchors() {
+        let doc = value("on: push\n80: http\nbase: &base [1, 2]\ncopy: *base\n");
+        assert_eq!(doc["on"], "push");
+        assert_eq!(doc["80"], "http");
+        assert_eq!(doc["copy"], serde_json::json!([1, 2]));
+    }
+
+    #[test]
+    fn test_merge_keys() {
+        let doc = value(
+            "base: &base {a: 1, b: 1}\n\
+             extra: &extra {b: 2, c: 2}\n\
+             single: {<<: *base, a: 3}\n\
+             list: {a: 3, <<: [*extra, *base]}\n\
+             quoted: {'<<': *base}\n",
+        );
+        assert_eq!(doc["single"], serde_json::json!({"a": 3, "b": 1}));
+        assert_eq!(doc["list"], serde_json::json!({"a": 3, "b": 2, "c": 2}));
+        assert_eq!(doc["quoted"], serde_json::json!({"<<": {"a": 1, "b": 1}}));
+        assert!(matches!(
+            load("a: {<<: 1}"),
+            Err(ParseError::InvalidStructure { .. })
+        ));
+    }
+
+    #[test]
+    fn test_limits() {
+        let laughs = "a: &a [x, x, x, x, x, x, x, x, x, x]\n\
+                      b: &b [*a, *a, *a, *a, *a, *a, *a, *a, *a, *a]\n\
+                      c: &c [*b, *b, *b, *b, *b, *b, *b, *b, *b, *b]\n\
+                      d: &d [*c, *c, *c, *c, *c, *c, *c, *c, *c, *c]\n\
+                      e: &e [*d, *d, *d, *d, *d, *d, *d, *d, *d, *d]\n\
+                      f: [*e, *e, *e, *e, *e, *e, *e, *e, *e, *e]\n";
+        assert!(matches!(
+            load(laughs),
+            Err(ParseError::YamlLimitExceeded { .. })
+        ));
+
+        let limits = YamlLimits {
+            max_alias_nodes: 20,
+            ..YamlLimits::default()
+        };
+        assert!(load_with_limits("a: &a [1, 2]\nb: [*a, *a]", &limits).is_ok());
+        assert!(
+            load_with_limits("a: &a [1, 2]\nb: [*a, *a, *a, *a, *a, *a, *a]", &limits).is_err()
+        );
+
+        let limits = YamlLimits {
+            max_depth: 3,
+            ..YamlLimits::default()
+        };
+        assert!(load_with_limits("a: [[1]]", &limits).is_ok());
+        assert!(load_with_limits("a: [[[1]]]", &limits).is_err());
+        assert!(load_with_limits("a: &a [[1]]\nb: [*a]", &limits).is_err());
+
+        let limits = YamlLimits {
+            max_document_size: 8,
+            ..YamlLimits::default()
+        };
+        assert!(load_with_limits("key: value", &limits).is_err());
+    }
+
+    #[test]
+    fn test_syntax_errors_come_from_serde_yaml() {
+        assert!(matches!(load("a: [1, 2"), Err(ParseError::Yaml(_))));
+    }
+}
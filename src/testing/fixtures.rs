@@ -1,7 +1,8 @@
 //! Test fixtures for consistent testing across the codebase
 
 use crate::types::parsed::{
-    ParsedGroup, ParsedHost, ParsedInventory, ParsedPlay, ParsedPlaybook, ParsedTask,
+    NotificationMap, ParsedGroup, ParsedHost, ParsedInventory, ParsedPlay, ParsedPlaybook,
+    ParsedTask, TaskKeywords,
 };
 use std::collections::HashMap;
 
@@ -30,6 +31,7 @@ pub fn create_test_inventory() -> ParsedInventory {
             become_method: None,
             become_user: None,
             become_flags: None,
+            source: None,
         },
     );
 
@@ -52,6 +54,7 @@ pub fn create_test_inventory() -> ParsedInventory {
             become_method: None,
             become_user: None,
             become_flags: None,
+            source: None,
         },
     );
 
@@ -81,6 +84,7 @@ pub fn create_test_inventory() -> ParsedInventory {
             become_method: None,
             become_user: None,
             become_flags: None,
+            source: None,
         },
     );
 
@@ -92,6 +96,7 @@ pub fn create_test_inventory() -> ParsedInventory {
             hosts: vec!["web1".to_string(), "web2".to_string()],
             children: vec![],
             vars: HashMap::new(),
+            source: None,
         },
     );
 
@@ -102,6 +107,7 @@ pub fn create_test_inventory() -> ParsedInventory {
             hosts: vec!["db1".to_string()],
             children: vec![],
             vars: HashMap::new(),
+            source: None,
         },
     );
 
@@ -112,6 +118,7 @@ pub fn create_test_inventory() -> ParsedInventory {
             hosts: vec![],
             children: vec!["webservers".to_string(), "databases".to_string()],
             vars: HashMap::new(),
+            source: None,
         },
     );
 
@@ -144,15 +151,22 @@ pub fn create_test_playbook() -> ParsedPlaybook {
                 args
             },
             vars: HashMap::new(),
-            when: None,
+            when: Vec::new(),
             loop_items: None,
             tags: vec![],
             notify: vec![],
+            listen: vec![],
             changed_when: None,
             failed_when: None,
-            ignore_errors: false,
+            ignore_errors: false.into(),
             delegate_to: None,
             dependencies: vec![],
+            block: None,
+            source: None,
+            register: None,
+            statically_skipped: false,
+            deferred_include: None,
+            keywords: TaskKeywords::default(),
         },
         ParsedTask {
             id: "task2".to_string(),
@@ -172,28 +186,42 @@ pub fn create_test_playbook() -> ParsedPlaybook {
                 args
             },
             vars: HashMap::new(),
-            when: None,
+            when: Vec::new(),
             loop_items: None,
             tags: vec!["service".to_string()],
             notify: vec![],
+            listen: vec![],
             changed_when: None,
             failed_when: None,
-            ignore_errors: false,
+            ignore_errors: false.into(),
             delegate_to: None,
             dependencies: vec![],
+            block: None,
+            source: None,
+            register: None,
+            statically_skipped: false,
+            deferred_include: None,
+            keywords: TaskKeywords::default(),
         },
     ];
 
     let play = ParsedPlay {
         name: "Configure web servers".to_string(),
         hosts: HostPattern::Single("webservers".to_string()),
+        tags: vec![],
         tasks,
         vars: HashMap::new(),
+        vars_files: Vec::new(),
         handlers: vec![],
         roles: vec![],
         strategy: crate::types::parsed::ExecutionStrategy::default(),
         serial: None,
         max_fail_percentage: None,
+        notifications: NotificationMap::default(),
+        source: None,
+        var_sources: HashMap::new(),
+        collections: Vec::new(),
+        matched_hosts: None,
     };
 
     ParsedPlaybook {
@@ -309,12 +337,16 @@ pub const INVALID_YAML: &str = r#"
         msg: "This has invalid YAML [
 "#;
 
-/// Sample vault encrypted content for testing
+/// `VAULT_PLAINTEXT` encrypted with `ansible-vault` using `VAULT_PASSWORD`
 pub const VAULT_ENCRYPTED_CONTENT: &str = r#"
 $ANSIBLE_VAULT;1.1;AES256
-66396439633937376662326235343938376666663861326639323834646265383562623762396133
-3933316164366231363565396262636639653163636264370a623061386462663061613264623137
-36643735316636343939623634376463623638306265333335323638613163616264343033663736
-3266326365616566650a323433346361313166333364333039326439623831653664323634623461
-34623665383735313139343139316534366166313439363065316464386434666164
+30363237363931316331326566323661666261626238346139636232353861333235623830313737
+3333323861633663356433613837323838623466336363610a373163656366333432326237333665
+35633534356237323662656636303538346361303663306464303032313032613430363131333032
+3161393063623362640a306562666565313536316236383834356631633763306133633536636435
+31636131383665363131303765323232386135373039383033326662303365313366
 "#;
+
+pub const VAULT_PASSWORD: &str = "vault-pass";
+
+pub const VAULT_PLAINTEXT: &str = "db_password: s3cret\n";
@@ -1,5 +1,5 @@
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 pub type Value = serde_json::Value;
 
@@ -30,6 +30,18 @@ pub enum BooleanOrString {
     String(String),
 }
 
+/// A play's `serial` batch size
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Serial {
+    /// A number of hosts per batch
+    Count(u32),
+    /// A percentage of the play's hosts such as `30%`, or a template
+    Expression(String),
+    /// Successive batch sizes, the last repeating until every host has run
+    Batches(Vec<Serial>),
+}
+
 impl From<bool> for BooleanOrString {
     fn from(value: bool) -> Self {
         BooleanOrString::Boolean(value)
@@ -80,13 +92,51 @@ pub struct PlaybookMetadata {
 pub struct ParsedPlay {
     pub name: String,
     pub hosts: HostPattern,
+    /// Inventory hosts `hosts` selects, once an inventory is attached
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub matched_hosts: Option<Vec<String>>,
+    #[serde(default)]
+    pub tags: Vec<String>,
     pub vars: HashMap<String, Value>,
+    /// Entries of the play's `vars_files`, which are read when the play runs. A
+    /// list entry names alternatives, of which the first that exists is read.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub vars_files: Vec<Value>,
     pub tasks: Vec<ParsedTask>,
     pub handlers: Vec<ParsedTask>,
     pub roles: Vec<ParsedRole>,
+    /// Collections searched for short role names, from the play's `collections`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub collections: Vec<String>,
     pub strategy: ExecutionStrategy,
-    pub serial: Option<u32>,
+    pub serial: Option<Serial>,
     pub max_fail_percentage: Option<f32>,
+    #[serde(default)]
+    pub notifications: NotificationMap,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<SourceLocation>,
+    /// Where each play variable was defined
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub var_sources: HashMap<String, SourceLocation>,
+}
+
+/// Handler notifications resolved for a single play.
+///
+/// Ansible notifies handlers by name or by `listen` topic, and a handler may in
+/// turn notify other handlers. `triggers` maps the id of every notifying task or
+/// handler to the ids of all handlers that would run as a result, in handler
+/// definition order.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct NotificationMap {
+    pub triggers: BTreeMap<String, Vec<String>>,
+    pub unresolved: Vec<UnresolvedNotification>,
+}
+
+/// A `notify` entry that matched no handler name or `listen` topic.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UnresolvedNotification {
+    pub task_id: String,
+    pub target: String,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -147,15 +197,232 @@ pub struct ParsedTask {
     pub module: String,
     pub args: HashMap<String, Value>,
     pub vars: HashMap<String, Value>,
-    pub when: Option<String>,
+    /// Conditions that must all hold, in evaluation order
+    #[serde(default)]
+    pub when: Vec<Condition>,
     pub loop_items: Option<Value>,
+    /// Variable the task result is registered as
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub register: Option<String>,
     pub tags: Vec<String>,
     pub notify: Vec<String>,
+    #[serde(default)]
+    pub listen: Vec<String>,
     pub changed_when: Option<BooleanOrString>,
     pub failed_when: Option<BooleanOrString>,
-    pub ignore_errors: bool,
+    /// A boolean, or a template only known at runtime
+    pub ignore_errors: BooleanOrString,
     pub delegate_to: Option<String>,
+    /// Privilege escalation, environment and logging keywords
+    #[serde(flatten)]
+    pub keywords: TaskKeywords,
     pub dependencies: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block: Option<ParsedBlock>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<SourceLocation>,
+    /// Set when static condition evaluation found the task can never run
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub statically_skipped: bool,
+    /// Set on an `include_tasks` whose file is only known at runtime
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deferred_include: Option<DeferredInclude>,
+}
+
+/// Task keywords that are unset unless a task, or an import or `apply` around
+/// it, sets them
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TaskKeywords {
+    #[serde(
+        rename = "become",
+        default,
+        deserialize_with = "crate::parser::keywords::deserialize_bool_or_template",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub r#become: Option<BooleanOrString>,
+    #[serde(
+        default,
+        deserialize_with = "crate::parser::keywords::deserialize_string",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub become_user: Option<String>,
+    #[serde(
+        default,
+        deserialize_with = "crate::parser::keywords::deserialize_string",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub become_method: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub environment: Option<Value>,
+    #[serde(
+        default,
+        deserialize_with = "crate::parser::keywords::deserialize_bool_or_template",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub no_log: Option<BooleanOrString>,
+    #[serde(
+        default,
+        deserialize_with = "crate::parser::keywords::deserialize_bool_or_template",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub check_mode: Option<BooleanOrString>,
+}
+
+impl TaskKeywords {
+    /// Fill in every keyword not set here from `parent`
+    pub fn inherit(&mut self, parent: &TaskKeywords) {
+        fn fill<T: Clone>(own: &mut Option<T>, parent: &Option<T>) {
+            if own.is_none() {
+                own.clone_from(parent);
+            }
+        }
+        fill(&mut self.r#become, &parent.r#become);
+        fill(&mut self.become_user, &parent.become_user);
+        fill(&mut self.become_method, &parent.become_method);
+        fill(&mut self.environment, &parent.environment);
+        fill(&mut self.no_log, &parent.no_log);
+        fill(&mut self.check_mode, &parent.check_mode);
+    }
+}
+
+/// An include whose file name depends on variables only known at runtime.
+///
+/// The files on disk the name can match are parsed up front, so a consumer
+/// that knows the variables can pick the right candidate without reparsing.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeferredInclude {
+    /// The file name as written, e.g. `{{ ansible_os_family | lower }}.yml`
+    pub expression: String,
+    /// Variables in the file name that are undefined at parse time
+    pub variables: Vec<String>,
+    pub candidates: Vec<IncludeCandidate>,
+}
+
+/// A file a deferred include may load, with its parsed tasks
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IncludeCandidate {
+    /// Path relative to the including file, as the rendered file name would be
+    pub file: String,
+    pub tasks: Vec<ParsedTask>,
+}
+
+/// A single `when` condition: the expression as written and its parsed form.
+///
+/// `ast` is `None` for expressions that are valid Jinja but use syntax the
+/// condition parser does not model, and for conditions wrapped in `{{ }}` that
+/// are only known after templating.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Condition {
+    pub expression: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ast: Option<ConditionExpr>,
+}
+
+/// Jinja expression tree of a condition
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ConditionExpr {
+    Literal {
+        value: Value,
+    },
+    Name {
+        name: String,
+    },
+    GetAttr {
+        object: Box<ConditionExpr>,
+        attr: String,
+    },
+    GetItem {
+        object: Box<ConditionExpr>,
+        index: Box<ConditionExpr>,
+    },
+    List {
+        items: Vec<ConditionExpr>,
+    },
+    Dict {
+        entries: Vec<(ConditionExpr, ConditionExpr)>,
+    },
+    Unary {
+        op: UnaryOp,
+        operand: Box<ConditionExpr>,
+    },
+    Binary {
+        op: BinaryOp,
+        left: Box<ConditionExpr>,
+        right: Box<ConditionExpr>,
+    },
+    /// `operand is [not] name(args)`
+    Test {
+        operand: Box<ConditionExpr>,
+        name: String,
+        args: Vec<CallArg>,
+        negated: bool,
+    },
+    /// `operand | name(args)`
+    Filter {
+        operand: Box<ConditionExpr>,
+        name: String,
+        args: Vec<CallArg>,
+    },
+    Call {
+        function: Box<ConditionExpr>,
+        args: Vec<CallArg>,
+    },
+    /// `then if condition else otherwise`
+    IfElse {
+        condition: Box<ConditionExpr>,
+        then: Box<ConditionExpr>,
+        otherwise: Option<Box<ConditionExpr>>,
+    },
+}
+
+/// Positional or keyword argument of a call, filter or test
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CallArg {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub value: ConditionExpr,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnaryOp {
+    Not,
+    Neg,
+    Pos,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BinaryOp {
+    And,
+    Or,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    In,
+    NotIn,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    FloorDiv,
+    Rem,
+    Pow,
+    Concat,
+}
+
+/// Sections of a `block` task. Tasks inside carry the block's tags as inherited tags.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ParsedBlock {
+    pub block: Vec<ParsedTask>,
+    #[serde(default)]
+    pub rescue: Vec<ParsedTask>,
+    #[serde(default)]
+    pub always: Vec<ParsedTask>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -163,8 +430,30 @@ pub struct ParsedRole {
     pub name: String,
     pub src: Option<String>,
     pub version: Option<String>,
+    /// Role parameters and `vars` given where the role is used
     pub vars: HashMap<String, Value>,
     pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<SourceLocation>,
+    /// Conditions applied to every task and handler of the role
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub when: Vec<Condition>,
+    /// Directory the role was loaded from; `None` if it was not found
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// Variables from the role's `defaults/main.yml`
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub defaults: HashMap<String, Value>,
+    /// Variables from the role's `vars/main.yml`
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub role_vars: HashMap<String, Value>,
+    /// Roles from `meta/main.yml` `dependencies`, which run before this one
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dependencies: Vec<ParsedRole>,
+    /// Set when the same role already ran with the same parameters and does not
+    /// allow duplicates, so this invocation contributes no tasks
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub deduplicated: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
@@ -207,6 +496,9 @@ pub struct ParsedHost {
     pub become_method: Option<String>, // sudo, su, pbrun, etc.
     pub become_user: Option<String>,
     pub become_flags: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<SourceLocation>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -215,6 +507,22 @@ pub struct ParsedGroup {
     pub hosts: Vec<String>,
     pub children: Vec<String>,
     pub vars: HashMap<String, Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<SourceLocation>,
+}
+
+/// Position of a parsed element in the file it was read from. Lines and columns are 1-based.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceLocation {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl std::fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.column)
+    }
 }
 
 /// Temporary struct for extracting all connection info from inventory
@@ -431,3 +431,111 @@ localhost
         .stdout(predicate::str::contains("Complex test"))
         .stdout(predicate::str::contains("test_value"));
 }
+
+#[test]
+fn test_cli_tags_and_list_tags() {
+    let playbook_content = r#"
+---
+- name: Tagged playbook
+  hosts: localhost
+  tags: [site]
+  tasks:
+    - name: Web task
+      debug:
+        msg: "web"
+      tags: [web]
+    - name: Db task
+      debug:
+        msg: "db"
+      tags: [db]
+"#;
+
+    let playbook_file = create_temp_playbook(playbook_content);
+
+    let mut cmd = Command::cargo_bin("rustle-parse").unwrap();
+    cmd.arg(playbook_file.path()).arg("--list-tags");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Tagged playbook\tTAGS: [site]"))
+        .stdout(predicate::str::contains("TASK TAGS: [db, site, web]"));
+
+    let mut cmd = Command::cargo_bin("rustle-parse").unwrap();
+    cmd.arg(playbook_file.path())
+        .arg("--tags")
+        .arg("web")
+        .arg("--list-tasks");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Web task"))
+        .stdout(predicate::str::contains("Db task").not());
+
+    let mut cmd = Command::cargo_bin("rustle-parse").unwrap();
+    cmd.arg(playbook_file.path())
+        .arg("--skip-tags")
+        .arg("web")
+        .arg("--list-tasks");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Db task"))
+        .stdout(predicate::str::contains("Web task").not());
+}
+
+#[test]
+fn test_cli_static_conditions() {
+    let playbook_content = r#"
+---
+- name: Conditional playbook
+  hosts: all
+  vars:
+    use_cache: false
+  tasks:
+    - name: Cache task
+      debug:
+        msg: "cache"
+      when: use_cache
+    - name: Prod task
+      debug:
+        msg: "prod"
+      when: deploy_env == "prod"
+    - name: Debian task
+      debug:
+        msg: "debian"
+      when: ansible_os_family == "Debian"
+"#;
+    let inventory_content = r#"
+[web]
+web1
+web2
+
+[all:vars]
+deploy_env=staging
+"#;
+
+    let playbook_file = create_temp_playbook(playbook_content);
+    let inventory_file = create_temp_inventory(inventory_content);
+
+    let mut cmd = Command::cargo_bin("rustle-parse").unwrap();
+    cmd.arg(playbook_file.path())
+        .arg("--inventory")
+        .arg(inventory_file.path())
+        .arg("--static-conditions")
+        .arg("prune")
+        .arg("--list-tasks");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Debian task"))
+        .stdout(predicate::str::contains("Cache task").not())
+        .stdout(predicate::str::contains("Prod task").not());
+
+    let mut cmd = Command::cargo_bin("rustle-parse").unwrap();
+    cmd.arg(playbook_file.path())
+        .arg("--extra-vars")
+        .arg("use_cache=true")
+        .arg("--static-conditions")
+        .arg("flag")
+        .arg("--list-tasks");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Cache task"))
+        .stdout(predicate::str::contains("Statically skipped").not());
+}
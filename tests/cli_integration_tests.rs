@@ -663,3 +663,178 @@ fn test_cli_binary_output_not_implemented_error() {
         "Binary output format not yet implemented",
     ));
 }
+
+#[test]
+fn test_cli_requirements_lockfile() {
+    let temp_dir = TempDir::new().unwrap();
+    let playbook = temp_dir.path().join("site.yml");
+    fs::write(
+        &playbook,
+        "- hosts: all\n  tasks:\n    - name: firewall\n      community.general.ufw:\n        rule: allow\n",
+    )
+    .unwrap();
+    let requirements = temp_dir.path().join("requirements.yml");
+    fs::write(&requirements, "collections:\n  - community.general\n").unwrap();
+
+    let mut cmd = Command::cargo_bin("rustle-parse").unwrap();
+    cmd.arg(&playbook).arg("--requirements").arg(&requirements);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("\"lock\""))
+        .stdout(predicate::str::contains("\"name\": \"community.general\""));
+
+    fs::write(&requirements, "roles:\n  - geerlingguy.nginx\n").unwrap();
+    let mut cmd = Command::cargo_bin("rustle-parse").unwrap();
+    cmd.arg(&playbook).arg("--requirements").arg(&requirements);
+    cmd.assert()
+        .failure()
+        .stdout(predicate::str::contains(
+            "\"missing_collections\": [\n      \"community.general\"",
+        ))
+        .stderr(predicate::str::contains(
+            "Required role 'geerlingguy.nginx' is not used",
+        ))
+        .stderr(predicate::str::contains(
+            "does not match the roles and collections the playbook uses",
+        ));
+
+    // The lockfile finds roles in --roles-path
+    let shared = temp_dir.path().join("shared");
+    fs::create_dir_all(shared.join("geerlingguy.nginx/meta")).unwrap();
+    fs::write(
+        shared.join("geerlingguy.nginx/meta/.galaxy_install_info"),
+        "version: 3.1.4\n",
+    )
+    .unwrap();
+    let mut cmd = Command::cargo_bin("rustle-parse").unwrap();
+    cmd.arg(&playbook)
+        .arg("--requirements")
+        .arg(&requirements)
+        .arg("--roles-path")
+        .arg(&shared);
+    cmd.assert()
+        .failure()
+        .stdout(predicate::str::contains("\"installed_version\": \"3.1.4\""));
+}
+
+#[test]
+fn test_cli_reads_ansible_cfg() {
+    let temp_dir = TempDir::new().unwrap();
+    let project = temp_dir.path().join("project");
+    let shared_role = temp_dir.path().join("shared_roles/common/tasks");
+    fs::create_dir_all(&project).unwrap();
+    fs::create_dir_all(&shared_role).unwrap();
+    fs::write(
+        shared_role.join("main.yml"),
+        "- name: Shared role task\n  debug:\n    msg: shared\n",
+    )
+    .unwrap();
+    fs::write(
+        project.join("ansible.cfg"),
+        "[defaults]\ninventory = hosts.ini\nroles_path = ../shared_roles\n",
+    )
+    .unwrap();
+    fs::write(
+        project.join("hosts.ini"),
+        "[web]\nweb1 ansible_host=10.0.0.1\n",
+    )
+    .unwrap();
+    fs::write(
+        project.join("other.ini"),
+        "[db]\ndb1 ansible_host=10.0.0.2\n",
+    )
+    .unwrap();
+    fs::write(
+        project.join("site.yml"),
+        "- hosts: all\n  roles:\n    - common\n",
+    )
+    .unwrap();
+
+    let command = || {
+        let mut cmd = Command::cargo_bin("rustle-parse").unwrap();
+        cmd.current_dir(&project)
+            .env("HOME", temp_dir.path())
+            .env_remove("ANSIBLE_CONFIG")
+            .env_remove("ANSIBLE_INVENTORY")
+            .env_remove("ANSIBLE_ROLES_PATH");
+        cmd
+    };
+
+    command()
+        .arg("site.yml")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Shared role task"))
+        .stdout(predicate::str::contains("\"web1\""));
+
+    // The environment overrides the file, and options override both
+    command()
+        .env("ANSIBLE_INVENTORY", "other.ini")
+        .arg("site.yml")
+        .arg("--list-hosts")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("db1:"))
+        .stdout(predicate::str::contains("web1:").not());
+    command()
+        .env("ANSIBLE_INVENTORY", "other.ini")
+        .arg("site.yml")
+        .arg("--inventory")
+        .arg("hosts.ini")
+        .arg("--list-hosts")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("web1:"));
+}
+
+#[test]
+fn test_cli_parser_settings() {
+    let temp_dir = TempDir::new().unwrap();
+    let roles = temp_dir.path().join("roles_elsewhere/common/tasks");
+    fs::create_dir_all(&roles).unwrap();
+    fs::write(
+        roles.join("main.yml"),
+        "- name: Common role task\n  debug:\n    msg: common\n",
+    )
+    .unwrap();
+    let playbook = temp_dir.path().join("site.yml");
+    fs::write(&playbook, "- hosts: all\n  roles:\n    - common\n").unwrap();
+
+    let mut cmd = Command::cargo_bin("rustle-parse").unwrap();
+    cmd.arg(&playbook)
+        .arg("--roles-path")
+        .arg(temp_dir.path().join("roles_elsewhere"));
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Common role task"));
+
+    let mut cmd = Command::cargo_bin("rustle-parse").unwrap();
+    cmd.arg(&playbook).arg("--max-yaml-size").arg("10");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("limit"));
+}
+
+#[test]
+fn test_cli_multiple_inventory_sources() {
+    let temp_dir = TempDir::new().unwrap();
+    let inventory_dir = temp_dir.path().join("inventory");
+    fs::create_dir_all(&inventory_dir).unwrap();
+    fs::write(inventory_dir.join("web.ini"), "[web]\nweb1\n").unwrap();
+    fs::write(inventory_dir.join("db.ini"), "[db]\ndb1\n").unwrap();
+    let extra = temp_dir.path().join("extra.ini");
+    fs::write(&extra, "[cache]\ncache1\n").unwrap();
+
+    let mut cmd = Command::cargo_bin("rustle-parse").unwrap();
+    cmd.arg("-i")
+        .arg(&inventory_dir)
+        .arg("-i")
+        .arg(&extra)
+        .arg("--list-hosts");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("web1:"))
+        .stdout(predicate::str::contains("db1:"))
+        .stdout(predicate::str::contains("cache1:"))
+        .stdout(predicate::str::contains("source:").and(predicate::str::contains("extra.ini")));
+}
@@ -188,3 +188,46 @@ fn test_limit_updates_all_group() {
     assert!(all_hosts.contains(&Value::String("web01".to_string())));
     assert!(all_hosts.contains(&Value::String("web02".to_string())));
 }
+
+#[test]
+fn test_limit_pattern_operators_and_retry_file() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let retry_file = temp_dir.path().join("simple.retry");
+    std::fs::write(&retry_file, "web02\ndb-master\n").unwrap();
+
+    let limited_hosts = |limit: &str| {
+        let mut cmd = Command::cargo_bin("rustle-parse").unwrap();
+        let assert = cmd
+            .arg("tests/fixtures/playbooks/simple.yml")
+            .arg("-i")
+            .arg("tests/fixtures/inventories/complex.ini")
+            .arg("--limit")
+            .arg(limit)
+            .assert()
+            .success();
+        let output = String::from_utf8(assert.get_output().stdout.clone()).unwrap();
+        let json: Value = serde_json::from_str(&output).unwrap();
+        let mut hosts: Vec<String> = json["inventory"]["hosts"]
+            .as_object()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect();
+        hosts.sort();
+        hosts
+    };
+
+    assert_eq!(
+        limited_hosts("webservers:!web-lb:!web03"),
+        vec!["web01", "web02"]
+    );
+    assert_eq!(limited_hosts("~web0[13]"), vec!["web01", "web03"]);
+    assert_eq!(
+        limited_hosts(&format!("@{}", retry_file.display())),
+        vec!["db-master", "web02"]
+    );
+    assert_eq!(
+        limited_hosts(&format!("@{}:&webservers", retry_file.display())),
+        vec!["web02"]
+    );
+}
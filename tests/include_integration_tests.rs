@@ -315,6 +315,7 @@ mod include_integration_tests {
     }
 
     /// Recursively copy directory contents
+    #[allow(clippy::type_complexity)]
     fn copy_dir_recursive(
         src: &Path,
         dst: &Path,
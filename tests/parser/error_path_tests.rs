@@ -77,10 +77,10 @@ fn test_vault_decryptor_decrypt_returns_error() {
 
     assert!(result.is_err());
     match result.unwrap_err() {
-        ParseError::UnsupportedFeature { feature } => {
-            assert!(feature.contains("Vault decryption"));
+        ParseError::VaultDecryption { message } => {
+            assert!(message.contains("header"));
         }
-        _ => panic!("Expected UnsupportedFeature error"),
+        _ => panic!("Expected VaultDecryption error"),
     }
 }
 
@@ -114,10 +114,10 @@ fn test_vault_decryptor_error_message() {
     let result = decryptor.decrypt("test");
 
     match result.unwrap_err() {
-        ParseError::UnsupportedFeature { feature } => {
-            assert!(feature.contains("Vault decryption"));
+        ParseError::VaultDecryption { message } => {
+            assert!(message.contains("$ANSIBLE_VAULT"));
         }
-        _ => panic!("Expected UnsupportedFeature error"),
+        _ => panic!("Expected VaultDecryption error"),
     }
 }
 
@@ -137,27 +137,41 @@ fn test_resolve_task_dependencies_single_task() {
         module: "debug".to_string(),
         args: HashMap::new(),
         vars: HashMap::new(),
-        when: None,
+        when: Vec::new(),
         loop_items: None,
         tags: Vec::new(),
         notify: Vec::new(),
+        listen: Vec::new(),
         changed_when: None,
         failed_when: None,
-        ignore_errors: false,
+        ignore_errors: false.into(),
         delegate_to: None,
         dependencies: Vec::new(),
+        block: None,
+        source: None,
+        register: None,
+        statically_skipped: false,
+        deferred_include: None,
+        keywords: TaskKeywords::default(),
     };
 
     let play = ParsedPlay {
         name: "Test play".to_string(),
         hosts: HostPattern::All,
+        tags: vec![],
         vars: HashMap::new(),
+        vars_files: Vec::new(),
         tasks: vec![task],
         handlers: Vec::new(),
         roles: Vec::new(),
         strategy: ExecutionStrategy::Linear,
         serial: None,
         max_fail_percentage: None,
+        notifications: NotificationMap::default(),
+        source: None,
+        var_sources: HashMap::new(),
+        collections: Vec::new(),
+        matched_hosts: None,
     };
 
     let plays = vec![play];
@@ -176,15 +190,22 @@ fn test_resolve_task_dependencies_multiple_tasks() {
         module: "debug".to_string(),
         args: HashMap::new(),
         vars: HashMap::new(),
-        when: None,
+        when: Vec::new(),
         loop_items: None,
         tags: Vec::new(),
         notify: vec!["handler1".to_string()],
+        listen: vec![],
         changed_when: None,
         failed_when: None,
-        ignore_errors: false,
+        ignore_errors: false.into(),
         delegate_to: None,
         dependencies: Vec::new(),
+        block: None,
+        source: None,
+        register: None,
+        statically_skipped: false,
+        deferred_include: None,
+        keywords: TaskKeywords::default(),
     };
 
     let task2 = ParsedTask {
@@ -193,15 +214,22 @@ fn test_resolve_task_dependencies_multiple_tasks() {
         module: "shell".to_string(),
         args: HashMap::new(),
         vars: HashMap::new(),
-        when: None,
+        when: Vec::new(),
         loop_items: None,
         tags: Vec::new(),
         notify: Vec::new(),
+        listen: Vec::new(),
         changed_when: None,
         failed_when: None,
-        ignore_errors: false,
+        ignore_errors: false.into(),
         delegate_to: None,
         dependencies: Vec::new(),
+        block: None,
+        source: None,
+        register: None,
+        statically_skipped: false,
+        deferred_include: None,
+        keywords: TaskKeywords::default(),
     };
 
     let handler = ParsedTask {
@@ -210,27 +238,41 @@ fn test_resolve_task_dependencies_multiple_tasks() {
         module: "service".to_string(),
         args: HashMap::new(),
         vars: HashMap::new(),
-        when: None,
+        when: Vec::new(),
         loop_items: None,
         tags: Vec::new(),
         notify: Vec::new(),
+        listen: Vec::new(),
         changed_when: None,
         failed_when: None,
-        ignore_errors: false,
+        ignore_errors: false.into(),
         delegate_to: None,
         dependencies: Vec::new(),
+        block: None,
+        source: None,
+        register: None,
+        statically_skipped: false,
+        deferred_include: None,
+        keywords: TaskKeywords::default(),
     };
 
     let play = ParsedPlay {
         name: "Test play".to_string(),
         hosts: HostPattern::All,
+        tags: vec![],
         vars: HashMap::new(),
+        vars_files: Vec::new(),
         tasks: vec![task1, task2],
         handlers: vec![handler],
         roles: Vec::new(),
         strategy: ExecutionStrategy::Linear,
         serial: None,
         max_fail_percentage: None,
+        notifications: NotificationMap::default(),
+        source: None,
+        var_sources: HashMap::new(),
+        collections: Vec::new(),
+        matched_hosts: None,
     };
 
     let plays = vec![play];
@@ -251,15 +293,22 @@ fn test_resolve_task_dependencies_complex_scenario() {
         module: "debug".to_string(),
         args: HashMap::new(),
         vars: HashMap::new(),
-        when: None,
+        when: Vec::new(),
         loop_items: None,
         tags: Vec::new(),
         notify: Vec::new(),
+        listen: Vec::new(),
         changed_when: None,
         failed_when: None,
-        ignore_errors: false,
+        ignore_errors: false.into(),
         delegate_to: None,
         dependencies: Vec::new(),
+        block: None,
+        source: None,
+        register: None,
+        statically_skipped: false,
+        deferred_include: None,
+        keywords: TaskKeywords::default(),
     };
 
     let task2 = ParsedTask {
@@ -268,39 +317,60 @@ fn test_resolve_task_dependencies_complex_scenario() {
         module: "shell".to_string(),
         args: HashMap::new(),
         vars: HashMap::new(),
-        when: None,
+        when: Vec::new(),
         loop_items: None,
         tags: Vec::new(),
         notify: Vec::new(),
+        listen: Vec::new(),
         changed_when: None,
         failed_when: None,
-        ignore_errors: false,
+        ignore_errors: false.into(),
         delegate_to: None,
         dependencies: Vec::new(),
+        block: None,
+        source: None,
+        register: None,
+        statically_skipped: false,
+        deferred_include: None,
+        keywords: TaskKeywords::default(),
     };
 
     let play1 = ParsedPlay {
         name: "First play".to_string(),
         hosts: HostPattern::Single("webservers".to_string()),
+        tags: vec![],
         vars: HashMap::new(),
+        vars_files: Vec::new(),
         tasks: vec![task1],
         handlers: Vec::new(),
         roles: Vec::new(),
         strategy: ExecutionStrategy::Linear,
         serial: None,
         max_fail_percentage: None,
+        notifications: NotificationMap::default(),
+        source: None,
+        var_sources: HashMap::new(),
+        collections: Vec::new(),
+        matched_hosts: None,
     };
 
     let play2 = ParsedPlay {
         name: "Second play".to_string(),
         hosts: HostPattern::Single("databases".to_string()),
+        tags: vec![],
         vars: HashMap::new(),
+        vars_files: Vec::new(),
         tasks: vec![task2],
         handlers: Vec::new(),
         roles: Vec::new(),
         strategy: ExecutionStrategy::Free,
-        serial: Some(2),
+        serial: Some(Serial::Count(2)),
         max_fail_percentage: Some(10.0),
+        notifications: NotificationMap::default(),
+        source: None,
+        var_sources: HashMap::new(),
+        collections: Vec::new(),
+        matched_hosts: None,
     };
 
     let plays = vec![play1, play2];
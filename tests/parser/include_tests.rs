@@ -1,7 +1,10 @@
 use rustle_parse::parser::{
     include::{ImportSpec, IncludeConfig, IncludeContext, IncludeHandler, IncludeSpec},
+    task_id::TaskPath,
     template::TemplateEngine,
+    PlaybookParser,
 };
+use rustle_parse::types::parsed::{ParsedTask, TaskKeywords};
 use std::collections::HashMap;
 use tempfile::TempDir;
 use tokio::fs;
@@ -65,6 +68,7 @@ async fn test_include_playbook_functionality() {
         delegate_to: None,
         delegate_facts: None,
         run_once: None,
+        name: None,
     };
 
     let context = IncludeContext {
@@ -80,6 +84,7 @@ async fn test_include_playbook_functionality() {
         include_depth: 0,
         tags: vec![],
         when_condition: None,
+        task_path: TaskPath::default(),
     };
 
     let result = handler.include_playbook(&include_spec, &context).await;
@@ -152,6 +157,9 @@ async fn test_import_playbook_functionality() {
         }),
         when_condition: None,
         tags: Some(vec!["imported".to_string()]),
+        delegate_to: None,
+        ignore_errors: None,
+        keywords: TaskKeywords::default(),
     };
 
     let context = IncludeContext {
@@ -160,6 +168,7 @@ async fn test_import_playbook_functionality() {
         include_depth: 0,
         tags: vec![],
         when_condition: None,
+        task_path: TaskPath::default(),
     };
 
     let result = handler.import_playbook(&import_spec, &context).await;
@@ -194,14 +203,14 @@ async fn test_playbook_include_with_conditions() {
 - name: Conditional Play
   hosts: all
   tasks:
-- name: Always runs
-  debug:
-    msg: "This always runs"
+    - name: Always runs
+      debug:
+        msg: "This always runs"
 
-- name: Conditional task
-  debug:
-    msg: "This runs conditionally"
-  when: run_conditional | default(false)
+    - name: Conditional task
+      debug:
+        msg: "This runs conditionally"
+      when: run_conditional | default(false)
 "#;
     fs::write(base_path.join("conditional.yml"), conditional_playbook)
         .await
@@ -218,12 +227,13 @@ async fn test_playbook_include_with_conditions() {
     let include_spec = IncludeSpec {
         file: "conditional.yml".to_string(),
         vars: None,
-        when_condition: Some("include_this | default(false)".to_string()),
+        when_condition: Some(vec!["include_this | default(false)".to_string()]),
         tags: None,
         apply: None,
         delegate_to: None,
         delegate_facts: None,
         run_once: None,
+        name: None,
     };
 
     let context = IncludeContext {
@@ -236,18 +246,19 @@ async fn test_playbook_include_with_conditions() {
         include_depth: 0,
         tags: vec![],
         when_condition: None,
+        task_path: TaskPath::default(),
     };
 
     let result = handler.include_playbook(&include_spec, &context).await;
 
     match result {
         Ok(plays) => {
-            // Should return empty because when condition is false
-            assert!(
-                plays.is_empty(),
-                "Should not include plays when condition is false"
-            );
-            println!("✅ Conditional include test passed (correctly excluded)");
+            // The plays are kept and carry the include's condition, which
+            // static conditions evaluate when enabled
+            assert!(!plays.is_empty(), "Should include plays");
+            let gate = &plays[0].tasks[0].when[0];
+            assert_eq!(gate.expression, "include_this | default(false)");
+            println!("✅ Conditional include test passed (condition inherited)");
         }
         Err(e) => {
             panic!("Conditional include failed: {e:?}");
@@ -313,13 +324,18 @@ async fn test_playbook_include_with_apply_block() {
         tags: Some(vec!["include_tag".to_string()]),
         apply: Some(rustle_parse::parser::include::ApplySpec {
             tags: Some(vec!["apply_tag".to_string()]),
-            when_condition: Some("apply_condition".to_string()),
-            r#become: Some(true),
-            become_user: Some("root".to_string()),
+            when_condition: Some(vec!["apply_condition".to_string()]),
+            keywords: TaskKeywords {
+                r#become: Some(true.into()),
+                become_user: Some("root".to_string()),
+                ..TaskKeywords::default()
+            },
+            ..Default::default()
         }),
         delegate_to: None,
         delegate_facts: None,
         run_once: None,
+        name: None,
     };
 
     let context = IncludeContext {
@@ -328,6 +344,7 @@ async fn test_playbook_include_with_apply_block() {
         include_depth: 0,
         tags: vec![],
         when_condition: None,
+        task_path: TaskPath::default(),
     };
 
     let result = handler.include_playbook(&include_spec, &context).await;
@@ -341,10 +358,15 @@ async fn test_playbook_include_with_apply_block() {
                 // Check that apply tags are added
                 assert!(task.tags.contains(&"include_tag".to_string()));
                 assert!(task.tags.contains(&"apply_tag".to_string()));
+                assert_eq!(task.keywords.r#become, Some(true.into()));
+                assert_eq!(task.keywords.become_user.as_deref(), Some("root"));
 
                 // Check that apply when condition is combined with existing conditions
-                if let Some(when_condition) = &task.when {
-                    assert!(when_condition.contains("apply_condition"));
+                if !task.when.is_empty() {
+                    assert!(task
+                        .when
+                        .iter()
+                        .any(|c| c.expression.contains("apply_condition")));
                 }
             }
 
@@ -356,6 +378,277 @@ async fn test_playbook_include_with_apply_block() {
     }
 }
 
+/// Task list exercising templated args, blocks, keywords and conditions
+const SHARED_TASKS: &str = r#"---
+- name: Render a message
+  debug:
+    msg: "Deploying {{ app_name }} as {{ result.stdout | default('nobody') }}"
+  when: deploy_enabled
+  tags: [deploy]
+  become: true
+  become_user: deployer
+  notify: restart app
+  changed_when: false
+- name: Guarded block
+  block:
+    - name: Inside block
+      ansible.builtin.command: echo {{ app_name }}
+      register: echo_result
+  rescue:
+    - name: Recover
+      debug:
+        msg: failed
+  when: run_block | default(true)
+"#;
+
+/// A task as JSON without the fields that depend on where it was loaded from
+fn task_shape(task: &ParsedTask) -> serde_json::Value {
+    fn strip(value: &mut serde_json::Value) {
+        match value {
+            serde_json::Value::Object(map) => {
+                map.remove("id");
+                map.remove("source");
+                map.values_mut().for_each(strip);
+            }
+            serde_json::Value::Array(items) => items.iter_mut().for_each(strip),
+            _ => {}
+        }
+    }
+    let mut value = serde_json::to_value(task).unwrap();
+    strip(&mut value);
+    value
+}
+
+#[tokio::test]
+async fn test_same_task_parses_identically_everywhere() {
+    let temp_dir = create_test_environment().await;
+    let base_path = temp_dir.path().to_path_buf();
+
+    let indented: String = SHARED_TASKS
+        .lines()
+        .skip(1)
+        .map(|line| format!("    {line}\n"))
+        .collect();
+    let inline = format!("---\n- hosts: all\n  vars:\n    app_name: shop\n  tasks:\n{indented}");
+    fs::write(base_path.join("inline.yml"), inline)
+        .await
+        .unwrap();
+    fs::write(base_path.join("shared.yml"), SHARED_TASKS)
+        .await
+        .unwrap();
+    let with_includes = "---\n- hosts: all\n  vars:\n    app_name: shop\n  tasks:\n    - include_tasks: shared.yml\n    - import_tasks: shared.yml\n    - include_role:\n        name: shared_role\n    - import_role:\n        name: shared_role\n    - import_tasks: nested.yml\n";
+    fs::write(base_path.join("includes.yml"), with_includes)
+        .await
+        .unwrap();
+    let role_tasks = base_path.join("roles/shared_role/tasks");
+    fs::create_dir_all(&role_tasks).await.unwrap();
+    fs::write(role_tasks.join("main.yml"), SHARED_TASKS)
+        .await
+        .unwrap();
+
+    // Includes inside included files and roles are expanded the same way
+    fs::write(
+        base_path.join("nested.yml"),
+        "- include_tasks: shared.yml\n- include_role:\n    name: nesting_role\n",
+    )
+    .await
+    .unwrap();
+    let nesting_tasks = base_path.join("roles/nesting_role/tasks");
+    fs::create_dir_all(&nesting_tasks).await.unwrap();
+    fs::write(
+        nesting_tasks.join("main.yml"),
+        "- import_tasks: inner.yml\n",
+    )
+    .await
+    .unwrap();
+    fs::write(nesting_tasks.join("inner.yml"), SHARED_TASKS)
+        .await
+        .unwrap();
+
+    let template_engine = TemplateEngine::new();
+    let extra_vars = HashMap::new();
+    let parser = PlaybookParser::new(&template_engine, &extra_vars);
+
+    let inline = parser.parse(&base_path.join("inline.yml")).await.unwrap();
+    let expected: Vec<_> = inline.plays[0].tasks.iter().map(task_shape).collect();
+    assert_eq!(expected.len(), 2);
+    assert_eq!(
+        expected[0]["args"]["msg"],
+        serde_json::json!("Deploying shop as nobody")
+    );
+    assert_eq!(expected[1]["module"], serde_json::json!("block"));
+
+    let included = parser
+        .parse_with_includes(&base_path.join("includes.yml"))
+        .await
+        .unwrap();
+    let loaded: Vec<_> = included.plays[0].tasks.iter().map(task_shape).collect();
+    assert_eq!(loaded[..2], expected[..], "include_tasks");
+    assert_eq!(loaded[2..4], expected[..], "import_tasks");
+
+    assert_eq!(loaded[4..6], expected[..], "include_role");
+    assert_eq!(loaded[6..8], expected[..], "import_role");
+    assert_eq!(loaded[8..10], expected[..], "nested include_tasks");
+    assert_eq!(loaded[10..], expected[..], "nested include_role");
+}
+
+#[tokio::test]
+async fn test_imported_playbooks_parse_like_top_level() {
+    let temp_dir = create_test_environment().await;
+    let base_path = temp_dir.path().to_path_buf();
+
+    fs::write(
+        base_path.join("site.yml"),
+        "---\n- import_playbook: web.yml\n  tags: web\n",
+    )
+    .await
+    .unwrap();
+    fs::write(
+        base_path.join("web.yml"),
+        "---\n- hosts: web\n  tasks:\n    - include_tasks: setup.yml\n    - name: Tagged\n      debug:\n        msg: tagged\n      tags: [web]\n- import_playbook: db.yml\n",
+    )
+    .await
+    .unwrap();
+    fs::write(
+        base_path.join("setup.yml"),
+        "- name: Setup\n  debug:\n    msg: setup\n",
+    )
+    .await
+    .unwrap();
+    fs::write(
+        base_path.join("db.yml"),
+        "---\n- hosts: db\n  tasks:\n    - import_tasks: setup.yml\n",
+    )
+    .await
+    .unwrap();
+
+    let template_engine = TemplateEngine::new();
+    let extra_vars = HashMap::new();
+    let parser = PlaybookParser::new(&template_engine, &extra_vars);
+    let parsed = parser
+        .parse_with_includes(&base_path.join("site.yml"))
+        .await
+        .unwrap();
+
+    // Task includes in imported playbooks, nested imports included, are expanded
+    let names: Vec<Vec<&str>> = parsed
+        .plays
+        .iter()
+        .map(|play| play.tasks.iter().map(|t| t.name.as_str()).collect())
+        .collect();
+    assert_eq!(names, vec![vec!["Setup", "Tagged"], vec!["Setup"]]);
+
+    // The import's tags are not repeated on tasks that already have them
+    for task in parsed.plays.iter().flat_map(|play| &play.tasks) {
+        assert_eq!(task.tags, vec!["web".to_string()]);
+    }
+
+    // A playbook that fails to parse leaves no file on the include stack
+    fs::write(
+        base_path.join("broken.yml"),
+        "---\n- hosts: all\n  tasks: 5\n",
+    )
+    .await
+    .unwrap();
+    let mut handler = IncludeHandler::new(base_path.clone(), TemplateEngine::new());
+    let include_spec = IncludeSpec {
+        file: "broken.yml".to_string(),
+        vars: None,
+        when_condition: None,
+        tags: None,
+        apply: None,
+        delegate_to: None,
+        delegate_facts: None,
+        run_once: None,
+        name: None,
+    };
+    let context = IncludeContext {
+        variables: HashMap::new(),
+        current_file: base_path.join("site.yml"),
+        include_depth: 0,
+        tags: vec![],
+        when_condition: None,
+        task_path: TaskPath::default(),
+    };
+    assert!(handler
+        .include_playbook(&include_spec, &context)
+        .await
+        .is_err());
+    assert_eq!(handler.get_stats().current_depth, 0);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_concurrent_loading_keeps_task_order() {
+    let temp_dir = create_test_environment().await;
+    let base_path = temp_dir.path().to_path_buf();
+
+    let mut playbook = String::from("---\n- hosts: all\n  roles:\n");
+    for role in 0..8 {
+        playbook.push_str(&format!("    - role_{role}\n"));
+        let role_dir = base_path.join(format!("roles/role_{role}"));
+        fs::create_dir_all(role_dir.join("tasks")).await.unwrap();
+        fs::create_dir_all(role_dir.join("defaults")).await.unwrap();
+        fs::write(
+            role_dir.join("defaults/main.yml"),
+            format!("role_label: role {role}\n"),
+        )
+        .await
+        .unwrap();
+        fs::write(
+            role_dir.join("tasks/main.yml"),
+            "- debug:\n    msg: \"{{ role_label }}\"\n",
+        )
+        .await
+        .unwrap();
+    }
+    playbook.push_str("  tasks:\n");
+    for file in 0..12 {
+        let directive = if file % 2 == 0 {
+            "include_tasks"
+        } else {
+            "import_tasks"
+        };
+        playbook.push_str(&format!("    - {directive}: tasks/file_{file}.yml\n"));
+        fs::create_dir_all(base_path.join("tasks")).await.unwrap();
+        fs::write(
+            base_path.join(format!("tasks/file_{file}.yml")),
+            format!(
+                "- debug:\n    msg: file {file} first\n- debug:\n    msg: file {file} second\n"
+            ),
+        )
+        .await
+        .unwrap();
+    }
+    fs::write(base_path.join("site.yml"), playbook)
+        .await
+        .unwrap();
+
+    let template_engine = TemplateEngine::new();
+    let extra_vars = HashMap::new();
+    let parser = PlaybookParser::new(&template_engine, &extra_vars);
+
+    let mut expected: Vec<_> = (0..8)
+        .map(|role| serde_json::json!(format!("role {role}")))
+        .collect();
+    for file in 0..12 {
+        expected.push(serde_json::json!(format!("file {file} first")));
+        expected.push(serde_json::json!(format!("file {file} second")));
+    }
+    let mut ids = None;
+    for _ in 0..3 {
+        let parsed = parser
+            .parse_with_includes(&base_path.join("site.yml"))
+            .await
+            .unwrap();
+        let tasks = &parsed.plays[0].tasks;
+        let messages: Vec<_> = tasks.iter().map(|task| task.args["msg"].clone()).collect();
+        assert_eq!(messages, expected);
+
+        let task_ids: Vec<_> = tasks.iter().map(|task| task.id.clone()).collect();
+        assert_eq!(ids.get_or_insert_with(|| task_ids.clone()), &task_ids);
+    }
+}
+
 async fn create_test_environment() -> TempDir {
     TempDir::new().expect("Failed to create temp directory")
 }
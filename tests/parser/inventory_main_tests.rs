@@ -1,11 +1,14 @@
 use pretty_assertions::assert_eq;
+use rustle_parse::parser::config::{HashBehaviour, TransformInvalidGroupChars};
 use rustle_parse::parser::error::ParseError;
 use rustle_parse::parser::inventory::ini::InventoryParserConfig;
 use rustle_parse::parser::inventory::InventoryParser;
 use rustle_parse::parser::template::TemplateEngine;
+use rustle_parse::parser::yaml::YamlLimits;
 use rustle_parse::types::parsed::*;
 use std::collections::HashMap;
 use std::path::Path;
+use std::time::Duration;
 use tempfile::NamedTempFile;
 
 fn create_temp_file(content: &str) -> NamedTempFile {
@@ -121,6 +124,45 @@ all:
     std::fs::remove_file(path_with_yaml_ext).ok();
 }
 
+#[tokio::test]
+async fn test_inventory_source_locations() {
+    let (template_engine, extra_vars) = setup_parser();
+    let parser = InventoryParser::new(&template_engine, &extra_vars);
+    let temp_dir = tempfile::TempDir::new().unwrap();
+
+    let ini_path = temp_dir.path().join("hosts.ini");
+    std::fs::write(
+        &ini_path,
+        "[webservers]\nweb1 ansible_host=10.0.0.1\n  web2\n\n[databases:vars]\nport=5432\n",
+    )
+    .unwrap();
+    let inventory = parser.parse(&ini_path).await.unwrap();
+
+    let web2 = inventory.hosts["web2"].source.as_ref().unwrap();
+    assert_eq!(web2.file, ini_path.to_string_lossy());
+    assert_eq!((web2.line, web2.column), (3, 3));
+    let webservers = inventory.groups["webservers"].source.as_ref().unwrap();
+    assert_eq!(webservers.line, 1);
+    assert_eq!(
+        inventory.groups["databases"].source.as_ref().unwrap().line,
+        5
+    );
+
+    let yaml_path = temp_dir.path().join("hosts.yml");
+    std::fs::write(
+        &yaml_path,
+        "all:\n  children:\n    webservers:\n      hosts:\n        web1:\n          ansible_host: 10.0.0.1\n",
+    )
+    .unwrap();
+    let inventory = parser.parse(&yaml_path).await.unwrap();
+
+    let web1 = inventory.hosts["web1"].source.as_ref().unwrap();
+    assert_eq!((web1.line, web1.column), (5, 9));
+    let webservers = inventory.groups["webservers"].source.as_ref().unwrap();
+    assert_eq!((webservers.line, webservers.column), (3, 5));
+    assert!(inventory.groups["all"].source.is_some());
+}
+
 #[tokio::test]
 async fn test_parse_json_inventory_file() {
     let (template_engine, extra_vars) = setup_parser();
@@ -328,6 +370,10 @@ async fn test_parser_with_config() {
         max_pattern_expansion: 1000,
         validate_hosts: true,
         resolve_dns: false,
+        yaml_limits: YamlLimits::default(),
+        hash_behaviour: HashBehaviour::default(),
+        transform_invalid_group_chars: TransformInvalidGroupChars::default(),
+        script_timeout: Duration::from_secs(30),
     };
     let parser = InventoryParser::with_config(&template_engine, &extra_vars, config);
 
@@ -472,6 +518,7 @@ async fn test_resolve_group_inheritance() {
             );
             vars
         },
+        source: None,
     };
 
     let child_group = ParsedGroup {
@@ -486,6 +533,7 @@ async fn test_resolve_group_inheritance() {
             );
             vars
         },
+        source: None,
     };
 
     inventory.groups.insert("parent".to_string(), parent_group);
@@ -520,6 +568,7 @@ async fn test_validate_inventory() {
             become_method: None,
             become_user: None,
             become_flags: None,
+            source: None,
         },
     );
 
@@ -531,6 +580,7 @@ async fn test_validate_inventory() {
             hosts: vec!["web1.example.com".to_string()],
             children: vec![],
             vars: HashMap::new(),
+            source: None,
         },
     );
 
@@ -542,6 +592,7 @@ async fn test_validate_inventory() {
             hosts: vec!["web1.example.com".to_string()],
             children: vec!["webservers".to_string()],
             vars: HashMap::new(),
+            source: None,
         },
     );
 
@@ -657,3 +708,391 @@ async fn test_empty_inventory_file() {
 
     std::fs::remove_file(path_with_ini_ext).ok();
 }
+
+#[tokio::test]
+async fn test_hash_behaviour_merge() {
+    let (template_engine, extra_vars) = setup_parser();
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let path = temp_dir.path().join("hosts.yml");
+    std::fs::write(
+        &path,
+        r#"
+all:
+  children:
+    webservers:
+      vars:
+        app: {port: 80, workers: 4, tls: {enabled: false, cert: default.pem}}
+      hosts:
+        web1:
+          app: {port: 8080, tls: {enabled: true}}
+"#,
+    )
+    .unwrap();
+
+    let replaced = InventoryParser::new(&template_engine, &extra_vars)
+        .parse(&path)
+        .await
+        .unwrap();
+    assert_eq!(
+        replaced.hosts["web1"].vars["app"],
+        serde_json::json!({"port": 8080, "tls": {"enabled": true}})
+    );
+
+    let config = InventoryParserConfig {
+        hash_behaviour: HashBehaviour::Merge,
+        ..Default::default()
+    };
+    let merged = InventoryParser::with_config(&template_engine, &extra_vars, config)
+        .parse(&path)
+        .await
+        .unwrap();
+    assert_eq!(
+        merged.hosts["web1"].vars["app"],
+        serde_json::json!({
+            "port": 8080,
+            "workers": 4,
+            "tls": {"enabled": true, "cert": "default.pem"}
+        })
+    );
+}
+
+#[tokio::test]
+async fn test_transform_invalid_group_chars() {
+    let (template_engine, extra_vars) = setup_parser();
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let path = temp_dir.path().join("hosts.ini");
+    std::fs::write(
+        &path,
+        r#"
+[web-servers]
+web1
+
+[web-servers:vars]
+http_port=80
+
+[dc:children]
+web-servers
+"#,
+    )
+    .unwrap();
+
+    let kept = InventoryParser::new(&template_engine, &extra_vars)
+        .parse(&path)
+        .await
+        .unwrap();
+    assert!(kept.groups.contains_key("web-servers"));
+
+    for mode in [
+        TransformInvalidGroupChars::Always,
+        TransformInvalidGroupChars::Silently,
+    ] {
+        let config = InventoryParserConfig {
+            transform_invalid_group_chars: mode,
+            ..Default::default()
+        };
+        let inventory = InventoryParser::with_config(&template_engine, &extra_vars, config)
+            .parse(&path)
+            .await
+            .unwrap();
+
+        assert!(!inventory.groups.contains_key("web-servers"));
+        let group = &inventory.groups["web_servers"];
+        assert_eq!(group.name, "web_servers");
+        assert_eq!(group.hosts, vec!["web1"]);
+        assert_eq!(inventory.groups["dc"].children, vec!["web_servers"]);
+        assert!(inventory.hosts["web1"]
+            .groups
+            .contains(&"web_servers".to_string()));
+        assert_eq!(
+            inventory.hosts["web1"].vars["http_port"],
+            serde_json::json!(80)
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_group_vars_and_host_vars_directories() {
+    let (template_engine, extra_vars) = setup_parser();
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let inventory_dir = temp_dir.path().join("inventory");
+    let playbook_dir = temp_dir.path().join("playbooks");
+    let write = |path: std::path::PathBuf, content: &str| {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    };
+
+    write(
+        inventory_dir.join("hosts.ini"),
+        r#"
+[web]
+web1 inline_host=1
+web2
+
+[dc:children]
+web
+
+[all:vars]
+source=inventory_all
+"#,
+    );
+    write(
+        inventory_dir.join("group_vars/all.yml"),
+        "source: group_vars_all\nall_only: true\n",
+    );
+    write(
+        inventory_dir.join("group_vars/dc.yml"),
+        "source: dc\ndc_only: true\nport: 80\n",
+    );
+    write(
+        inventory_dir.join("group_vars/web/main.yml"),
+        "port: 8080\n",
+    );
+    write(
+        inventory_dir.join("group_vars/web/settings.json"),
+        r#"{"workers": 2}"#,
+    );
+    write(
+        inventory_dir.join("host_vars/web1"),
+        "port: 9090\ninline_host: 2\n",
+    );
+    write(playbook_dir.join("group_vars/web.yml"), "workers: 4\n");
+    write(playbook_dir.join("host_vars/web2.yaml"), "port: 7070\n");
+    // Vars files of groups and hosts the inventory does not have are ignored
+    write(playbook_dir.join("group_vars/db.yml"), "port: 5432\n");
+
+    let parser = InventoryParser::new(&template_engine, &extra_vars);
+    let inventory = parser
+        .parse(&inventory_dir.join("hosts.ini"))
+        .await
+        .unwrap();
+    let web2 = &inventory.hosts["web2"].vars;
+    assert_eq!(web2["port"], serde_json::json!(8080));
+    assert_eq!(web2["workers"], serde_json::json!(2));
+
+    let inventory = parser
+        .with_playbook_dir(&playbook_dir)
+        .parse(&inventory_dir.join("hosts.ini"))
+        .await
+        .unwrap();
+
+    assert!(!inventory.groups.contains_key("db"));
+    assert_eq!(
+        inventory.groups["all"].vars["source"],
+        serde_json::json!("group_vars_all")
+    );
+
+    let web1 = &inventory.hosts["web1"].vars;
+    // host_vars over group_vars, and over variables inline in the inventory
+    assert_eq!(web1["port"], serde_json::json!(9090));
+    assert_eq!(web1["inline_host"], serde_json::json!(2));
+    // Child groups over parents, and any group over `all`
+    assert_eq!(web1["source"], serde_json::json!("dc"));
+    assert_eq!(web1["dc_only"], serde_json::json!(true));
+    assert_eq!(web1["all_only"], serde_json::json!(true));
+    // The playbook's group_vars over the inventory's
+    assert_eq!(web1["workers"], serde_json::json!(4));
+
+    let web2 = &inventory.hosts["web2"].vars;
+    assert_eq!(web2["port"], serde_json::json!(7070));
+    assert_eq!(web2["workers"], serde_json::json!(4));
+}
+
+#[tokio::test]
+async fn test_inventory_directory_and_multiple_sources() {
+    let (template_engine, extra_vars) = setup_parser();
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let prod = temp_dir.path().join("inventories/prod");
+    let extra = temp_dir.path().join("extra.yml");
+    let write = |path: std::path::PathBuf, content: &str| {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    };
+
+    write(
+        prod.join("01-hosts.ini"),
+        "[web]\nweb1 ansible_host=10.0.0.1 tier=frontend\nweb2\n\n[web:vars]\nport=80\n",
+    );
+    write(
+        prod.join("02-db.yml"),
+        "db:\n  hosts:\n    db1:\n      ansible_host: 10.0.1.1\n",
+    );
+    write(prod.join("group_vars/web.yml"), "workers: 4\n");
+    write(prod.join("README.md"), "# Production inventory\n");
+    write(prod.join("01-hosts.ini.bak"), "[old]\nold1\n");
+    write(
+        extra.clone(),
+        "web:\n  hosts:\n    web1:\n      tier: edge\n    web3:\n  vars:\n    port: 8080\n",
+    );
+
+    let parser = InventoryParser::new(&template_engine, &extra_vars);
+    let inventory = parser.parse(&prod).await.unwrap();
+    assert_eq!(inventory.hosts.len(), 3);
+    assert!(!inventory.groups.contains_key("old"));
+    assert_eq!(
+        inventory.hosts["web1"].vars["workers"],
+        serde_json::json!(4)
+    );
+    assert_eq!(inventory.hosts["db1"].address.as_deref(), Some("10.0.1.1"));
+
+    let inventory = parser
+        .parse_sources(&[prod.clone(), extra.clone()])
+        .await
+        .unwrap();
+    let web1 = &inventory.hosts["web1"];
+    // The later source wins for variables, the earlier keeps the rest
+    assert_eq!(web1.vars["tier"], serde_json::json!("edge"));
+    assert_eq!(web1.address.as_deref(), Some("10.0.0.1"));
+    assert_eq!(web1.vars["port"], serde_json::json!(8080));
+    assert_eq!(web1.vars["workers"], serde_json::json!(4));
+
+    let mut web_hosts = inventory.groups["web"].hosts.clone();
+    web_hosts.sort();
+    assert_eq!(web_hosts, vec!["web1", "web2", "web3"]);
+
+    // Each host reports the source it was first defined in
+    let source_file = |host: &str| inventory.hosts[host].source.as_ref().unwrap().file.clone();
+    assert!(source_file("web1").ends_with("01-hosts.ini"));
+    assert!(source_file("db1").ends_with("02-db.yml"));
+    assert!(source_file("web3").ends_with("extra.yml"));
+
+    let missing = parser
+        .parse_sources(&[prod, temp_dir.path().join("missing")])
+        .await;
+    assert!(matches!(missing, Err(ParseError::FileNotFound { .. })));
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_executable_inventory_script() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let inventory_dir = temp_dir.path().join("inventory");
+    std::fs::create_dir_all(&inventory_dir).unwrap();
+    std::fs::write(inventory_dir.join("static.ini"), "[web]\nweb1\n").unwrap();
+    let calls = temp_dir.path().join("calls");
+    let script = inventory_dir.join("cloud.py");
+    std::fs::write(
+        &script,
+        format!(
+            r#"#!/bin/sh
+echo "$@" >> {}
+cat <<'JSON'
+{{
+  "web": ["web2", "web3"],
+  "db": {{"hosts": ["db1"], "vars": {{"engine": "postgres"}}}},
+  "_meta": {{"hostvars": {{"web2": {{"ansible_host": "10.0.0.2"}}}}}}
+}}
+JSON
+"#,
+            calls.display()
+        ),
+    )
+    .unwrap();
+    std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let parser = rustle_parse::Parser::new();
+    let inventory = parser.parse_inventory(&inventory_dir).await.unwrap();
+
+    let mut web_hosts = inventory.groups["web"].hosts.clone();
+    web_hosts.sort();
+    assert_eq!(web_hosts, vec!["web1", "web2", "web3"]);
+    assert_eq!(inventory.hosts["web2"].address.as_deref(), Some("10.0.0.2"));
+    assert_eq!(
+        inventory.hosts["db1"].vars["engine"],
+        serde_json::json!("postgres")
+    );
+    assert!(inventory.hosts["db1"]
+        .source
+        .as_ref()
+        .unwrap()
+        .file
+        .ends_with("cloud.py"));
+
+    // The script runs once per parser however often the inventory is read
+    parser.parse_inventory(&script).await.unwrap();
+    let calls = std::fs::read_to_string(calls).unwrap();
+    assert_eq!(calls.lines().collect::<Vec<_>>(), vec!["--list"]);
+}
+
+#[tokio::test]
+async fn test_constructed_inventory_source() {
+    let (template_engine, extra_vars) = setup_parser();
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let inventory_dir = temp_dir.path().join("inventory");
+    let write = |path: std::path::PathBuf, content: &str| {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    };
+
+    // Listed before the hosts it constructs from, but run after them
+    write(
+        inventory_dir.join("00-constructed.yml"),
+        r#"
+plugin: ansible.builtin.constructed
+compose:
+  ansible_host: private_ip
+  fqdn: inventory_hostname ~ '.example.com'
+groups:
+  webservers: "'web' in role"
+  large: memory_gb > 8
+keyed_groups:
+  - key: os_family
+    prefix: os
+    parent_group: operating_systems
+"#,
+    );
+    write(
+        inventory_dir.join("hosts.yml"),
+        r#"
+cloud:
+  hosts:
+    web1:
+      private_ip: 10.0.0.1
+      role: [web, cache]
+      memory_gb: 16
+    db1:
+      private_ip: 10.0.0.2
+      role: [db]
+      memory_gb: 4
+  vars:
+    os_family: RedHat
+"#,
+    );
+    write(
+        inventory_dir.join("group_vars/os_RedHat.yml"),
+        "package_manager: dnf\n",
+    );
+
+    let parser = InventoryParser::new(&template_engine, &extra_vars);
+    let inventory = parser.parse(&inventory_dir).await.unwrap();
+
+    let web1 = &inventory.hosts["web1"];
+    assert_eq!(web1.vars["ansible_host"], serde_json::json!("10.0.0.1"));
+    assert_eq!(web1.vars["fqdn"], serde_json::json!("web1.example.com"));
+    assert!(web1.groups.contains(&"webservers".to_string()));
+    assert!(web1.groups.contains(&"large".to_string()));
+    assert!(!inventory.hosts["db1"]
+        .groups
+        .contains(&"webservers".to_string()));
+
+    let mut os_hosts = inventory.groups["os_RedHat"].hosts.clone();
+    os_hosts.sort();
+    assert_eq!(os_hosts, vec!["db1", "web1"]);
+    assert_eq!(
+        inventory.groups["operating_systems"].children,
+        vec!["os_RedHat"]
+    );
+    // group_vars of constructed groups apply like any other group's
+    assert_eq!(
+        inventory.hosts["db1"].vars["package_manager"],
+        serde_json::json!("dnf")
+    );
+
+    write(
+        inventory_dir.join("aws_ec2.yml"),
+        "plugin: amazon.aws.aws_ec2\n",
+    );
+    let result = parser.parse(&inventory_dir).await;
+    assert!(matches!(result, Err(ParseError::UnsupportedFeature { .. })));
+}
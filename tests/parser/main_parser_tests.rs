@@ -1,4 +1,4 @@
-use rustle_parse::parser::{ParseError, Parser};
+use rustle_parse::parser::{ParseError, Parser, ParserConfig};
 use std::collections::HashMap;
 use std::path::Path;
 use tempfile::{NamedTempFile, TempDir};
@@ -501,3 +501,120 @@ async fn test_validate_syntax_whitespace_only() {
     // Whitespace-only file should be invalid
     assert!(result.is_err());
 }
+
+#[tokio::test]
+async fn test_parser_config_reaches_sub_parsers() {
+    let temp_dir = TempDir::new().unwrap();
+    let dir = temp_dir.path();
+    std::fs::write(
+        dir.join("site.yml"),
+        "- hosts: all\n  vars:\n    port: 8080\n  tasks:\n    - include_tasks: listen.yml\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("listen.yml"),
+        "- name: Listen\n  debug:\n    msg: \"{{ port }}\"\n",
+    )
+    .unwrap();
+    std::fs::write(dir.join("hosts.ini"), "[web]\nweb1\nweb1\n").unwrap();
+
+    let playbook = Parser::new()
+        .parse_playbook(&dir.join("site.yml"))
+        .await
+        .unwrap();
+    let task = &playbook.plays[0].tasks[0];
+    assert_eq!(task.args["msg"], serde_json::json!("8080"));
+    assert!(Parser::new()
+        .parse_inventory(&dir.join("hosts.ini"))
+        .await
+        .is_ok());
+
+    let mut config = ParserConfig::default();
+    config.include.max_include_depth = 0;
+    config.inventory.strict_mode = true;
+    let parser = Parser::new().with_config(config);
+    assert_eq!(parser.config().include.max_include_depth, 0);
+    assert!(matches!(
+        parser.parse_playbook(&dir.join("site.yml")).await,
+        Err(ParseError::MaxIncludeDepthExceeded { .. })
+    ));
+    assert!(matches!(
+        parser.parse_inventory(&dir.join("hosts.ini")).await,
+        Err(ParseError::DuplicateHost { .. })
+    ));
+
+    let config = ParserConfig {
+        jinja2_native: true,
+        ..ParserConfig::default()
+    };
+    let playbook = Parser::new()
+        .with_config(config)
+        .parse_playbook(&dir.join("site.yml"))
+        .await
+        .unwrap();
+    assert_eq!(
+        playbook.plays[0].tasks[0].args["msg"],
+        serde_json::json!(8080)
+    );
+}
+
+#[tokio::test]
+async fn test_resolve_play_hosts() {
+    let temp_dir = TempDir::new().unwrap();
+    let dir = temp_dir.path();
+    std::fs::write(
+        dir.join("site.yml"),
+        r#"
+- name: Web outside staging
+  hosts: webservers:!staging
+  tasks: []
+- name: First database
+  hosts: databases[0]
+  tasks: []
+- name: Everything
+  hosts: all
+  tasks: []
+- name: Listed patterns
+  hosts: ["~web[12]", "&staging"]
+  tasks: []
+- name: From a variable
+  hosts: "{{ target }}"
+  tasks: []
+- name: From a runtime variable
+  hosts: "{{ discovered }}"
+  tasks: []
+"#,
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("hosts.ini"),
+        "[webservers]\nweb1\nweb2\nweb3\n\n[databases]\ndb2\ndb1\n\n[staging]\nweb1\ndb1\n",
+    )
+    .unwrap();
+
+    let parser = Parser::new().with_extra_vars(HashMap::from([(
+        "target".to_string(),
+        serde_json::json!("databases"),
+    )]));
+    let mut playbook = parser.parse_playbook(&dir.join("site.yml")).await.unwrap();
+    playbook.inventory = parser
+        .parse_inventory(&dir.join("hosts.ini"))
+        .await
+        .unwrap();
+    parser.resolve_play_hosts(&mut playbook).unwrap();
+
+    let matched = |index: usize| playbook.plays[index].matched_hosts.clone();
+    assert_eq!(matched(0), Some(vec!["web2".into(), "web3".into()]));
+    assert_eq!(matched(1), Some(vec!["db2".into()]));
+    assert_eq!(matched(2).unwrap().len(), 5);
+    assert_eq!(matched(3), Some(vec!["web1".into()]));
+    assert_eq!(matched(4), Some(vec!["db2".into(), "db1".into()]));
+    assert_eq!(matched(5), None);
+
+    // A limit narrows the hosts every play can match
+    parser
+        .filter_inventory(&mut playbook.inventory, "all:!web2")
+        .unwrap();
+    parser.resolve_play_hosts(&mut playbook).unwrap();
+    assert_eq!(playbook.plays[0].matched_hosts, Some(vec!["web3".into()]));
+}
@@ -7,5 +7,7 @@ mod main_parser_tests;
 mod pattern_expansion_tests;
 mod playbook_comprehensive_tests;
 mod playbook_tests;
+mod requirements_tests;
+mod tags_tests;
 mod template_comprehensive_tests;
 mod template_tests;
@@ -97,8 +97,8 @@ async fn test_parse_complex_playbook_with_multiple_plays() {
 
     let pg_task = &second_play.tasks[0];
     assert_eq!(
-        pg_task.when,
-        Some("ansible_os_family == \"Debian\"".to_string())
+        pg_task.when[0].expression,
+        "ansible_os_family == \"Debian\""
     );
 }
 
@@ -257,16 +257,16 @@ async fn test_parse_playbook_with_loops_and_conditionals() {
     assert_eq!(loop_task.name, "Install packages");
     assert!(loop_task.loop_items.is_some());
     assert_eq!(
-        loop_task.when,
-        Some("ansible_os_family == \"Debian\"".to_string())
+        loop_task.when[0].expression,
+        "ansible_os_family == \"Debian\""
     );
     assert!(loop_task.tags.contains(&"packages".to_string()));
 
     // Conditional task
     let conditional_task = &play.tasks[1];
     assert_eq!(
-        conditional_task.when,
-        Some("ansible_os_family == \"RedHat\"".to_string())
+        conditional_task.when[0].expression,
+        "ansible_os_family == \"RedHat\""
     );
     assert_eq!(
         conditional_task.changed_when,
@@ -276,7 +276,10 @@ async fn test_parse_playbook_with_loops_and_conditionals() {
         conditional_task.failed_when,
         Some(rustle_parse::types::parsed::BooleanOrString::Boolean(false))
     );
-    assert_eq!(conditional_task.ignore_errors, true);
+    assert_eq!(
+        conditional_task.ignore_errors,
+        BooleanOrString::Boolean(true)
+    );
 
     // Delegation task
     let delegation_task = &play.tasks[2];
@@ -347,7 +350,7 @@ async fn test_parse_playbook_with_execution_strategy() {
 
     let play = &playbook.plays[0];
     assert_eq!(play.strategy, ExecutionStrategy::Free);
-    assert_eq!(play.serial, Some(3));
+    assert_eq!(play.serial, Some(Serial::Count(3)));
     assert_eq!(play.max_fail_percentage, Some(25.0));
 }
 
@@ -818,8 +821,5 @@ async fn test_parse_system_facts_playbook() {
         linux_task.args["msg"],
         serde_json::Value::String("This is a Linux system with  CPU cores".to_string())
     );
-    assert_eq!(
-        linux_task.when,
-        Some("ansible_system == \"Linux\"".to_string())
-    );
+    assert_eq!(linux_task.when[0].expression, "ansible_system == \"Linux\"");
 }
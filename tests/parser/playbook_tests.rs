@@ -1,4 +1,6 @@
 use rustle_parse::parser::{ParseError, Parser};
+use rustle_parse::types::parsed::{BooleanOrString, ConditionExpr, ParsedTask, Serial};
+use rustle_parse::{StaticConditionMode, YamlLimits};
 use std::collections::HashMap;
 use std::path::PathBuf;
 use tempfile::NamedTempFile;
@@ -85,3 +87,1608 @@ async fn test_parse_nonexistent_file() {
         other => panic!("Expected FileNotFound error, got: {other:?}"),
     }
 }
+
+#[tokio::test]
+async fn test_parse_handler_listen_and_notify_resolution() {
+    let playbook_content = r#"
+- name: Handler notification test
+  hosts: all
+  tasks:
+    - name: Update config
+      copy:
+        src: app.conf
+        dest: /etc/app.conf
+      notify: restart web stack
+
+    - name: Update certificates
+      copy:
+        src: cert.pem
+        dest: /etc/cert.pem
+      notify:
+        - reload nginx
+        - not a handler
+  handlers:
+    - name: restart memcached
+      service:
+        name: memcached
+        state: restarted
+      listen: restart web stack
+
+    - name: reload nginx
+      service:
+        name: nginx
+        state: reloaded
+      listen:
+        - restart web stack
+      notify: restart memcached
+"#;
+
+    let temp_file = NamedTempFile::new().unwrap();
+    fs::write(temp_file.path(), playbook_content).await.unwrap();
+
+    let parser = Parser::new();
+    let playbook = parser.parse_playbook(temp_file.path()).await.unwrap();
+    let play = &playbook.plays[0];
+
+    assert_eq!(play.handlers[0].listen, vec!["restart web stack"]);
+    assert_eq!(play.handlers[1].listen, vec!["restart web stack"]);
+
+    let handler_ids: Vec<String> = play.handlers.iter().map(|h| h.id.clone()).collect();
+    assert_eq!(play.notifications.triggers[&play.tasks[0].id], handler_ids);
+
+    assert_eq!(play.notifications.unresolved.len(), 1);
+    assert_eq!(play.notifications.unresolved[0].target, "not a handler");
+}
+
+fn collect_task_ids(tasks: &[rustle_parse::types::parsed::ParsedTask], ids: &mut Vec<String>) {
+    for task in tasks {
+        ids.push(task.id.clone());
+        if let Some(block) = &task.block {
+            collect_task_ids(&block.block, ids);
+            collect_task_ids(&block.rescue, ids);
+            collect_task_ids(&block.always, ids);
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_task_ids_are_unique_and_stable() {
+    let playbook_content = r#"
+- name: First play
+  hosts: all
+  tasks:
+    - name: Install package
+      package:
+        name: nginx
+    - include_tasks: common.yml
+    - name: Guarded
+      block:
+        - name: Inside block
+          debug:
+            msg: block
+      rescue:
+        - name: Inside rescue
+          debug:
+            msg: rescue
+    - include_tasks: common.yml
+  handlers:
+    - name: restart nginx
+      service:
+        name: nginx
+        state: restarted
+
+- name: Second play
+  hosts: all
+  tasks:
+    - name: Install package
+      package:
+        name: nginx
+"#;
+    let common_tasks = r#"
+- name: Common task
+  debug:
+    msg: common
+"#;
+
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let playbook_path = temp_dir.path().join("site.yml");
+    fs::write(&playbook_path, playbook_content).await.unwrap();
+    fs::write(temp_dir.path().join("common.yml"), common_tasks)
+        .await
+        .unwrap();
+
+    let parser = Parser::new();
+    let playbook = parser.parse_playbook(&playbook_path).await.unwrap();
+
+    let mut ids = Vec::new();
+    for play in &playbook.plays {
+        collect_task_ids(&play.tasks, &mut ids);
+        collect_task_ids(&play.handlers, &mut ids);
+    }
+    let unique: std::collections::HashSet<&String> = ids.iter().collect();
+    assert_eq!(ids.len(), 8);
+    assert_eq!(unique.len(), ids.len(), "duplicate task ids: {ids:?}");
+    assert!(ids.iter().all(|id| id.starts_with("task_")));
+
+    // Editing an unrelated task keeps every id, and so does parsing again
+    let edited =
+        playbook_content.replace("name: nginx\n    - include", "name: apache2\n    - include");
+    fs::write(&playbook_path, edited).await.unwrap();
+    let reparsed = parser.parse_playbook(&playbook_path).await.unwrap();
+
+    let mut reparsed_ids = Vec::new();
+    for play in &reparsed.plays {
+        collect_task_ids(&play.tasks, &mut reparsed_ids);
+        collect_task_ids(&play.handlers, &mut reparsed_ids);
+    }
+    assert_eq!(reparsed.plays[0].tasks[0].args["name"], "apache2");
+    assert_eq!(reparsed_ids, ids);
+}
+
+#[tokio::test]
+async fn test_task_ids_survive_inserted_tasks() {
+    let playbook_content = r#"- hosts: all
+  tasks:
+    - name: Install package
+      package:
+        name: nginx
+    - service:
+        name: nginx
+    - name: Guarded
+      block:
+        - name: Inside block
+          debug:
+            msg: block
+"#;
+
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let playbook_path = temp_dir.path().join("site.yml");
+    fs::write(&playbook_path, playbook_content).await.unwrap();
+    let parser = Parser::new();
+    let playbook = parser.parse_playbook(&playbook_path).await.unwrap();
+    let mut ids = Vec::new();
+    collect_task_ids(&playbook.plays[0].tasks, &mut ids);
+
+    // A task inserted in front and one inside the block shift every index
+    let edited = playbook_content
+        .replace(
+            "  tasks:\n",
+            "  tasks:\n    - name: Added\n      debug:\n        msg: new\n",
+        )
+        .replace(
+            "      block:\n",
+            "      block:\n        - command: /bin/true\n",
+        );
+    fs::write(&playbook_path, edited).await.unwrap();
+    let reparsed = parser.parse_playbook(&playbook_path).await.unwrap();
+    let mut reparsed_ids = Vec::new();
+    collect_task_ids(&reparsed.plays[0].tasks, &mut reparsed_ids);
+
+    assert_eq!(reparsed_ids.len(), ids.len() + 2);
+    for id in &ids {
+        assert!(reparsed_ids.contains(id), "{id} changed: {reparsed_ids:?}");
+    }
+}
+
+#[tokio::test]
+async fn test_source_locations() {
+    let playbook_content = r#"- name: Located play
+  hosts: all
+  vars:
+    http_port: 80
+  roles:
+    - common
+  tasks:
+    - name: Install package
+      package:
+        name: nginx
+    - block:
+        - name: Inside block
+          debug:
+            msg: block
+      rescue:
+        - name: Inside rescue
+          debug:
+            msg: rescue
+    - include_tasks: common.yml
+  handlers:
+    - name: restart nginx
+      service:
+        name: nginx
+        state: restarted
+"#;
+    let common_tasks = r#"
+- name: Common task
+  debug:
+    msg: common
+"#;
+
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let playbook_path = temp_dir.path().join("site.yml");
+    fs::write(&playbook_path, playbook_content).await.unwrap();
+    fs::write(temp_dir.path().join("common.yml"), common_tasks)
+        .await
+        .unwrap();
+
+    let parser = Parser::new();
+    let playbook = parser.parse_playbook(&playbook_path).await.unwrap();
+    let play = &playbook.plays[0];
+    let line_col = |source: &Option<rustle_parse::types::parsed::SourceLocation>| {
+        let source = source.as_ref().expect("missing source location");
+        (source.line, source.column)
+    };
+
+    let play_source = play.source.as_ref().unwrap();
+    assert_eq!(play_source.file, playbook_path.to_string_lossy());
+    assert_eq!((play_source.line, play_source.column), (1, 3));
+    assert_eq!(line_col(&play.tasks[0].source), (8, 7));
+    assert_eq!(line_col(&play.handlers[0].source), (21, 7));
+    assert_eq!(line_col(&play.roles[0].source), (6, 7));
+    assert_eq!(
+        (
+            play.var_sources["http_port"].line,
+            play.var_sources["http_port"].column
+        ),
+        (4, 5)
+    );
+
+    let block_task = &play.tasks[1];
+    let block = block_task.block.as_ref().unwrap();
+    assert_eq!(line_col(&block_task.source), (11, 7));
+    assert_eq!(line_col(&block.block[0].source), (12, 11));
+    assert_eq!(line_col(&block.rescue[0].source), (16, 11));
+
+    let included = play.tasks[2].source.as_ref().unwrap();
+    assert!(included.file.ends_with("common.yml"));
+    assert_eq!((included.line, included.column), (2, 3));
+
+    let json = serde_json::to_value(&playbook).unwrap();
+    assert_eq!(json["plays"][0]["tasks"][0]["source"]["line"], 8);
+}
+
+#[tokio::test]
+async fn test_when_condition_forms() {
+    let playbook_content = r#"- hosts: all
+  tasks:
+    - name: List form
+      debug:
+        msg: list
+      when:
+        - ansible_os_family == "Debian"
+        - result.rc is defined
+    - name: Boolean form
+      debug:
+        msg: never
+      when: false
+    - name: Templated form
+      debug:
+        msg: templated
+      when: "{{ enabled | bool }}"
+"#;
+
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let playbook_path = temp_dir.path().join("site.yml");
+    fs::write(&playbook_path, playbook_content).await.unwrap();
+
+    let playbook = Parser::new().parse_playbook(&playbook_path).await.unwrap();
+    let tasks = &playbook.plays[0].tasks;
+
+    let expressions: Vec<&str> = tasks[0]
+        .when
+        .iter()
+        .map(|c| c.expression.as_str())
+        .collect();
+    assert_eq!(
+        expressions,
+        vec!["ansible_os_family == \"Debian\"", "result.rc is defined"]
+    );
+    assert!(tasks[0].when.iter().all(|c| c.ast.is_some()));
+
+    assert_eq!(tasks[1].when.len(), 1);
+    assert_eq!(tasks[1].when[0].expression, "false");
+    assert_eq!(
+        tasks[1].when[0].ast,
+        Some(ConditionExpr::Literal {
+            value: serde_json::json!(false)
+        })
+    );
+
+    assert!(matches!(
+        tasks[2].when[0].ast,
+        Some(ConditionExpr::Filter { ref name, .. }) if name == "bool"
+    ));
+}
+
+#[tokio::test]
+async fn test_invalid_when_condition_reports_location() {
+    let playbook_content = r#"- hosts: all
+  tasks:
+    - name: Fine
+      debug:
+        msg: ok
+    - name: Broken
+      debug:
+        msg: broken
+      when: result.rc ==
+"#;
+
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let playbook_path = temp_dir.path().join("site.yml");
+    fs::write(&playbook_path, playbook_content).await.unwrap();
+
+    let err = Parser::new()
+        .parse_playbook(&playbook_path)
+        .await
+        .unwrap_err();
+    match err {
+        ParseError::InvalidCondition {
+            condition,
+            location,
+            ..
+        } => {
+            assert_eq!(condition, "result.rc ==");
+            assert_eq!(location, format!("{}:6:7", playbook_path.display()));
+        }
+        other => panic!("Expected InvalidCondition, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn test_static_conditions() {
+    let playbook_content = r#"- hosts: all
+  vars:
+    env: prod
+    feature: false
+  tasks:
+    - name: Dev only
+      debug:
+        msg: dev
+      when: env == "dev"
+    - name: Prod and runtime
+      debug:
+        msg: prod
+      when:
+        - env == "prod"
+        - ansible_os_family == "Debian"
+    - name: Redefine feature
+      set_fact:
+        feature: true
+    - name: Feature enabled
+      debug:
+        msg: feature
+      when: feature | bool
+    - block:
+        - name: Inside skipped block
+          debug:
+            msg: never
+      when: env != "prod"
+    - block:
+        - name: Task var wins
+          debug:
+            msg: local
+          vars:
+            env: dev
+          when: env == "prod"
+"#;
+
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let playbook_path = temp_dir.path().join("site.yml");
+    fs::write(&playbook_path, playbook_content).await.unwrap();
+
+    let flagged = Parser::new()
+        .with_static_conditions(StaticConditionMode::Flag)
+        .parse_playbook(&playbook_path)
+        .await
+        .unwrap();
+    let skipped: Vec<bool> = flagged.plays[0]
+        .tasks
+        .iter()
+        .map(|t| t.statically_skipped)
+        .collect();
+    assert_eq!(skipped, vec![true, false, false, false, true, false]);
+    assert_eq!(flagged.plays[0].tasks[1].when.len(), 2);
+    let nested = &flagged.plays[0].tasks[5].block.as_ref().unwrap().block[0];
+    assert!(nested.statically_skipped);
+
+    let pruned = Parser::new()
+        .with_static_conditions(StaticConditionMode::Prune)
+        .parse_playbook(&playbook_path)
+        .await
+        .unwrap();
+    let names: Vec<&str> = pruned.plays[0]
+        .tasks
+        .iter()
+        .map(|t| t.name.as_str())
+        .collect();
+    assert_eq!(
+        names,
+        vec!["Prod and runtime", "Redefine feature", "Feature enabled"]
+    );
+
+    // Conditions that always hold are dropped, runtime ones are kept
+    let expressions: Vec<&str> = pruned.plays[0].tasks[0]
+        .when
+        .iter()
+        .map(|c| c.expression.as_str())
+        .collect();
+    assert_eq!(expressions, vec!["ansible_os_family == \"Debian\""]);
+    assert_eq!(pruned.plays[0].tasks[2].when.len(), 1);
+}
+
+#[tokio::test]
+async fn test_static_conditions_on_includes() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let root = temp_dir.path();
+    fs::create_dir_all(root.join("os")).await.unwrap();
+    for (file, task) in [
+        (
+            "debian.yml",
+            "- name: Debian task\n  debug:\n    msg: debian\n",
+        ),
+        ("dev.yml", "- name: Dev task\n  debug:\n    msg: dev\n"),
+        (
+            "os/redhat.yml",
+            "- name: Pick repo\n  set_fact:\n    repo: epel\n",
+        ),
+    ] {
+        fs::write(root.join(file), task).await.unwrap();
+    }
+
+    let playbook_path = root.join("site.yml");
+    fs::write(
+        &playbook_path,
+        r#"- hosts: all
+  vars:
+    env: prod
+  tasks:
+    - include_tasks: debian.yml
+      when: ansible_os_family == "Debian" and undefined_flag
+    - include_tasks: dev.yml
+      when: env == "dev"
+    - include_tasks: "os/{{ ansible_os_family | lower }}.yml"
+    - set_fact: cluster=blue note="a b=c"
+  handlers:
+    - name: mark restarted
+      set_fact:
+        restarted: true
+- hosts: all
+  vars:
+    cluster: green
+    restarted: false
+    repo: base
+    b: 1
+  tasks:
+    - name: Cluster
+      debug:
+        msg: cluster
+      when: cluster == "green"
+    - name: Restarted
+      debug:
+        msg: restarted
+      when: not restarted
+    - name: Repo
+      debug:
+        msg: repo
+      when: repo == "base"
+    - name: Quoted text is not a key
+      debug:
+        msg: b
+      when: b == 2
+"#,
+    )
+    .await
+    .unwrap();
+
+    // Without static conditions every include is loaded, carrying its condition
+    let playbook = Parser::new().parse_playbook(&playbook_path).await.unwrap();
+    let names: Vec<&str> = playbook.plays[0]
+        .tasks
+        .iter()
+        .map(|t| t.name.as_str())
+        .collect();
+    assert_eq!(names[..2], ["Debian task", "Dev task"]);
+    assert_eq!(
+        playbook.plays[0].tasks[1].when[0].expression,
+        "env == \"dev\""
+    );
+
+    let flagged = Parser::new()
+        .with_static_conditions(StaticConditionMode::Flag)
+        .parse_playbook(&playbook_path)
+        .await
+        .unwrap();
+    let skipped: Vec<bool> = flagged.plays[0]
+        .tasks
+        .iter()
+        .map(|t| t.statically_skipped)
+        .collect();
+    assert_eq!(skipped[..2], [false, true]);
+
+    let pruned = Parser::new()
+        .with_static_conditions(StaticConditionMode::Prune)
+        .parse_playbook(&playbook_path)
+        .await
+        .unwrap();
+    assert_eq!(pruned.plays[0].tasks[0].name, "Debian task");
+    assert_eq!(pruned.plays[0].tasks[0].when.len(), 1);
+    assert!(pruned.plays[0].tasks.iter().all(|t| t.name != "Dev task"));
+
+    // Names set by free-form `set_fact`, handlers and include candidates of an
+    // earlier play are runtime variables in later plays
+    let names: Vec<&str> = pruned.plays[1]
+        .tasks
+        .iter()
+        .map(|t| t.name.as_str())
+        .collect();
+    assert_eq!(names, vec!["Cluster", "Restarted", "Repo"]);
+    assert!(pruned.plays[1].tasks.iter().all(|t| t.when.len() == 1));
+}
+
+#[tokio::test]
+async fn test_static_conditions_with_role_include_and_file_vars() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let root = temp_dir.path();
+    let role_tasks = root.join("roles").join("app").join("tasks");
+    fs::create_dir_all(&role_tasks).await.unwrap();
+    fs::write(
+        role_tasks.join("main.yml"),
+        "- name: Staging role task\n  debug:\n    msg: staging\n  when: env == 'staging'\n",
+    )
+    .await
+    .unwrap();
+    fs::write(
+        root.join("inc.yml"),
+        "- name: QA include task\n  debug:\n    msg: qa\n  when: env == 'qa'\n",
+    )
+    .await
+    .unwrap();
+    fs::write(root.join("env.yml"), "env: dev\n").await.unwrap();
+
+    let playbook_path = root.join("site.yml");
+    fs::write(
+        &playbook_path,
+        r#"- hosts: all
+  vars:
+    env: prod
+  roles:
+    - role: app
+      env: staging
+  tasks:
+    - include_tasks: inc.yml
+      vars:
+        env: qa
+- hosts: all
+  vars:
+    env: prod
+  vars_files:
+    - env.yml
+  tasks:
+    - name: Dev from vars file
+      debug:
+        msg: dev
+      when: env == 'dev'
+- hosts: all
+  vars:
+    env: prod
+  tasks:
+    - name: Dev without overrides
+      debug:
+        msg: dev
+      when: env == 'dev'
+"#,
+    )
+    .await
+    .unwrap();
+
+    let flagged = Parser::new()
+        .with_static_conditions(StaticConditionMode::Flag)
+        .parse_playbook(&playbook_path)
+        .await
+        .unwrap();
+    let skipped: Vec<(&str, bool)> = flagged
+        .plays
+        .iter()
+        .flat_map(|play| &play.tasks)
+        .map(|t| (t.name.as_str(), t.statically_skipped))
+        .collect();
+    assert_eq!(
+        skipped,
+        vec![
+            ("Staging role task", false),
+            ("QA include task", false),
+            ("Dev from vars file", false),
+            ("Dev without overrides", true),
+        ]
+    );
+    assert_eq!(flagged.plays[0].tasks[1].vars["env"], "qa");
+    assert_eq!(flagged.plays[1].vars_files, vec!["env.yml"]);
+
+    let pruned = Parser::new()
+        .with_static_conditions(StaticConditionMode::Prune)
+        .parse_playbook(&playbook_path)
+        .await
+        .unwrap();
+    let names: Vec<&str> = pruned
+        .plays
+        .iter()
+        .flat_map(|play| &play.tasks)
+        .map(|t| t.name.as_str())
+        .collect();
+    assert_eq!(
+        names,
+        vec!["Staging role task", "QA include task", "Dev from vars file"]
+    );
+}
+
+#[tokio::test]
+async fn test_lenient_keyword_values() {
+    let playbook_content = r#"- name: 2024
+  hosts: all
+  tags: site
+  serial: "5"
+  max_fail_percentage: "20%"
+  tasks:
+    - name: 42
+      debug:
+        msg: hi
+      tags: "web, db"
+      become: "yes"
+      ignore_errors: 1
+      notify: restart
+    - name: Listed
+      debug:
+        msg: hi
+      tags: [web, 7]
+    - name: Templated
+      debug:
+        msg: hi
+      ignore_errors: "{{ allow_fail }}"
+      no_log: 1
+      check_mode: 'y'
+      become_user: 1000
+  handlers:
+    - name: restart
+      debug:
+        msg: restarted
+- import_playbook: other.yml
+  tags: "a, b"
+- import_playbook: other.yml
+  tags: 7
+"#;
+
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let playbook_path = temp_dir.path().join("site.yml");
+    fs::write(&playbook_path, playbook_content).await.unwrap();
+    fs::write(
+        temp_dir.path().join("other.yml"),
+        "- hosts: all\n  tasks:\n    - debug:\n        msg: hi\n",
+    )
+    .await
+    .unwrap();
+
+    let playbook = Parser::new().parse_playbook(&playbook_path).await.unwrap();
+    let play = &playbook.plays[0];
+    assert_eq!(play.name, "2024");
+    assert_eq!(play.tags, vec!["site"]);
+    assert_eq!(play.serial, Some(Serial::Count(5)));
+    assert_eq!(play.max_fail_percentage, Some(20.0));
+
+    let task = &play.tasks[0];
+    assert_eq!(task.name, "42");
+    assert!(task.tags.contains(&"web".to_string()));
+    assert!(task.tags.contains(&"db".to_string()));
+    assert_eq!(task.ignore_errors, BooleanOrString::Boolean(true));
+    assert_eq!(task.notify, vec!["restart"]);
+    assert!(play.tasks[1].tags.contains(&"7".to_string()));
+
+    // Templates and YAML 1.1 spellings are kept or read like `become`
+    let templated = &play.tasks[2];
+    assert_eq!(
+        templated.ignore_errors,
+        BooleanOrString::String("{{ allow_fail }}".to_string())
+    );
+    assert_eq!(
+        templated.keywords.no_log,
+        Some(BooleanOrString::Boolean(true))
+    );
+    assert_eq!(
+        templated.keywords.check_mode,
+        Some(BooleanOrString::Boolean(true))
+    );
+    assert_eq!(templated.keywords.become_user.as_deref(), Some("1000"));
+
+    let imported_tags = &playbook.plays[1].tasks[0].tags;
+    assert!(imported_tags.contains(&"a".to_string()));
+    assert!(imported_tags.contains(&"b".to_string()));
+    assert!(playbook.plays[2].tasks[0].tags.contains(&"7".to_string()));
+}
+
+#[tokio::test]
+async fn test_templated_and_percentage_keywords() {
+    let playbook_content = r#"- hosts: all
+  serial: [1, "30%"]
+  tasks:
+    - name: Maybe privileged
+      debug:
+        msg: hi
+      become: "{{ use_become | default(false) }}"
+      notify: [1, restart]
+- hosts: all
+  serial: "25%"
+  tasks: []
+"#;
+
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let playbook_path = temp_dir.path().join("site.yml");
+    fs::write(&playbook_path, playbook_content).await.unwrap();
+
+    let playbook = Parser::new().parse_playbook(&playbook_path).await.unwrap();
+    let play = &playbook.plays[0];
+    assert_eq!(
+        play.serial,
+        Some(Serial::Batches(vec![
+            Serial::Count(1),
+            Serial::Expression("30%".to_string())
+        ]))
+    );
+    assert_eq!(
+        play.tasks[0].keywords.r#become,
+        Some(BooleanOrString::String(
+            "{{ use_become | default(false) }}".to_string()
+        ))
+    );
+    assert_eq!(play.tasks[0].notify, vec!["1", "restart"]);
+    assert_eq!(
+        playbook.plays[1].serial,
+        Some(Serial::Expression("25%".to_string()))
+    );
+}
+
+#[tokio::test]
+async fn test_yaml_1_1_scalar_resolution() {
+    let playbook_content = r#"- hosts: all
+  vars:
+    legacy_octal: 0644
+    sexagesimal: 1:30
+    quoted_yes: "yes"
+    plain_y: y
+  tasks:
+    - name: Copy file
+      copy:
+        src: a
+        dest: /tmp/a
+        mode: 0644
+        backup: yes
+        force: "no"
+        validate: Off
+"#;
+
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let playbook_path = temp_dir.path().join("site.yml");
+    fs::write(&playbook_path, playbook_content).await.unwrap();
+
+    let playbook = Parser::new().parse_playbook(&playbook_path).await.unwrap();
+    let play = &playbook.plays[0];
+    assert_eq!(play.vars["legacy_octal"], serde_json::json!(420));
+    assert_eq!(play.vars["sexagesimal"], serde_json::json!(90));
+    assert_eq!(play.vars["quoted_yes"], serde_json::json!("yes"));
+    assert_eq!(play.vars["plain_y"], serde_json::json!("y"));
+
+    let args = &play.tasks[0].args;
+    assert_eq!(args["mode"], serde_json::json!(420));
+    assert_eq!(args["backup"], serde_json::json!(true));
+    assert_eq!(args["force"], serde_json::json!("no"));
+    assert_eq!(args["validate"], serde_json::json!(false));
+
+    let inventory_content = r#"all:
+  hosts:
+    web1:
+      http_port: 0x50
+      enabled: on
+      label: 'on'
+"#;
+    let inventory_path = temp_dir.path().join("hosts.yml");
+    fs::write(&inventory_path, inventory_content).await.unwrap();
+
+    let inventory = Parser::new()
+        .parse_inventory(&inventory_path)
+        .await
+        .unwrap();
+    let vars = &inventory.hosts["web1"].vars;
+    assert_eq!(vars["http_port"], serde_json::json!(80));
+    assert_eq!(vars["enabled"], serde_json::json!(true));
+    assert_eq!(vars["label"], serde_json::json!("on"));
+}
+
+#[tokio::test]
+async fn test_yaml_merge_keys_and_limits() {
+    let playbook_content = r#"- hosts: all
+  vars:
+    defaults: &defaults
+      port: 80
+      user: web
+    service:
+      <<: *defaults
+      port: 8080
+  tasks:
+    - name: Base
+      copy: &common_args
+        owner: root
+        mode: "0644"
+        dest: /etc/a
+    - name: Override
+      copy:
+        <<: *common_args
+        dest: /etc/b
+"#;
+
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let playbook_path = temp_dir.path().join("site.yml");
+    fs::write(&playbook_path, playbook_content).await.unwrap();
+
+    let playbook = Parser::new().parse_playbook(&playbook_path).await.unwrap();
+    let play = &playbook.plays[0];
+    assert_eq!(
+        play.vars["service"],
+        serde_json::json!({"port": 8080, "user": "web"})
+    );
+    let args = &play.tasks[1].args;
+    assert_eq!(args["owner"], serde_json::json!("root"));
+    assert_eq!(args["mode"], serde_json::json!("0644"));
+    assert_eq!(args["dest"], serde_json::json!("/etc/b"));
+
+    let inventory_content = r#"all:
+  vars:
+    common: &common
+      ansible_user: deploy
+  hosts:
+    web1:
+      <<: *common
+      ansible_port: 2222
+"#;
+    let inventory_path = temp_dir.path().join("hosts.yml");
+    fs::write(&inventory_path, inventory_content).await.unwrap();
+    let inventory = Parser::new()
+        .parse_inventory(&inventory_path)
+        .await
+        .unwrap();
+    assert_eq!(
+        inventory.hosts["web1"].vars["ansible_user"],
+        serde_json::json!("deploy")
+    );
+
+    let limited = Parser::new().with_yaml_limits(YamlLimits {
+        max_alias_nodes: 1,
+        ..YamlLimits::default()
+    });
+    let err = limited.parse_playbook(&playbook_path).await.unwrap_err();
+    assert!(matches!(err, ParseError::YamlLimitExceeded { .. }));
+    let err = limited.parse_inventory(&inventory_path).await.unwrap_err();
+    assert!(matches!(err, ParseError::YamlLimitExceeded { .. }));
+}
+
+#[tokio::test]
+async fn test_play_roles_are_expanded() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let role_dir = temp_dir.path().join("roles").join("web");
+    for (file, content) in [
+        (
+            "tasks/main.yml",
+            "- name: Install package\n  package:\n    name: \"{{ package }}\"\n    state: \"{{ state }}\"\n  notify: restart web\n- include_tasks: extra.yml\n",
+        ),
+        (
+            "tasks/extra.yml",
+            "- name: Listen\n  debug:\n    msg: \"{{ http_port }}\"\n",
+        ),
+        (
+            "handlers/main.yml",
+            "- name: restart web\n  service:\n    name: nginx\n",
+        ),
+        (
+            "defaults/main.yml",
+            "package: apache\nstate: present\nhttp_port: 80\n",
+        ),
+        ("vars/main.yml", "package: nginx\n"),
+    ] {
+        let path = role_dir.join(file);
+        fs::create_dir_all(path.parent().unwrap()).await.unwrap();
+        fs::write(&path, content).await.unwrap();
+    }
+
+    let playbook_content = r#"- hosts: all
+  vars:
+    state: latest
+    package: httpd
+  pre_tasks:
+    - name: Before
+      debug:
+        msg: pre
+  roles:
+    - role: web
+      http_port: 8080
+      when: deploy_web
+      tags: [web]
+    - not_installed
+  tasks:
+    - name: Main
+      debug:
+        msg: main
+  post_tasks:
+    - name: After
+      debug:
+        msg: post
+  handlers:
+    - name: Play handler
+      debug:
+        msg: handler
+"#;
+    let playbook_path = temp_dir.path().join("site.yml");
+    fs::write(&playbook_path, playbook_content).await.unwrap();
+
+    let playbook = Parser::new().parse_playbook(&playbook_path).await.unwrap();
+    let play = &playbook.plays[0];
+
+    let names: Vec<&str> = play.tasks.iter().map(|t| t.name.as_str()).collect();
+    assert_eq!(
+        names,
+        vec!["Before", "Install package", "Listen", "Main", "After"]
+    );
+
+    // Role vars beat play vars, which beat role defaults
+    let install = &play.tasks[1];
+    assert_eq!(install.args["name"], serde_json::json!("nginx"));
+    assert_eq!(install.args["state"], serde_json::json!("latest"));
+    assert!(install.tags.contains(&"web".to_string()));
+    assert_eq!(install.when[0].expression, "deploy_web");
+    assert_eq!(play.tasks[2].when[0].expression, "deploy_web");
+    assert!(play.tasks[3].when.is_empty());
+
+    let handler_names: Vec<&str> = play.handlers.iter().map(|h| h.name.as_str()).collect();
+    assert_eq!(handler_names, vec!["restart web", "Play handler"]);
+    assert_eq!(
+        play.notifications.triggers[&install.id],
+        vec![play.handlers[0].id.clone()]
+    );
+
+    let web = &play.roles[0];
+    assert!(web.path.is_some());
+    assert_eq!(web.vars["http_port"], serde_json::json!(8080));
+    assert_eq!(web.defaults["http_port"], serde_json::json!(80));
+    assert_eq!(web.role_vars["package"], serde_json::json!("nginx"));
+    assert_eq!(play.roles[1].name, "not_installed");
+    assert!(play.roles[1].path.is_none());
+}
+
+#[tokio::test]
+async fn test_imported_playbook_roles_are_expanded() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let plays_dir = temp_dir.path().join("plays");
+    let role_dir = plays_dir.join("roles").join("app");
+    for (file, content) in [
+        (
+            "tasks/main.yml",
+            "- name: Deploy\n  debug:\n    msg: \"{{ release }} on {{ port }}\"\n  notify: restart app\n",
+        ),
+        (
+            "handlers/main.yml",
+            "- name: restart app\n  service:\n    name: app\n",
+        ),
+        ("defaults/main.yml", "port: 80\n"),
+        ("vars/main.yml", "release: stable\n"),
+    ] {
+        let path = role_dir.join(file);
+        fs::create_dir_all(path.parent().unwrap()).await.unwrap();
+        fs::write(&path, content).await.unwrap();
+    }
+    fs::write(
+        plays_dir.join("web.yml"),
+        "- hosts: web\n  roles:\n    - app\n",
+    )
+    .await
+    .unwrap();
+
+    let playbook_path = temp_dir.path().join("site.yml");
+    fs::write(&playbook_path, "- import_playbook: plays/web.yml\n")
+        .await
+        .unwrap();
+
+    // Roles are found next to the imported playbook and expanded as in any play
+    let playbook = Parser::new().parse_playbook(&playbook_path).await.unwrap();
+    let play = &playbook.plays[0];
+    let names: Vec<&str> = play.tasks.iter().map(|t| t.name.as_str()).collect();
+    assert_eq!(names, vec!["Deploy"]);
+    assert_eq!(play.tasks[0].args["msg"], serde_json::json!("stable on 80"));
+
+    let handler_names: Vec<&str> = play.handlers.iter().map(|h| h.name.as_str()).collect();
+    assert_eq!(handler_names, vec!["restart app"]);
+    assert_eq!(
+        play.notifications.triggers[&play.tasks[0].id],
+        vec![play.handlers[0].id.clone()]
+    );
+
+    let app = &play.roles[0];
+    assert!(app.path.is_some());
+    assert_eq!(app.defaults["port"], serde_json::json!(80));
+    assert_eq!(app.role_vars["release"], serde_json::json!("stable"));
+}
+
+async fn write_role(root: &std::path::Path, name: &str, meta: Option<&str>) {
+    let role_dir = root.join("roles").join(name);
+    fs::create_dir_all(role_dir.join("tasks")).await.unwrap();
+    fs::write(
+        role_dir.join("tasks").join("main.yml"),
+        format!("- name: {name} task\n  debug:\n    msg: \"{{{{ who | default('{name}') }}}}\"\n"),
+    )
+    .await
+    .unwrap();
+    if let Some(meta) = meta {
+        fs::create_dir_all(role_dir.join("meta")).await.unwrap();
+        fs::write(role_dir.join("meta").join("main.yml"), meta)
+            .await
+            .unwrap();
+    }
+}
+
+#[tokio::test]
+async fn test_role_dependencies() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let root = temp_dir.path();
+    write_role(root, "common", None).await;
+    write_role(root, "repeat", Some("allow_duplicates: yes\n")).await;
+    write_role(
+        root,
+        "web",
+        Some("galaxy_info:\n  author: me\ndependencies:\n  - common\n  - role: repeat\n    when: repeat_enabled\n"),
+    )
+    .await;
+    write_role(
+        root,
+        "db",
+        Some("dependencies:\n  - common\n  - repeat\n  - role: common\n    who: again\n"),
+    )
+    .await;
+
+    let playbook_content = r#"- hosts: all
+  roles:
+    - role: web
+      tags: [web]
+    - db
+"#;
+    let playbook_path = root.join("site.yml");
+    fs::write(&playbook_path, playbook_content).await.unwrap();
+
+    let playbook = Parser::new().parse_playbook(&playbook_path).await.unwrap();
+    let play = &playbook.plays[0];
+    let names: Vec<&str> = play.tasks.iter().map(|t| t.name.as_str()).collect();
+    assert_eq!(
+        names,
+        vec![
+            "common task",
+            "repeat task",
+            "web task",
+            "repeat task",
+            "common task",
+            "db task"
+        ]
+    );
+
+    // Dependencies inherit the tags and conditions of the roles needing them
+    assert!(play.tasks[0].tags.contains(&"web".to_string()));
+    assert_eq!(play.tasks[1].when[0].expression, "repeat_enabled");
+    assert!(!play.tasks[5].tags.contains(&"web".to_string()));
+    assert_eq!(play.tasks[4].args["msg"], serde_json::json!("again"));
+
+    let web = &play.roles[0];
+    let dependencies: Vec<&str> = web.dependencies.iter().map(|r| r.name.as_str()).collect();
+    assert_eq!(dependencies, vec!["common", "repeat"]);
+    let db = &play.roles[1];
+    assert!(db.dependencies[0].deduplicated);
+    assert!(!db.dependencies[1].deduplicated);
+    assert!(!db.dependencies[2].deduplicated);
+
+    write_role(root, "a", Some("dependencies: [b]\n")).await;
+    write_role(root, "b", Some("dependencies: [a]\n")).await;
+    fs::write(&playbook_path, "- hosts: all\n  roles: [a]\n")
+        .await
+        .unwrap();
+    let err = Parser::new()
+        .parse_playbook(&playbook_path)
+        .await
+        .unwrap_err();
+    match err {
+        ParseError::CircularDependency { cycle } => assert_eq!(cycle, "a -> b -> a"),
+        other => panic!("expected a circular dependency, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn test_role_argument_specs() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let root = temp_dir.path();
+    write_role(root, "web", None).await;
+    let role_dir = root.join("roles").join("web");
+    fs::create_dir_all(role_dir.join("meta")).await.unwrap();
+    fs::write(
+        role_dir.join("meta").join("argument_specs.yml"),
+        "argument_specs:\n  main:\n    options:\n      http_port:\n        type: int\n        required: true\n      state:\n        choices: [present, absent]\n",
+    )
+    .await
+    .unwrap();
+    fs::create_dir_all(role_dir.join("defaults")).await.unwrap();
+    fs::write(
+        role_dir.join("defaults").join("main.yml"),
+        "state: present\n",
+    )
+    .await
+    .unwrap();
+
+    let playbook_path = root.join("site.yml");
+    fs::write(
+        &playbook_path,
+        "- hosts: all\n  roles:\n    - role: web\n      http_port: 8080\n",
+    )
+    .await
+    .unwrap();
+    assert!(Parser::new().parse_playbook(&playbook_path).await.is_ok());
+
+    fs::write(
+        &playbook_path,
+        "- hosts: all\n  vars:\n    state: running\n  roles:\n    - role: web\n      http_port: http\n",
+    )
+    .await
+    .unwrap();
+    let err = Parser::new()
+        .parse_playbook(&playbook_path)
+        .await
+        .unwrap_err();
+    match err {
+        ParseError::RoleArgumentSpec {
+            role,
+            entry_point,
+            message,
+        } => {
+            assert_eq!(role, "web");
+            assert_eq!(entry_point, "main");
+            assert!(message.contains("'http_port' is of type str but must be int"));
+            assert!(message.contains("'state' is 'running'"));
+        }
+        other => panic!("expected an argument spec error, got {other:?}"),
+    }
+
+    // `include_role` and `import_role` check the role's inputs too, task `vars` included
+    fs::write(
+        &playbook_path,
+        "- hosts: all\n  tasks:\n    - include_role:\n        name: web\n      vars:\n        http_port: 8080\n    - import_role:\n        name: web\n      vars:\n        http_port: 80\n",
+    )
+    .await
+    .unwrap();
+    assert!(Parser::new().parse_playbook(&playbook_path).await.is_ok());
+
+    for directive in ["include_role", "import_role"] {
+        fs::write(
+            &playbook_path,
+            format!("- hosts: all\n  tasks:\n    - {directive}:\n        name: web\n      vars:\n        http_port: 8080\n        state: z\n"),
+        )
+        .await
+        .unwrap();
+        let err = Parser::new()
+            .parse_playbook(&playbook_path)
+            .await
+            .unwrap_err();
+        match err {
+            ParseError::RoleArgumentSpec { role, message, .. } => {
+                assert_eq!(role, "web");
+                assert!(message.contains("'state' is 'z'"), "{directive}: {message}");
+            }
+            other => panic!("{directive}: expected an argument spec error, got {other:?}"),
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_collection_roles_and_modules() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let root = temp_dir.path();
+
+    // A collection next to the playbook, and one in a configured collections path
+    let acme = root.join("collections/ansible_collections/acme/web");
+    for (role, tasks, meta) in [
+        (
+            "nginx",
+            "- name: nginx task\n  acme.web.vhost:\n    name: site\n",
+            "dependencies: [common]\n",
+        ),
+        (
+            "common",
+            "- name: common task\n  ping:\n",
+            "dependencies: []\n",
+        ),
+    ] {
+        let role_dir = acme.join("roles").join(role);
+        fs::create_dir_all(role_dir.join("tasks")).await.unwrap();
+        fs::create_dir_all(role_dir.join("meta")).await.unwrap();
+        fs::write(role_dir.join("tasks/main.yml"), tasks)
+            .await
+            .unwrap();
+        fs::write(role_dir.join("meta/main.yml"), meta)
+            .await
+            .unwrap();
+    }
+    fs::create_dir_all(acme.join("plugins/modules"))
+        .await
+        .unwrap();
+    fs::write(acme.join("plugins/modules/vhost.py"), "")
+        .await
+        .unwrap();
+    fs::write(
+        acme.join("galaxy.yml"),
+        "namespace: acme\nname: web\nversion: 2.0.0\n",
+    )
+    .await
+    .unwrap();
+
+    let shared = root.join("shared");
+    let general = shared.join("ansible_collections/community/general");
+    fs::create_dir_all(general.join("plugins/modules"))
+        .await
+        .unwrap();
+    fs::write(general.join("plugins/modules/ufw.py"), "")
+        .await
+        .unwrap();
+
+    let playbook_path = root.join("site.yml");
+    fs::write(
+        &playbook_path,
+        r#"- hosts: all
+  collections:
+    - acme.web
+  roles:
+    - nginx
+    - community.general.missing
+  tasks:
+    - name: allow ssh
+      community.general.ufw:
+        rule: allow
+    - name: greet
+      ansible.builtin.debug:
+        msg: hi
+    - name: not installed
+      other.collection.thing: {}
+"#,
+    )
+    .await
+    .unwrap();
+
+    let parser = Parser::new().with_collections_paths(vec![shared.clone()]);
+    let index = parser.collection_index(&playbook_path).unwrap();
+    assert_eq!(
+        index.get("acme.web").unwrap().version.as_deref(),
+        Some("2.0.0")
+    );
+
+    let playbook = parser.parse_playbook(&playbook_path).await.unwrap();
+    let play = &playbook.plays[0];
+    assert_eq!(play.collections, vec!["acme.web"]);
+    let modules: Vec<(&str, &str)> = play
+        .tasks
+        .iter()
+        .map(|t| (t.name.as_str(), t.module.as_str()))
+        .collect();
+    assert_eq!(
+        modules,
+        vec![
+            ("common task", "ping"),
+            ("nginx task", "acme.web.vhost"),
+            ("allow ssh", "community.general.ufw"),
+            ("greet", "ansible.builtin.debug"),
+            ("not installed", "other.collection.thing"),
+        ]
+    );
+    assert!(play.roles[0]
+        .path
+        .as_deref()
+        .unwrap()
+        .contains("ansible_collections/acme/web/roles/nginx"));
+    assert_eq!(play.roles[0].dependencies[0].name, "common");
+    assert!(play.roles[1].path.is_none());
+
+    // Modules missing from installed collections are kept, or rejected when strict
+    fs::write(
+        &playbook_path,
+        "- hosts: all\n  tasks:\n    - name: typo\n      community.general.uwf:\n        rule: allow\n",
+    )
+    .await
+    .unwrap();
+    let playbook = parser.parse_playbook(&playbook_path).await.unwrap();
+    assert_eq!(playbook.plays[0].tasks[0].module, "community.general.uwf");
+
+    let parser = parser.with_strict_modules(true);
+    let err = parser.parse_playbook(&playbook_path).await.unwrap_err();
+    match err {
+        ParseError::InvalidModule { module, task } => {
+            assert_eq!(module, "community.general.uwf");
+            assert_eq!(task, "typo");
+        }
+        other => panic!("expected an invalid module error, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn test_templated_include_candidates() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let root = temp_dir.path();
+    fs::create_dir_all(root.join("tasks")).await.unwrap();
+    for os in ["debian", "redhat", "web"] {
+        fs::write(
+            root.join("tasks").join(format!("{os}.yml")),
+            format!("- name: {os} task\n  debug:\n    msg: {os}\n"),
+        )
+        .await
+        .unwrap();
+    }
+    // Matches the file name but is not a task list
+    fs::write(root.join("tasks/settings.yml"), "key: value\n")
+        .await
+        .unwrap();
+
+    let playbook_path = root.join("site.yml");
+    fs::write(
+        &playbook_path,
+        r#"- hosts: all
+  vars:
+    flavor: web
+    run_os: true
+  tasks:
+    - name: os specific
+      include_tasks: "tasks/{{ ansible_os_family | lower }}.yml"
+      when: run_os
+      tags: [os]
+    - name: flavor
+      include_tasks: "tasks/{{ flavor }}.yml"
+"#,
+    )
+    .await
+    .unwrap();
+
+    let playbook = Parser::new().parse_playbook(&playbook_path).await.unwrap();
+    let tasks = &playbook.plays[0].tasks;
+    assert_eq!(tasks.len(), 2);
+
+    let marker = &tasks[0];
+    assert_eq!(marker.name, "os specific");
+    assert_eq!(marker.module, "include_tasks");
+    assert_eq!(marker.when[0].expression, "run_os");
+    assert_eq!(marker.tags, vec!["os"]);
+    let deferred = marker.deferred_include.as_ref().unwrap();
+    assert_eq!(
+        deferred.expression,
+        "tasks/{{ ansible_os_family | lower }}.yml"
+    );
+    assert_eq!(deferred.variables, vec!["ansible_os_family"]);
+    let files: Vec<&str> = deferred
+        .candidates
+        .iter()
+        .map(|c| c.file.as_str())
+        .collect();
+    assert_eq!(
+        files,
+        vec!["tasks/debian.yml", "tasks/redhat.yml", "tasks/web.yml"]
+    );
+    assert_eq!(deferred.candidates[1].tasks[0].name, "redhat task");
+    assert_eq!(deferred.candidates[1].tasks[0].when[0].expression, "run_os");
+
+    // Variables known at parse time select the file directly
+    assert_eq!(tasks[1].name, "web task");
+    assert!(tasks[1].deferred_include.is_none());
+
+    fs::write(
+        &playbook_path,
+        "- hosts: all\n  tasks:\n    - import_tasks: \"tasks/{{ os }}.yml\"\n",
+    )
+    .await
+    .unwrap();
+    let err = Parser::new()
+        .parse_playbook(&playbook_path)
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(err, ParseError::InvalidIncludeDirective { ref message } if message.contains("use include_tasks")),
+        "{err:?}"
+    );
+}
+
+#[tokio::test]
+async fn test_include_apply_and_import_inheritance() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let root = temp_dir.path();
+    fs::write(
+        root.join("setup.yml"),
+        r#"- name: install
+  debug:
+    msg: install
+  when: install_enabled
+- name: own user
+  debug:
+    msg: own
+  become_user: app
+  no_log: false
+"#,
+    )
+    .await
+    .unwrap();
+
+    let playbook_path = root.join("site.yml");
+    fs::write(
+        &playbook_path,
+        r#"- hosts: all
+  vars:
+    run_setup: true
+  tasks:
+    - name: dynamic
+      include_tasks:
+        file: setup.yml
+        apply:
+          become: yes
+          become_user: root
+          environment:
+            PATH: /opt/bin
+          tags: [applied]
+          when: apply_enabled
+      when:
+        - run_setup
+        - other_flag | default(true)
+      tags: [included]
+      delegate_to: bastion
+      no_log: true
+    - name: static
+      import_tasks: setup.yml
+      when: [imported_a, imported_b]
+      tags: imported
+      become: true
+      become_user: admin
+      check_mode: yes
+      ignore_errors: true
+      vars:
+        from_import: 1
+"#,
+    )
+    .await
+    .unwrap();
+
+    let playbook = Parser::new().parse_playbook(&playbook_path).await.unwrap();
+    let tasks = &playbook.plays[0].tasks;
+    assert_eq!(tasks.len(), 4);
+    let expressions = |task: &ParsedTask| -> Vec<String> {
+        task.when.iter().map(|c| c.expression.clone()).collect()
+    };
+
+    // Only `apply` reaches dynamically included tasks, behind the include's own clauses
+    let included = &tasks[0];
+    assert_eq!(
+        expressions(included),
+        vec![
+            "run_setup",
+            "other_flag | default(true)",
+            "apply_enabled",
+            "install_enabled"
+        ]
+    );
+    assert_eq!(included.tags, vec!["applied"]);
+    assert_eq!(included.delegate_to, None);
+    assert_eq!(included.keywords.r#become, Some(true.into()));
+    assert_eq!(included.keywords.become_user.as_deref(), Some("root"));
+    assert_eq!(
+        included.keywords.environment,
+        Some(serde_json::json!({"PATH": "/opt/bin"}))
+    );
+    assert_eq!(included.keywords.no_log, None);
+    assert_eq!(tasks[1].keywords.become_user.as_deref(), Some("app"));
+    assert_eq!(
+        tasks[1].keywords.no_log,
+        Some(BooleanOrString::Boolean(false))
+    );
+
+    // Every keyword of a static import is inherited, the task's own winning
+    let imported = &tasks[2];
+    assert_eq!(
+        expressions(imported),
+        vec!["imported_a", "imported_b", "install_enabled"]
+    );
+    assert_eq!(imported.tags, vec!["imported"]);
+    assert_eq!(imported.keywords.r#become, Some(true.into()));
+    assert_eq!(imported.keywords.become_user.as_deref(), Some("admin"));
+    assert_eq!(
+        imported.keywords.check_mode,
+        Some(BooleanOrString::Boolean(true))
+    );
+    assert_eq!(imported.ignore_errors, BooleanOrString::Boolean(true));
+    assert_eq!(imported.vars["from_import"], serde_json::json!(1));
+    assert_eq!(tasks[3].keywords.become_user.as_deref(), Some("app"));
+}
+
+#[tokio::test]
+async fn test_include_role_and_import_role_inheritance() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let root = temp_dir.path();
+    write_role(root, "web", None).await;
+    fs::write(
+        root.join("roles/web/tasks/main.yml"),
+        "- name: web task\n  debug:\n    msg: web\n  when: web_enabled\n",
+    )
+    .await
+    .unwrap();
+    fs::create_dir_all(root.join("roles/web/handlers"))
+        .await
+        .unwrap();
+    fs::write(
+        root.join("roles/web/handlers/main.yml"),
+        "- name: restart web\n  debug:\n    msg: restart\n",
+    )
+    .await
+    .unwrap();
+
+    let playbook_path = root.join("site.yml");
+    fs::write(
+        &playbook_path,
+        r#"- hosts: all
+  tasks:
+    - name: dynamic
+      include_role:
+        name: web
+        apply:
+          become: yes
+          tags: [applied]
+          when: apply_enabled
+      when: run_web
+      tags: [included]
+      become_user: nobody
+    - name: static
+      import_role:
+        name: web
+      when: imported
+      tags: imported
+      become_user: admin
+    - name: chosen at runtime
+      include_role:
+        name: "{{ runtime_role }}"
+    - include_vars: extra.yml
+"#,
+    )
+    .await
+    .unwrap();
+
+    let playbook = Parser::new().parse_playbook(&playbook_path).await.unwrap();
+    let play = &playbook.plays[0];
+    let expressions = |task: &ParsedTask| -> Vec<String> {
+        task.when.iter().map(|c| c.expression.clone()).collect()
+    };
+
+    // Only `apply` reaches the tasks of an included role, behind the include's `when`
+    let included = &play.tasks[0];
+    assert_eq!(included.name, "web task");
+    assert_eq!(
+        expressions(included),
+        vec!["run_web", "apply_enabled", "web_enabled"]
+    );
+    assert_eq!(included.tags, vec!["applied"]);
+    assert_eq!(included.keywords.r#become, Some(true.into()));
+    assert_eq!(included.keywords.become_user, None);
+
+    // Every keyword of an imported role is inherited
+    let imported = &play.tasks[1];
+    assert_eq!(expressions(imported), vec!["imported", "web_enabled"]);
+    assert_eq!(imported.tags, vec!["imported"]);
+    assert_eq!(imported.keywords.become_user.as_deref(), Some("admin"));
+
+    // Roles that cannot be found stay as tasks, as does `include_vars`
+    let modules: Vec<&str> = play.tasks[2..].iter().map(|t| t.module.as_str()).collect();
+    assert_eq!(modules, vec!["include_role", "include_vars"]);
+
+    // The roles are recorded with the play's, and their handlers join the play's
+    let roles: Vec<&str> = play.roles.iter().map(|r| r.name.as_str()).collect();
+    assert_eq!(roles, vec!["web", "web"]);
+    assert!(play.roles[0].path.is_some());
+    let handlers: Vec<&str> = play.handlers.iter().map(|h| h.name.as_str()).collect();
+    assert_eq!(handlers, vec!["restart web", "restart web"]);
+    assert_eq!(expressions(&play.handlers[1]), vec!["imported"]);
+}
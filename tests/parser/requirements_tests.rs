@@ -0,0 +1,139 @@
+use rustle_parse::parser::{Parser, Requirements};
+use tokio::fs;
+
+#[tokio::test]
+async fn test_requirements_check_and_lock() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let root = temp_dir.path();
+
+    // A role installed by ansible-galaxy, a project role and an installed collection
+    let nginx = root.join("roles/geerlingguy.nginx");
+    fs::create_dir_all(nginx.join("tasks")).await.unwrap();
+    fs::create_dir_all(nginx.join("meta")).await.unwrap();
+    fs::write(nginx.join("tasks/main.yml"), "- name: nginx\n  debug:\n")
+        .await
+        .unwrap();
+    fs::write(
+        nginx.join("meta/.galaxy_install_info"),
+        "install_date: today\nversion: 3.1.4\n",
+    )
+    .await
+    .unwrap();
+    let mysql = root.join("roles/geerlingguy.mysql");
+    fs::create_dir_all(mysql.join("tasks")).await.unwrap();
+    fs::write(mysql.join("tasks/main.yml"), "- name: mysql\n  debug:\n")
+        .await
+        .unwrap();
+    fs::create_dir_all(mysql.join("meta")).await.unwrap();
+    fs::write(mysql.join("meta/.galaxy_install_info"), "version: 4.0.0\n")
+        .await
+        .unwrap();
+    fs::create_dir_all(root.join("roles/common/tasks"))
+        .await
+        .unwrap();
+    fs::write(
+        root.join("roles/common/tasks/main.yml"),
+        "- name: common\n  ping:\n",
+    )
+    .await
+    .unwrap();
+    let general = root.join("collections/ansible_collections/community/general");
+    fs::create_dir_all(general.join("plugins/modules"))
+        .await
+        .unwrap();
+    fs::write(general.join("plugins/modules/ufw.py"), "")
+        .await
+        .unwrap();
+    fs::write(
+        general.join("MANIFEST.json"),
+        r#"{"collection_info": {"namespace": "community", "name": "general", "version": "8.0.0"}}"#,
+    )
+    .await
+    .unwrap();
+
+    let playbook_path = root.join("site.yml");
+    fs::write(
+        &playbook_path,
+        r#"- hosts: all
+  roles:
+    - geerlingguy.nginx
+    - geerlingguy.mysql
+    - common
+  tasks:
+    - name: firewall
+      community.general.ufw:
+        rule: allow
+    - name: posix
+      ansible.posix.sysctl:
+        name: vm.swappiness
+        value: 10
+"#,
+    )
+    .await
+    .unwrap();
+    let requirements_path = root.join("requirements.yml");
+    fs::write(
+        &requirements_path,
+        r#"roles:
+  - src: geerlingguy.nginx
+    version: 3.1.4
+  - src: geerlingguy.apache
+collections:
+  - name: community.general
+    version: ">=8.0.0"
+  - community.docker
+"#,
+    )
+    .await
+    .unwrap();
+
+    let parser = Parser::new().with_collections_paths(Vec::new());
+    let playbook = parser.parse_playbook(&playbook_path).await.unwrap();
+    let requirements: Requirements = parser.parse_requirements(&requirements_path).await.unwrap();
+
+    let report = requirements.check(&playbook, root);
+    assert_eq!(report.missing_roles, vec!["geerlingguy.mysql"]);
+    assert_eq!(report.missing_collections, vec!["ansible.posix"]);
+    assert_eq!(report.unused_roles, vec!["geerlingguy.apache"]);
+    assert_eq!(report.unused_collections, vec!["community.docker"]);
+    assert!(!report.is_clean());
+
+    let lock = requirements.lock(
+        &playbook_path,
+        &[],
+        &parser.collection_index(&playbook_path).unwrap(),
+    );
+    assert_eq!(lock.roles[0].installed_version.as_deref(), Some("3.1.4"));
+    assert!(lock.roles[0]
+        .path
+        .as_deref()
+        .unwrap()
+        .ends_with("roles/geerlingguy.nginx"));
+    assert!(lock.roles[1].path.is_none());
+    assert_eq!(
+        lock.collections[0].installed_version.as_deref(),
+        Some("8.0.0")
+    );
+    assert_eq!(
+        lock.collections[0].required_version.as_deref(),
+        Some(">=8.0.0")
+    );
+    assert!(lock.collections[1].path.is_none());
+
+    let json = serde_json::to_value(&lock).unwrap();
+    assert_eq!(json["collections"][0]["name"], "community.general");
+
+    // Roles installed in a configured roles path are found too
+    let shared = root.join("shared_roles");
+    let apache = shared.join("geerlingguy.apache");
+    fs::create_dir_all(apache.join("meta")).await.unwrap();
+    fs::write(apache.join("meta/.galaxy_install_info"), "version: 2.0.0\n")
+        .await
+        .unwrap();
+    let lock = requirements.lock(
+        &playbook_path,
+        &[shared],
+        &parser.collection_index(&playbook_path).unwrap(),
+    );
+    assert_eq!(lock.roles[1].installed_version.as_deref(), Some("2.0.0"));
+}
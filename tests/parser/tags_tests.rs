@@ -0,0 +1,212 @@
+use rustle_parse::parser::tags::list_tags;
+use rustle_parse::parser::{Parser, TagSelection};
+use rustle_parse::ParsedTask;
+use tempfile::TempDir;
+use tokio::fs;
+
+const TAGGED_PLAYBOOK: &str = r#"
+- name: Tagged play
+  hosts: all
+  tags: [deploy]
+  tasks:
+    - name: Install packages
+      package:
+        name: nginx
+      tags:
+        - packages
+
+    - name: Configure
+      block:
+        - name: Write config
+          template:
+            src: nginx.conf.j2
+            dest: /etc/nginx/nginx.conf
+        - name: Validate config
+          command: nginx -t
+          tags: [validate]
+      rescue:
+        - name: Restore config
+          copy:
+            src: nginx.conf.bak
+            dest: /etc/nginx/nginx.conf
+      tags: [config]
+
+    - name: Always check health
+      uri:
+        url: http://localhost/health
+      tags: [always]
+
+    - name: Debug dump
+      debug:
+        var: hostvars
+      tags: [never]
+
+    - import_tasks: imported.yml
+      tags: [imported]
+
+    - include_tasks: included.yml
+      tags: [included]
+"#;
+
+const IMPORTED_TASKS: &str = r#"
+- name: Imported task
+  debug:
+    msg: imported
+"#;
+
+const INCLUDED_TASKS: &str = r#"
+- name: Included task
+  debug:
+    msg: included
+  tags: [inner]
+"#;
+
+async fn write_playbook() -> TempDir {
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(temp_dir.path().join("site.yml"), TAGGED_PLAYBOOK)
+        .await
+        .unwrap();
+    fs::write(temp_dir.path().join("imported.yml"), IMPORTED_TASKS)
+        .await
+        .unwrap();
+    fs::write(temp_dir.path().join("included.yml"), INCLUDED_TASKS)
+        .await
+        .unwrap();
+    temp_dir
+}
+
+fn task_names(tasks: &[ParsedTask]) -> Vec<String> {
+    let mut names = Vec::new();
+    for task in tasks {
+        names.push(task.name.clone());
+        if let Some(block) = &task.block {
+            names.extend(task_names(&block.block));
+            names.extend(task_names(&block.rescue));
+            names.extend(task_names(&block.always));
+        }
+    }
+    names
+}
+
+async fn parse_with(tags: &[&str], skip_tags: &[&str]) -> Vec<String> {
+    let temp_dir = write_playbook().await;
+    let parser = Parser::new().with_tag_selection(TagSelection::new(tags, skip_tags));
+    let playbook = parser
+        .parse_playbook(&temp_dir.path().join("site.yml"))
+        .await
+        .unwrap();
+    task_names(&playbook.plays[0].tasks)
+}
+
+#[tokio::test]
+async fn test_tags_inherited_from_play_block_and_import() {
+    let temp_dir = write_playbook().await;
+    let playbook = Parser::new()
+        .parse_playbook(&temp_dir.path().join("site.yml"))
+        .await
+        .unwrap();
+    let play = &playbook.plays[0];
+
+    assert_eq!(play.tags, vec!["deploy"]);
+    assert!(play.tasks[0].tags.contains(&"deploy".to_string()));
+
+    let block = play.tasks[1]
+        .block
+        .as_ref()
+        .expect("block should be parsed");
+    assert_eq!(block.block.len(), 2);
+    assert_eq!(block.rescue.len(), 1);
+    for tag in ["config", "deploy"] {
+        assert!(block.block[0].tags.contains(&tag.to_string()));
+        assert!(block.rescue[0].tags.contains(&tag.to_string()));
+    }
+
+    let imported = play
+        .tasks
+        .iter()
+        .find(|t| t.name == "Imported task")
+        .unwrap();
+    assert!(imported.tags.contains(&"imported".to_string()));
+
+    // Tags on a dynamic include are not inherited by the included tasks
+    let included = play
+        .tasks
+        .iter()
+        .find(|t| t.name == "Included task")
+        .unwrap();
+    assert!(!included.tags.contains(&"included".to_string()));
+    assert!(included.tags.contains(&"inner".to_string()));
+}
+
+#[tokio::test]
+async fn test_no_selection_keeps_every_task() {
+    let temp_dir = write_playbook().await;
+    let playbook = Parser::new()
+        .parse_playbook(&temp_dir.path().join("site.yml"))
+        .await
+        .unwrap();
+    let names = task_names(&playbook.plays[0].tasks);
+    assert!(names.contains(&"Debug dump".to_string()));
+}
+
+#[tokio::test]
+async fn test_select_tags() {
+    let names = parse_with(&["validate"], &[]).await;
+    assert_eq!(
+        names,
+        vec!["Configure", "Validate config", "Always check health"]
+    );
+
+    let names = parse_with(&["packages,imported"], &[]).await;
+    assert_eq!(
+        names,
+        vec!["Install packages", "Always check health", "Imported task"]
+    );
+}
+
+#[tokio::test]
+async fn test_select_include_requires_tag_on_included_tasks() {
+    // The include runs, but its tasks only run when they match on their own
+    let names = parse_with(&["included"], &[]).await;
+    assert_eq!(names, vec!["Always check health"]);
+
+    let names = parse_with(&["included,inner"], &[]).await;
+    assert_eq!(names, vec!["Always check health", "Included task"]);
+
+    // An unselected include is never expanded
+    let names = parse_with(&["inner"], &[]).await;
+    assert_eq!(names, vec!["Always check health"]);
+}
+
+#[tokio::test]
+async fn test_never_and_skip_tags() {
+    let names = parse_with(&[], &[]).await;
+    assert!(!names.contains(&"Debug dump".to_string()));
+
+    let names = parse_with(&["never"], &[]).await;
+    assert_eq!(names, vec!["Always check health", "Debug dump"]);
+
+    let names = parse_with(&[], &["config", "always"]).await;
+    assert_eq!(
+        names,
+        vec!["Install packages", "Imported task", "Included task"]
+    );
+}
+
+#[tokio::test]
+async fn test_list_tags() {
+    let temp_dir = write_playbook().await;
+    let playbook = Parser::new()
+        .parse_playbook(&temp_dir.path().join("site.yml"))
+        .await
+        .unwrap();
+
+    let tags = list_tags(&playbook);
+    assert_eq!(tags.len(), 1);
+    assert_eq!(tags[0].play_tags, vec!["deploy"]);
+    let task_tags: Vec<&str> = tags[0].task_tags.iter().map(|t| t.as_str()).collect();
+    assert_eq!(
+        task_tags,
+        vec!["always", "config", "deploy", "imported", "inner", "never", "packages", "validate"]
+    );
+}
@@ -77,3 +77,55 @@ fn test_template_filters() {
     assert!(result.is_ok());
     assert_eq!(result.unwrap(), "HELLO WORLD");
 }
+
+#[test]
+fn test_undefined_variables() {
+    let engine = TemplateEngine::new();
+    let mut vars = HashMap::new();
+    vars.insert("flavor".to_string(), serde_json::json!("web"));
+
+    let undefined = engine
+        .undefined_variables("{{ flavor }}/{{ os | lower }}-{{ arch }}.yml", &vars)
+        .unwrap();
+    assert_eq!(undefined, vec!["arch", "os"]);
+    assert!(engine
+        .undefined_variables("{{ flavor }}.yml", &vars)
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn test_native_types() {
+    let mut vars = HashMap::new();
+    vars.insert("port".to_string(), serde_json::json!(8080));
+    vars.insert("users".to_string(), serde_json::json!(["alice", "bob"]));
+
+    let input_value = serde_json::json!({
+        "port": "{{ port + 1 }}",
+        "users": "{{ users }}",
+        "enabled": "{{ port > 1024 }}",
+        "label": "port {{ port }}",
+        "missing": "{{ undefined_var }}"
+    });
+
+    let native = TemplateEngine::new()
+        .with_native_types(true)
+        .render_value(&input_value, &vars)
+        .unwrap();
+    assert_eq!(
+        native,
+        serde_json::json!({
+            "port": 8081,
+            "users": ["alice", "bob"],
+            "enabled": true,
+            "label": "port 8080",
+            "missing": ""
+        })
+    );
+
+    let strings = TemplateEngine::new()
+        .render_value(&input_value, &vars)
+        .unwrap();
+    assert_eq!(strings["port"], serde_json::json!("8081"));
+    assert_eq!(strings["enabled"], serde_json::json!("True"));
+}
@@ -1,7 +1,9 @@
 use rustle_parse::parser::{
     include::{ImportSpec, IncludeConfig, IncludeContext, IncludeHandler, IncludeSpec},
+    task_id::TaskPath,
     template::TemplateEngine,
 };
+use rustle_parse::types::parsed::TaskKeywords;
 use std::collections::HashMap;
 use tempfile::TempDir;
 use tokio::fs;
@@ -46,6 +48,7 @@ async fn test_basic_include_functionality() {
         delegate_to: None,
         delegate_facts: None,
         run_once: None,
+        name: None,
     };
 
     let context = IncludeContext {
@@ -54,6 +57,7 @@ async fn test_basic_include_functionality() {
         include_depth: 0,
         tags: vec![],
         when_condition: None,
+        task_path: TaskPath::default(),
     };
 
     // Test the include_playbook functionality
@@ -85,6 +89,9 @@ async fn test_basic_include_functionality() {
         vars: None,
         when_condition: None,
         tags: Some(vec!["imported".to_string()]),
+        delegate_to: None,
+        ignore_errors: None,
+        keywords: TaskKeywords::default(),
     };
 
     let result_import = handler.import_playbook(&import_spec, &context).await;